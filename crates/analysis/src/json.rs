@@ -0,0 +1,314 @@
+//! Minimal JSON
+//!
+//! Enough to write experiment manifests and to read back the small documents
+//! written by the tools themselves, like program metadata. Reading supports
+//! all of JSON except `\u` escapes outside of the basic multilingual plane.
+
+use std::fmt;
+use std::convert::TryFrom;
+
+/// A JSON value
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+
+    /// An object, with its keys in the order they are written
+    Object(Vec<(String, Json)>),
+
+    /// Already serialized JSON, written out verbatim
+    Raw(String),
+}
+
+impl Json {
+    /// Create an object from `(key, value)` pairs
+    pub fn object<K: Into<String>>(fields: Vec<(K, Json)>) -> Self {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+}
+
+impl Json {
+    /// Parse the JSON document `s`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (value, rest) = parse_value(s)?;
+        if !rest.trim_start().is_empty() {
+            return Err("Trailing characters after JSON value".into());
+        }
+        Ok(value)
+    }
+
+    /// Value of `key` if this is an object which has it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter()
+                .find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The value as a non-negative integer
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Json::Integer(x) => u64::try_from(x).ok(),
+            _ => None,
+        }
+    }
+
+    /// The value as a float, integers are converted
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Integer(x) => Some(x as f64),
+            Json::Float(x)   => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The value as a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The value as a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The value as an array
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a quoted string at the start of `s`, returning it and the rest of
+/// `s`
+fn parse_string(s: &str) -> Result<(String, &str), String> {
+    let rest = s.strip_prefix('"').ok_or("Expected a string")?;
+    let mut out = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((ii, ch)) = chars.next() {
+        match ch {
+            '"' => return Ok((out, &rest[ii + 1..])),
+            '\\' => match chars.next().map(|x| x.1) {
+                Some('"')  => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('/')  => out.push('/'),
+                Some('b')  => out.push('\u{8}'),
+                Some('f')  => out.push('\u{c}'),
+                Some('n')  => out.push('\n'),
+                Some('r')  => out.push('\r'),
+                Some('t')  => out.push('\t'),
+                Some('u')  => {
+                    let hex: String = (0..4).filter_map(|_| chars.next())
+                        .map(|x| x.1).collect();
+                    out.push(u32::from_str_radix(&hex, 16).ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("Invalid escape `\\u{}`",
+                                               hex))?);
+                }
+                _ => return Err("Invalid escape in string".into()),
+            },
+            _ => out.push(ch),
+        }
+    }
+    Err("Unterminated string".into())
+}
+
+/// Parse a single value at the start of `s`, returning the value and the rest
+/// of `s`
+fn parse_value(s: &str) -> Result<(Json, &str), String> {
+    let s = s.trim_start();
+
+    if s.starts_with('"') {
+        let (string, rest) = parse_string(s)?;
+        return Ok((Json::String(string), rest));
+    }
+
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut values = Vec::new();
+        if let Some(after) = rest.trim_start().strip_prefix(']') {
+            return Ok((Json::Array(values), after));
+        }
+        loop {
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Json::Array(values), after));
+            } else {
+                return Err("Expected `,` or `]` in array".into());
+            }
+        }
+    }
+
+    if let Some(mut rest) = s.strip_prefix('{') {
+        let mut fields = Vec::new();
+        if let Some(after) = rest.trim_start().strip_prefix('}') {
+            return Ok((Json::Object(fields), after));
+        }
+        loop {
+            let (key, after) = parse_string(rest.trim_start())?;
+            let after = after.trim_start().strip_prefix(':')
+                .ok_or("Expected `:` after object key")?;
+            let (value, after) = parse_value(after)?;
+            fields.push((key, value));
+
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('}') {
+                return Ok((Json::Object(fields), after));
+            } else {
+                return Err("Expected `,` or `}` in object".into());
+            }
+        }
+    }
+
+    // Bare value, runs until a delimiter
+    let end = s.find(|c: char| matches!(c, ',' | ']' | '}') ||
+                              c.is_whitespace())
+        .unwrap_or(s.len());
+    let (token, rest) = s.split_at(end);
+
+    let value = match token {
+        "null"  => Json::Null,
+        "true"  => Json::Bool(true),
+        "false" => Json::Bool(false),
+        _ => if let Ok(int) = token.parse::<i64>() {
+            Json::Integer(int)
+        } else if let Ok(float) = token.parse::<f64>() {
+            Json::Float(float)
+        } else {
+            return Err(format!("Invalid value `{}`", token));
+        }
+    };
+
+    Ok((value, rest))
+}
+
+impl From<bool> for Json {
+    fn from(x: bool) -> Self { Json::Bool(x) }
+}
+
+impl From<usize> for Json {
+    fn from(x: usize) -> Self { Json::Integer(x as i64) }
+}
+
+impl From<u64> for Json {
+    fn from(x: u64) -> Self { Json::Integer(x as i64) }
+}
+
+impl From<f64> for Json {
+    fn from(x: f64) -> Self { Json::Float(x) }
+}
+
+impl From<&str> for Json {
+    fn from(x: &str) -> Self { Json::String(x.into()) }
+}
+
+impl From<String> for Json {
+    fn from(x: String) -> Self { Json::String(x) }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(x: Option<T>) -> Self { x.map_or(Json::Null, Into::into) }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(x: Vec<T>) -> Self {
+        Json::Array(x.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Write `s` as a quoted JSON string
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"'  => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Json {
+    /// Compact JSON, or indented by two spaces per level with `{:#}`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Write the value at nesting `depth`
+        fn write(f: &mut fmt::Formatter, value: &Json, depth: usize)
+                -> fmt::Result {
+            let pretty = f.alternate();
+            let newline = |f: &mut fmt::Formatter, depth: usize| {
+                if pretty {
+                    write!(f, "\n{:1$}", "", depth * 2)
+                } else {
+                    Ok(())
+                }
+            };
+
+            match value {
+                Json::Null       => f.write_str("null"),
+                Json::Bool(x)    => write!(f, "{}", x),
+                Json::Integer(x) => write!(f, "{}", x),
+                Json::Float(x) if x.is_finite() => write!(f, "{:?}", x),
+                Json::Float(_)   => f.write_str("null"),
+                Json::String(x)  => write_string(f, x),
+                Json::Raw(x)     => f.write_str(x),
+                Json::Array(values) => {
+                    if values.is_empty() {
+                        return f.write_str("[]");
+                    }
+                    f.write_str("[")?;
+                    for (ii, value) in values.iter().enumerate() {
+                        if ii > 0 {
+                            f.write_str(",")?;
+                        }
+                        newline(f, depth + 1)?;
+                        write(f, value, depth + 1)?;
+                    }
+                    newline(f, depth)?;
+                    f.write_str("]")
+                }
+                Json::Object(fields) => {
+                    if fields.is_empty() {
+                        return f.write_str("{}");
+                    }
+                    f.write_str("{")?;
+                    for (ii, (key, value)) in fields.iter().enumerate() {
+                        if ii > 0 {
+                            f.write_str(",")?;
+                        }
+                        newline(f, depth + 1)?;
+                        write_string(f, key)?;
+                        f.write_str(if pretty { ": " } else { ":" })?;
+                        write(f, value, depth + 1)?;
+                    }
+                    newline(f, depth)?;
+                    f.write_str("}")
+                }
+            }
+        }
+
+        write(f, self, 0)
+    }
+}
@@ -0,0 +1,206 @@
+//! Analysis of simulation results
+//!
+//! Sweeps reduce many trials into one [`Record`] per datapoint using
+//! [`Stats`], and store the records of each fuzzer configuration as a
+//! whitespace separated text file which can be plotted directly with gnuplot.
+//! Lines starting with `#` are comments, which gnuplot ignores as well. Each
+//! file starts with a comment header giving the [`SCHEMA_VERSION`] of the
+//! format, the names of the columns, and what the results measure.
+//! Every run also writes a [`json`] manifest describing how the results were
+//! produced.
+
+use std::io::{self, Write};
+
+pub mod json;
+
+/// Running statistics over the results of a series of trials
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Stats {
+    /// Number of samples
+    count: usize,
+
+    /// Sum of all samples
+    sum: f64,
+
+    /// Sum of the squares of all samples
+    sum_pow2: f64,
+}
+
+impl Stats {
+    /// Create a new, empty set of statistics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample
+    pub fn push(&mut self, sample: f64) {
+        self.count    += 1;
+        self.sum      += sample;
+        self.sum_pow2 += sample * sample;
+    }
+
+    /// Number of samples
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Mean of the samples, `NaN` if there are none
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    /// Population standard deviation of the samples, `NaN` if there are none
+    pub fn stddev(&self) -> f64 {
+        let mean = self.mean();
+        ((self.sum_pow2 / self.count as f64) - (mean * mean)).sqrt()
+    }
+}
+
+/// The result of a single datapoint of a sweep
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Record {
+    /// Number of simulated workers
+    pub workers: usize,
+
+    /// Mean result over all trials
+    pub mean: f64,
+
+    /// Standard deviation of the results over all trials
+    pub stddev: f64,
+
+    /// Set if a time constrained trial found all possible coverage, which
+    /// means the data is invalid and should not be used
+    pub exhausted: bool,
+
+    /// Mean number of distinct crashes found over all trials, `NaN` for
+    /// results predating crash tracking
+    pub crashes: f64,
+}
+
+/// Reduce the records of the same configuration on several programs, one
+/// `Vec` per program, to one record per worker count. The mean is the mean
+/// over the programs, and the standard deviation is the spread between the
+/// programs rather than between trials. Only worker counts recorded for every
+/// program are kept.
+pub fn aggregate(runs: &[Vec<Record>]) -> Vec<Record> {
+    let first = match runs.first() {
+        Some(first) => first,
+        None        => return Vec::new(),
+    };
+
+    first.iter().filter_map(|record| {
+        let mut stats = Stats::new();
+        let mut crashes = Stats::new();
+        let mut exhausted = false;
+        for run in runs {
+            let other = run.iter().find(|x| x.workers == record.workers)?;
+            stats.push(other.mean);
+            crashes.push(other.crashes);
+            exhausted |= other.exhausted;
+        }
+        Some(Record {
+            workers: record.workers,
+            mean:    stats.mean(),
+            stddev:  stats.stddev(),
+            exhausted,
+            crashes: crashes.mean(),
+        })
+    }).collect()
+}
+
+/// Version of the result file format. Bump this whenever the columns or
+/// their meaning change. Files without a header predate versioning and are
+/// version 0, which has the same columns as version 1. Version 2 added the
+/// `crashes` column.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Start of the first header line, followed by `v<version>`
+pub const SCHEMA_MAGIC: &str = "# fuzztheory results";
+
+/// Names of the columns of a [`Record`], in order
+pub const COLUMNS: [&str; 5] =
+    ["workers", "mean", "stddev", "exhausted", "crashes"];
+
+/// Write the header of a result file whose results are described by
+/// `description`, like "uptime to find all coverage"
+pub fn write_header<W: Write>(mut out: W, description: &str)
+        -> io::Result<()> {
+    writeln!(out, "{} v{}", SCHEMA_MAGIC, SCHEMA_VERSION)?;
+    writeln!(out, "# columns: {}", COLUMNS.join(" "))?;
+    writeln!(out, "# result: {}", description)
+}
+
+/// Version of the result file format `contents` are in, 0 if they have no
+/// header
+pub fn schema_version(contents: &str) -> Result<u32, String> {
+    let first = contents.lines().next().unwrap_or("");
+    match first.strip_prefix(SCHEMA_MAGIC) {
+        Some(version) => version.trim().strip_prefix('v')
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(|| format!("malformed header `{}`", first)),
+        None => Ok(0),
+    }
+}
+
+/// Comment line appended to result files of sweeps which were interrupted
+/// before all datapoints were collected
+pub const TRUNCATED_MARKER: &str = "# truncated";
+
+/// Returns `true` if the result file `contents` carry the
+/// [`TRUNCATED_MARKER`]
+pub fn is_truncated(contents: &str) -> bool {
+    contents.lines().any(|line| line.trim() == TRUNCATED_MARKER)
+}
+
+/// Write `records` in the result file format, one per line
+pub fn write_records<W: Write>(mut out: W, records: &[Record])
+        -> io::Result<()> {
+    for record in records {
+        writeln!(out, "{:10} {:20.10} {:20.10} {:6} {:14.4}",
+            record.workers, record.mean, record.stddev, record.exhausted,
+            record.crashes)?;
+    }
+    Ok(())
+}
+
+/// Parse the contents of a file written by [`write_records`]. Files written
+/// in a newer format than this version understands are rejected, and files
+/// written before version 2 have `NaN` crashes.
+pub fn parse_records(contents: &str) -> Result<Vec<Record>, String> {
+    let version = schema_version(contents)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!("results are in format v{}, only up to v{} is \
+                            supported", version, SCHEMA_VERSION));
+    }
+
+    // Files before version 2 have no crashes column
+    let columns = if version < 2 { 4 } else { COLUMNS.len() };
+
+    let mut records = Vec::new();
+
+    for (lineno, line) in contents.lines().enumerate() {
+        let err = || format!("line {}: malformed record `{}`",
+                             lineno + 1, line);
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || fields[0].starts_with('#') {
+            continue;
+        }
+        if fields.len() != columns {
+            return Err(err());
+        }
+
+        records.push(Record {
+            workers:   fields[0].parse().map_err(|_| err())?,
+            mean:      fields[1].parse().map_err(|_| err())?,
+            stddev:    fields[2].parse().map_err(|_| err())?,
+            exhausted: fields[3].parse().map_err(|_| err())?,
+            crashes:   match fields.get(4) {
+                Some(crashes) => crashes.parse().map_err(|_| err())?,
+                None          => f64::NAN,
+            },
+        });
+    }
+
+    Ok(records)
+}
@@ -0,0 +1,453 @@
+//! C backend for generated programs
+//!
+//! [`Program::to_c`] emits the same program as [`Program::to_rust`] as a
+//! single C translation unit, so it can be built with `afl-cc` or `clang`
+//! and fuzzed by real fuzzers for comparison with the simulation. Rather than
+//! returning their ID, crash sites crash for real, with an out-of-bounds
+//! read or write of a global buffer, or an `abort()` for assertions. The
+//! accesses are just past the end of the buffer, so it takes a sanitizer
+//! like AddressSanitizer to catch them, like most real bugs.
+
+use crate::{derived_term, Block, Cond, CrashClass, Operation, Program};
+use crate::Stmt;
+
+/// Fuzzing harness emitted along with a C program
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Harness {
+    /// No harness, only the `crashme` entry point
+    None,
+
+    /// A `LLVMFuzzerTestOneInput` wrapper, to build the program with
+    /// `clang -fsanitize=fuzzer,address` and run libFuzzer against it
+    LibFuzzer,
+
+    /// A `main` reading the input from the file given as its argument or
+    /// from stdin, or with AFL++ persistent mode when built with `afl-cc`.
+    /// Every crash site is an `abort()`, so crashes are caught without a
+    /// sanitizer.
+    Afl,
+}
+
+impl Program {
+    /// Emit the program as C source, with the harness `harness`
+    ///
+    /// This produces a `void crashme(const uint8_t *data, size_t size)`
+    /// entry point, and a `crashme_<n>` like it for every other entry point
+    /// of the program. Fixed size inputs are zero padded or cut to the
+    /// program's input size first. Every crash goes through a function of
+    /// its own, `crash_<id>`, so its stack trace names it, and the coverage
+    /// of every block is reported to the `fuzztheory_hit` hook if it is set,
+    /// labeled with the block's [stable ID](Program::stable_ids).
+    pub fn to_c(&self, harness: Harness) -> String {
+        let mut program = String::new();
+        let ids = self.stable_ids();
+        if harness == Harness::LibFuzzer {
+            program += "// Build with `clang -fsanitize=fuzzer,address`, and \
+                        run with\n";
+            program += &format!("// `-max_len={}`, the program's largest \
+                                 input\n\n", self.input_bytes);
+        }
+        if harness == Harness::Afl {
+            program += "// Build with `afl-cc`, and fuzz with \
+                        `afl-fuzz -i <seeds> -o <out> -- <binary>`\n\n";
+        }
+        program += "#include <stddef.h>\n";
+        if harness == Harness::Afl {
+            program += "#include <stdio.h>\n";
+        }
+        program += "#include <stdint.h>\n";
+        program += "#include <stdlib.h>\n";
+        program += "#include <string.h>\n\n";
+
+        // Coverage goes through a hook which can be set to compare with the
+        // simulation, keeping every block distinct when it isn't
+        program += "void (*fuzztheory_hit)(uint32_t) = NULL;\n";
+        program += "#define ft_hit(id) do { if (fuzztheory_hit) \
+                    fuzztheory_hit(id); } while (0)\n\n";
+
+        // Flaky blocks draw from a xorshift RNG of every thread running the
+        // program, see `to_rust`
+        if self.num_flaky != 0 {
+            program += "static int ft_flaky(void) {\n";
+            program += "    static _Thread_local uint64_t state = \
+                        0x2545f4914f6cdd1dULL;\n";
+            program += "    state ^= state << 13;\n";
+            program += "    state ^= state >> 7;\n";
+            program += "    state ^= state << 17;\n";
+            program += &format!("    return state % {} == 0;\n",
+                                self.params.flaky_hit_chance);
+            program += "}\n\n";
+        }
+
+        // Helpers for magic values and checksums
+        program += "static inline uint64_t ft_le(const uint8_t *bytes, size_t size) {\n";
+        program += "    uint64_t value = 0;\n";
+        program += "    for (size_t ii = size; ii > 0; ii--) {\n";
+        program += "        value = (value << 8) | bytes[ii - 1];\n";
+        program += "    }\n";
+        program += "    return value;\n";
+        program += "}\n\n";
+        program += "static inline uint8_t ft_sum(const uint8_t *bytes, size_t size) {\n";
+        program += "    uint8_t sum = 0;\n";
+        program += "    for (size_t ii = 0; ii < size; ii++) {\n";
+        program += "        sum += bytes[ii];\n";
+        program += "    }\n";
+        program += "    return sum;\n";
+        program += "}\n\n";
+
+        // The crash sites, indexing the buffer with a volatile so the
+        // compiler can't see the access is out of bounds
+        program += "static volatile uint8_t ft_buffer[16];\n";
+        program += "static volatile size_t ft_oob = sizeof(ft_buffer);\n\n";
+        for (id, class) in self.crash_classes.iter().enumerate() {
+            program += &format!("__attribute__((noinline)) static void \
+                                 crash_{}(void) {{\n", id);
+            program += match class {
+                _ if harness == Harness::Afl => "    abort();\n",
+                CrashClass::OobRead  => "    (void)ft_buffer[ft_oob];\n",
+                CrashClass::OobWrite => "    ft_buffer[ft_oob] = 0;\n",
+                CrashClass::Assert   => "    abort();\n",
+            };
+            program += "}\n\n";
+        }
+
+        // Functions only call functions generated before them
+        for (ii, function) in self.functions.iter().enumerate() {
+            program += &format!("__attribute__((noinline)) static void \
+                                 func_{}(const uint8_t *_input, \
+                                 size_t _len) {{\n", ii);
+            program += "    (void)_input;\n";
+            program += "    (void)_len;\n";
+            emit_c_block(&mut program, function, 1, &ids);
+            program += "}\n\n";
+        }
+
+        // Every entry point fits the input to the program, and runs the
+        // entry block or calls the function it is
+        let entries = Some(None).into_iter()
+            .chain(self.entry_points.iter().map(Some));
+        for (ii, function) in entries.enumerate() {
+            if ii == 0 {
+                program += "void crashme(const uint8_t *data, size_t size) {\n";
+            } else {
+                program += &format!("\nvoid crashme_{}(const uint8_t *data, \
+                                     size_t size) {{\n", ii);
+            }
+            if self.params.variable_length {
+                program += "    const uint8_t *_input = data;\n";
+                program += "    size_t _len = size;\n";
+            } else {
+                program += &format!("    uint8_t _input[{}] = {{ 0 }};\n",
+                                    self.input_bytes.max(1));
+                program += "    size_t _len = sizeof(_input);\n";
+                program += "    memcpy(_input, data, size < _len ? size : _len);\n";
+            }
+            program += "    (void)_input;\n";
+            program += "    (void)_len;\n";
+            match function {
+                None => emit_c_block(&mut program, &self.entry, 1, &ids),
+                Some(function) => {
+                    program += &format!("    func_{}(_input, _len);\n",
+                                        function);
+                }
+            }
+            program += "}\n";
+        }
+
+        if harness == Harness::LibFuzzer {
+            program += "\nint LLVMFuzzerTestOneInput(const uint8_t *data, \
+                        size_t size) {\n";
+            program += "    crashme(data, size);\n";
+            program += "    return 0;\n";
+            program += "}\n";
+        }
+
+        // AFL++ hands persistent mode inputs over in shared memory, and
+        // otherwise inputs are read once, up to the program's largest
+        if harness == Harness::Afl {
+            program += "\n#ifdef __AFL_FUZZ_TESTCASE_LEN\n";
+            program += "__AFL_FUZZ_INIT();\n";
+            program += "#endif\n\n";
+            program += "int main(int argc, char **argv) {\n";
+            program += "#ifdef __AFL_FUZZ_TESTCASE_LEN\n";
+            program += "    (void)argc;\n";
+            program += "    (void)argv;\n";
+            program += "    __AFL_INIT();\n";
+            program += "    unsigned char *data = __AFL_FUZZ_TESTCASE_BUF;\n";
+            program += "    while (__AFL_LOOP(10000)) {\n";
+            program += "        crashme(data, __AFL_FUZZ_TESTCASE_LEN);\n";
+            program += "    }\n";
+            program += "#else\n";
+            program += &format!("    static uint8_t data[{}];\n",
+                                self.input_bytes.max(1));
+            program += "    FILE *file = argc > 1 ? fopen(argv[1], \"rb\") \
+                        : stdin;\n";
+            program += "    if (!file) {\n";
+            program += "        perror(argv[1]);\n";
+            program += "        return 1;\n";
+            program += "    }\n";
+            program += "    size_t size = fread(data, 1, sizeof(data), \
+                        file);\n";
+            program += "    crashme(data, size);\n";
+            program += "#endif\n";
+            program += "    return 0;\n";
+            program += "}\n";
+        }
+        program
+    }
+}
+
+impl Cond {
+    /// The condition as a C expression
+    pub fn c(&self) -> String {
+        match *self {
+            Cond::Bits { byte, mask, target } => {
+                format!("(_input[{}] & {:#04x}) == {:#04x}", byte, mask,
+                        target)
+            }
+            Cond::Magic { byte, size, value } => {
+                format!("ft_le(_input + {}, {}) == {:#x}ULL", byte, size,
+                        value)
+            }
+            Cond::Span { byte, mask, target } => {
+                format!("(ft_le(_input + {}, 2) & {:#06x}) == {:#06x}", byte,
+                        mask, target)
+            }
+            Cond::Range { byte, size, low, high } => {
+                format!("(ft_le(_input + {0}, {1}) >= {2:#x}ULL && \
+                         ft_le(_input + {0}, {1}) <= {3:#x}ULL)", byte, size,
+                        low, high)
+            }
+            Cond::Compare { lhs, rhs, relation } => {
+                format!("_input[{}] {} _input[{}]", lhs, relation.operator(),
+                        rhs)
+            }
+            Cond::Checksum { start, len, byte } => {
+                format!("ft_sum(_input + {}, {}) == _input[{}]", start, len,
+                        byte)
+            }
+            Cond::Length { len } => format!("_len >= {}", len),
+            Cond::Field { field, offset, mask, target } => {
+                format!("(size_t)_input[{0}] + {1} < _len && \
+                         (_input[_input[{0}] + {1}] & {2:#04x}) == {3:#04x}",
+                        field, field + 1 + offset, mask, target)
+            }
+            Cond::Derived { var, mask, target, .. } => {
+                format!("(_v{} & {:#04x}) == {:#04x}", var, mask, target)
+            }
+        }
+    }
+
+    /// Declarations of the chain of locals computing the value of a derived
+    /// value condition in C, like [`Cond::derivation`]
+    fn c_derivation(&self) -> Vec<String> {
+        let Cond::Derived { var, base, ref terms, .. } = *self else {
+            return Vec::new();
+        };
+        let mut stmts = vec![format!("uint8_t _v{}_0 = _input[{}];", var,
+                                     base)];
+        for (ii, &(op, byte, shift)) in terms.iter().enumerate() {
+            let local = if ii + 1 == terms.len() {
+                format!("_v{}", var)
+            } else {
+                format!("_v{}_{}", var, ii + 1)
+            };
+            let operator = match op {
+                Operation::Add => "+",
+                Operation::Sub => "-",
+                Operation::Xor => "^",
+            };
+            // Shifts bind looser than the other operators in C
+            let term = if shift != 0 {
+                format!("({})", derived_term(byte, shift))
+            } else {
+                derived_term(byte, shift)
+            };
+            stmts.push(format!("uint8_t {} = (uint8_t)(_v{}_{} {} {});",
+                               local, var, ii, operator, term));
+        }
+        stmts
+    }
+}
+
+/// Emit the contents of `block` as C at a tab depth of `depth`, labeling the
+/// coverage of every block with its stable ID in `ids`
+fn emit_c_block(program: &mut String, block: &Block, depth: usize,
+                ids: &[u64]) {
+    // Tab in the program by `depth` tabs
+    let tab = |program: &mut String, depth: usize| {
+        for _ in 0..depth { *program += "    "; }
+    };
+
+    tab(program, depth);
+    if block.flaky {
+        *program += &format!("if (ft_flaky()) ft_hit({}); // {:016x}\n",
+                             block.id, ids[block.id]);
+    } else {
+        *program += &format!("ft_hit({}); // {:016x}\n", block.id,
+                             ids[block.id]);
+    }
+
+    // Declare the flags of the block's joins
+    for stmt in &block.stmts {
+        if let Stmt::Join { join, .. } = stmt {
+            tab(program, depth);
+            *program += &format!("int _join_{} = 0;\n", join);
+        }
+    }
+
+    // Compute the values derived from the input which the block's
+    // conditions test
+    for cond in block.derived() {
+        for stmt in cond.c_derivation() {
+            tab(program, depth);
+            *program += &stmt;
+            *program += "\n";
+        }
+    }
+
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::If { cond, body, else_body } => {
+                tab(program, depth);
+                *program += &format!("if ({}) {{\n", cond.c());
+                emit_c_block(program, body, depth + 1, ids);
+                if let Some(else_body) = else_body {
+                    tab(program, depth);
+                    *program += "} else {\n";
+                    emit_c_block(program, else_body, depth + 1, ids);
+                }
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Match { byte, mask, arms } => {
+                tab(program, depth);
+                *program += &format!("switch (_input[{}] & {:#04x}) {{\n",
+                                     byte, mask);
+                for (value, body) in arms {
+                    tab(program, depth);
+                    *program += &format!("case {:#04x}: {{\n", value);
+                    emit_c_block(program, body, depth + 1, ids);
+                    tab(program, depth + 1);
+                    *program += "break;\n";
+                    tab(program, depth);
+                    *program += "}\n";
+                }
+                tab(program, depth);
+                *program += "default:\n";
+                tab(program, depth + 1);
+                *program += "break;\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Call { function } => {
+                tab(program, depth);
+                *program += &format!("func_{}(_input, _len);\n", function);
+            }
+            Stmt::SetJoin { join } => {
+                tab(program, depth);
+                *program += &format!("_join_{} = 1;\n", join);
+            }
+            Stmt::Join { join, body } => {
+                tab(program, depth);
+                *program += &format!("if (_join_{}) {{\n", join);
+                emit_c_block(program, body, depth + 1, ids);
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Records { arms } => {
+                tab(program, depth);
+                *program += "size_t _pos = 0;\n";
+                tab(program, depth);
+                *program += "while (_pos + 2 <= _len) {\n";
+                tab(program, depth + 1);
+                *program += "size_t _end = _pos + 2 + _input[_pos + 1];\n";
+                tab(program, depth + 1);
+                *program += "if (_end > _len) _end = _len;\n";
+                tab(program, depth + 1);
+                *program += "switch (_input[_pos]) {\n";
+                for (tag, function) in arms {
+                    tab(program, depth + 1);
+                    *program += &format!(
+                        "case {:#04x}: func_{}(_input + _pos + 2, \
+                         _end - _pos - 2); break;\n", tag, function);
+                }
+                tab(program, depth + 1);
+                *program += "default: break;\n";
+                tab(program, depth + 1);
+                *program += "}\n";
+                tab(program, depth + 1);
+                *program += "_pos = _end;\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Loop { byte, mask, body } => {
+                tab(program, depth);
+                *program += &format!(
+                    "for (unsigned _i{0} = 0; _i{0} < (unsigned)((_input[{1}] \
+                     & {2:#04x}) >> {3}); _i{0}++) {{\n",
+                    depth, byte, mask, mask.trailing_zeros());
+                emit_c_block(program, body, depth + 1, ids);
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Crash { cond, id, block, .. } => {
+                tab(program, depth);
+                *program += &format!("if ({}) {{\n", cond.c());
+                if let Some(block) = block {
+                    tab(program, depth + 1);
+                    *program += &format!("ft_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                }
+                tab(program, depth + 1);
+                *program += &format!("crash_{}();\n", id);
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Reject { cond, block } => {
+                tab(program, depth);
+                *program += &format!("if ({}) {{\n", cond.c());
+                tab(program, depth + 1);
+                *program += &format!("ft_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                tab(program, depth + 1);
+                *program += "return;\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Conjunction { parts, id, block, .. } => {
+                // Count the parts which hold in a scope of their own
+                tab(program, depth);
+                *program += "{\n";
+                tab(program, depth + 1);
+                *program += "unsigned _parts = 0;\n";
+                for (cond, block) in parts {
+                    tab(program, depth + 1);
+                    *program += &format!("if ({}) {{\n", cond.c());
+                    if let Some(block) = block {
+                        tab(program, depth + 2);
+                        *program += &format!("ft_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                    }
+                    tab(program, depth + 2);
+                    *program += "_parts++;\n";
+                    tab(program, depth + 1);
+                    *program += "}\n";
+                }
+                tab(program, depth + 1);
+                *program += &format!("if (_parts == {}) {{\n", parts.len());
+                if let Some(block) = block {
+                    tab(program, depth + 2);
+                    *program += &format!("ft_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                }
+                tab(program, depth + 2);
+                *program += &format!("crash_{}();\n", id);
+                tab(program, depth + 1);
+                *program += "}\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//! Importing the control flow graphs of real programs
+//!
+//! Random generation gives programs a made up shape. To mirror a specific
+//! real target instead, its control flow graph can be exported from a
+//! disassembler like Ghidra or angr and imported with [`Cfg::load`], and
+//! [`Cfg::generate`] generates a program with the same branching structure
+//! and depth profile.
+//!
+//! Graphs are read from Graphviz DOT, as in `a -> b;`, or from JSON like
+//! `{"entry": "0x1000", "edges": [["0x1000", "0x1010"], ...]}`, where the
+//! edges may be given as a list of `blocks` with an `id` and `successors`
+//! instead. Blocks are named by strings or numbers, and the entry is the
+//! first block named unless it is given.
+//!
+//! Programs are trees, so the graph is cut down to the tree of the shortest
+//! paths from the entry, in which every block is as deep as the fewest
+//! branches it takes to reach it. Blocks with one successor in the tree
+//! guard it with an if statement, those with two with an if statement and
+//! its else block, and those with more with a match on a slice of bits with
+//! an arm for each.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::Path;
+
+use fuzztheory_analysis::json::Json;
+
+use crate::decisions::Draws;
+use crate::rng::Rng;
+use crate::{condition_bits, find_unused_bits, pick_weighted, slice_mask};
+use crate::{verbose, Block, Cond, CrashClass, Params, Program, Stmt};
+
+/// A control flow graph
+#[derive(Clone, Debug)]
+pub struct Cfg {
+    /// Name of every block, as in the description of the graph
+    pub names: Vec<String>,
+
+    /// Successors of every block, by index in `names`
+    pub succs: Vec<Vec<usize>>,
+
+    /// Index of the entry block
+    pub entry: usize,
+}
+
+impl Cfg {
+    /// Read the graph described by the file at `path`, as JSON if it starts
+    /// with `{` and as DOT otherwise
+    pub fn load(path: &Path) -> Result<Cfg, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| {
+            format!("failed to read {}: {}", path.display(), err)
+        })?;
+        let cfg = if text.trim_start().starts_with('{') {
+            Cfg::from_json(&text)
+        } else {
+            Cfg::from_dot(&text)
+        };
+        cfg.map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Parse a graph from its JSON description
+    pub fn from_json(text: &str) -> Result<Cfg, String> {
+        let json = Json::parse(text)?;
+
+        // Blocks are named by strings or numbers
+        let name = |json: &Json| match json {
+            Json::String(name)  => Ok(name.clone()),
+            Json::Integer(name) => Ok(name.to_string()),
+            _ => Err(format!("Invalid block name {}", json)),
+        };
+        fn list<'a>(json: &'a Json, what: &str)
+                -> Result<&'a [Json], String> {
+            json.as_array().ok_or_else(|| {
+                format!("`{}` must be a list", what)
+            })
+        }
+
+        let mut builder = Builder::default();
+        if let Some(edges) = json.get("edges") {
+            for edge in list(edges, "edges")? {
+                match edge.as_array() {
+                    Some([from, to]) => builder.edge(name(from)?, name(to)?),
+                    _ => return Err(format!("Invalid edge {}", edge)),
+                }
+            }
+        } else if let Some(blocks) = json.get("blocks") {
+            for block in list(blocks, "blocks")? {
+                let from = name(block.get("id").ok_or_else(|| {
+                    format!("Block {} has no `id`", block)
+                })?)?;
+                builder.node(from.clone());
+                if let Some(succs) = block.get("successors") {
+                    for to in list(succs, "successors")? {
+                        builder.edge(from.clone(), name(to)?);
+                    }
+                }
+            }
+        } else {
+            return Err("Expected `edges` or `blocks`".into());
+        }
+
+        let entry = json.get("entry").map(name).transpose()?;
+        builder.finish(entry)
+    }
+
+    /// Parse a graph from its Graphviz DOT description. Only node and edge
+    /// statements are read, attributes and subgraphs are skipped, and
+    /// undirected edges are taken to go from left to right.
+    pub fn from_dot(text: &str) -> Result<Cfg, String> {
+        let tokens = dot_tokens(text)?;
+
+        let mut builder = Builder::default();
+
+        // Block the statement so far ends with, and whether an edge from it
+        // is waiting for its other end
+        let mut last: Option<String> = None;
+        let mut edge = false;
+        let mut ii = 0;
+        while ii < tokens.len() {
+            match &tokens[ii] {
+                Token::Id(id, quoted) => {
+                    let keyword = !quoted && ["strict", "graph", "digraph",
+                        "subgraph", "node", "edge"].iter()
+                        .any(|x| id.eq_ignore_ascii_case(x));
+                    if tokens.get(ii + 1) == Some(&Token::Punct('=')) {
+                        // An attribute of the graph
+                        ii += 2;
+                    } else if keyword {
+                        // Graphs and subgraphs may be named
+                        let named = ["graph", "digraph", "subgraph"].iter()
+                            .any(|x| id.eq_ignore_ascii_case(x));
+                        if named && matches!(tokens.get(ii + 1),
+                                             Some(Token::Id(..))) {
+                            ii += 1;
+                        }
+                        last = None;
+                    } else {
+                        if edge {
+                            builder.edge(last.take().unwrap(), id.clone());
+                        } else {
+                            builder.node(id.clone());
+                        }
+                        last = Some(id.clone());
+                        edge = false;
+                    }
+                }
+                Token::Edge => {
+                    if last.is_none() || edge {
+                        return Err("Edge without a block to start from"
+                                   .into());
+                    }
+                    edge = true;
+                }
+                Token::Punct('[') => {
+                    // Skip the attributes of a node or edge
+                    while tokens.get(ii).is_some_and(|x| {
+                        *x != Token::Punct(']')
+                    }) {
+                        ii += 1;
+                    }
+                }
+                Token::Punct(':') => {
+                    // Ports of a node are part of the node
+                    ii += 1;
+                }
+                Token::Punct(_) => {
+                    if edge {
+                        return Err("Edge without a block to end at".into());
+                    }
+                    last = None;
+                }
+            }
+            ii += 1;
+        }
+
+        builder.finish(None)
+    }
+
+    /// The tree of the shortest paths from the entry, as the children of
+    /// every block in the order they are successors, and the depth of every
+    /// block, `None` for blocks the entry doesn't reach
+    pub fn tree(&self) -> (Vec<Vec<usize>>, Vec<Option<usize>>) {
+        let mut children = vec![Vec::new(); self.names.len()];
+        let mut depths = vec![None; self.names.len()];
+        depths[self.entry] = Some(0);
+        let mut queue = VecDeque::from([self.entry]);
+        while let Some(block) = queue.pop_front() {
+            let depth = depths[block].unwrap();
+            for &succ in &self.succs[block] {
+                if depths[succ].is_none() {
+                    depths[succ] = Some(depth + 1);
+                    children[block].push(succ);
+                    queue.push_back(succ);
+                }
+            }
+        }
+        (children, depths)
+    }
+
+    /// Generate a program with the shape of the graph, with the conditions
+    /// and crashes drawn like [`generate`](crate::generate) does as
+    /// `params` say. Every block of the tree of the graph, see
+    /// [`Cfg::tree`], is a block of the program, unless the input runs out
+    /// of bits for the conditions guarding it.
+    pub fn generate(&self, params: &Params) -> Program {
+        let mut gen = Generator {
+            params,
+            rng:           Rng::new(params.seed),
+            draws:         Draws::default(),
+            used_bits:     BTreeSet::new(),
+            num_blocks:    0,
+            crash_classes: Vec::new(),
+            hidden:        0,
+            dropped:       0,
+        };
+
+        let (children, depths) = self.tree();
+        let entry = gen.block(self.entry, &children);
+        gen.draws.finish(&gen.rng);
+
+        let reached = depths.iter().filter(|x| x.is_some()).count();
+        let edges: usize = self.succs.iter().map(|x| x.len()).sum();
+        let mut profile: Vec<usize> = Vec::new();
+        for &depth in depths.iter().flatten() {
+            if profile.len() <= depth {
+                profile.resize(depth + 1, 0);
+            }
+            profile[depth] += 1;
+        }
+        let profile: Vec<String> = profile.iter().enumerate()
+            .map(|(depth, count)| format!("{}: {}", depth, count))
+            .collect();
+        verbose!("proggen: imported {} blocks and {} edges, {} blocks \
+                  reached by {} edges of the tree, blocks by depth {}",
+                 self.names.len(), edges, reached, reached - 1,
+                 profile.join(", "));
+        if gen.dropped != 0 {
+            verbose!("proggen: ran out of input bits for {} blocks",
+                     gen.dropped);
+        }
+        verbose!("proggen: {} blocks, {} crashes ({} without coverage), {} \
+                  of {} input bits used", gen.num_blocks,
+                 gen.crash_classes.len(), gen.hidden, gen.used_bits.len(),
+                 params.max_input_size_bits);
+
+        Program {
+            params:        params.clone(),
+            input_bytes:   params.max_input_size_bits.div_ceil(8),
+            num_blocks:    gen.num_blocks,
+            num_crashes:   gen.crash_classes.len(),
+            root_causes:   (0..gen.crash_classes.len()).collect(),
+            crash_classes: gen.crash_classes,
+            num_flaky:     0,
+            entry,
+            functions:     Vec::new(),
+            variant:       0,
+            modules:       Vec::new(),
+            entry_points:  Vec::new(),
+            draws:         gen.draws,
+        }
+    }
+}
+
+/// Names of blocks and the edges between them, as they are read
+#[derive(Default)]
+struct Builder {
+    /// Index of every block by name
+    index: BTreeMap<String, usize>,
+
+    /// The graph so far
+    names: Vec<String>,
+    succs: Vec<Vec<usize>>,
+}
+
+impl Builder {
+    /// Index of the block `name`, adding it if it's new
+    fn node(&mut self, name: String) -> usize {
+        if let Some(&index) = self.index.get(&name) {
+            return index;
+        }
+        self.index.insert(name.clone(), self.names.len());
+        self.names.push(name);
+        self.succs.push(Vec::new());
+        self.names.len() - 1
+    }
+
+    /// Add an edge from the block `from` to `to`, unless there is one
+    fn edge(&mut self, from: String, to: String) {
+        let (from, to) = (self.node(from), self.node(to));
+        if !self.succs[from].contains(&to) {
+            self.succs[from].push(to);
+        }
+    }
+
+    /// The graph, with the block `entry` as its entry, or the first block
+    fn finish(self, entry: Option<String>) -> Result<Cfg, String> {
+        if self.names.is_empty() {
+            return Err("The graph has no blocks".into());
+        }
+        let entry = match entry {
+            Some(entry) => *self.index.get(&entry).ok_or_else(|| {
+                format!("The entry block {} is not in the graph", entry)
+            })?,
+            None => 0,
+        };
+        Ok(Cfg { names: self.names, succs: self.succs, entry })
+    }
+}
+
+/// A token of a DOT description
+#[derive(PartialEq, Debug)]
+enum Token {
+    /// An identifier, number, or string, and whether it was quoted
+    Id(String, bool),
+
+    /// `->` or `--`
+    Edge,
+
+    /// Any other character with a meaning
+    Punct(char),
+}
+
+/// Split the DOT description `text` into tokens, dropping comments
+fn dot_tokens(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+    while let Some(chr) = chars.next() {
+        let at_line_start = line_start;
+        line_start = chr == '\n' || (line_start && chr.is_whitespace());
+        match chr {
+            _ if chr.is_whitespace() => {}
+            '#' if at_line_start => {
+                // Preprocessor output lines
+                while chars.next_if(|&x| x != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&x| x != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if prev == '*' => break,
+                        Some(chr) => prev = chr,
+                        None => return Err("Unterminated comment".into()),
+                    }
+                }
+            }
+            '-' if matches!(chars.peek(), Some('>') | Some('-')) => {
+                chars.next();
+                tokens.push(Token::Edge);
+            }
+            '"' => {
+                let mut id = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.peek() == Some(&'"') => {
+                            id.push(chars.next().unwrap());
+                        }
+                        Some(chr) => id.push(chr),
+                        None => return Err("Unterminated string".into()),
+                    }
+                }
+                tokens.push(Token::Id(id, true));
+            }
+            _ if chr.is_alphanumeric() || "_.-".contains(chr) => {
+                let mut id = chr.to_string();
+                while let Some(chr) = chars.next_if(|&x| {
+                    x.is_alphanumeric() || x == '_' || x == '.'
+                }) {
+                    id.push(chr);
+                }
+                tokens.push(Token::Id(id, false));
+            }
+            '<' => {
+                // HTML strings, which are only ever labels
+                let mut nesting = 1;
+                while nesting != 0 {
+                    match chars.next() {
+                        Some('<') => nesting += 1,
+                        Some('>') => nesting -= 1,
+                        Some(_) => {}
+                        None => return Err("Unterminated HTML string".into()),
+                    }
+                }
+                tokens.push(Token::Id(String::new(), true));
+            }
+            _ => tokens.push(Token::Punct(chr)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// State of generating a program from a graph
+struct Generator<'a> {
+    params: &'a Params,
+    rng:    Rng,
+
+    /// What was drawn from `rng` for which block
+    draws: Draws,
+
+    /// Bits of the input used by conditions so far
+    used_bits: BTreeSet<usize>,
+
+    /// Number of blocks so far
+    num_blocks: usize,
+
+    /// Class of every crash so far, and how many have no coverage event
+    crash_classes: Vec<CrashClass>,
+    hidden:        usize,
+
+    /// Number of blocks of the tree left out for a lack of input bits
+    dropped: usize,
+}
+
+impl Generator<'_> {
+    /// Allocate a new, empty block with a unique coverage ID
+    fn new_block(&mut self) -> Block {
+        self.num_blocks += 1;
+        Block { id: self.num_blocks - 1, flaky: false, stmts: Vec::new() }
+    }
+
+    /// Allocate `num_bits` unused bits of the input in a single byte,
+    /// returning the byte and a mask selecting them
+    fn bits(&mut self, num_bits: usize) -> Option<(usize, u8)> {
+        let (start, end) = find_unused_bits(
+            &mut self.used_bits, &mut self.rng,
+            0..self.params.max_input_size_bits, num_bits, false, 1000)?;
+        Some((start / 8, slice_mask(start, end)))
+    }
+
+    /// A new condition on a slice of unused bits
+    fn cond(&mut self) -> Option<Cond> {
+        let num_bits = condition_bits(&mut self.rng,
+                                      &self.params.condition_bits);
+        let (byte, mask) = self.bits(num_bits)?;
+        Some(Cond::Bits { byte, mask, target: self.rng.rand() as u8 & mask })
+    }
+
+    /// Number of blocks in the subtree of `block` of the tree `children`
+    fn size(block: usize, children: &[Vec<usize>]) -> usize {
+        1 + children[block].iter()
+            .map(|&child| Generator::size(child, children)).sum::<usize>()
+    }
+
+    /// Generate the block for `block` of the tree `children` of the graph,
+    /// and the subtree nested in it
+    fn block(&mut self, block: usize, children: &[Vec<usize>]) -> Block {
+        let params = self.params;
+        let mut new = self.new_block();
+        self.draws.start(Some(new.id), &self.rng);
+
+        // Random chance for the block to have a crash, like in `generate`.
+        // Nothing is drawn when crashes are disabled.
+        if params.crash_chance != 0 &&
+                (params.max_crashes == 0 ||
+                 self.crash_classes.len() < params.max_crashes) &&
+                self.rng.rand().is_multiple_of(params.crash_chance) {
+            if let Some(cond) = self.cond() {
+                let hidden = params.non_coverage_crash_chance != 0 &&
+                    self.rng.rand().is_multiple_of(
+                        params.non_coverage_crash_chance);
+                let coverage = (!hidden).then(|| self.new_block().id);
+                let class = if params.crash_classes.is_empty() {
+                    CrashClass::ALL[self.rng.rand() % CrashClass::ALL.len()]
+                } else {
+                    CrashClass::ALL[pick_weighted(&mut self.rng,
+                                                  &params.crash_classes)]
+                };
+                new.stmts.push(Stmt::Crash {
+                    cond,
+                    id:    self.crash_classes.len(),
+                    class,
+                    block: coverage,
+                });
+                self.crash_classes.push(class);
+                self.hidden += hidden as usize;
+            }
+        }
+
+        let kids = &children[block];
+        match kids.len() {
+            0 => {}
+            1 | 2 => {
+                let Some(cond) = self.cond() else {
+                    self.dropped += kids.iter()
+                        .map(|&x| Generator::size(x, children)).sum::<usize>();
+                    return new;
+                };
+                let body = self.block(kids[0], children);
+                let else_body = kids.get(1)
+                    .map(|&kid| self.block(kid, children));
+                new.stmts.push(Stmt::If { cond, body, else_body });
+            }
+            _ => {
+                // A match has an arm for every value of its bits, the arms
+                // past the first 256 are left out
+                let arms = kids.len().min(256);
+                let num_bits = (usize::BITS - (arms - 1).leading_zeros())
+                    as usize;
+                let Some((byte, mask)) = self.bits(num_bits) else {
+                    self.dropped += kids.iter()
+                        .map(|&x| Generator::size(x, children)).sum::<usize>();
+                    return new;
+                };
+                self.dropped += kids[arms..].iter()
+                    .map(|&x| Generator::size(x, children)).sum::<usize>();
+
+                // Pick distinct values of the bits by a partial shuffle
+                let mut values: Vec<u8> = (0..=255u8)
+                    .filter(|&x| x & mask == x).collect();
+                for ii in 0..arms {
+                    let jj = ii + self.rng.rand() % (values.len() - ii);
+                    values.swap(ii, jj);
+                }
+                let arms = values[..arms].iter().zip(kids)
+                    .map(|(&value, &kid)| (value, self.block(kid, children)))
+                    .collect();
+                new.stmts.push(Stmt::Match { byte, mask, arms });
+            }
+        }
+        new
+    }
+}
@@ -0,0 +1,189 @@
+//! JSON export of the decisions made generating a program
+//!
+//! Every block is listed with the block it is nested in, the constraint
+//! guarding it there, and the random values drawn while it was the block
+//! being generated, which decided what went into it: the statements nested
+//! in it, their conditions, and whether it was closed. Together with the
+//! draws made for no block, in the order they were made, this is the whole
+//! trace of generating the program, so derived quantities can be computed
+//! from it without parsing the emitted program.
+//!
+//! Draws are recorded as the state of the RNG at the start of every run of
+//! draws for the same block, which costs next to nothing while generating,
+//! and drawn again from those states when they are written out.
+
+use crate::rng::Rng;
+use crate::{Block, Cond, Program, Stmt};
+
+/// Random draws made generating a program
+#[derive(Clone, Debug, Default)]
+pub struct Draws {
+    /// Block every run of draws was made for, if any, and the RNG it
+    /// started from, in the order the runs were made
+    runs: Vec<(Option<usize>, Rng)>,
+
+    /// The RNG after the last draw
+    end: Option<Rng>,
+}
+
+impl Draws {
+    /// Start a run of draws from `rng` for `block`, or for no block, ending
+    /// the run before. Runs without any draws are left out.
+    pub(crate) fn start(&mut self, block: Option<usize>, rng: &Rng) {
+        if self.runs.last().is_some_and(|(_, start)| start == rng) {
+            self.runs.pop();
+        }
+        if self.runs.last().is_none_or(|&(last, _)| last != block) {
+            self.runs.push((block, rng.clone()));
+        }
+    }
+
+    /// End the last run at `rng`, after the last draw
+    pub(crate) fn finish(&mut self, rng: &Rng) {
+        self.end = Some(rng.clone());
+    }
+
+    /// The runs of draws in the order they were made, with the block each
+    /// was made for, if any, and the values drawn
+    pub fn runs(&self) -> Vec<(Option<usize>, Vec<usize>)> {
+        let ends = self.runs.iter().skip(1).map(|(_, start)| start)
+            .chain(self.end.as_ref());
+        self.runs.iter().zip(ends).map(|((block, start), end)| {
+            let mut rng = start.clone();
+            let mut values = Vec::new();
+            while rng != *end {
+                values.push(rng.rand());
+            }
+            (*block, values)
+        }).collect()
+    }
+}
+
+/// A constraint that `cond` holds, or does not
+fn holds(cond: &Cond, holds: bool) -> String {
+    format!("{{\"cond\":{},\"holds\":{}}}", cond.json(), holds)
+}
+
+/// Add `block`, in `function` below `parent`, and every block nested in it
+/// to `blocks` as JSON keyed by ID, left open for the draws. The block is
+/// of `kind`, and reached from its parent if `constraint` holds.
+fn decide_block(block: &Block, function: Option<usize>,
+                parent: Option<usize>, kind: &str, constraint: &str,
+                blocks: &mut Vec<(usize, String)>) {
+    // JSON of an optional number
+    fn opt(x: Option<usize>) -> String {
+        x.map_or("null".into(), |x| x.to_string())
+    }
+
+    // JSON of a block of `kind` reached if `constraint` holds
+    let block_json = |id: usize, parent: Option<usize>, kind: &str,
+                      constraint: &str| {
+        (id, format!("{{\"id\":{},\"kind\":\"{}\",\"function\":{},\
+                      \"parent\":{},\"constraint\":{}", id, kind,
+                     opt(function), opt(parent), constraint))
+    };
+
+    blocks.push(block_json(block.id, parent, kind, constraint));
+    let parent = Some(block.id);
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::If { cond, body, else_body } => {
+                decide_block(body, function, parent, "if", &holds(cond, true),
+                             blocks);
+                if let Some(else_body) = else_body {
+                    decide_block(else_body, function, parent, "else",
+                                 &holds(cond, false), blocks);
+                }
+            }
+            Stmt::Match { byte, mask, arms } => {
+                for &(target, ref body) in arms {
+                    let cond = Cond::Bits { byte: *byte, mask: *mask, target };
+                    decide_block(body, function, parent, "arm",
+                                 &holds(&cond, true), blocks);
+                }
+            }
+            Stmt::Loop { byte, mask, body } => {
+                // The body runs if the bits of the count are not all zero
+                let cond = Cond::Bits { byte: *byte, mask: *mask, target: 0 };
+                decide_block(body, function, parent, "loop",
+                             &holds(&cond, false), blocks);
+            }
+            Stmt::Join { join, body } => {
+                let any_of = format!("{{\"any_of\":{:?}}}",
+                                     block.join_sources(*join));
+                decide_block(body, function, parent, "join", &any_of, blocks);
+            }
+            Stmt::Crash { cond, block: Some(coverage), .. } => {
+                blocks.push(block_json(*coverage, parent, "crash",
+                                       &holds(cond, true)));
+            }
+            Stmt::Conjunction { parts, block: coverage, .. } => {
+                let all: Vec<String> = parts.iter()
+                    .map(|(cond, _)| holds(cond, true)).collect();
+                for ((_, part), constraint) in parts.iter().zip(&all) {
+                    if let Some(part) = *part {
+                        blocks.push(block_json(part, parent, "part",
+                                               constraint));
+                    }
+                }
+                if let Some(coverage) = *coverage {
+                    let all_of = format!("{{\"all_of\":[{}]}}",
+                                         all.join(","));
+                    blocks.push(block_json(coverage, parent, "crash",
+                                           &all_of));
+                }
+            }
+            Stmt::Reject { cond, block: coverage } => {
+                blocks.push(block_json(*coverage, parent, "reject",
+                                       &holds(cond, true)));
+            }
+            Stmt::Crash { block: None, .. } | Stmt::Call { .. } |
+                Stmt::Records { .. } | Stmt::SetJoin { .. } => {}
+        }
+    }
+}
+
+impl Program {
+    /// JSON trace of the decisions made generating the program, along with
+    /// its `seed` and `hash`. Every block lists its `id`, its `kind`, the
+    /// `function` it is in (null for `crashme`), its `parent`, the
+    /// `constraint` on the input guarding it in its parent (null for the
+    /// entry blocks of functions), and the `draws` made while it was the
+    /// block being generated. The constraint is a [`Cond::json`] which
+    /// `holds` or not, for join blocks the blocks of which `any_of` must be
+    /// reached, and for the coverage of a conjunction the constraints
+    /// `all_of` which must hold. The `other_draws` are those made for no
+    /// block, before the blocks were generated and after, and the `runs`
+    /// list the block (or null) and number of draws of every run of draws
+    /// in the order they were made. A variant lists the draws of the
+    /// program it is a variant of.
+    pub fn decisions(&self) -> String {
+        let mut blocks = Vec::new();
+        for (ii, function) in self.functions.iter().enumerate() {
+            decide_block(function, Some(ii), None, "entry", "null",
+                         &mut blocks);
+        }
+        decide_block(&self.entry, None, None, "entry", "null", &mut blocks);
+        blocks.sort();
+
+        let mut draws = vec![Vec::new(); self.num_blocks];
+        let mut other_draws = Vec::new();
+        let mut runs = Vec::new();
+        for (block, values) in self.draws.runs() {
+            runs.push(format!("[{},{}]",
+                block.map_or("null".into(), |x| x.to_string()),
+                values.len()));
+            match block {
+                Some(block) => draws[block].extend(values),
+                None        => other_draws.extend(values),
+            }
+        }
+        let blocks: Vec<String> = blocks.into_iter().map(|(id, json)| {
+            format!("{},\"draws\":{:?}}}", json, draws[id])
+        }).collect();
+
+        format!("{{\"seed\":{},\"hash\":\"{:016x}\",\"blocks\":[{}],\
+                 \"other_draws\":{:?},\"runs\":[{}]}}", self.params.seed,
+                self.hash(), blocks.join(","), other_draws, runs.join(","))
+    }
+}
@@ -0,0 +1,180 @@
+//! Graphviz export of the control flow graph of generated programs
+//!
+//! Every block is a node, labeled with its ID, and every edge into a nested
+//! block is labeled with the condition under which it's taken. Crashes are
+//! red octagons, reached through their coverage event if they have one.
+//! Rejects are inverted houses, ending the way through their function.
+//! Every function is a cluster of its blocks, which the blocks calling it
+//! have dotted edges to.
+
+use crate::{Block, Program, Stmt};
+
+impl Program {
+    /// DOT source of the program's control flow graph, to be rendered with
+    /// Graphviz, for example with `dot -Tsvg`
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot += "digraph program {\n";
+        dot += "    compound=true;\n";
+        dot += "    node [shape=box, fontname=\"monospace\"];\n";
+        dot += "    edge [fontname=\"monospace\"];\n";
+
+        let mut edges = String::new();
+        for (ii, function) in self.functions.iter().enumerate() {
+            dot += &format!("    subgraph cluster_func_{} {{\n", ii);
+            match self.entry_points.iter().position(|&x| x == ii) {
+                Some(entry) => {
+                    dot += &format!("        label=\"func_{} \
+                                     (crashme_{})\";\n", ii, entry + 1);
+                }
+                None => dot += &format!("        label=\"func_{}\";\n", ii),
+            }
+            dot_block(&mut dot, &mut edges, function, &self.functions);
+            dot += "    }\n";
+        }
+        dot += "    subgraph cluster_crashme {\n";
+        dot += "        label=\"crashme\";\n";
+        dot_block(&mut dot, &mut edges, &self.entry, &self.functions);
+        dot += "    }\n";
+
+        dot += &edges;
+        dot += "}\n";
+        dot
+    }
+}
+
+/// Escape `text` for a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Add the nodes of `block` and everything nested in it to `dot`, and the
+/// edges between them to `edges`. Calls go to the first block of their
+/// function in `functions`.
+fn dot_block(dot: &mut String, edges: &mut String, block: &Block,
+             functions: &[Block]) {
+    // A coverage event of a crash
+    let coverage = |dot: &mut String, id: usize| {
+        *dot += &format!("        b{} [label=\"block {}\"];\n", id, id);
+    };
+    // An edge from `from` to `to`, taken if `label` holds
+    let edge = |edges: &mut String, from: &str, to: &str, label: &str,
+                style: &str| {
+        *edges += &format!("    {} -> {} [label=\"{}\"{}];\n", from, to,
+                           escape(label), style);
+    };
+
+    let style = if block.flaky { ", style=dashed" } else { "" };
+    *dot += &format!("        b{} [label=\"block {}{}\"{}];\n", block.id,
+                     block.id, if block.flaky { " (flaky)" } else { "" },
+                     style);
+
+    let from = format!("b{}", block.id);
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::If { cond, body, else_body } => {
+                edge(edges, &from, &format!("b{}", body.id),
+                     &cond.to_string(), "");
+                dot_block(dot, edges, body, functions);
+                if let Some(else_body) = else_body {
+                    edge(edges, &from, &format!("b{}", else_body.id),
+                         &format!("!({})", cond), "");
+                    dot_block(dot, edges, else_body, functions);
+                }
+            }
+            Stmt::Match { byte, mask, arms } => {
+                for (value, body) in arms {
+                    edge(edges, &from, &format!("b{}", body.id),
+                         &format!("_input[{}] & {:#010b} == {:#010b}", byte,
+                                  mask, value), "");
+                    dot_block(dot, edges, body, functions);
+                }
+            }
+            Stmt::Loop { byte, mask, body } => {
+                edge(edges, &from, &format!("b{}", body.id),
+                     &format!("loop (_input[{}] & {:#010b}) >> {}", byte,
+                              mask, mask.trailing_zeros()), "");
+                dot_block(dot, edges, body, functions);
+            }
+            Stmt::SetJoin { .. } => {}
+            Stmt::Join { join, body } => {
+                // Edges from every source of the join
+                let sets = |source: &Block| source.stmts.iter().any(|x| {
+                    matches!(x, Stmt::SetJoin { join: x } if x == join)
+                });
+                for stmt in &block.stmts {
+                    if let Stmt::If { body: source, else_body, .. } = stmt {
+                        for source in std::iter::once(source)
+                                .chain(else_body.iter()) {
+                            if sets(source) {
+                                edge(edges, &format!("b{}", source.id),
+                                     &format!("b{}", body.id),
+                                     &format!("join {}", join),
+                                     ", style=dashed");
+                            }
+                        }
+                    }
+                }
+                dot_block(dot, edges, body, functions);
+            }
+            Stmt::Call { function } => {
+                edge(edges, &from, &format!("b{}", functions[*function].id),
+                     "call", &format!(", style=dotted, \
+                                       lhead=cluster_func_{}", function));
+            }
+            Stmt::Records { arms } => {
+                for &(tag, function) in arms {
+                    edge(edges, &from,
+                         &format!("b{}", functions[function].id),
+                         &format!("record {:#04x}", tag),
+                         &format!(", style=dotted, lhead=cluster_func_{}",
+                                  function));
+                }
+            }
+            Stmt::Crash { cond, id, class, block: cov } => {
+                *dot += &format!("        c{} [label=\"crash {}\\n{}\", \
+                                  shape=octagon, color=red];\n", id, id,
+                                 class.name());
+                let crash = format!("c{}", id);
+                match *cov {
+                    Some(cov) => {
+                        coverage(dot, cov);
+                        edge(edges, &from, &format!("b{}", cov),
+                             &cond.to_string(), "");
+                        edge(edges, &format!("b{}", cov), &crash, "", "");
+                    }
+                    None => edge(edges, &from, &crash, &cond.to_string(), ""),
+                }
+            }
+            Stmt::Reject { cond, block } => {
+                *dot += &format!("        b{} [label=\"block {}\\nreject\", \
+                                  shape=invhouse];\n", block, block);
+                edge(edges, &from, &format!("b{}", block), &cond.to_string(),
+                     "");
+            }
+            Stmt::Conjunction { parts, id, class, block: cov } => {
+                *dot += &format!("        c{} [label=\"crash {}\\n{}\", \
+                                  shape=octagon, color=red];\n", id, id,
+                                 class.name());
+                let crash = format!("c{}", id);
+                let all = parts.iter().map(|(cond, _)| cond.to_string())
+                    .collect::<Vec<String>>().join(" && ");
+                for (cond, part) in parts {
+                    if let Some(part) = *part {
+                        coverage(dot, part);
+                        edge(edges, &from, &format!("b{}", part),
+                             &cond.to_string(), "");
+                    }
+                }
+                match *cov {
+                    Some(cov) => {
+                        coverage(dot, cov);
+                        edge(edges, &from, &format!("b{}", cov), &all, "");
+                        edge(edges, &format!("b{}", cov), &crash, "", "");
+                    }
+                    None => edge(edges, &from, &crash, &all, ""),
+                }
+            }
+        }
+    }
+}
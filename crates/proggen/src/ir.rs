@@ -0,0 +1,1052 @@
+//! Compact IR of generated programs and its interpreter
+//!
+//! [`Program::to_ir`] flattens a program into a list of [`Node`]s, which
+//! [`Ir::run`] interprets directly, so a program can be run without emitting
+//! Rust and compiling it. Every block is a [`Node::Block`] followed by the
+//! nodes of its statements in order, each statement followed by the blocks
+//! nested in it. Blocks know their size, so the ones not taken are skipped
+//! without walking them.
+//!
+//! [`Ir::encode`] serializes the IR, along with the program's
+//! [`Program::info`] JSON, so one process can load any number of programs
+//! from files with [`Ir::decode`]. Numbers are little endian, and the file
+//! starts with [`MAGIC`] and [`VERSION`].
+
+use std::cell::Cell;
+use std::convert::TryInto;
+
+use crate::{Block, Cond, Operation, Program, Relation, Stmt};
+
+/// Bytes every encoded IR starts with
+pub const MAGIC: &[u8; 4] = b"FTIR";
+
+/// Version of the encoding, bumped whenever it changes
+pub const VERSION: u32 = 1;
+
+/// A node of the IR
+#[derive(Clone, PartialEq, Debug)]
+pub enum Node {
+    /// Record coverage of the block `id`, only by chance if `flaky`, then
+    /// run the `stmts` statements following it. The block and everything
+    /// nested in it are `size` nodes.
+    Block {
+        id:    u32,
+        flaky: bool,
+        stmts: u32,
+        size:  u32,
+    },
+
+    /// Run the following block if `cond` holds, otherwise the one after it
+    /// if there is an `else_body`
+    If {
+        cond:      Cond,
+        else_body: bool,
+    },
+
+    /// Run the following block of the arm whose value in `arms` equals the
+    /// bits of the input byte `byte` selected by `mask`, if any
+    Match {
+        byte: u32,
+        mask: u8,
+        arms: Vec<u8>,
+    },
+
+    /// Run the following block as many times as the bits of the input byte
+    /// `byte` selected by `mask`, shifted down, say
+    Loop {
+        byte: u32,
+        mask: u8,
+    },
+
+    /// Call the function `function`, crashing if it crashes
+    Call {
+        function: u32,
+    },
+
+    /// Mark the join `join` of an enclosing block as reached
+    SetJoin {
+        join: u32,
+    },
+
+    /// Run the following block if the join `join` was reached
+    Join {
+        join: u32,
+    },
+
+    /// Parse the input as records, calling the function of the arm whose tag
+    /// matches, see [`Stmt::Records`]
+    Records {
+        arms: Vec<(u8, u32)>,
+    },
+
+    /// Crash with the ID `id` if `cond` holds, first recording coverage of
+    /// the block `block` if it is set
+    Crash {
+        cond:  Cond,
+        id:    u32,
+        block: Option<u32>,
+    },
+
+    /// Crash with the ID `id` if all of the conditions of `parts` hold, see
+    /// [`Stmt::Conjunction`]
+    Conjunction {
+        parts: Vec<(Cond, Option<u32>)>,
+        id:    u32,
+        block: Option<u32>,
+    },
+
+    /// Return from the function if `cond` holds, first recording coverage
+    /// of the block `block`
+    Reject {
+        cond:  Cond,
+        block: u32,
+    },
+}
+
+/// Why running a block stopped before its end
+enum Exit {
+    /// The input triggered the crash with this ID
+    Crash(u32),
+
+    /// A reject returned from the function the block is in
+    Reject,
+}
+
+/// `result` of running a function, which returns normally on a reject
+fn returned(result: Result<(), Exit>) -> Result<(), Exit> {
+    match result {
+        Err(Exit::Reject) => Ok(()),
+        result            => result,
+    }
+}
+
+/// ID of the crash the `result` of running a function is, if any
+fn crashed(result: Result<(), Exit>) -> Option<u32> {
+    match result {
+        Err(Exit::Crash(id)) => Some(id),
+        _                    => None,
+    }
+}
+
+/// A program as IR
+#[derive(Clone, Debug)]
+pub struct Ir {
+    /// The program's [`Program::info`] JSON
+    pub info: String,
+
+    /// Flaky blocks record their coverage once in this many times
+    pub flaky_hit_chance: u32,
+
+    /// Node of the entry block of the program
+    pub entry: u32,
+
+    /// Nodes of the entry blocks of the program's functions, indexed by
+    /// function number
+    pub functions: Vec<u32>,
+
+    /// Nodes of the entry block and every function
+    pub nodes: Vec<Node>,
+
+    /// Number of joins in the program
+    joins: usize,
+}
+
+impl Program {
+    /// Flatten the program into its IR
+    pub fn to_ir(&self) -> Ir {
+        let mut nodes = Vec::new();
+        let functions = self.functions.iter().map(|function| {
+            let start = nodes.len() as u32;
+            ir_block(&mut nodes, function);
+            start
+        }).collect();
+        let entry = nodes.len() as u32;
+        ir_block(&mut nodes, &self.entry);
+
+        Ir {
+            info:             self.info(),
+            flaky_hit_chance: self.params.flaky_hit_chance as u32,
+            joins:            num_joins(&nodes),
+            entry,
+            functions,
+            nodes,
+        }
+    }
+}
+
+/// Append the nodes of `block` and everything nested in it to `nodes`
+fn ir_block(nodes: &mut Vec<Node>, block: &Block) {
+    let start = nodes.len();
+    nodes.push(Node::Block {
+        id:    block.id as u32,
+        flaky: block.flaky,
+        stmts: block.stmts.len() as u32,
+        size:  0,
+    });
+
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::If { cond, body, else_body } => {
+                nodes.push(Node::If {
+                    cond:      cond.clone(),
+                    else_body: else_body.is_some(),
+                });
+                ir_block(nodes, body);
+                if let Some(else_body) = else_body {
+                    ir_block(nodes, else_body);
+                }
+            }
+            Stmt::Match { byte, mask, arms } => {
+                nodes.push(Node::Match {
+                    byte: *byte as u32,
+                    mask: *mask,
+                    arms: arms.iter().map(|x| x.0).collect(),
+                });
+                for (_, body) in arms {
+                    ir_block(nodes, body);
+                }
+            }
+            Stmt::Loop { byte, mask, body } => {
+                nodes.push(Node::Loop { byte: *byte as u32, mask: *mask });
+                ir_block(nodes, body);
+            }
+            Stmt::Call { function } => {
+                nodes.push(Node::Call { function: *function as u32 });
+            }
+            Stmt::SetJoin { join } => {
+                nodes.push(Node::SetJoin { join: *join as u32 });
+            }
+            Stmt::Join { join, body } => {
+                nodes.push(Node::Join { join: *join as u32 });
+                ir_block(nodes, body);
+            }
+            Stmt::Records { arms } => {
+                nodes.push(Node::Records {
+                    arms: arms.iter()
+                        .map(|&(tag, function)| (tag, function as u32))
+                        .collect(),
+                });
+            }
+            Stmt::Crash { cond, id, block, .. } => {
+                nodes.push(Node::Crash {
+                    cond:  cond.clone(),
+                    id:    *id as u32,
+                    block: block.map(|x| x as u32),
+                });
+            }
+            Stmt::Conjunction { parts, id, block, .. } => {
+                nodes.push(Node::Conjunction {
+                    parts: parts.iter()
+                        .map(|(cond, part)| {
+                            (cond.clone(), part.map(|x| x as u32))
+                        }).collect(),
+                    id:    *id as u32,
+                    block: block.map(|x| x as u32),
+                });
+            }
+            Stmt::Reject { cond, block } => {
+                nodes.push(Node::Reject {
+                    cond:  cond.clone(),
+                    block: *block as u32,
+                });
+            }
+        }
+    }
+
+    let end = (nodes.len() - start) as u32;
+    if let Node::Block { size, .. } = &mut nodes[start] {
+        *size = end;
+    }
+}
+
+/// Number of joins set or checked by `nodes`
+fn num_joins(nodes: &[Node]) -> usize {
+    nodes.iter().filter_map(|node| match *node {
+        Node::SetJoin { join } | Node::Join { join } => {
+            Some(join as usize + 1)
+        }
+        _ => None,
+    }).max().unwrap_or(0)
+}
+
+thread_local! {
+    /// State of the xorshift RNG flaky blocks draw from, like the one of
+    /// the emitted programs
+    static FLAKY: Cell<u64> = const { Cell::new(0x2545f4914f6cdd1d) };
+}
+
+/// Whether a flaky block records its coverage this time
+fn flaky(chance: u32) -> bool {
+    FLAKY.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        x % chance as u64 == 0
+    })
+}
+
+impl Ir {
+    /// Run the program on `input`, invoking `hit` with the ID of every block
+    /// as it is reached, exactly like the `crashme` function of
+    /// [`Program::to_rust`]. Returns the ID of the crash the input
+    /// triggered, if any.
+    pub fn run<F: FnMut(u32)>(&self, input: &[u8], hit: &mut F)
+            -> Option<u32> {
+        let mut joins = vec![false; self.joins];
+        crashed(self.block(self.entry as usize, input, hit, &mut joins))
+    }
+
+    /// Run function `function` of the program on `input` like [`Ir::run`],
+    /// as the `crashme_<n>` functions of [`Program::to_rust`] do for the
+    /// other entry points
+    pub fn run_function<F: FnMut(u32)>(&self, function: usize, input: &[u8],
+                                       hit: &mut F) -> Option<u32> {
+        let mut joins = vec![false; self.joins];
+        crashed(self.block(self.functions[function] as usize, input, hit,
+                           &mut joins))
+    }
+
+    /// Size in nodes of the block at `node`
+    fn size(&self, node: usize) -> usize {
+        match self.nodes[node] {
+            Node::Block { size, .. } => size as usize,
+            _ => unreachable!("node {} is not a block", node),
+        }
+    }
+
+    /// Run the block at `node` on `input`, see [`Ir::run`], failing with
+    /// the crash the input triggered or on a reject. Joins are cleared once
+    /// checked, and those of the rest of the block on a reject, so `joins`
+    /// is clear again after every block which doesn't crash.
+    fn block<F: FnMut(u32)>(&self, node: usize, input: &[u8], hit: &mut F,
+                            joins: &mut [bool]) -> Result<(), Exit> {
+        let Node::Block { id, flaky: is_flaky, stmts, size } =
+            self.nodes[node]
+        else {
+            unreachable!("node {} is not a block", node);
+        };
+        if !is_flaky || flaky(self.flaky_hit_chance) {
+            hit(id);
+        }
+
+        let mut pos = node + 1;
+        let result = self.stmts(&mut pos, stmts, input, hit, joins);
+        if let Err(Exit::Reject) = result {
+            for node in &self.nodes[pos..node + size as usize] {
+                if let Node::Join { join } = *node {
+                    joins[join as usize] = false;
+                }
+            }
+        }
+        result
+    }
+
+    /// Run the `stmts` statements at `pos` of a block, see [`Ir::block`],
+    /// leaving `pos` past the last one run
+    fn stmts<F: FnMut(u32)>(&self, pos: &mut usize, stmts: u32, input: &[u8],
+                            hit: &mut F, joins: &mut [bool])
+            -> Result<(), Exit> {
+        // Bits of an input byte, if it's in the input. The generated
+        // programs never read past the end of the input.
+        let bits = |byte: u32, mask: u8| {
+            input.get(byte as usize).map(|x| x & mask)
+        };
+
+        for _ in 0..stmts {
+            let stmt = *pos;
+            *pos += 1;
+            match &self.nodes[stmt] {
+                Node::If { cond, else_body } => {
+                    let body = *pos;
+                    *pos += self.size(body);
+                    let else_pos = *pos;
+                    if *else_body {
+                        *pos += self.size(else_pos);
+                    }
+
+                    if cond.holds(input) {
+                        self.block(body, input, hit, joins)?;
+                    } else if *else_body {
+                        self.block(else_pos, input, hit, joins)?;
+                    }
+                }
+                Node::Match { byte, mask, arms } => {
+                    let value = bits(*byte, *mask);
+                    let mut taken = None;
+                    for &arm in arms {
+                        if Some(arm) == value && taken.is_none() {
+                            taken = Some(*pos);
+                        }
+                        *pos += self.size(*pos);
+                    }
+                    if let Some(body) = taken {
+                        self.block(body, input, hit, joins)?;
+                    }
+                }
+                Node::Loop { byte, mask } => {
+                    let body = *pos;
+                    *pos += self.size(body);
+                    let count = bits(*byte, *mask).unwrap_or(0) >>
+                        mask.trailing_zeros();
+                    for _ in 0..count {
+                        self.block(body, input, hit, joins)?;
+                    }
+                }
+                Node::Call { function } => {
+                    returned(self.block(self.functions[*function as usize]
+                                        as usize, input, hit, joins))?;
+                }
+                Node::SetJoin { join } => joins[*join as usize] = true,
+                Node::Join { join } => {
+                    let body = *pos;
+                    *pos += self.size(body);
+                    if std::mem::take(&mut joins[*join as usize]) {
+                        self.block(body, input, hit, joins)?;
+                    }
+                }
+                Node::Records { arms } => {
+                    let mut record = 0;
+                    while record + 2 <= input.len() {
+                        let end = (record + 2 + input[record + 1] as usize)
+                            .min(input.len());
+                        let tag = input[record];
+                        if let Some(&(_, function)) =
+                                arms.iter().find(|x| x.0 == tag) {
+                            returned(self.block(
+                                self.functions[function as usize] as usize,
+                                &input[record + 2..end], hit, joins))?;
+                        }
+                        record = end;
+                    }
+                }
+                Node::Crash { cond, id, block } => {
+                    if cond.holds(input) {
+                        if let Some(block) = *block {
+                            hit(block);
+                        }
+                        return Err(Exit::Crash(*id));
+                    }
+                }
+                Node::Conjunction { parts, id, block } => {
+                    let mut held = 0;
+                    for (cond, part) in parts {
+                        if cond.holds(input) {
+                            if let Some(part) = *part {
+                                hit(part);
+                            }
+                            held += 1;
+                        }
+                    }
+                    if held == parts.len() {
+                        if let Some(block) = *block {
+                            hit(block);
+                        }
+                        return Err(Exit::Crash(*id));
+                    }
+                }
+                Node::Reject { cond, block } => {
+                    if cond.holds(input) {
+                        hit(*block);
+                        return Err(Exit::Reject);
+                    }
+                }
+                Node::Block { .. } => {
+                    unreachable!("block {} in place of a statement", stmt);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the IR, see [`Ir::decode`]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        put(&mut out, VERSION);
+        put(&mut out, self.info.len() as u32);
+        out.extend_from_slice(self.info.as_bytes());
+        put(&mut out, self.flaky_hit_chance);
+        put(&mut out, self.entry);
+        put(&mut out, self.functions.len() as u32);
+        for &function in &self.functions {
+            put(&mut out, function);
+        }
+        put(&mut out, self.nodes.len() as u32);
+        for node in &self.nodes {
+            encode_node(&mut out, node);
+        }
+        out
+    }
+
+    /// Deserialize IR encoded with [`Ir::encode`], checking that it is well
+    /// formed so running it can't go out of bounds
+    pub fn decode(bytes: &[u8]) -> Result<Ir, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err("not a fuzztheory IR file".into());
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("IR version {} is not supported, expected {}",
+                               version, VERSION));
+        }
+        let len  = reader.u32()? as usize;
+        let info = String::from_utf8(reader.take(len)?.to_vec())
+            .map_err(|_| "IR metadata is not UTF-8".to_string())?;
+        let flaky_hit_chance = reader.u32()?;
+        let entry = reader.u32()?;
+        let functions = (0..reader.u32()?).map(|_| reader.u32())
+            .collect::<Result<Vec<u32>, String>>()?;
+        let nodes = (0..reader.u32()?).map(|_| decode_node(&mut reader))
+            .collect::<Result<Vec<Node>, String>>()?;
+        if reader.pos != bytes.len() {
+            return Err("trailing bytes after the IR".into());
+        }
+
+        let ir = Ir {
+            joins: num_joins(&nodes),
+            info, flaky_hit_chance, entry, functions, nodes,
+        };
+        ir.check()?;
+        Ok(ir)
+    }
+
+    /// Check that every function and the entry are well formed blocks
+    fn check(&self) -> Result<(), String> {
+        if self.flaky_hit_chance == 0 {
+            return Err("IR has a zero flaky hit chance".into());
+        }
+        for &start in self.functions.iter().chain(Some(&self.entry)) {
+            self.check_block(start as usize)?;
+        }
+        Ok(())
+    }
+
+    /// Check that the node at `node` is a block whose size covers exactly
+    /// its statements and the blocks nested in them
+    fn check_block(&self, node: usize) -> Result<(), String> {
+        let Some(&Node::Block { stmts, size, .. }) = self.nodes.get(node)
+        else {
+            return Err(format!("IR node {} is not a block", node));
+        };
+        let function = |function: u32| {
+            if (function as usize) < self.functions.len() {
+                Ok(())
+            } else {
+                Err(format!("IR node {} calls missing function {}", node,
+                            function))
+            }
+        };
+
+        let mut pos = node + 1;
+        for _ in 0..stmts {
+            let Some(stmt) = self.nodes.get(pos) else {
+                return Err(format!("IR block {} ends early", node));
+            };
+            pos += 1;
+            let blocks = match stmt {
+                Node::If { else_body, .. } => 1 + *else_body as usize,
+                Node::Match { arms, .. } => arms.len(),
+                Node::Loop { .. } | Node::Join { .. } => 1,
+                Node::Call { function: x } => {
+                    function(*x)?;
+                    0
+                }
+                Node::Records { arms } => {
+                    for &(_, x) in arms {
+                        function(x)?;
+                    }
+                    0
+                }
+                Node::SetJoin { .. } | Node::Crash { .. } |
+                    Node::Conjunction { .. } | Node::Reject { .. } => 0,
+                Node::Block { .. } => {
+                    return Err(format!("IR node {} is a block in place of \
+                                        a statement", pos - 1));
+                }
+            };
+            for _ in 0..blocks {
+                self.check_block(pos)?;
+                pos += self.size(pos);
+            }
+        }
+
+        if pos - node != size as usize {
+            return Err(format!("IR block {} has the wrong size", node));
+        }
+        Ok(())
+    }
+}
+
+/// Append `x` to `out`
+fn put(out: &mut Vec<u8>, x: u32) {
+    out.extend_from_slice(&x.to_le_bytes());
+}
+
+/// Append the optional block `block` to `out`, with `u32::MAX` for none
+fn put_block(out: &mut Vec<u8>, block: Option<u32>) {
+    put(out, block.unwrap_or(u32::MAX));
+}
+
+/// Append `cond` to `out`, as its kind and the fields of its variant
+fn encode_cond(out: &mut Vec<u8>, cond: &Cond) {
+    match *cond {
+        Cond::Bits { byte, mask, target } => {
+            out.push(0);
+            put(out, byte as u32);
+            out.extend_from_slice(&[mask, target]);
+        }
+        Cond::Magic { byte, size, value } => {
+            out.push(1);
+            put(out, byte as u32);
+            out.push(size as u8);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        Cond::Span { byte, mask, target } => {
+            out.push(8);
+            put(out, byte as u32);
+            out.extend_from_slice(&mask.to_le_bytes());
+            out.extend_from_slice(&target.to_le_bytes());
+        }
+        Cond::Range { byte, size, low, high } => {
+            out.push(7);
+            put(out, byte as u32);
+            out.push(size as u8);
+            out.extend_from_slice(&low.to_le_bytes());
+            out.extend_from_slice(&high.to_le_bytes());
+        }
+        Cond::Compare { lhs, rhs, relation } => {
+            out.push(2);
+            put(out, lhs as u32);
+            put(out, rhs as u32);
+            out.push(match relation {
+                Relation::Equal => 0,
+                Relation::Less  => 1,
+            });
+        }
+        Cond::Checksum { start, len, byte } => {
+            out.push(3);
+            put(out, start as u32);
+            put(out, len as u32);
+            put(out, byte as u32);
+        }
+        Cond::Length { len } => {
+            out.push(4);
+            put(out, len as u32);
+        }
+        Cond::Field { field, offset, mask, target } => {
+            out.push(5);
+            put(out, field as u32);
+            put(out, offset as u32);
+            out.extend_from_slice(&[mask, target]);
+        }
+        Cond::Derived { var, base, ref terms, mask, target } => {
+            out.push(6);
+            put(out, var as u32);
+            put(out, base as u32);
+            put(out, terms.len() as u32);
+            for &(op, byte, shift) in terms {
+                out.push(match op {
+                    Operation::Add => 0,
+                    Operation::Sub => 1,
+                    Operation::Xor => 2,
+                });
+                put(out, byte as u32);
+                out.push(shift as u8);
+            }
+            out.extend_from_slice(&[mask, target]);
+        }
+    }
+}
+
+/// Encode conditions which must hold or fail, each as a byte which is 1 if
+/// it must hold followed by the condition encoded like in the IR. Program
+/// metadata lists the guards of blocks this way, see [`decode_guards`].
+pub fn encode_guards(guards: &[(Cond, bool)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (cond, holds) in guards {
+        out.push(*holds as u8);
+        encode_cond(&mut out, cond);
+    }
+    out
+}
+
+/// Decode conditions encoded with [`encode_guards`]
+pub fn decode_guards(bytes: &[u8]) -> Result<Vec<(Cond, bool)>, String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut guards = Vec::new();
+    while reader.pos != bytes.len() {
+        let holds = reader.bool()?;
+        guards.push((decode_cond(&mut reader)?, holds));
+    }
+    Ok(guards)
+}
+
+/// Append `node` to `out`, as its kind and the fields of its variant
+fn encode_node(out: &mut Vec<u8>, node: &Node) {
+    match node {
+        Node::Block { id, flaky, stmts, size } => {
+            out.push(0);
+            put(out, *id);
+            out.push(*flaky as u8);
+            put(out, *stmts);
+            put(out, *size);
+        }
+        Node::If { cond, else_body } => {
+            out.push(1);
+            encode_cond(out, cond);
+            out.push(*else_body as u8);
+        }
+        Node::Match { byte, mask, arms } => {
+            out.push(2);
+            put(out, *byte);
+            out.push(*mask);
+            put(out, arms.len() as u32);
+            out.extend_from_slice(arms);
+        }
+        Node::Loop { byte, mask } => {
+            out.push(3);
+            put(out, *byte);
+            out.push(*mask);
+        }
+        Node::Call { function } => {
+            out.push(4);
+            put(out, *function);
+        }
+        Node::SetJoin { join } => {
+            out.push(5);
+            put(out, *join);
+        }
+        Node::Join { join } => {
+            out.push(6);
+            put(out, *join);
+        }
+        Node::Records { arms } => {
+            out.push(7);
+            put(out, arms.len() as u32);
+            for &(tag, function) in arms {
+                out.push(tag);
+                put(out, function);
+            }
+        }
+        Node::Crash { cond, id, block } => {
+            out.push(8);
+            encode_cond(out, cond);
+            put(out, *id);
+            put_block(out, *block);
+        }
+        Node::Conjunction { parts, id, block } => {
+            out.push(9);
+            put(out, parts.len() as u32);
+            for (cond, part) in parts {
+                encode_cond(out, cond);
+                put_block(out, *part);
+            }
+            put(out, *id);
+            put_block(out, *block);
+        }
+        Node::Reject { cond, block } => {
+            out.push(10);
+            encode_cond(out, cond);
+            put(out, *block);
+        }
+    }
+}
+
+/// Cursor over an encoded IR
+struct Reader<'a> {
+    /// The encoded IR
+    bytes: &'a [u8],
+
+    /// Offset of the next byte to read
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Read the next `len` bytes
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.bytes.get(self.pos..self.pos.saturating_add(len))
+            .ok_or_else(|| "IR ends early".to_string())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Read a byte
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read a boolean byte
+    fn bool(&mut self) -> Result<bool, String> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            x => Err(format!("invalid boolean {} in IR", x)),
+        }
+    }
+
+    /// Read a little endian `u32`
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Read a little endian `u32` as a `usize`
+    fn usize(&mut self) -> Result<usize, String> {
+        Ok(self.u32()? as usize)
+    }
+
+    /// Read an optional block, see [`put_block`]
+    fn block(&mut self) -> Result<Option<u32>, String> {
+        Ok(Some(self.u32()?).filter(|&x| x != u32::MAX))
+    }
+}
+
+/// Read a condition, see [`encode_cond`]
+fn decode_cond(reader: &mut Reader) -> Result<Cond, String> {
+    Ok(match reader.u8()? {
+        0 => Cond::Bits {
+            byte:   reader.usize()?,
+            mask:   reader.u8()?,
+            target: reader.u8()?,
+        },
+        1 => {
+            let byte = reader.usize()?;
+            let size = reader.u8()? as usize;
+            if !(1..=8).contains(&size) {
+                return Err(format!("invalid magic value size {} in IR",
+                                   size));
+            }
+            let value = u64::from_le_bytes(reader.take(8)?.try_into()
+                                           .unwrap());
+            Cond::Magic { byte, size, value }
+        }
+        2 => Cond::Compare {
+            lhs:      reader.usize()?,
+            rhs:      reader.usize()?,
+            relation: match reader.u8()? {
+                0 => Relation::Equal,
+                1 => Relation::Less,
+                x => return Err(format!("invalid relation {} in IR", x)),
+            },
+        },
+        3 => Cond::Checksum {
+            start: reader.usize()?,
+            len:   reader.usize()?,
+            byte:  reader.usize()?,
+        },
+        4 => Cond::Length { len: reader.usize()? },
+        5 => Cond::Field {
+            field:  reader.usize()?,
+            offset: reader.usize()?,
+            mask:   reader.u8()?,
+            target: reader.u8()?,
+        },
+        6 => {
+            let var  = reader.usize()?;
+            let base = reader.usize()?;
+            let mut terms = Vec::new();
+            for _ in 0..reader.u32()? {
+                let op = match reader.u8()? {
+                    0 => Operation::Add,
+                    1 => Operation::Sub,
+                    2 => Operation::Xor,
+                    x => return Err(format!("invalid operation {} in IR",
+                                            x)),
+                };
+                let byte  = reader.usize()?;
+                let shift = reader.u8()? as u32;
+                if shift > 7 {
+                    return Err(format!("invalid shift {} in IR", shift));
+                }
+                terms.push((op, byte, shift));
+            }
+            Cond::Derived {
+                var,
+                base,
+                terms,
+                mask:   reader.u8()?,
+                target: reader.u8()?,
+            }
+        }
+        7 => {
+            let byte = reader.usize()?;
+            let size = reader.u8()? as usize;
+            if !(1..=4).contains(&size) {
+                return Err(format!("invalid range size {} in IR", size));
+            }
+            let low  = u64::from_le_bytes(reader.take(8)?.try_into()
+                                          .unwrap());
+            let high = u64::from_le_bytes(reader.take(8)?.try_into()
+                                          .unwrap());
+            Cond::Range { byte, size, low, high }
+        }
+        8 => Cond::Span {
+            byte:   reader.usize()?,
+            mask:   u16::from_le_bytes(reader.take(2)?.try_into().unwrap()),
+            target: u16::from_le_bytes(reader.take(2)?.try_into().unwrap()),
+        },
+        x => return Err(format!("invalid condition kind {} in IR", x)),
+    })
+}
+
+/// Read a node, see [`encode_node`]
+fn decode_node(reader: &mut Reader) -> Result<Node, String> {
+    Ok(match reader.u8()? {
+        0 => Node::Block {
+            id:    reader.u32()?,
+            flaky: reader.bool()?,
+            stmts: reader.u32()?,
+            size:  reader.u32()?,
+        },
+        1 => Node::If {
+            cond:      decode_cond(reader)?,
+            else_body: reader.bool()?,
+        },
+        2 => {
+            let byte = reader.u32()?;
+            let mask = reader.u8()?;
+            let len  = reader.usize()?;
+            Node::Match { byte, mask, arms: reader.take(len)?.to_vec() }
+        }
+        3 => Node::Loop { byte: reader.u32()?, mask: reader.u8()? },
+        4 => Node::Call { function: reader.u32()? },
+        5 => Node::SetJoin { join: reader.u32()? },
+        6 => Node::Join { join: reader.u32()? },
+        7 => Node::Records {
+            arms: (0..reader.u32()?).map(|_| {
+                Ok((reader.u8()?, reader.u32()?))
+            }).collect::<Result<_, String>>()?,
+        },
+        8 => Node::Crash {
+            cond:  decode_cond(reader)?,
+            id:    reader.u32()?,
+            block: reader.block()?,
+        },
+        9 => Node::Conjunction {
+            parts: (0..reader.u32()?).map(|_| {
+                Ok((decode_cond(reader)?, reader.block()?))
+            }).collect::<Result<_, String>>()?,
+            id:    reader.u32()?,
+            block: reader.block()?,
+        },
+        10 => Node::Reject {
+            cond:  decode_cond(reader)?,
+            block: reader.u32()?,
+        },
+        x => return Err(format!("invalid node kind {} in IR", x)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::solve::Goal;
+    use crate::{generate, Params};
+
+    /// A small program mixing the kinds of conditions and statements
+    fn program() -> Program {
+        generate(&Params {
+            max_input_size_bits: 512,
+            min_blocks:          200,
+            else_chance:         4,
+            match_chance:        8,
+            magic_chance:        8,
+            compare_chance:      8,
+            checksum_chance:     16,
+            ..Params::default()
+        })
+    }
+
+    #[test]
+    fn decoded_ir_runs_like_the_program() {
+        let program = program();
+        let ir = program.to_ir();
+        let decoded = Ir::decode(&ir.encode()).unwrap();
+        assert_eq!(decoded.info, ir.info);
+        assert_eq!(decoded.nodes, ir.nodes);
+
+        // Random inputs barely get past the entry, so also run the inputs
+        // the solver builds for every leaf, which must reach it
+        let mut rng = Rng::new(1);
+        let mut inputs: Vec<(Option<usize>, Vec<u8>)> = (0..64).map(|_| {
+            (None, (0..program.input_bytes).map(|_| rng.rand() as u8)
+                .collect())
+        }).collect();
+        for solution in program.solve() {
+            if let (Goal::Block(id), Some(input)) =
+                    (solution.goal, solution.input) {
+                inputs.push((Some(id), input));
+            }
+        }
+        assert!(inputs.len() > 64);
+
+        for (goal, input) in &inputs {
+            let mut trace = Vec::new();
+            assert_eq!(ir.run(input, &mut |x| trace.push(x)), None);
+            let mut decoded_trace = Vec::new();
+            decoded.run(input, &mut |x| decoded_trace.push(x));
+            assert_eq!(decoded_trace, trace);
+            if let Some(id) = *goal {
+                assert!(trace.contains(&(id as u32)), "block {}", id);
+            }
+        }
+    }
+
+    /// Blocks of `program` which `input` reaches according to the guards
+    /// of every block, independently of the IR
+    fn expected(program: &Program, input: &[u8]) -> Vec<bool> {
+        let guards = program.block_guards();
+        let mut reached: Vec<Option<bool>> = vec![None; guards.len()];
+        fn reach(id: usize, guards: &[(usize, Vec<(Cond, bool)>)],
+                 input: &[u8], reached: &mut [Option<bool>]) -> bool {
+            if let Some(x) = reached[id] {
+                return x;
+            }
+            let (parent, conds) = &guards[id];
+            let x = (*parent == id ||
+                     reach(*parent, guards, input, reached)) &&
+                conds.iter().all(|(cond, holds)| cond.holds(input) == *holds);
+            reached[id] = Some(x);
+            x
+        }
+        (0..guards.len())
+            .map(|id| reach(id, &guards, input, &mut reached)).collect()
+    }
+
+    #[test]
+    fn guards_predict_the_blocks_reached() {
+        let program = program();
+        let ir = program.to_ir();
+        let mut rng = Rng::new(2);
+        let mut inputs: Vec<Vec<u8>> = (0..64).map(|_| {
+            (0..program.input_bytes).map(|_| rng.rand() as u8).collect()
+        }).collect();
+        inputs.extend(program.solve().into_iter().filter_map(|x| x.input));
+
+        for input in &inputs {
+            let mut reached = vec![false; program.num_blocks];
+            ir.run(input, &mut |x| reached[x as usize] = true);
+            assert_eq!(reached, expected(&program, input));
+        }
+    }
+
+    #[test]
+    fn guards_round_trip() {
+        let program = program();
+        for (_, guards) in program.block_guards() {
+            assert_eq!(decode_guards(&encode_guards(&guards)).unwrap(),
+                       guards);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_truncated_ir() {
+        let encoded = program().to_ir().encode();
+        assert!(Ir::decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(Ir::decode(b"ELF\x7f").is_err());
+    }
+}
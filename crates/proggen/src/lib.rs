@@ -0,0 +1,3410 @@
+//! Synthetic program generation
+//!
+//! Programs are generated as a tree of blocks, each of which records a unique
+//! coverage event when it is reached. Every nested block is guarded by a
+//! condition on the input: a slice of bits, a multi-byte magic value, a range
+//! of values of an input integer, a comparison of two input bytes, a checksum
+//! over a range of input bytes, or bits of a value derived from several input
+//! bytes through a chain of locals. Each bit of the input is used by at most
+//! one condition, unless bits are deliberately reused to correlate conditions.
+//!
+//! Blocks may have an else block taken when their condition fails, and matches
+//! on a slice of bits dispatch to one of several blocks. Loops run a block as
+//! many times as a slice of bits says, recording its coverage event on every
+//! iteration. Join blocks reached from any of several if statements turn the
+//! tree into a graph with converging paths.
+//!
+//! Some conditions guard a crash rather than a block, optionally without any
+//! coverage event of their own, or only together with several sibling
+//! conditions. Every crash has a class like an out of bounds write.
+//!
+//! Flaky blocks only record their coverage event by chance, like the
+//! nondeterministic coverage of real targets. Integrity gates wrap subtrees in
+//! a checksum over the whole input, like file format checksums and
+//! anti-fuzzing checks.
+//!
+//! The tree can be split into a chain of stages, each nested in the last, to
+//! model parsers which must get through every stage in order. Programs can
+//! take inputs of any length, checking the length of the input before using
+//! bytes past it, and checking bits past records whose length is read from a
+//! length field. Instead of a single tree, the program can be a loop parsing
+//! the input as a sequence of records, dispatching on the tag of each to a
+//! handler with a subtree of its own. Rather than at random, the tree can also
+//! be shaped after the control flow graph of a real program, see [`cfg`].
+//!
+//! The tree can then be emitted as Rust source which is compiled into a
+//! harness binary by the `fuzztheory-target` crate.
+
+use std::fmt;
+use std::ops::Range;
+use std::collections::{BTreeSet, HashSet};
+
+pub mod c;
+pub mod cfg;
+pub mod decisions;
+pub mod dot;
+pub mod ir;
+pub mod log;
+pub mod patch;
+pub mod probability;
+pub mod rng;
+pub mod smt;
+pub mod solve;
+pub mod stats;
+pub mod wasm;
+
+use decisions::Draws;
+use rng::Rng;
+
+/// Default seed used for program generation
+pub const SEED: usize = 0x2f7151ffd59720b3;
+
+/// Parameters controlling the shape of generated programs
+///
+/// All the chances are "one in <val>" chance figures.
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// Seed of the RNG making all random decisions, the same seed and
+    /// parameters always generate the same program
+    pub seed: usize,
+
+    /// Maximum size of the input file in bits. This means bit indicies which
+    /// are used for the input of the program always are in a range of
+    /// [0, max_input_size_bits).
+    pub max_input_size_bits: usize,
+
+    /// Chance of generating an if statement
+    pub if_chance: usize,
+
+    /// Chance of ending the current if statement (ending the block)
+    pub end_block_chance: usize,
+
+    /// Chance of ending the program generation, finishing all unfinished
+    /// blocks unconditionally.
+    /// This is effectively what limits the size of the program (and the
+    /// `max_input_size_bits`)
+    pub done_chance: usize,
+
+    /// Minimum number of blocks to generate (exiting the loop will not occur
+    /// until at least this many blocks are generated).
+    pub min_blocks: u64,
+
+    /// Maximum number of blocks to generate. Generation stops once there
+    /// are this many, and the blocks closing it off may go past it, so
+    /// programs are checked against it too, see [`generate_accepted`]. Zero
+    /// doesn't limit the blocks.
+    pub max_blocks: u64,
+
+    /// Number of blocks to aim for, replacing `min_blocks` and the chance
+    /// of stopping with `done_chance`. Generation stops as soon as there
+    /// are this many, and programs are checked to be within
+    /// `target_tolerance` of it, see [`generate_accepted`]. Zero leaves the
+    /// size to `min_blocks` and `done_chance`.
+    pub target_blocks: u64,
+
+    /// Fraction of `target_blocks` the number of blocks may be off by
+    pub target_tolerance: f64,
+
+    /// Chance of a generated if statement guarding a crash instead of a
+    /// block. Zero never generates crashes.
+    pub crash_chance: usize,
+
+    /// Chance of a crash not recording a coverage event when it is reached,
+    /// so coverage feedback can't tell the fuzzer it is close. Zero gives
+    /// every crash a coverage event.
+    pub non_coverage_crash_chance: usize,
+
+    /// Fraction of the crashes recording no coverage event, kept to as
+    /// crashes are generated rather than drawn with
+    /// `non_coverage_crash_chance`. Zero uses the chance instead.
+    pub non_coverage_crash_fraction: f64,
+
+    /// Target distribution of the depths of crashes without a coverage
+    /// event, as the relative weight of depths 1, 2, and so on, like
+    /// `crash_depths`. Crashes at depths which already have their share are
+    /// given a coverage event instead. Empty allows any depth.
+    pub non_coverage_crash_depths: Vec<usize>,
+
+    /// Minimum number of crashes to generate, like `min_blocks`
+    pub min_crashes: usize,
+
+    /// Maximum number of crashes to generate, no more conditions guard a
+    /// crash once there are this many. Zero doesn't limit the crashes.
+    pub max_crashes: usize,
+
+    /// Minimum nesting depth of the deepest block in its function, which
+    /// generation doesn't aim for, but programs are checked against with
+    /// `min_blocks` and `min_crashes`, see [`generate_accepted`]. Zero
+    /// accepts any depth.
+    pub min_depth: usize,
+
+    /// Mean nesting depth of blocks in their function to aim for. Blocks
+    /// are closed with a chance growing with their depth, so that they are
+    /// closed as often as new ones are opened at this depth, rather than
+    /// with `end_block_chance`. Functions outlined from nested blocks start
+    /// over at the top, and matches with many arms hold on to their depth,
+    /// so both end up off the target. Zero leaves the depth to the chances.
+    pub target_depth: f64,
+
+    /// Maximum nesting depth of blocks in their function. No if statements,
+    /// matches, or loops are opened in blocks this deep, though checks of
+    /// the input length can still wrap their statements. Zero doesn't limit
+    /// the depth.
+    pub max_depth: usize,
+
+    /// Number of times a program falling short of `min_blocks`,
+    /// `min_crashes`, or `min_depth`, as generation does when it runs out of
+    /// free input bits, or missing `max_blocks` or `target_blocks`, is
+    /// generated again from a new seed derived from
+    /// `seed`, see [`generate_accepted`]. Generation fails if the last try
+    /// falls short too. Zero keeps the first program, reporting if it falls
+    /// short.
+    pub max_retries: usize,
+
+    /// Target distribution of crash depths, as the relative weight of
+    /// depths 1, 2, and so on. Crashes are only generated at depths which
+    /// are below their share of the crashes so far, and never at depths
+    /// past the end. Empty generates crashes at any depth.
+    pub crash_depths: Vec<usize>,
+
+    /// Distribution of the classes of crashes, as the relative weight of
+    /// every class in [`CrashClass::ALL`]. Empty picks uniformly.
+    pub crash_classes: Vec<usize>,
+
+    /// Chance of a crash being another site of the root cause of an earlier
+    /// crash, checking the same condition elsewhere in the program and of
+    /// the same class, like a bad length overflowing several buffers. The
+    /// ground truth groups the crashes by root cause. Zero gives every crash
+    /// a root cause of its own.
+    pub root_cause_chance: usize,
+
+    /// Chance of a crash requiring a conjunction of `conjunction_size`
+    /// sibling conditions to all hold, rather than a single condition. Zero
+    /// never generates conjunctions.
+    pub conjunction_chance: usize,
+
+    /// Number of conditions in a crash conjunction, at least 2
+    pub conjunction_size: usize,
+
+    /// Chance of a generated if statement guarding a reject instead of a
+    /// block, which records coverage of a block of its own and returns from
+    /// the function without crashing, like a parser bailing out on invalid
+    /// input. Everything after it in the function is only reached if its
+    /// condition fails. Zero never generates rejects.
+    pub reject_chance: usize,
+
+    /// Chance of a block getting an else block when it is closed. Zero
+    /// never generates else blocks.
+    pub else_chance: usize,
+
+    /// Chance to generate a match on a slice of input bits, with each arm
+    /// opening a block. Zero never generates matches.
+    pub match_chance: usize,
+
+    /// Chance to generate a loop opening a block which runs as many times as
+    /// a slice of up to 4 input bits says. Zero never generates loops.
+    pub loop_chance: usize,
+
+    /// Chance of a generated if statement comparing a 16, 32, or 64-bit
+    /// magic value in the input rather than a slice of bits. Zero never
+    /// generates magic values.
+    pub magic_chance: usize,
+
+    /// Chance of a generated if statement comparing two input bytes with
+    /// each other rather than a slice of bits with a constant. Zero never
+    /// generates comparisons.
+    pub compare_chance: usize,
+
+    /// Chance of a generated if statement checking that an 8, 16, or 32-bit
+    /// integer in the input is below, above, or within a range of values
+    /// rather than comparing a slice of bits. The range is about as likely
+    /// to hold as the slice would be, give or take a factor of two. Zero
+    /// never generates ranges.
+    pub range_chance: usize,
+
+    /// Chance of a generated if statement at a depth of at least
+    /// `checksum_min_depth` checking that a byte of the input is the sum of
+    /// a range of 2 to 16 other input bytes. Zero never generates checksums.
+    pub checksum_chance: usize,
+
+    /// Minimum nesting depth of checksum conditions, so they can be made to
+    /// only guard deep subtrees
+    pub checksum_min_depth: usize,
+
+    /// Chance of a generated if statement being an integrity gate around its
+    /// block, checking that the last byte of the input is the wrapping sum
+    /// of every byte before it, like the checksum of a whole file or an
+    /// anti-fuzzing check. The last byte is reserved for it, and gates are
+    /// not nested in each other, as the inner one would always hold. Zero
+    /// never generates gates.
+    pub integrity_chance: usize,
+
+    /// Chance of a generated if statement checking bits of a value derived
+    /// from 2 to 4 whole input bytes, combined by wrapping adds,
+    /// subtractions, and xors of shifted bytes, rather than a slice of bits
+    /// of a single byte. The value is computed in a chain of locals which
+    /// the condition tests, so the input only reaches the branch through
+    /// dataflow, as taint tracking has to follow. Zero never generates
+    /// derived values.
+    pub derived_chance: usize,
+
+    /// Number of sequential stages to generate the program as. Every stage
+    /// is a subtree of about `min_blocks / stages` blocks, ending with the
+    /// condition guarding the next stage, so a stage is only reached once
+    /// all of the stages before it are satisfied. Zero or one generates a
+    /// single tree.
+    pub stages: usize,
+
+    /// Number of input bits checked by the condition guarding every stage
+    /// after the first, rather than a number drawn from `condition_bits`.
+    /// More than 8 bits is a magic value of whole bytes, so with two stages
+    /// and 32 bits a random input reaches the second half of the program
+    /// with a chance of 2^-32, and coverage plateaus until one does. Zero
+    /// draws the bits like other conditions.
+    pub plateau_bits: usize,
+
+    /// Number of record types to generate the program as a parsing loop of.
+    /// The entry block loops over the input as a sequence of records, each a
+    /// tag byte, a length byte, and that many bytes of body, calling the
+    /// handler of the tag with the body as its input. Every handler is a
+    /// function with a subtree of about `min_blocks / record_tags` blocks.
+    /// Needs `variable_length`, as bodies can be of any length. Zero
+    /// generates a single tree.
+    pub record_tags: usize,
+
+    /// Chance of the contents of an if statement's block being moved into a
+    /// new function when the block is closed, leaving a call in its place.
+    /// Zero keeps the whole program in one function.
+    pub function_chance: usize,
+
+    /// Chance to generate another call to an already generated function, so
+    /// functions can be reached from several call sites. Zero never does.
+    pub call_chance: usize,
+
+    /// Chance of each if statement's block, and else block, becoming a
+    /// source of a join block after the if statements of a block. A join
+    /// block is reached if any of its sources were, so it has several paths
+    /// to it. Joins need at least two sources. Zero never generates joins.
+    pub join_chance: usize,
+
+    /// Chance of a generated if statement reusing the bits and target of an
+    /// earlier condition on a slice of bits, rather than unused bits, so
+    /// satisfying one satisfies the other too. Conditions the path to the
+    /// new block already decides are not reused, as one of their blocks
+    /// would be unreachable. Zero never reuses bits.
+    pub reuse_chance: usize,
+
+    /// Distribution of the number of bits checked by conditions on slices of
+    /// bits, as the relative weight of 1 to 8 bits. Empty picks uniformly.
+    pub condition_bits: Vec<usize>,
+
+    /// Let conditions on slices of bits span the boundary between two input
+    /// bytes, checking the bits selected by a mask of a 16-bit value rather
+    /// than of a single byte
+    pub span_slices: bool,
+
+    /// Fraction of the input bytes kept dead, picked at random before
+    /// generating and never checked by any condition, so mutations landing
+    /// in them change nothing. Bytes the program happens not to use are
+    /// dead too, see [`stats::Stats::dead_fraction`]. Zero reserves no bytes.
+    pub dead_fraction: f64,
+
+    /// Size in bytes of the config buffer of programs taking their input as
+    /// two independent buffers, like harnesses taking options and a
+    /// document. The config buffer is at the start of the input and the
+    /// data buffer is the rest of it. Comparisons of two input bytes, see
+    /// `compare_chance`, compare a byte of the config buffer with one of
+    /// the data buffer, so the buffers constrain each other. Zero takes the
+    /// input as a single buffer.
+    pub config_bytes: usize,
+
+    /// Exponent `a` of the power law the chances of a random input reaching
+    /// blocks are shaped toward. Each branch on a slice of bits draws a
+    /// rarity for the block it guards, at most `x` with a chance of `x^a`,
+    /// opens it in the deepest open block common enough to leave it at
+    /// least one bit, and checks as many bits as it takes to reach the
+    /// drawn rarity. A few blocks are then extremely rare and most are
+    /// common, like in real programs. Smaller exponents make rare blocks
+    /// more common. Zero picks the bits from `condition_bits` instead and
+    /// leaves the nesting alone.
+    pub rarity_exponent: f64,
+
+    /// Chance of a block being flaky, recording its coverage event only with
+    /// a one in `flaky_hit_chance` chance every time it is reached, like
+    /// the nondeterministic coverage of real targets. Zero never generates
+    /// flaky blocks.
+    pub flaky_chance: usize,
+
+    /// Chance of a flaky block recording its coverage event when reached
+    pub flaky_hit_chance: usize,
+
+    /// Generate programs taking inputs of any length up to the input size,
+    /// rather than exactly the input size. Statements using bytes past the
+    /// length checked by the blocks they are in are put in a new block
+    /// checking `_input.len()` first.
+    pub variable_length: bool,
+
+    /// Chance of a generated if statement checking bits at an offset past
+    /// the end of a record whose length is read from an input byte, rather
+    /// than at a fixed offset. Needs `variable_length`. Zero never does.
+    pub length_field_chance: usize,
+
+    /// Maximum number of bit allocation failures until we finally give up.
+    ///
+    /// Allowing failures effectively makes deeper branches less complex,
+    /// which typically will make the graph not very realistic to a real
+    /// program as it can go exponential as subsequent branches are easier to
+    /// solve.
+    pub max_alloc_failures: usize,
+
+    /// Number of branches added, removed, or changed by each patch of
+    /// [`Program::patch`]
+    pub patches: usize,
+
+    /// Number of modules the program is partitioned into, like a binary
+    /// and the shared libraries it loads, each with a coverage array of its
+    /// own. The entry is in module 0, the main binary, and every function is
+    /// put in a random module. One keeps the whole program in one module.
+    pub modules: usize,
+
+    /// Number of entry points of the program, like the several fuzzable
+    /// APIs of a library. Besides `crashme`, randomly picked functions are
+    /// exported as `crashme_1`, `crashme_2`, and so on. Their trees overlap
+    /// where they call the same functions, and `crashme` may call them too.
+    /// There are fewer if the program has fewer functions. Needs
+    /// `function_chance`, and one keeps the single entry point.
+    pub entry_points: usize,
+
+    /// Control flow graph to take the shape of the program from, the path
+    /// of a DOT or JSON description of it, see [`cfg`]. The program then
+    /// has a block for every block of the graph instead of a random tree,
+    /// the limits on the number of blocks don't apply, and of the other
+    /// parameters only the input size, `condition_bits`, and those of
+    /// crashes other than their depths and conjunctions are used. Empty
+    /// generates the shape at random.
+    pub cfg: String,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            seed:                SEED,
+            max_input_size_bits: 1024,
+            if_chance:           4,
+            end_block_chance:    4,
+            done_chance:         128,
+            min_blocks:          5000,
+            max_blocks:          0,
+            target_blocks:       0,
+            target_tolerance:    0.05,
+            crash_chance:        0,
+            non_coverage_crash_chance: 0,
+            non_coverage_crash_fraction: 0.,
+            non_coverage_crash_depths: Vec::new(),
+            min_crashes:         0,
+            max_crashes:         0,
+            min_depth:           0,
+            target_depth:        0.,
+            max_depth:           0,
+            max_retries:         0,
+            crash_depths:        Vec::new(),
+            crash_classes:       Vec::new(),
+            root_cause_chance:   0,
+            conjunction_chance:  0,
+            conjunction_size:    2,
+            reject_chance:       0,
+            else_chance:         0,
+            match_chance:        0,
+            loop_chance:         0,
+            magic_chance:        0,
+            compare_chance:      0,
+            range_chance:        0,
+            checksum_chance:     0,
+            checksum_min_depth:  0,
+            integrity_chance:    0,
+            derived_chance:      0,
+            stages:              0,
+            plateau_bits:        0,
+            record_tags:         0,
+            function_chance:     0,
+            call_chance:         0,
+            join_chance:         0,
+            reuse_chance:        0,
+            condition_bits:      Vec::new(),
+            span_slices:         false,
+            dead_fraction:       0.,
+            config_bytes:        0,
+            rarity_exponent:     0.,
+            flaky_chance:        0,
+            flaky_hit_chance:    2,
+            variable_length:     false,
+            length_field_chance: 0,
+            max_alloc_failures:  1,
+            patches:             4,
+            modules:             1,
+            entry_points:        1,
+            cfg:                 String::new(),
+        }
+    }
+}
+
+impl Params {
+    /// Whether conditions can check the same input bits, and so contradict
+    /// each other: reused conditions, and ones past length fields, which
+    /// can pick any byte after the field
+    pub fn conditions_overlap(&self) -> bool {
+        self.reuse_chance != 0 || self.length_field_chance != 0
+    }
+}
+
+/// A condition on the input
+#[derive(Clone, PartialEq, Debug)]
+pub enum Cond {
+    /// The bits of the input byte `byte` selected by `mask` are equal to
+    /// `target`
+    Bits {
+        byte:   usize,
+        mask:   u8,
+        target: u8,
+    },
+
+    /// The `size` bytes of the input starting at `byte`, as a little endian
+    /// integer, are equal to `value`
+    Magic {
+        byte:  usize,
+        size:  usize,
+        value: u64,
+    },
+
+    /// The bits selected by `mask` of the 2 bytes of the input starting at
+    /// `byte`, as a little endian integer, are equal to `target`
+    Span {
+        byte:   usize,
+        mask:   u16,
+        target: u16,
+    },
+
+    /// The `size` bytes of the input starting at `byte`, as a little endian
+    /// integer, are at least `low` and at most `high`
+    Range {
+        byte: usize,
+        size: usize,
+        low:  u64,
+        high: u64,
+    },
+
+    /// The input bytes `lhs` and `rhs` are in the relation `relation`
+    Compare {
+        lhs:      usize,
+        rhs:      usize,
+        relation: Relation,
+    },
+
+    /// The wrapping sum of the `len` input bytes starting at `start` is equal
+    /// to the input byte `byte`
+    Checksum {
+        start: usize,
+        len:   usize,
+        byte:  usize,
+    },
+
+    /// The input is at least `len` bytes long
+    Length {
+        len: usize,
+    },
+
+    /// The bits selected by `mask` of the input byte `offset` bytes past the
+    /// end of the record following the length field `field` are equal to
+    /// `target`. The record is as long as the value of the field, and the
+    /// condition fails if the input ends before the byte.
+    Field {
+        field:  usize,
+        offset: usize,
+        mask:   u8,
+        target: u8,
+    },
+
+    /// The bits selected by `mask` of a value derived from the input are
+    /// equal to `target`. The value is the input byte `base` combined with
+    /// every term in turn, each an operation, an input byte, and how far the
+    /// byte is shifted left first, all wrapping at 8 bits. It is computed in
+    /// a chain of locals named after `var` before the condition tests it.
+    Derived {
+        var:    usize,
+        base:   usize,
+        terms:  Vec<(Operation, usize, u32)>,
+        mask:   u8,
+        target: u8,
+    },
+}
+
+/// An operation combining an input byte into a derived value, see
+/// [`Cond::Derived`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Operation {
+    /// Wrapping addition
+    Add,
+
+    /// Wrapping subtraction
+    Sub,
+
+    /// Exclusive or
+    Xor,
+}
+
+impl Operation {
+    /// Every operation
+    pub const ALL: [Operation; 3] =
+        [Operation::Add, Operation::Sub, Operation::Xor];
+
+    /// Name of the operation, as used in the ground truth
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Add => "add",
+            Operation::Sub => "sub",
+            Operation::Xor => "xor",
+        }
+    }
+
+    /// Combine `rhs` into `lhs`
+    pub fn apply(&self, lhs: u8, rhs: u8) -> u8 {
+        match self {
+            Operation::Add => lhs.wrapping_add(rhs),
+            Operation::Sub => lhs.wrapping_sub(rhs),
+            Operation::Xor => lhs ^ rhs,
+        }
+    }
+
+    /// The operation as a Rust expression combining `rhs` into `lhs`, which
+    /// must not bind looser than a method call
+    pub fn rust(&self, lhs: &str, rhs: &str) -> String {
+        match self {
+            Operation::Add => format!("{}.wrapping_add({})", lhs, rhs),
+            Operation::Sub => format!("{}.wrapping_sub({})", lhs, rhs),
+            Operation::Xor => format!("{} ^ {}", lhs, rhs),
+        }
+    }
+}
+
+/// A relation between two input bytes, see [`Cond::Compare`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Relation {
+    /// The bytes are equal
+    Equal,
+
+    /// The left byte is less than the right one
+    Less,
+}
+
+impl Relation {
+    /// The Rust operator for the relation
+    pub fn operator(&self) -> &'static str {
+        match self {
+            Relation::Equal => "==",
+            Relation::Less  => "<",
+        }
+    }
+}
+
+impl fmt::Display for Cond {
+    /// Write out the condition as a Rust expression
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Cond::Bits { byte, mask, target } => {
+                write!(f, "_input[{}] & {:#010b} == {:#010b}",
+                       byte, mask, target)
+            }
+            Cond::Magic { byte, size, value } => {
+                write!(f, "u{}::from_le_bytes([", size * 8)?;
+                for ii in 0..size {
+                    if ii != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "_input[{}]", byte + ii)?;
+                }
+                write!(f, "]) == {:#0width$x}", value, width = size * 2 + 2)
+            }
+            Cond::Span { byte, mask, target } => {
+                write!(f, "{} & {:#018b} == {:#018b}", le_value(byte, 2),
+                       mask, target)
+            }
+            Cond::Range { byte, size, low, high } => {
+                // Bounds which are the smallest or largest values are left
+                // out, so the check reads as a plain inequality
+                let value = le_value(byte, size);
+                let max = u64::MAX >> (64 - size * 8);
+                let width = size * 2 + 2;
+                if low == 0 && high != max {
+                    write!(f, "{} < {:#0width$x}", value, high + 1)
+                } else if high == max && low != 0 {
+                    write!(f, "{} > {:#0width$x}", value, low - 1)
+                } else {
+                    write!(f, "({:#0width$x}..={:#0width$x}).contains(&{})",
+                           low, high, value)
+                }
+            }
+            Cond::Compare { lhs, rhs, relation } => {
+                write!(f, "_input[{}] {} _input[{}]", lhs, relation.operator(),
+                       rhs)
+            }
+            Cond::Checksum { start, len, byte } => {
+                write!(f, "_input[{}..{}].iter().fold(0u8, |x, y| \
+                           x.wrapping_add(*y)) == _input[{}]",
+                       start, start + len, byte)
+            }
+            Cond::Length { len } => {
+                write!(f, "_input.len() >= {}", len)
+            }
+            Cond::Field { field, offset, mask, target } => {
+                write!(f, "_input.get(_input[{}] as usize + {})\
+                           .is_some_and(|x| x & {:#010b} == {:#010b})",
+                       field, field + 1 + offset, mask, target)
+            }
+            Cond::Derived { base, ref terms, mask, target, .. } => {
+                // The whole derivation in one expression
+                let value = terms.iter().fold(format!("_input[{}]", base),
+                    |value, &(op, byte, shift)| {
+                        let term = derived_term(byte, shift);
+                        let value = op.rust(&value, &term);
+                        if op == Operation::Xor {
+                            format!("({})", value)
+                        } else {
+                            value
+                        }
+                    });
+                write!(f, "{} & {:#010b} == {:#010b}", value, mask, target)
+            }
+        }
+    }
+}
+
+/// The `size` bytes of the input starting at `byte` as a little endian
+/// integer in Rust, see [`Cond::Span`] and [`Cond::Range`]
+fn le_value(byte: usize, size: usize) -> String {
+    if size == 1 {
+        return format!("_input[{}]", byte);
+    }
+    let bytes: Vec<String> = (byte..byte + size)
+        .map(|x| format!("_input[{}]", x)).collect();
+    format!("u{}::from_le_bytes([{}])", size * 8, bytes.join(", "))
+}
+
+/// An input byte shifted left by `shift`, as a term of a derived value in
+/// Rust or C, see [`Cond::Derived`]
+fn derived_term(byte: usize, shift: u32) -> String {
+    if shift == 0 {
+        format!("_input[{}]", byte)
+    } else {
+        format!("_input[{}] << {}", byte, shift)
+    }
+}
+
+impl Cond {
+    /// JSON object describing the condition, with its `kind` and the fields
+    /// of its variant
+    pub fn json(&self) -> String {
+        match *self {
+            Cond::Bits { byte, mask, target } => {
+                format!("{{\"kind\":\"bits\",\"byte\":{},\"mask\":{},\
+                         \"target\":{}}}", byte, mask, target)
+            }
+            Cond::Magic { byte, size, value } => {
+                format!("{{\"kind\":\"magic\",\"byte\":{},\"size\":{},\
+                         \"value\":{}}}", byte, size, value)
+            }
+            Cond::Span { byte, mask, target } => {
+                format!("{{\"kind\":\"span\",\"byte\":{},\"mask\":{},\
+                         \"target\":{}}}", byte, mask, target)
+            }
+            Cond::Range { byte, size, low, high } => {
+                format!("{{\"kind\":\"range\",\"byte\":{},\"size\":{},\
+                         \"low\":{},\"high\":{}}}", byte, size, low, high)
+            }
+            Cond::Compare { lhs, rhs, relation } => {
+                format!("{{\"kind\":\"compare\",\"lhs\":{},\"rhs\":{},\
+                         \"relation\":\"{}\"}}", lhs, rhs,
+                        relation.operator())
+            }
+            Cond::Checksum { start, len, byte } => {
+                format!("{{\"kind\":\"checksum\",\"start\":{},\"len\":{},\
+                         \"byte\":{}}}", start, len, byte)
+            }
+            Cond::Length { len } => {
+                format!("{{\"kind\":\"length\",\"len\":{}}}", len)
+            }
+            Cond::Field { field, offset, mask, target } => {
+                format!("{{\"kind\":\"field\",\"field\":{},\"offset\":{},\
+                         \"mask\":{},\"target\":{}}}", field, offset, mask,
+                        target)
+            }
+            Cond::Derived { var, base, ref terms, mask, target } => {
+                let terms: Vec<String> = terms.iter()
+                    .map(|&(op, byte, shift)| {
+                        format!("{{\"op\":\"{}\",\"byte\":{},\"shift\":{}}}",
+                                op.name(), byte, shift)
+                    }).collect();
+                format!("{{\"kind\":\"derived\",\"var\":{},\"base\":{},\
+                         \"terms\":[{}],\"mask\":{},\"target\":{}}}", var,
+                        base, terms.join(","), mask, target)
+            }
+        }
+    }
+
+    /// The condition as a Rust expression in emitted programs, where
+    /// derived values are tested through their locals, see
+    /// [`Cond::derivation`]
+    pub fn rust(&self) -> String {
+        match *self {
+            Cond::Derived { var, mask, target, .. } => {
+                format!("_v{} & {:#010b} == {:#010b}", var, mask, target)
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// Statements declaring the chain of locals computing the value of a
+    /// derived value condition in Rust, with every step of the derivation
+    /// in a local of its own and the value in `_v<var>`. Empty for other
+    /// conditions.
+    pub fn derivation(&self) -> Vec<String> {
+        let Cond::Derived { var, base, ref terms, .. } = *self else {
+            return Vec::new();
+        };
+        let mut stmts = vec![format!("let _v{}_0 = _input[{}];", var, base)];
+        for (ii, &(op, byte, shift)) in terms.iter().enumerate() {
+            let local = if ii + 1 == terms.len() {
+                format!("_v{}", var)
+            } else {
+                format!("_v{}_{}", var, ii + 1)
+            };
+            stmts.push(format!("let {} = {};", local,
+                               op.rust(&format!("_v{}_{}", var, ii),
+                                       &derived_term(byte, shift))));
+        }
+        stmts
+    }
+
+    /// Value a derived value condition derives from `input`, `None` for
+    /// other conditions or if the input ends before a byte it is derived
+    /// from
+    pub fn derive(&self, input: &[u8]) -> Option<u8> {
+        let Cond::Derived { base, ref terms, .. } = *self else {
+            return None;
+        };
+        terms.iter().try_fold(*input.get(base)?, |value, &(op, byte, shift)| {
+            Some(op.apply(value, input.get(byte)? << shift))
+        })
+    }
+
+    /// Number of bytes the input must have for the condition to be checked
+    /// without indexing out of bounds
+    pub fn input_len(&self) -> usize {
+        match *self {
+            Cond::Bits { byte, .. }           => byte + 1,
+            Cond::Magic { byte, size, .. }    => byte + size,
+            Cond::Span { byte, .. }           => byte + 2,
+            Cond::Range { byte, size, .. }    => byte + size,
+            Cond::Compare { lhs, rhs, .. }    => lhs.max(rhs) + 1,
+            Cond::Checksum { start, len, byte } => {
+                (start + len).max(byte + 1)
+            }
+            Cond::Length { .. }               => 0,
+            Cond::Field { field, .. }         => field + 1,
+            Cond::Derived { base, ref terms, .. } => {
+                terms.iter().map(|x| x.1).max().unwrap_or(0).max(base) + 1
+            }
+        }
+    }
+}
+
+/// The class of a crash, telling how the bug behind it would manifest in a
+/// real program, so crashes can be triaged and weighted differently
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CrashClass {
+    /// An out of bounds read
+    OobRead,
+
+    /// An out of bounds write
+    OobWrite,
+
+    /// A failed assertion
+    Assert,
+}
+
+impl CrashClass {
+    /// Every class, in the order of [`Params::crash_classes`]
+    pub const ALL: [CrashClass; 3] =
+        [CrashClass::OobRead, CrashClass::OobWrite, CrashClass::Assert];
+
+    /// Name of the class, as used in the program metadata
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrashClass::OobRead  => "oob-read",
+            CrashClass::OobWrite => "oob-write",
+            CrashClass::Assert   => "assert",
+        }
+    }
+}
+
+/// A basic block, which records coverage when reached
+#[derive(Clone, Debug)]
+pub struct Block {
+    /// Unique coverage identifier for this block
+    pub id: usize,
+
+    /// Whether the block's coverage event is only recorded by chance, see
+    /// [`Params::flaky_chance`]
+    pub flaky: bool,
+
+    /// Statements executed in the block, in order
+    pub stmts: Vec<Stmt>,
+}
+
+/// A statement in a block
+#[derive(Clone, Debug)]
+pub enum Stmt {
+    /// Execute `body` if `cond` holds, otherwise `else_body` if there is one
+    If {
+        cond:      Cond,
+        body:      Block,
+        else_body: Option<Block>,
+    },
+
+    /// Execute the block of the arm whose value equals the bits of the input
+    /// byte `byte` selected by `mask`, if any
+    Match {
+        byte: usize,
+        mask: u8,
+        arms: Vec<(u8, Block)>,
+    },
+
+    /// Call the function `function`, crashing if it crashes
+    Call {
+        function: usize,
+    },
+
+    /// Crash with the unique crash ID `id` of class `class` if all of the
+    /// conditions of
+    /// `parts` hold, first recording coverage of the block `block` if it is
+    /// set. The conditions are checked one after the other rather than
+    /// nested, each recording coverage of its block if it is set when it
+    /// holds.
+    Conjunction {
+        parts: Vec<(Cond, Option<usize>)>,
+        id:    usize,
+        class: CrashClass,
+        block: Option<usize>,
+    },
+
+    /// Mark the join `join` of an enclosing block as reached
+    SetJoin {
+        join: usize,
+    },
+
+    /// Execute `body` if any of the sources of the join `join` were reached
+    /// since the start of the block this is in
+    Join {
+        join: usize,
+        body: Block,
+    },
+
+    /// Loop over the input as a sequence of records, each a tag byte, a
+    /// length byte, and that many bytes of body, calling the function of
+    /// the arm whose value equals the tag, if any, with the body as its
+    /// input. Crashes if the function crashes.
+    Records {
+        arms: Vec<(u8, usize)>,
+    },
+
+    /// Execute `body` as many times as the bits of the input byte `byte`
+    /// selected by `mask`, shifted down, say
+    Loop {
+        byte: usize,
+        mask: u8,
+        body: Block,
+    },
+
+    /// Crash with the unique crash ID `id` of class `class` if `cond` holds,
+    /// first recording coverage of the block `block` if it is set
+    Crash {
+        cond:  Cond,
+        id:    usize,
+        class: CrashClass,
+        block: Option<usize>,
+    },
+
+    /// Return from the function without crashing if `cond` holds, first
+    /// recording coverage of the block `block`
+    Reject {
+        cond:  Cond,
+        block: usize,
+    },
+}
+
+/// A generated program
+#[derive(Clone, Debug)]
+pub struct Program {
+    /// Parameters the program was generated with, including the seed
+    pub params: Params,
+
+    /// Size of the input to the program in bytes
+    pub input_bytes: usize,
+
+    /// Number of blocks (and thus coverage events) in the program
+    pub num_blocks: usize,
+
+    /// Number of crashes in the program
+    pub num_crashes: usize,
+
+    /// Classes of the crashes, indexed by crash ID
+    pub crash_classes: Vec<CrashClass>,
+
+    /// Root causes of the crashes, indexed by crash ID, numbered in the
+    /// order of their first crash. Crashes checking the same condition in
+    /// different places share one, see [`Params::root_cause_chance`].
+    pub root_causes: Vec<usize>,
+
+    /// Number of flaky blocks in the program
+    pub num_flaky: usize,
+
+    /// Entry block of the program
+    pub entry: Block,
+
+    /// Entry blocks of the functions called by the program, indexed by
+    /// function number. Functions only call functions with lower numbers.
+    pub functions: Vec<Block>,
+
+    /// Number of the variant of the generated program this is, zero for the
+    /// generated program itself, see [`Program::patch`]
+    pub variant: usize,
+
+    /// Module of every function, indexed by function number, see
+    /// [`Params::modules`]
+    pub modules: Vec<usize>,
+
+    /// Functions which are entry points after `crashme`, so entry point `n`
+    /// is function `entry_points[n - 1]`, see [`Params::entry_points`]
+    pub entry_points: Vec<usize>,
+
+    /// Random draws made generating the program, see [`Program::decisions`]
+    pub draws: Draws,
+}
+
+/// Find unused bits among `bits` in `used_bits` by randomly
+/// generating bit slices and only returning once a bit slice is found that
+/// is not already used. Further, unless `span` is set, this will only look
+/// for bit slices which fit inside of a byte value which is aligned. This
+/// ensures that the bit slice can be a simple mask and compare against a
+/// single volatile byte read, rather than a 16-bit one. Slices of more than
+/// 8 bits are whole, contiguous bytes instead.
+fn find_unused_bits(used_bits: &mut BTreeSet<usize>, rng: &mut Rng,
+                    bits: Range<usize>, num_bits: usize, span: bool,
+                    timeout: u64) -> Option<(usize, usize)> {
+    // Make sure the number of bits fits within a byte, or is whole bytes
+    assert!(num_bits > 0 && (num_bits <= 8 || num_bits.is_multiple_of(8)),
+            "Invalid bit size for find_unused_bits");
+
+    let mut iters = 0u64;
+    'try_another_slice: loop {
+        // Give up on the search after a user-defined threshold
+        if iters >= timeout {
+            break None;
+        }
+        iters += 1;
+
+        // Find the start and end bit indicies [bit_start, bit_end]
+        let mut bit_start = bits.start + rng.rand() % bits.len();
+        if num_bits > 8 {
+            bit_start -= bit_start % 8;
+        }
+        let bit_end = bit_start + num_bits - 1;
+
+        // Bit overflow or a small slice spanning a byte boundary
+        if bit_end >= bits.end ||
+                (num_bits <= 8 && !span &&
+                 (bit_start / 8) != (bit_end / 8)) {
+            continue 'try_another_slice;
+        }
+
+        // Go through each bit index looking for if it is used
+        for bit in bit_start..bit_end + 1 {
+            if used_bits.contains(&bit) {
+                continue 'try_another_slice;
+            }
+        }
+
+        // At this point the slice is free! Mark it as used!
+        for bit in bit_start..bit_end + 1 {
+            used_bits.insert(bit);
+        }
+
+        break Some((bit_start, bit_end));
+    }
+}
+
+/// Pick the number of bits checked by a condition from the distribution
+/// `weights`, see `Params::condition_bits`
+fn condition_bits(rng: &mut Rng, weights: &[usize]) -> usize {
+    if weights.is_empty() {
+        return rng.rand() % 8 + 1;
+    }
+    pick_weighted(rng, weights) + 1
+}
+
+/// Pick an index of `weights`, which has a non-zero weight, with a chance
+/// proportional to its weight
+fn pick_weighted(rng: &mut Rng, weights: &[usize]) -> usize {
+    let mut pick = rng.rand() % weights.iter().sum::<usize>();
+    for (ii, &weight) in weights.iter().enumerate() {
+        if pick < weight {
+            return ii;
+        }
+        pick -= weight;
+    }
+    unreachable!()
+}
+
+/// Byte mask selecting the bits `start..=end` of a byte
+fn slice_mask(start: usize, end: usize) -> u8 {
+    let (start_bit, end_bit) = (start % 8, end % 8);
+    let mask = (!0u8 >> start_bit) << start_bit;
+    (mask << (7 - end_bit)) >> (7 - end_bit)
+}
+
+/// Generate a new program with the shape described by `params`
+///
+/// # Panics
+///
+/// If [`Params::cfg`] is set and the graph can't be loaded
+pub fn generate(params: &Params) -> Program {
+    // Programs shaped after a control flow graph are generated from it
+    if !params.cfg.is_empty() {
+        let cfg = cfg::Cfg::load(std::path::Path::new(&params.cfg))
+            .unwrap_or_else(|err| panic!("{}", err));
+        return cfg.generate(params);
+    }
+
+    // Create an RNG, and record what is drawn from it for which block
+    let mut rng = Rng::new(params.seed);
+    let mut draws = Draws::default();
+    draws.start(None, &rng);
+
+    // A set containing all of the bit indicies which have been used from the
+    // input file. This allows us to allocate out bit slices from the input
+    // file to generate different conditions. In a parsing loop every record
+    // handler has a set of its own, as the bits are those of its record.
+    let mut used_bits: BTreeSet<usize> = BTreeSet::new();
+
+    let max_input_size_bits = params.max_input_size_bits;
+
+    // Number of bits conditions can use, in a parsing loop those of the
+    // largest record body which fits in the input and its length byte
+    let max_bits = if params.record_tags != 0 {
+        (max_input_size_bits - 16).min(255 * 8)
+    } else {
+        max_input_size_bits
+    };
+
+    // Find unused bits of the first `max_bits` in `used_bits` for a
+    // condition on a slice of bits, which may span two bytes
+    let find_slice_bits = |used_bits: &mut BTreeSet<usize>, rng: &mut Rng,
+                           num_bits: usize, timeout: u64| {
+        find_unused_bits(used_bits, rng, 0..max_bits, num_bits,
+                         params.span_slices, timeout)
+    };
+
+    // Find a whole unused byte of the config buffer, or the data buffer if
+    // `data` is set, in `used_bits`. A single buffer is all `max_bits`.
+    let config_bits = params.config_bytes * 8;
+    let find_buffer_byte = |used_bits: &mut BTreeSet<usize>, rng: &mut Rng,
+                            data: bool| {
+        let bits = match (config_bits, data) {
+            (0, _)     => 0..max_bits,
+            (_, false) => 0..config_bits,
+            (_, true)  => config_bits..max_bits,
+        };
+        find_unused_bits(used_bits, rng, bits, 8, false, 1000)
+    };
+
+    // Find unused bits of the first `max_bits` in `used_bits`
+    let find_unused_bits = |used_bits: &mut BTreeSet<usize>, rng: &mut Rng,
+                            num_bits: usize, timeout: u64| {
+        find_unused_bits(used_bits, rng, 0..max_bits, num_bits, false,
+                         timeout)
+    };
+
+    // Number of blocks
+    let mut num_blocks = 0usize;
+
+    // Allocate a new, empty block with a unique coverage ID
+    fn new_block(num_blocks: &mut usize) -> Block {
+        *num_blocks += 1;
+        Block { id: *num_blocks - 1, flaky: false, stmts: Vec::new() }
+    }
+
+    // Kind of statement an open block belongs to
+    enum Open {
+        // The entry block
+        Entry,
+
+        // The body of an if, or its else block if `body` is set
+        If { cond: Cond, body: Option<Block> },
+
+        // The arm for `values[arms.len()]` of a match, with the arms before
+        // it
+        Match { byte: usize, mask: u8, values: Vec<u8>,
+                arms: Vec<(u8, Block)> },
+
+        // The body of a loop
+        Loop { byte: usize, mask: u8 },
+    }
+
+    // Stack of blocks which are currently open, along with the statement
+    // they belong to. The depth of the stack is the nested if depth of the
+    // program.
+    let mut stack: Vec<(Open, Block)> =
+        vec![(Open::Entry, new_block(&mut num_blocks))];
+
+    // Close the innermost open block, attaching it to its parent. If
+    // `next` is given, it is opened in its place instead, as the else block
+    // of an if or the next arm of a match.
+    fn end_block(stack: &mut Vec<(Open, Block)>, next: Option<Block>) {
+        let (open, block) = stack.pop().unwrap();
+        let stmt = match (open, next) {
+            (Open::If { cond, body: None }, Some(next)) => {
+                stack.push((Open::If { cond, body: Some(block) }, next));
+                return;
+            }
+            (Open::If { cond, body: None }, None) => Stmt::If {
+                cond,
+                body:      block,
+                else_body: None,
+            },
+            (Open::If { cond, body: Some(body) }, _) => Stmt::If {
+                cond,
+                body,
+                else_body: Some(block),
+            },
+            (Open::Match { byte, mask, values, mut arms }, next) => {
+                arms.push((values[arms.len()], block));
+                if let Some(next) = next {
+                    stack.push((Open::Match { byte, mask, values, arms },
+                                next));
+                    return;
+                }
+                Stmt::Match { byte, mask, arms }
+            }
+            (Open::Loop { byte, mask }, _) => {
+                Stmt::Loop { byte, mask, body: block }
+            }
+            (Open::Entry, _) => unreachable!("closed the entry block"),
+        };
+        stack.last_mut().unwrap().1.stmts.push(stmt);
+    }
+
+    // Close open blocks until only `depth` are left, leaving the remaining
+    // arms of matches empty
+    fn close_blocks(stack: &mut Vec<(Open, Block)>, depth: usize,
+                    num_blocks: &mut usize, num_arms: &mut usize) {
+        while stack.len() > depth {
+            let next = match &stack.last().unwrap().0 {
+                Open::Match { values, arms, .. }
+                        if arms.len() + 1 < values.len() => {
+                    *num_arms += 1;
+                    Some(new_block(num_blocks))
+                }
+                _ => None,
+            };
+            end_block(stack, next);
+        }
+    }
+
+    // Rarity of a block, in bits, as the negative log2 of the chance of a
+    // random input reaching it from the start of its function, for blocks
+    // opened as `open`. Comparisons and checksums count as 8 bits, less
+    // than comparisons as one.
+    fn rarity(stack: &[(Open, Block)]) -> f64 {
+        // Bits of a condition holding, and of it failing
+        let holds = |bits: u32| bits as f64;
+        let fails = |bits: u32| -(1. - 0.5f64.powi(bits as i32)).log2();
+        stack.iter().map(|(open, _)| match open {
+            Open::Entry => 0.,
+            Open::If { cond, body } => {
+                let bits = match *cond {
+                    Cond::Bits { mask, .. } | Cond::Field { mask, .. } |
+                    Cond::Derived { mask, .. } => mask.count_ones(),
+                    Cond::Span { mask, .. } => mask.count_ones(),
+                    Cond::Magic { size, .. } => 8 * size as u32,
+                    Cond::Range { size, low, high, .. } => {
+                        8 * size as u32 -
+                            (high - low).saturating_add(1).ilog2()
+                    }
+                    Cond::Compare { relation: Relation::Less, .. } => 1,
+                    Cond::Compare { .. } | Cond::Checksum { .. } => 8,
+                    Cond::Length { .. } => 0,
+                };
+                if body.is_some() { fails(bits) } else { holds(bits) }
+            }
+            Open::Match { mask, .. } => holds(mask.count_ones()),
+            Open::Loop { mask, .. } => fails(mask.count_ones()),
+        }).sum()
+    }
+
+    // Pick the rarity of a new block, in bits, following the power law of
+    // `exponent`, see `Params::rarity_exponent`. Rarities in bits following
+    // an exponential distribution are chances following the power law.
+    fn rarity_target(rng: &mut Rng, exponent: f64) -> f64 {
+        let uniform = 1. - (rng.rand() >> 11) as f64 / (1u64 << 53) as f64;
+        -uniform.log2() / exponent
+    }
+
+    // Length of the input checked by the open blocks, which statements in
+    // the innermost block can use without checking it again
+    fn checked_len(stack: &[(Open, Block)]) -> usize {
+        stack.iter().map(|(open, _)| match open {
+            Open::If { cond: Cond::Length { len }, body: None } => *len,
+            _ => 0,
+        }).max().unwrap_or(0)
+    }
+
+    // Conditions on the path to the innermost open block, each of which
+    // holds or fails, as `Program::solve` sees them
+    fn path(stack: &[(Open, Block)]) -> Vec<(Cond, bool)> {
+        stack.iter().filter_map(|(open, _)| match open {
+            Open::Entry => None,
+            Open::If { cond, body } => Some((cond.clone(), body.is_none())),
+            Open::Match { byte, mask, values, arms } => {
+                let target = values[arms.len()];
+                Some((Cond::Bits { byte: *byte, mask: *mask, target }, true))
+            }
+            Open::Loop { byte, mask } => {
+                Some((Cond::Bits { byte: *byte, mask: *mask, target: 0 },
+                      false))
+            }
+        }).collect()
+    }
+
+    // Open a block checking the input is at least `len` bytes long, unless
+    // the open blocks already check that. See `Params::variable_length`.
+    fn check_len(stack: &mut Vec<(Open, Block)>, len: usize,
+                 num_blocks: &mut usize, num_checks: &mut usize,
+                 max_depth: &mut usize) {
+        if len <= checked_len(stack) {
+            return;
+        }
+        let block = new_block(num_blocks);
+        trace!("proggen: block {:6} at depth {:3} checks for {} input bytes",
+               block.id, stack.len(), len);
+        stack.push((Open::If { cond: Cond::Length { len }, body: None },
+                    block));
+        *num_checks += 1;
+        *max_depth = (*max_depth).max(stack.len() - 1);
+    }
+
+    // Number of else blocks
+    let mut num_else = 0usize;
+
+    // Number of matches, and of arms in all of them
+    let mut num_matches = 0usize;
+    let mut num_arms    = 0usize;
+
+    // Number of loops
+    let mut num_loops = 0usize;
+
+    // Functions generated so far, the length of the input checked where
+    // they were generated, and the number of calls to them
+    let mut functions: Vec<Block> = Vec::new();
+    let mut function_lens: Vec<usize> = Vec::new();
+    let mut num_calls = 0usize;
+
+    // Number of bit allocation failures
+    let mut alloc_failures = 0;
+
+    // Number of derived values, which name their locals
+    let mut num_derived = 0usize;
+
+    // Deepest nesting of blocks generated
+    let mut max_depth = 0;
+
+    // Number of crashes generated at every depth, and the class and root
+    // cause of every crash
+    let mut crash_depths: Vec<usize> = Vec::new();
+    let mut crash_classes: Vec<CrashClass> = Vec::new();
+    let mut root_causes: Vec<usize> = Vec::new();
+    let mut num_root_causes = 0usize;
+
+    // Crashes on a single condition generated so far, whose root cause
+    // another crash can share, with the block they are in
+    let mut crash_sites: Vec<(Cond, usize, usize, CrashClass)> = Vec::new();
+
+    // Whether a crash at `depth` keeps the `total` crashes so far, `counts`
+    // of them at every depth, within the target distribution `weights`. A
+    // depth is allowed while it has less than its share of the crashes,
+    // counting the new one.
+    fn crash_depth_ok(weights: &[usize], counts: &[usize], total: usize,
+                      depth: usize) -> bool {
+        if weights.is_empty() {
+            return true;
+        }
+        let weight = weights.get(depth - 1).copied().unwrap_or(0);
+        let count  = counts.get(depth).copied().unwrap_or(0);
+        weight != 0 &&
+            count * weights.iter().sum::<usize>() < (total + 1) * weight
+    }
+
+    // Conditions on slices of bits generated so far, which can be reused
+    let mut bit_conds: Vec<Cond> = Vec::new();
+
+    // Number of length checks, and the bytes used as length fields so far
+    let mut num_length_checks = 0usize;
+    let mut length_fields: Vec<usize> = Vec::new();
+
+    // Condition of the integrity gates, whose last byte no other condition
+    // may use
+    let last = max_input_size_bits.div_ceil(8) - 1;
+    let gate = Cond::Checksum { start: 0, len: last, byte: last };
+    if params.integrity_chance != 0 {
+        used_bits.extend(last * 8..last * 8 + 8);
+    }
+
+    // Bits of the bytes kept dead, picked by a partial shuffle of the bytes
+    // conditions could use. They stay in `used_bits` for every record.
+    let mut dead_bits: BTreeSet<usize> = BTreeSet::new();
+    if params.dead_fraction > 0. {
+        let mut bytes: Vec<usize> = (0..max_bits / 8).collect();
+        let dead = (params.dead_fraction * bytes.len() as f64).round()
+            as usize;
+        for ii in 0..dead.min(bytes.len()) {
+            let jj = ii + rng.rand() % (bytes.len() - ii);
+            bytes.swap(ii, jj);
+            dead_bits.extend(bytes[ii] * 8..bytes[ii] * 8 + 8);
+        }
+        used_bits.extend(&dead_bits);
+    }
+
+    // Number of crashes, how many of them have no coverage event, by depth
+    // too, and how many are conjunctions
+    let mut num_crashes = 0usize;
+    let mut hidden_crashes = 0usize;
+    let mut hidden_depths: Vec<usize> = Vec::new();
+    let mut num_conjunctions = 0usize;
+
+    // Number of rejects
+    let mut num_rejects = 0usize;
+
+    // Current stage, the depth of the stack its block is at, which is never
+    // closed while the stage is being generated, and the number of blocks
+    // before it. In a parsing loop every record handler is a stage, with
+    // the handler's function body at the bottom of the stack above the
+    // entry block.
+    let parser = params.record_tags != 0;
+    let stages = if parser {
+        params.record_tags
+    } else {
+        params.stages.max(1)
+    };
+    let mut stage = 0;
+    let mut stage_depth = 1;
+    let mut stage_start = 0usize;
+    let mut handlers: Vec<usize> = Vec::new();
+    let mut handler_bits = 0usize;
+
+    // When conditions can contradict each other, blocks are only opened
+    // where the solver finds an input reaching them, so every block stays
+    // reachable, see `Program::verify`. Inputs are as long as the solver
+    // builds them, in a parsing loop those of a record body.
+    let overlap = params.conditions_overlap();
+    let fixed_len = if params.variable_length {
+        0
+    } else {
+        max_input_size_bits.div_ceil(8)
+    };
+    let max_len = if parser {
+        (max_input_size_bits.div_ceil(8) - 2).min(255)
+    } else {
+        max_input_size_bits.div_ceil(8)
+    };
+
+    // Whether an input reaches a block opened in the innermost open block
+    // of `stack` behind `conds` holding or failing
+    let reachable = |stack: &[(Open, Block)], conds: &[(Cond, bool)]| {
+        let mut path = path(stack);
+        path.extend_from_slice(conds);
+        solve::solve_path(&path, fixed_len, max_len).is_some()
+    };
+    if parser {
+        let block = new_block(&mut num_blocks);
+        trace!("proggen: record handler 1 starts with block {}", block.id);
+        stack.push((Open::Entry, block));
+        stage_depth = 2;
+    }
+
+    // Nesting depth of the innermost open block in its function, and
+    // whether it is as deep as blocks can be, so nothing is opened in it
+    let depth = |stack: &[(Open, Block)]| stack.len() - 1 - parser as usize;
+    let deep = |stack: &[(Open, Block)]| {
+        params.max_depth != 0 && depth(stack) >= params.max_depth
+    };
+
+    // Blocks opened and iterations of the loop so far, to estimate how
+    // often blocks are opened when aiming for a depth, starting out from
+    // the chance of an if statement, and likewise blocks closed and how
+    // many of them left their depth rather than being followed by an else
+    // block or the next arm of a match
+    let mut num_opens = 1usize;
+    let mut num_iters = params.if_chance;
+    let mut num_closes = 1usize;
+    let mut num_pops = 1usize;
+
+    // Number of blocks to generate at least, the target if there is one
+    let min_blocks = if params.target_blocks != 0 {
+        params.target_blocks
+    } else {
+        params.min_blocks
+    };
+
+    loop {
+        // What is drawn next decides what goes in the innermost open block
+        draws.start(Some(stack.last().unwrap().1.id), &rng);
+
+        // Stop at the cap on blocks, whatever is left to generate
+        if params.max_blocks != 0 && num_blocks as u64 >= params.max_blocks {
+            verbose!("proggen: stopping, reached `max_blocks` with {} \
+                      blocks", num_blocks);
+            break;
+        }
+
+        // Random chance to generate an if statement
+        let open_len = stack.len();
+        let mut failed_bits = None;
+        if !deep(&stack) && rng.rand().is_multiple_of(params.if_chance) {
+            // Random chance to reuse the bits, and target, of an earlier
+            // condition, nothing is drawn when reuse is disabled
+            let reuse = params.reuse_chance != 0 && !bit_conds.is_empty() &&
+                rng.rand().is_multiple_of(params.reuse_chance);
+
+            // Otherwise random chance to compare a magic value, likewise
+            let magic = !reuse && params.magic_chance != 0 &&
+                rng.rand().is_multiple_of(params.magic_chance);
+
+            // Otherwise random chance to compare two input bytes, likewise
+            let compare = !reuse && !magic && params.compare_chance != 0 &&
+                rng.rand().is_multiple_of(params.compare_chance);
+
+            // Otherwise random chance to check a checksum when deep enough,
+            // likewise
+            let checksum = !reuse && !magic && !compare &&
+                params.checksum_chance != 0 &&
+                stack.len() >= params.checksum_min_depth &&
+                rng.rand().is_multiple_of(params.checksum_chance);
+
+            // Otherwise random chance to check bits past a length field,
+            // likewise
+            let length_field = !reuse && !magic && !compare && !checksum &&
+                params.length_field_chance != 0 &&
+                rng.rand().is_multiple_of(params.length_field_chance);
+
+            // Otherwise random chance to check the integrity of the whole
+            // input, likewise, unless an open block already does
+            let integrity = !reuse && !magic && !compare && !checksum &&
+                !length_field && params.integrity_chance != 0 &&
+                !stack.iter().any(|(open, _)| {
+                    matches!(open, Open::If { cond, .. } if *cond == gate)
+                }) &&
+                rng.rand().is_multiple_of(params.integrity_chance);
+
+            // Otherwise random chance to check bits of a value derived from
+            // several input bytes, likewise
+            let derived = !reuse && !magic && !compare && !checksum &&
+                !length_field && !integrity && params.derived_chance != 0 &&
+                rng.rand().is_multiple_of(params.derived_chance);
+
+            // Otherwise random chance to check an input integer against a
+            // range of values, likewise
+            let range = !reuse && !magic && !compare && !checksum &&
+                !length_field && !integrity && !derived &&
+                params.range_chance != 0 &&
+                rng.rand().is_multiple_of(params.range_chance);
+
+            let num_bits = if reuse || integrity {
+                0
+            } else if magic {
+                [16, 32, 64][rng.rand() % 3]
+            } else if checksum {
+                (rng.rand() % 15 + 2) * 8
+            } else if compare {
+                8
+            } else if params.rarity_exponent > 0. {
+                // Put the block in the deepest open block of the stage
+                // which leaves it at least a bit to be as rare as drawn
+                let target = rarity_target(&mut rng, params.rarity_exponent);
+                let depth = (stage_depth..=stack.len()).rev()
+                    .find(|&depth| rarity(&stack[..depth]) + 1. <= target)
+                    .unwrap_or(stage_depth);
+                close_blocks(&mut stack, depth, &mut num_blocks,
+                             &mut num_arms);
+                (target - rarity(&stack)).round().clamp(1., 8.) as usize
+            } else {
+                condition_bits(&mut rng, &params.condition_bits)
+            };
+            let cond = if reuse {
+                let cond = bit_conds[rng.rand() % bit_conds.len()].clone();
+                trace!("proggen: reusing `{}`", cond);
+                Some(cond)
+            } else if integrity {
+                Some(gate.clone())
+            } else if magic {
+                let bits = find_unused_bits(&mut used_bits, &mut rng,
+                                            num_bits, 1000);
+                bits.map(|(start, _)| {
+                    // Generate the value, the top bits of a 64-bit draw for
+                    // smaller magic values
+                    let value = (rng.rand() as u64) >> (64 - num_bits);
+                    Cond::Magic { byte: start / 8, size: num_bits / 8, value }
+                })
+            } else if compare {
+                // Both bytes are used up whole, if only the first could be
+                // allocated its bits are left unused. With two buffers the
+                // comparison is across them.
+                let lhs = find_buffer_byte(&mut used_bits, &mut rng, false);
+                lhs.and_then(|(lhs, _)| {
+                    let (rhs, _) = find_buffer_byte(&mut used_bits, &mut rng,
+                                                    true)?;
+                    let relation = if rng.rand().is_multiple_of(2) {
+                        Relation::Equal
+                    } else {
+                        Relation::Less
+                    };
+                    Some(Cond::Compare { lhs: lhs / 8, rhs: rhs / 8,
+                                         relation })
+                })
+            } else if checksum {
+                // Like comparisons, the range is left used up if the checksum
+                // byte can't be allocated
+                let range = find_unused_bits(&mut used_bits, &mut rng,
+                                             num_bits, 1000);
+                range.and_then(|(start, _)| {
+                    let (byte, _) = find_unused_bits(&mut used_bits, &mut rng,
+                                                     8, 1000)?;
+                    Some(Cond::Checksum { start: start / 8, len: num_bits / 8,
+                                          byte: byte / 8 })
+                })
+            } else if length_field {
+                // Fields are whole bytes, and are shared by several
+                // conditions like the records of a real format. The bits
+                // checked past the record are not allocated, as where they
+                // are depends on the field.
+                let field = if !length_fields.is_empty() &&
+                        rng.rand().is_multiple_of(2) {
+                    Some(length_fields[rng.rand() % length_fields.len()])
+                } else {
+                    let bits = find_unused_bits(&mut used_bits, &mut rng, 8,
+                                                1000);
+                    bits.map(|(start, _)| {
+                        length_fields.push(start / 8);
+                        start / 8
+                    })
+                };
+                // The checked byte must fit in the input past an empty
+                // record at least, fields without room for it are of no use
+                let room = |field: usize| {
+                    (max_bits / 8).saturating_sub(field + 1).min(16)
+                };
+                field.filter(|&field| room(field) != 0).map(|field| {
+                    let start = rng.rand() % (9 - num_bits);
+                    let mask = slice_mask(start, start + num_bits - 1);
+                    Cond::Field {
+                        field,
+                        offset: rng.rand() % room(field),
+                        mask,
+                        target: rng.rand() as u8 & mask,
+                    }
+                })
+            } else if derived {
+                // Every byte the value is derived from is used up whole, if
+                // not all of them could be allocated the ones which were are
+                // left unused
+                let len = rng.rand() % 3 + 2;
+                let mut bytes = Vec::new();
+                while bytes.len() < len {
+                    match find_unused_bits(&mut used_bits, &mut rng, 8, 1000) {
+                        Some((start, _)) => bytes.push(start / 8),
+                        None => break,
+                    }
+                }
+                if bytes.len() == len {
+                    let terms = bytes[1..].iter().map(|&byte| {
+                        let op = Operation::ALL[rng.rand() % 3];
+                        (op, byte, (rng.rand() % 4) as u32)
+                    }).collect();
+                    let start = rng.rand() % (9 - num_bits);
+                    let mask = slice_mask(start, start + num_bits - 1);
+                    num_derived += 1;
+                    Some(Cond::Derived {
+                        var:    num_derived - 1,
+                        base:   bytes[0],
+                        terms,
+                        mask,
+                        target: rng.rand() as u8 & mask,
+                    })
+                } else {
+                    None
+                }
+            } else if range {
+                // The integer is used up whole, and as many of its values
+                // are in the range as would match a slice of `num_bits`
+                // bits, down to half as many
+                let size = [1, 2, 4][rng.rand() % 3];
+                let bits = find_unused_bits(&mut used_bits, &mut rng,
+                                            size * 8, 1000);
+                bits.map(|(start, _)| {
+                    let total = 1u64 << (size * 8);
+                    let most = total >> num_bits;
+                    let width = most - rng.rand() as u64 % most.div_ceil(2);
+                    let (low, high) = match rng.rand() % 3 {
+                        0 => (0, width - 1),
+                        1 => (total - width, total - 1),
+                        _ => {
+                            let low = rng.rand() as u64 % (total - width + 1);
+                            (low, low + width - 1)
+                        }
+                    };
+                    Cond::Range { byte: start / 8, size, low, high }
+                })
+            } else {
+                let bits = find_slice_bits(&mut used_bits, &mut rng,
+                                           num_bits, 1000);
+                bits.map(|(start, end)| {
+                    // Slices spanning two bytes check them as a 16-bit value
+                    if start / 8 != end / 8 {
+                        let mask = (((1u32 << (end - start + 1)) - 1) <<
+                                    (start % 8)) as u16;
+                        let target = rng.rand() as u16 & mask;
+                        return Cond::Span { byte: start / 8, mask, target };
+                    }
+
+                    // Generate a byte mask for these bits
+                    let mask = slice_mask(start, end);
+
+                    // Generate a target value for these bits
+                    let target = rng.rand() as u8 & mask;
+
+                    Cond::Bits { byte: start / 8, mask, target }
+                })
+            };
+            // Conditions which can't hold here, and reused ones which can't
+            // fail either, would only guard unreachable code
+            let rejected = overlap && cond.as_ref().is_some_and(|cond| {
+                !reachable(&stack, &[(cond.clone(), true)]) ||
+                    (reuse && !reachable(&stack, &[(cond.clone(), false)]))
+            });
+            if rejected {
+                trace!("proggen: `{}` would be unreachable",
+                       cond.as_ref().unwrap());
+            }
+            if let Some(cond) = cond.filter(|_| !rejected) {
+                if !reuse && matches!(cond, Cond::Bits { .. }) {
+                    bit_conds.push(cond.clone());
+                }
+                if params.variable_length {
+                    check_len(&mut stack, cond.input_len(), &mut num_blocks,
+                              &mut num_length_checks, &mut max_depth);
+                }
+
+                // Random chance for the condition to guard a crash, if a
+                // crash at this depth keeps to the depth distribution.
+                // Nothing is drawn when crashes are disabled, so programs
+                // generated before crashes existed stay the same.
+                if params.crash_chance != 0 &&
+                        (params.max_crashes == 0 ||
+                         num_crashes < params.max_crashes) &&
+                        rng.rand().is_multiple_of(params.crash_chance) &&
+                        crash_depth_ok(&params.crash_depths, &crash_depths,
+                                       num_crashes, stack.len()) {
+                    // A crash gets no coverage event by chance, or if that
+                    // keeps the crashes without one within their fraction,
+                    // and only at depths below their share of them
+                    let fraction = params.non_coverage_crash_fraction;
+                    let hidden = if fraction != 0. {
+                        (hidden_crashes + 1) as f64 <=
+                            fraction * (num_crashes + 1) as f64
+                    } else {
+                        params.non_coverage_crash_chance != 0 &&
+                            rng.rand().is_multiple_of(
+                                params.non_coverage_crash_chance)
+                    } && crash_depth_ok(&params.non_coverage_crash_depths,
+                                        &hidden_depths, hidden_crashes,
+                                        stack.len());
+                    let block = (!hidden).then(|| {
+                        new_block(&mut num_blocks).id
+                    });
+
+                    // Random chance for the crash to share the root cause of
+                    // an earlier one, checking its condition. The crashes in
+                    // open blocks are passed on the way here and would
+                    // always crash first. Nothing is drawn when root causes
+                    // aren't shared.
+                    let shared = if params.root_cause_chance != 0 &&
+                            rng.rand().is_multiple_of(
+                                params.root_cause_chance) {
+                        let sites: Vec<_> = crash_sites.iter()
+                            .filter(|(cond, block, ..)| {
+                                stack.iter().all(|(_, x)| x.id != *block) &&
+                                    (!overlap || reachable(&stack,
+                                        &[(cond.clone(), true)]))
+                            }).collect();
+                        (!sites.is_empty()).then(|| {
+                            sites[rng.rand() % sites.len()].clone()
+                        })
+                    } else {
+                        None
+                    };
+                    let cond = match &shared {
+                        Some((cond, ..)) => cond.clone(),
+                        None => cond,
+                    };
+                    trace!("proggen: crash {:6} at depth {:3} checks `{}`{}",
+                           num_crashes, stack.len(), cond,
+                           if hidden { " without coverage" } else { "" });
+
+                    // Random chance for the crash to need more conditions,
+                    // likewise. These are always slices of bits, and the
+                    // parts get coverage like the crash itself. If bits run
+                    // out the conjunction is left smaller.
+                    let mut parts = vec![(cond, None)];
+                    if shared.is_none() && params.conjunction_chance != 0 &&
+                            rng.rand()
+                            .is_multiple_of(params.conjunction_chance) {
+                        while parts.len() < params.conjunction_size {
+                            let num_bits = condition_bits(
+                                &mut rng, &params.condition_bits);
+                            let Some((start, end)) = find_unused_bits(
+                                    &mut used_bits, &mut rng, num_bits,
+                                    1000) else {
+                                break;
+                            };
+                            let mask = slice_mask(start, end);
+                            let cond = Cond::Bits {
+                                byte:   start / 8,
+                                mask,
+                                target: rng.rand() as u8 & mask,
+                            };
+                            if overlap {
+                                let conds: Vec<(Cond, bool)> = parts.iter()
+                                    .map(|(x, _)| x)
+                                    .chain([&cond])
+                                    .map(|x| (x.clone(), true)).collect();
+                                if !reachable(&stack, &conds) {
+                                    break;
+                                }
+                            }
+                            trace!("proggen: crash {:6} also checks `{}`",
+                                   num_crashes, cond);
+                            parts.push((cond, None));
+                        }
+                    }
+
+                    if params.variable_length {
+                        let len = parts.iter().map(|(cond, _)| {
+                            cond.input_len()
+                        }).max().unwrap();
+                        check_len(&mut stack, len, &mut num_blocks,
+                                  &mut num_length_checks, &mut max_depth);
+                    }
+
+                    let class = if let Some((.., class)) = shared {
+                        class
+                    } else if params.crash_classes.is_empty() {
+                        CrashClass::ALL[rng.rand() % CrashClass::ALL.len()]
+                    } else {
+                        CrashClass::ALL[pick_weighted(&mut rng,
+                                                      &params.crash_classes)]
+                    };
+                    crash_classes.push(class);
+                    let root_cause = match shared {
+                        Some((_, _, root_cause, _)) => root_cause,
+                        None => {
+                            num_root_causes += 1;
+                            num_root_causes - 1
+                        }
+                    };
+                    root_causes.push(root_cause);
+
+                    let stmt = if parts.len() == 1 {
+                        crash_sites.push((parts[0].0.clone(),
+                                          stack.last().unwrap().1.id,
+                                          root_cause, class));
+                        Stmt::Crash {
+                            cond: parts.pop().unwrap().0,
+                            id:   num_crashes,
+                            class,
+                            block,
+                        }
+                    } else {
+                        for part in &mut parts {
+                            part.1 = (!hidden).then(|| {
+                                new_block(&mut num_blocks).id
+                            });
+                        }
+                        num_conjunctions += 1;
+                        Stmt::Conjunction {
+                            parts,
+                            id: num_crashes,
+                            class,
+                            block,
+                        }
+                    };
+                    stack.last_mut().unwrap().1.stmts.push(stmt);
+                    num_crashes += 1;
+                    if hidden {
+                        hidden_crashes += 1;
+                        if hidden_depths.len() <= stack.len() {
+                            hidden_depths.resize(stack.len() + 1, 0);
+                        }
+                        hidden_depths[stack.len()] += 1;
+                    }
+                    if crash_depths.len() <= stack.len() {
+                        crash_depths.resize(stack.len() + 1, 0);
+                    }
+                    crash_depths[stack.len()] += 1;
+                    max_depth = max_depth.max(stack.len());
+                } else if params.reject_chance != 0 && !reuse &&
+                        !integrity && !length_field &&
+                        rng.rand().is_multiple_of(params.reject_chance) {
+                    // Random chance for the condition to guard a reject
+                    // instead, likewise. Everything after it needs the
+                    // condition to fail, so it's never reused.
+                    if matches!(cond, Cond::Bits { .. }) {
+                        bit_conds.pop();
+                    }
+                    let block = new_block(&mut num_blocks).id;
+                    trace!("proggen: block {:6} at depth {:3} rejects on \
+                            `{}`", block, stack.len(), cond);
+                    stack.last_mut().unwrap().1.stmts
+                        .push(Stmt::Reject { cond, block });
+                    num_rejects += 1;
+                    max_depth = max_depth.max(stack.len());
+                } else {
+                    let block = new_block(&mut num_blocks);
+                    trace!("proggen: block {:6} at depth {:3} checks `{}`",
+                           block.id, stack.len(), cond);
+                    stack.push((Open::If { cond, body: None }, block));
+                    max_depth = max_depth.max(stack.len() - 1);
+                }
+            } else if !rejected {
+                failed_bits = Some(num_bits);
+            }
+        }
+
+        // Random chance to generate a match, dispatching on 4 to 256 values
+        // of a slice of 2 to 8 bits. Like crashes, nothing is drawn when
+        // matches are disabled.
+        if failed_bits.is_none() && !deep(&stack) &&
+                params.match_chance != 0 &&
+                rng.rand().is_multiple_of(params.match_chance) {
+            let num_bits = rng.rand() % 7 + 2;
+            if let Some((start, end)) = find_unused_bits(
+                    &mut used_bits, &mut rng, num_bits, 1000) {
+                let mask = slice_mask(start, end);
+
+                // Pick the arms' values from all values of the slice by a
+                // partial shuffle
+                let mut values: Vec<u8> = (0..=255u8)
+                    .filter(|x| x & !mask == 0).collect();
+                let num_values = 4 + rng.rand() % (values.len() - 3);
+                for ii in 0..num_values {
+                    let jj = ii + rng.rand() % (values.len() - ii);
+                    values.swap(ii, jj);
+                }
+                values.truncate(num_values);
+                if overlap {
+                    values.retain(|&target| {
+                        let byte = start / 8;
+                        reachable(&stack, &[(Cond::Bits { byte, mask, target },
+                                             true)])
+                    });
+                }
+
+                if values.is_empty() {
+                    trace!("proggen: match on byte {:4} & {:#010b} would be \
+                            unreachable", start / 8, mask);
+                } else {
+                    if params.variable_length {
+                        check_len(&mut stack, start / 8 + 1, &mut num_blocks,
+                                  &mut num_length_checks, &mut max_depth);
+                    }
+                    let block = new_block(&mut num_blocks);
+                    trace!("proggen: match at depth {:3} on byte {:4} & \
+                            {:#010b}, {} arms from block {}", stack.len(),
+                            start / 8, mask, values.len(), block.id);
+                    stack.push((Open::Match {
+                        byte: start / 8,
+                        mask,
+                        values,
+                        arms: Vec::new(),
+                    }, block));
+                    max_depth = max_depth.max(stack.len() - 1);
+                    num_matches += 1;
+                    num_arms += 1;
+                }
+            } else {
+                failed_bits = Some(num_bits);
+            }
+        }
+
+        // Random chance to generate a loop, like matches nothing is drawn
+        // when loops are disabled
+        if failed_bits.is_none() && !deep(&stack) && params.loop_chance != 0 &&
+                rng.rand().is_multiple_of(params.loop_chance) {
+            let num_bits = rng.rand() % 4 + 1;
+            if let Some((start, end)) = find_unused_bits(
+                    &mut used_bits, &mut rng, num_bits, 1000) {
+                let mask = slice_mask(start, end);
+                let count = Cond::Bits { byte: start / 8, mask, target: 0 };
+                if overlap && !reachable(&stack, &[(count, false)]) {
+                    trace!("proggen: loop on byte {:4} & {:#010b} would be \
+                            unreachable", start / 8, mask);
+                } else {
+                    if params.variable_length {
+                        check_len(&mut stack, start / 8 + 1, &mut num_blocks,
+                                  &mut num_length_checks, &mut max_depth);
+                    }
+                    let block = new_block(&mut num_blocks);
+                    trace!("proggen: block {:6} at depth {:3} loops on byte \
+                            {:4} & {:#010b}", block.id, stack.len(),
+                            start / 8, mask);
+                    stack.push((Open::Loop { byte: start / 8, mask }, block));
+                    max_depth = max_depth.max(stack.len() - 1);
+                    num_loops += 1;
+                }
+            } else {
+                failed_bits = Some(num_bits);
+            }
+        }
+
+        // Random chance to call an existing function. Functions are only
+        // generated once complete, so calls can't recurse.
+        if failed_bits.is_none() && params.call_chance != 0 &&
+                !functions.is_empty() &&
+                rng.rand().is_multiple_of(params.call_chance) {
+            let function = rng.rand() % functions.len();
+            if params.variable_length {
+                check_len(&mut stack, function_lens[function],
+                          &mut num_blocks, &mut num_length_checks,
+                          &mut max_depth);
+            }
+            trace!("proggen: calling function {} at depth {:3}", function,
+                   stack.len());
+            stack.last_mut().unwrap().1.stmts.push(Stmt::Call { function });
+            num_calls += 1;
+        }
+
+        if let Some(num_bits) = failed_bits {
+            alloc_failures += 1;
+            trace!("proggen: failed to allocate {} free bits ({} of {} \
+                    failures)", num_bits, alloc_failures,
+                    params.max_alloc_failures);
+            if alloc_failures >= params.max_alloc_failures {
+                // Fail if there were too many failed attempts to find
+                // free bits.
+                verbose!("proggen: stopping, out of free input bits");
+                break;
+            }
+        }
+
+        num_opens += (stack.len() > open_len) as usize;
+        num_iters += 1;
+
+        // Random chance to de-tab. When aiming for a depth, the chance is
+        // the estimated chance of opening a block scaled by how deep the
+        // block is past the target, which settles around the target with
+        // closing and opening blocks in balance.
+        let close = stack.len() > stage_depth && if params.target_depth == 0. {
+            rng.rand().is_multiple_of(params.end_block_chance)
+        } else {
+            let chance = num_opens as f64 / num_iters as f64 *
+                num_closes as f64 / num_pops as f64 *
+                (depth(&stack) as f64 + 0.5) / params.target_depth;
+            ((rng.rand() >> 11) as f64 / (1u64 << 53) as f64) < chance
+        };
+        if close {
+            let (open, block) = stack.last().unwrap();
+            trace!("proggen: closing block {}", block.id);
+
+            // Every arm of a match is followed by the next one, and there is
+            // a random chance to follow an if with an else block. Nothing is
+            // drawn when else blocks are disabled, see crashes above. Length
+            // checks get no else, which could only run on inputs too short
+            // for the checks its statements would need.
+            let next = match open {
+                Open::Match { values, arms, .. }
+                        if arms.len() + 1 < values.len() => {
+                    num_arms += 1;
+                    Some(new_block(&mut num_blocks))
+                }
+                Open::If { cond: Cond::Length { .. }, .. } => None,
+                Open::If { cond, body: None } if params.else_chance != 0 &&
+                        rng.rand().is_multiple_of(params.else_chance) &&
+                        (!overlap || reachable(&stack[..stack.len() - 1],
+                                               &[(cond.clone(), false)])) => {
+                    let else_block = new_block(&mut num_blocks);
+                    trace!("proggen: block {:6} is the else of block {}",
+                           else_block.id, block.id);
+                    num_else += 1;
+                    Some(else_block)
+                }
+                _ => None,
+            };
+            num_closes += 1;
+            num_pops += next.is_none() as usize;
+
+            // Random chance to move the contents of an if statement's block
+            // to a new function, called from the block
+            let outline = next.is_none() &&
+                matches!(open, Open::If { body: None, .. }) &&
+                params.function_chance != 0 &&
+                rng.rand().is_multiple_of(params.function_chance);
+            if outline {
+                let block = &mut stack.last_mut().unwrap().1;
+                let mut body = new_block(&mut num_blocks);
+                body.stmts = std::mem::replace(&mut block.stmts,
+                    vec![Stmt::Call { function: functions.len() }]);
+                trace!("proggen: function {} from block {} starts with \
+                        block {}", functions.len(), block.id, body.id);
+                functions.push(body);
+                function_lens.push(checked_len(&stack));
+                num_calls += 1;
+            }
+
+            end_block(&mut stack, next);
+        }
+
+        // Random chance to end the stage, once it has its share of blocks,
+        // or right away when aiming for a number of blocks. The next stage
+        // is guarded by a condition at the end of this one.
+        if stage + 1 < stages &&
+                (num_blocks - stage_start) as u64 >=
+                    min_blocks / stages as u64 &&
+                (params.target_blocks != 0 ||
+                 rng.rand().is_multiple_of(params.done_chance)) {
+            close_blocks(&mut stack, stage_depth, &mut num_blocks,
+                         &mut num_arms);
+
+            // The next record handler starts over in a new function, with
+            // the bits of its own record
+            if parser {
+                handlers.push(functions.len());
+                functions.push(stack.pop().unwrap().1);
+                function_lens.push(0);
+                handler_bits += used_bits.difference(&dead_bits).count();
+                used_bits.clone_from(&dead_bits);
+                bit_conds.clear();
+                crash_sites.clear();
+                length_fields.clear();
+
+                verbose!("proggen: record handler {} of {} after {} blocks",
+                         stage + 2, stages, num_blocks - stage_start);
+                let block = new_block(&mut num_blocks);
+                trace!("proggen: record handler {} starts with block {}",
+                       stage + 2, block.id);
+                stack.push((Open::Entry, block));
+                stage += 1;
+                stage_start = num_blocks;
+                continue;
+            }
+
+            let num_bits = if params.plateau_bits != 0 {
+                params.plateau_bits
+            } else {
+                condition_bits(&mut rng, &params.condition_bits)
+            };
+            let Some((start, end)) = find_unused_bits(
+                    &mut used_bits, &mut rng, num_bits, 1000) else {
+                verbose!("proggen: stopping, out of free input bits for \
+                          stage {}", stage + 2);
+                break;
+            };
+            let cond = if num_bits > 8 {
+                let value = (rng.rand() as u64) >> (64 - num_bits);
+                Cond::Magic { byte: start / 8, size: num_bits / 8, value }
+            } else {
+                let mask = slice_mask(start, end);
+                Cond::Bits {
+                    byte:   start / 8,
+                    mask,
+                    target: rng.rand() as u8 & mask,
+                }
+            };
+
+            verbose!("proggen: stage {} of {} after {} blocks, checking \
+                      `{}`", stage + 2, stages, num_blocks - stage_start,
+                     cond);
+            if params.variable_length {
+                check_len(&mut stack, cond.input_len(), &mut num_blocks,
+                          &mut num_length_checks, &mut max_depth);
+            }
+            let block = new_block(&mut num_blocks);
+            stack.push((Open::If { cond, body: None }, block));
+            max_depth = max_depth.max(stack.len() - 1);
+            stage += 1;
+            stage_depth = stack.len();
+            stage_start = num_blocks;
+            continue;
+        }
+
+        // Random chance to end the loop, likewise
+        if stage + 1 == stages &&
+                num_blocks as u64 >= min_blocks &&
+                num_crashes >= params.min_crashes &&
+                (params.target_blocks != 0 ||
+                 rng.rand().is_multiple_of(params.done_chance)) {
+            verbose!("proggen: stopping, done after {} blocks", num_blocks);
+            break;
+        }
+    }
+
+    draws.start(None, &rng);
+
+    // Clean out brackets, along with the blocks of the stages, down to the
+    // entry block or the block of the last record handler
+    close_blocks(&mut stack, if parser { 2 } else { 1 }, &mut num_blocks,
+                 &mut num_arms);
+
+    // Finish the last record handler, and dispatch to the handlers on
+    // distinct random tags, picked by a partial shuffle
+    if parser {
+        handlers.push(functions.len());
+        functions.push(stack.pop().unwrap().1);
+        function_lens.push(0);
+
+        let mut tags: Vec<u8> = (0..=255u8).collect();
+        for ii in 0..handlers.len() {
+            let jj = ii + rng.rand() % (tags.len() - ii);
+            tags.swap(ii, jj);
+        }
+        let arms = tags.into_iter().zip(handlers.iter().copied()).collect();
+        stack[0].1.stmts.push(Stmt::Records { arms });
+    }
+
+    // Add join blocks to a finished block and all blocks nested in it
+    fn add_joins(block: &mut Block, rng: &mut Rng, chance: usize,
+                 num_blocks: &mut usize, num_joins: &mut usize) {
+        for stmt in &mut block.stmts {
+            match stmt {
+                Stmt::If { body, else_body, .. } => {
+                    add_joins(body, rng, chance, num_blocks, num_joins);
+                    if let Some(else_body) = else_body {
+                        add_joins(else_body, rng, chance, num_blocks,
+                                  num_joins);
+                    }
+                }
+                Stmt::Match { arms, .. } => {
+                    for (_, body) in arms {
+                        add_joins(body, rng, chance, num_blocks, num_joins);
+                    }
+                }
+                Stmt::Loop { body, .. } => {
+                    add_joins(body, rng, chance, num_blocks, num_joins);
+                }
+                _ => {}
+            }
+        }
+
+        // Pick the sources among the if statements in the block, as the
+        // index of the statement and whether it's the else block
+        let mut sources = Vec::new();
+        for (ii, stmt) in block.stmts.iter().enumerate() {
+            if let Stmt::If { else_body, .. } = stmt {
+                if rng.rand().is_multiple_of(chance) {
+                    sources.push((ii, false));
+                }
+                if else_body.is_some() && rng.rand().is_multiple_of(chance) {
+                    sources.push((ii, true));
+                }
+            }
+        }
+        if sources.len() < 2 {
+            return;
+        }
+
+        let join = *num_joins;
+        *num_joins += 1;
+        for &(ii, is_else) in &sources {
+            if let Stmt::If { body, else_body, .. } = &mut block.stmts[ii] {
+                let source = if is_else { else_body.as_mut().unwrap() }
+                             else { body };
+                source.stmts.push(Stmt::SetJoin { join });
+            }
+        }
+        let body = new_block(num_blocks);
+        trace!("proggen: block {:6} joins {} blocks in block {}", body.id,
+               sources.len(), block.id);
+        block.stmts.push(Stmt::Join { join, body });
+    }
+
+    // Number of join blocks
+    let mut num_joins = 0usize;
+
+    let mut entry = stack.pop().unwrap().1;
+    if params.join_chance != 0 {
+        for block in functions.iter_mut().chain(std::iter::once(&mut entry)) {
+            add_joins(block, &mut rng, params.join_chance, &mut num_blocks,
+                      &mut num_joins);
+        }
+    }
+
+    // Make blocks flaky at random, the finished block and all blocks nested
+    // in it
+    fn add_flaky(block: &mut Block, rng: &mut Rng, chance: usize,
+                 num_flaky: &mut usize) {
+        if rng.rand().is_multiple_of(chance) {
+            trace!("proggen: block {:6} is flaky", block.id);
+            block.flaky = true;
+            *num_flaky += 1;
+        }
+        for stmt in &mut block.stmts {
+            match stmt {
+                Stmt::If { body, else_body, .. } => {
+                    add_flaky(body, rng, chance, num_flaky);
+                    if let Some(else_body) = else_body {
+                        add_flaky(else_body, rng, chance, num_flaky);
+                    }
+                }
+                Stmt::Match { arms, .. } => {
+                    for (_, body) in arms {
+                        add_flaky(body, rng, chance, num_flaky);
+                    }
+                }
+                Stmt::Join { body, .. } | Stmt::Loop { body, .. } => {
+                    add_flaky(body, rng, chance, num_flaky);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Number of flaky blocks
+    let mut num_flaky = 0usize;
+
+    if params.flaky_chance != 0 {
+        for block in functions.iter_mut().chain(std::iter::once(&mut entry)) {
+            add_flaky(block, &mut rng, params.flaky_chance, &mut num_flaky);
+        }
+    }
+
+    // Put every function in a random module. Nothing is drawn for a single
+    // module.
+    let modules: Vec<usize> = if params.modules > 1 {
+        functions.iter().map(|_| rng.rand() % params.modules).collect()
+    } else {
+        vec![0; functions.len()]
+    };
+
+    // Pick distinct functions as the other entry points by a partial
+    // shuffle. Nothing is drawn for a single entry point.
+    let mut entry_points: Vec<usize> = (0..functions.len()).collect();
+    let count = params.entry_points.saturating_sub(1).min(functions.len());
+    for ii in 0..count {
+        let jj = ii + rng.rand() % (entry_points.len() - ii);
+        entry_points.swap(ii, jj);
+    }
+    entry_points.truncate(count);
+    if count + 1 < params.entry_points {
+        verbose!("proggen: only {} functions for {} entry points", count,
+                 params.entry_points);
+    }
+
+    if num_crashes != 0 {
+        let depths: Vec<String> = crash_depths.iter().enumerate()
+            .filter(|&(_, &count)| count != 0)
+            .map(|(depth, count)| format!("{}: {}", depth, count))
+            .collect();
+        verbose!("proggen: crashes by depth {}", depths.join(", "));
+    }
+    if num_derived != 0 {
+        verbose!("proggen: {} conditions on derived values", num_derived);
+    }
+    if num_rejects != 0 {
+        verbose!("proggen: {} rejects", num_rejects);
+    }
+    if num_root_causes != num_crashes {
+        verbose!("proggen: {} root causes of {} crashes", num_root_causes,
+                 num_crashes);
+    }
+
+    verbose!("proggen: {} blocks ({} else blocks, {} match arms in {} \
+              matches, {} loops, {} joins, {} length checks, {} flaky), {} \
+              functions ({} calls), {} crashes ({} without coverage, {} \
+              conjunctions), max depth {}, {} of {} input bits used",
+             num_blocks, num_else, num_arms, num_matches, num_loops,
+             num_joins, num_length_checks, num_flaky, functions.len(),
+             num_calls,
+             num_crashes, hidden_crashes, num_conjunctions, max_depth,
+             handler_bits + used_bits.difference(&dead_bits).count(),
+             max_bits * handlers.len().max(1));
+
+    draws.finish(&rng);
+    Program {
+        params:      params.clone(),
+        input_bytes: max_input_size_bits.div_ceil(8),
+        num_blocks,
+        num_crashes,
+        crash_classes,
+        root_causes,
+        num_flaky,
+        entry,
+        functions,
+        variant:     0,
+        modules,
+        entry_points,
+        draws,
+    }
+}
+
+/// Generate a program like [`generate`], trying again from new seeds up to
+/// [`Params::max_retries`] times while it falls short of the acceptance
+/// criteria of `params`, see [`Program::check_acceptance`]. Try `n` is
+/// generated from stream `n` of the mixed seed, apart from the streams
+/// campaigns derive the seeds of their programs from, and records the seed
+/// it was generated from like any other program.
+pub fn generate_accepted(params: &Params) -> Result<Program, String> {
+    let seed = rng::splitmix64(params.seed as u64);
+    let mut params = params.clone();
+    for retry in 0.. {
+        let program = generate(&params);
+        let Err(err) = program.check_acceptance() else {
+            return Ok(program);
+        };
+        if retry == params.max_retries {
+            if retry == 0 {
+                info!("proggen: seed {} {}", params.seed, err);
+                return Ok(program);
+            }
+            return Err(format!("seed {} {}, and so did the {} programs \
+                                before it", params.seed, err, retry));
+        }
+        let next = rng::derive(seed, retry as u64 + 1) as usize;
+        info!("proggen: seed {} {}, trying seed {}", params.seed, err,
+              next);
+        params.seed = next;
+    }
+    unreachable!()
+}
+
+impl Block {
+    /// Conditions on derived values of the block's own statements, whose
+    /// locals are computed at the start of the block, see
+    /// [`Cond::derivation`]
+    pub fn derived(&self) -> impl Iterator<Item = &Cond> {
+        self.conds().filter(|cond| matches!(cond, Cond::Derived { .. }))
+    }
+
+    /// Conditions of the block's own if statements, crashes, and rejects,
+    /// not counting those of the blocks nested in it
+    pub fn conds(&self) -> impl Iterator<Item = &Cond> {
+        self.stmts.iter().flat_map(|stmt| match stmt {
+            Stmt::If { cond, .. } | Stmt::Crash { cond, .. } |
+                Stmt::Reject { cond, .. } => vec![cond],
+            Stmt::Conjunction { parts, .. } => {
+                parts.iter().map(|(cond, _)| cond).collect()
+            }
+            _ => Vec::new(),
+        })
+    }
+
+    /// IDs of the blocks of the block's if statements setting `join`, any
+    /// of which reaches the join block
+    pub fn join_sources(&self, join: usize) -> Vec<usize> {
+        let sets = |source: &Block| source.stmts.iter().any(|x| {
+            matches!(x, Stmt::SetJoin { join: x } if *x == join)
+        });
+        let mut sources = Vec::new();
+        for stmt in &self.stmts {
+            if let Stmt::If { body, else_body, .. } = stmt {
+                for source in std::iter::once(body).chain(else_body.iter()) {
+                    if sets(source) {
+                        sources.push(source.id);
+                    }
+                }
+            }
+        }
+        sources
+    }
+}
+
+impl Program {
+    /// Check that the program meets the acceptance criteria of its
+    /// parameters: at least `min_blocks` blocks, or within
+    /// `target_tolerance` of `target_blocks`, at most `max_blocks`, at least
+    /// `min_crashes` crashes, and a block nested `min_depth` deep in its
+    /// function. Generation stops short of them when it runs out of free
+    /// input bits, and can go past the limits when closing off the
+    /// program. Returns which one it misses.
+    pub fn check_acceptance(&self) -> Result<(), String> {
+        let params = &self.params;
+        let depth = self.stats().blocks_by_depth.len().saturating_sub(1);
+        // The graph decides the number of blocks of programs shaped after
+        // one
+        let sized = params.cfg.is_empty();
+        let blocks = self.num_blocks as u64;
+        let target = params.target_blocks as f64;
+        if sized && params.target_blocks != 0 &&
+                (blocks as f64 - target).abs() >
+                    target * params.target_tolerance {
+            Err(format!("misses `target_blocks` with {} of {} blocks",
+                        self.num_blocks, params.target_blocks))
+        } else if sized && params.target_blocks == 0 &&
+                blocks < params.min_blocks {
+            Err(format!("falls short of `min_blocks` with {} of {} blocks",
+                        self.num_blocks, params.min_blocks))
+        } else if sized && params.max_blocks != 0 &&
+                blocks > params.max_blocks {
+            Err(format!("exceeds `max_blocks` with {} of {} blocks",
+                        self.num_blocks, params.max_blocks))
+        } else if self.num_crashes < params.min_crashes {
+            Err(format!("falls short of `min_crashes` with {} of {} crashes",
+                        self.num_crashes, params.min_crashes))
+        } else if depth < params.min_depth {
+            Err(format!("falls short of `min_depth` with a depth of {} of {}",
+                        depth, params.min_depth))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Emit the program as Rust source
+    ///
+    /// This produces a `crashme` function taking the input and a callback
+    /// which is invoked with the ID of every block as it is reached. It
+    /// returns the ID of the crash the input triggered, if any. The
+    /// program's other functions are emitted before it as `func_<n>`, with
+    /// the same signature. Programs with several entry points also get a
+    /// `crashme_<n>` function for every other entry point, and a
+    /// `crashme_entry` function running the entry point it is given. Every
+    /// coverage record is labeled with the block's
+    /// [stable ID](Program::stable_ids) in a comment. The size of the input
+    /// and the number of blocks and crashes are not part of the source, they
+    /// are read from [`Program::info`] at runtime so the harness code does
+    /// not depend on them.
+    pub fn to_rust(&self) -> String {
+        // Create a string to contain our output program source code
+        let mut program = String::new();
+        let ids = self.stable_ids();
+
+        // Flaky blocks draw from a xorshift RNG of every thread running the
+        // program
+        if self.num_flaky != 0 {
+            program += "fn _flaky() -> bool {\n";
+            program += "    thread_local!(static STATE: std::cell::Cell<u64> = const { std::cell::Cell::new(0x2545f4914f6cdd1d) });\n";
+            program += "    STATE.with(|state| {\n";
+            program += "        let mut x = state.get();\n";
+            program += "        x ^= x << 13;\n";
+            program += "        x ^= x >> 7;\n";
+            program += "        x ^= x << 17;\n";
+            program += "        state.set(x);\n";
+            program += &format!("        x % {} == 0\n",
+                                self.params.flaky_hit_chance);
+            program += "    })\n";
+            program += "}\n\n";
+        }
+
+        for (ii, function) in self.functions.iter().enumerate() {
+            program += &format!("#[inline(never)] fn func_{}<F: FnMut(u32)>(_input: &[u8], _hit: &mut F) -> Option<u32> {{\n", ii);
+            emit_rust_block(&mut program, function, 1, &ids);
+            program += "    None\n";
+            program += "}\n\n";
+        }
+
+        // The good stuff
+        program += "#[inline(never)] pub fn crashme<F: FnMut(u32)>(_input: &[u8], _hit: &mut F) -> Option<u32> {\n";
+
+        emit_rust_block(&mut program, &self.entry, 1, &ids);
+
+        // End the program
+        program += "    None\n";
+        program += "}\n";
+
+        // The other entry points, and a dispatcher running any of them
+        if !self.entry_points.is_empty() {
+            for (ii, function) in self.entry_points.iter().enumerate() {
+                program += &format!("\n#[inline(never)] pub fn crashme_{}<F: FnMut(u32)>(_input: &[u8], _hit: &mut F) -> Option<u32> {{\n", ii + 1);
+                program += &format!("    func_{}(_input, _hit)\n", function);
+                program += "}\n";
+            }
+            program += "\npub fn crashme_entry<F: FnMut(u32)>(_entry: usize, _input: &[u8], _hit: &mut F) -> Option<u32> {\n";
+            program += "    match _entry {\n";
+            for ii in 1..=self.entry_points.len() {
+                program += &format!("        {} => crashme_{}(_input, _hit),\n",
+                                    ii, ii);
+            }
+            program += "        _ => crashme(_input, _hit),\n";
+            program += "    }\n";
+            program += "}\n";
+        }
+        program
+    }
+
+    /// JSON object describing the ground truth of every coverage event and
+    /// crash of the program
+    ///
+    /// Every block, including the coverage events of crashes, lists its
+    /// `function` (null for `crashme`), its `parent` block, its nesting
+    /// `depth` in the function, whether it is `flaky`, the `crash` it is the
+    /// coverage event of if any, the functions it `calls`, its `stable_id`,
+    /// see [`Program::stable_ids`], and the `constraints` which must all
+    /// hold for it to be reached from the start of its function. A
+    /// constraint is a [`Cond::json`] which `holds` or not, or for join
+    /// blocks the blocks of which `any_of` must be reached.
+    /// The conditions of rejects earlier in the same block must fail.
+    /// Every crash lists its `class`, the `block` it is in, its `coverage`
+    /// event (null if it has none), its `depth`, its `constraints`, and its
+    /// `root_cause`, which crashes checking the same condition share. The
+    /// number of `root_causes` is listed too.
+    /// Both list the `probability` of a uniformly random input reaching
+    /// them, see [`Program::probabilities`].
+    pub fn ground_truth(&self) -> String {
+        let mut blocks  = Vec::new();
+        let mut crashes = Vec::new();
+        for (ii, function) in self.functions.iter().enumerate() {
+            truth_block(function, Some(ii), None, 0, &[], &mut blocks,
+                        &mut crashes);
+        }
+        truth_block(&self.entry, None, None, 0, &[], &mut blocks,
+                    &mut crashes);
+        blocks.sort();
+        crashes.sort();
+
+        // Label every block with its stable ID, and close every entry with
+        // its probability
+        let ids = self.stable_ids();
+        let blocks = blocks.into_iter().map(|(id, json)| {
+            (id, format!("{},\"stable_id\":\"{:016x}\"", json, ids[id]))
+        }).collect();
+        let crashes = crashes.into_iter().map(|(id, json)| {
+            (id, format!("{},\"root_cause\":{}", json, self.root_causes[id]))
+        }).collect();
+        let (block_odds, crash_odds) = self.probabilities();
+        let close = |odds: &[f64], entries: Vec<(usize, String)>| {
+            entries.into_iter().map(|(id, json)| {
+                format!("{},\"probability\":{:e}}}", json, odds[id])
+            }).collect::<Vec<String>>()
+        };
+        let blocks  = close(&block_odds, blocks);
+        let crashes = close(&crash_odds, crashes);
+
+        // How many crashes have no coverage event, and at which depths, and
+        // how many root causes there are
+        let stats = self.stats();
+        let root_causes = self.root_causes.iter().max().map_or(0, |x| x + 1);
+        format!("{{\"seed\":{},\"hash\":\"{:016x}\",\"blocks\":[{}],\
+                 \"crashes\":[{}],\"invisible_crashes\":{},\
+                 \"invisible_crashes_by_depth\":{:?},\"root_causes\":{}}}",
+                self.params.seed, self.hash(), blocks.join(","),
+                crashes.join(","), stats.invisible_crashes,
+                stats.invisible_crashes_by_depth, root_causes)
+    }
+
+    /// FNV-1a hash of the emitted program, identifying programs with the
+    /// exact same structure
+    pub fn hash(&self) -> u64 {
+        fnv1a(0xcbf29ce484222325, self.to_rust().as_bytes())
+    }
+
+    /// JSON object describing how the program was generated, recorded in
+    /// experiment manifests. This is also the program's metadata: `blocks`
+    /// and `input_bytes` are all a harness needs to know to run it.
+    pub fn info(&self) -> String {
+        let params = &self.params;
+        let classes: Vec<String> = self.crash_classes.iter()
+            .map(|x| format!("\"{}\"", x.name())).collect();
+
+        // The module of every block is only listed if there are several,
+        // and the function of every block if there are any functions
+        let mut block_modules = if params.modules > 1 {
+            format!(",\"block_modules\":{:?}", self.block_modules())
+        } else {
+            String::new()
+        };
+        if !self.functions.is_empty() {
+            block_modules += &format!(",\"block_functions\":{:?}",
+                                      self.block_functions());
+        }
+        if !self.entry_points.is_empty() {
+            block_modules += &format!(",\"entry_points\":{:?},\
+                                       \"block_entries\":{:?}",
+                                      self.entry_points,
+                                      self.block_entries());
+        }
+
+        // The guard of every block, as its parent and its conditions
+        // encoded like in the IR as hex strings
+        let (parents, guards): (Vec<usize>, Vec<String>) =
+            self.block_guards().into_iter().map(|(parent, guards)| {
+                let hex: String = ir::encode_guards(&guards).iter()
+                    .map(|x| format!("{:02x}", x)).collect();
+                (parent, format!("\"{}\"", hex))
+            }).unzip();
+        block_modules += &format!(",\"block_parents\":{:?},\
+                                   \"block_guards\":[{}]", parents,
+                                  guards.join(","));
+
+        // Magic values are listed as hex strings of their bytes, if there
+        // are any
+        let magic: Vec<String> = self.magic_values().iter().map(|value| {
+            value.iter().map(|x| format!("{:02x}", x)).collect()
+        }).map(|x: String| format!("\"{}\"", x)).collect();
+        if !magic.is_empty() {
+            block_modules += &format!(",\"magic_values\":[{}]",
+                                      magic.join(","));
+        }
+
+        // Stable IDs are 64-bit, so they're hex strings like the hash
+        let ids: Vec<String> = self.stable_ids().iter()
+            .map(|x| format!("\"{:016x}\"", x)).collect();
+        block_modules += &format!(",\"stable_ids\":[{}]", ids.join(","));
+        format!("{{\"seed\":{},\"hash\":\"{:016x}\",\"variant\":{},\
+                 \"blocks\":{},\"crashes\":{},\"crash_classes\":[{}],\
+                 \"root_causes\":{:?},\
+                 \"functions\":{},\"input_bytes\":{},\
+                 \"modules\":{}{},\"params\":{{\
+                 \"max_input_size_bits\":{},\"if_chance\":{},\
+                 \"end_block_chance\":{},\"done_chance\":{},\
+                 \"min_blocks\":{},\"max_blocks\":{},\
+                 \"target_blocks\":{},\"target_tolerance\":{},\
+                 \"crash_chance\":{},\
+                 \"non_coverage_crash_chance\":{},\
+                 \"non_coverage_crash_fraction\":{},\
+                 \"non_coverage_crash_depths\":{:?},\"min_crashes\":{},\
+                 \"max_crashes\":{},\
+                 \"min_depth\":{},\"target_depth\":{},\
+                 \"max_depth\":{},\"max_retries\":{},\
+                 \"conjunction_chance\":{},\"conjunction_size\":{},\
+                 \"reject_chance\":{},\
+                 \"crash_depths\":{:?},\"crash_classes\":{:?},\
+                 \"root_cause_chance\":{},\
+                 \"else_chance\":{},\"match_chance\":{},\
+                 \"loop_chance\":{},\"magic_chance\":{},\
+                 \"compare_chance\":{},\"range_chance\":{},\
+                 \"checksum_chance\":{},\
+                 \"checksum_min_depth\":{},\"integrity_chance\":{},\
+                 \"derived_chance\":{},\
+                 \"stages\":{},\
+                 \"plateau_bits\":{},\
+                 \"record_tags\":{},\
+                 \"function_chance\":{},\"call_chance\":{},\
+                 \"join_chance\":{},\"reuse_chance\":{},\
+                 \"condition_bits\":{:?},\"span_slices\":{},\
+                 \"dead_fraction\":{},\"config_bytes\":{},\
+                 \"rarity_exponent\":{},\
+                 \"flaky_chance\":{},\
+                 \"flaky_hit_chance\":{},\"variable_length\":{},\
+                 \"length_field_chance\":{},\"max_alloc_failures\":{},\
+                 \"patches\":{},\"modules\":{},\"entry_points\":{},\
+                 \"cfg\":{:?}}}}}",
+                params.seed, self.hash(), self.variant, self.num_blocks,
+                self.num_crashes,
+                classes.join(","), self.root_causes, self.functions.len(),
+                self.input_bytes, params.modules.max(1), block_modules,
+                params.max_input_size_bits,
+                params.if_chance, params.end_block_chance,
+                params.done_chance, params.min_blocks, params.max_blocks,
+                params.target_blocks, params.target_tolerance,
+                params.crash_chance,
+                params.non_coverage_crash_chance,
+                params.non_coverage_crash_fraction,
+                params.non_coverage_crash_depths, params.min_crashes,
+                params.max_crashes,
+                params.min_depth, params.target_depth, params.max_depth,
+                params.max_retries,
+                params.conjunction_chance, params.conjunction_size,
+                params.reject_chance, params.crash_depths,
+                params.crash_classes, params.root_cause_chance,
+                params.else_chance, params.match_chance, params.loop_chance,
+                params.magic_chance, params.compare_chance,
+                params.range_chance, params.checksum_chance,
+                params.checksum_min_depth,
+                params.integrity_chance, params.derived_chance,
+                params.stages, params.plateau_bits, params.record_tags,
+                params.function_chance,
+                params.call_chance, params.join_chance, params.reuse_chance,
+                params.condition_bits, params.span_slices,
+                params.dead_fraction, params.config_bytes,
+                params.rarity_exponent,
+                params.flaky_chance,
+                params.flaky_hit_chance, params.variable_length,
+                params.length_field_chance, params.max_alloc_failures,
+                params.patches, params.modules, params.entry_points,
+                params.cfg)
+    }
+
+    /// Magic values the program compares slices of its input against, as
+    /// the little endian bytes the input has to hold, sorted and without
+    /// duplicates, see [`Cond::Magic`]. This is the dictionary a fuzzer
+    /// knowing the program's input format would be given.
+    pub fn magic_values(&self) -> Vec<Vec<u8>> {
+        // Add the magic values of `block` and every block nested in it
+        fn collect(block: &Block, values: &mut BTreeSet<Vec<u8>>) {
+            for cond in block.conds() {
+                if let Cond::Magic { size, value, .. } = *cond {
+                    values.insert(value.to_le_bytes()[..size].to_vec());
+                }
+            }
+            for stmt in &block.stmts {
+                match stmt {
+                    Stmt::If { body, else_body, .. } => {
+                        collect(body, values);
+                        if let Some(else_body) = else_body {
+                            collect(else_body, values);
+                        }
+                    }
+                    Stmt::Match { arms, .. } => {
+                        for (_, body) in arms {
+                            collect(body, values);
+                        }
+                    }
+                    Stmt::Loop { body, .. } | Stmt::Join { body, .. } => {
+                        collect(body, values);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut values = BTreeSet::new();
+        for function in self.functions.iter().chain(Some(&self.entry)) {
+            collect(function, &mut values);
+        }
+        values.into_iter().collect()
+    }
+
+    /// Module of every block, indexed by block ID, see [`Params::modules`].
+    /// The IDs of blocks removed from a variant are left in module 0.
+    pub fn block_modules(&self) -> Vec<usize> {
+        self.block_functions().into_iter()
+            .map(|function| self.modules.get(function).copied().unwrap_or(0))
+            .collect()
+    }
+
+    /// Function of every block, indexed by block ID. The blocks of the entry
+    /// are in function `functions.len()`, after every called function, as
+    /// are the IDs of blocks removed from a variant.
+    pub fn block_functions(&self) -> Vec<usize> {
+        // Put `block` and every block nested in it in `function`
+        fn assign(block: &Block, function: usize,
+                  functions: &mut [usize]) {
+            functions[block.id] = function;
+            for stmt in &block.stmts {
+                match stmt {
+                    Stmt::If { body, else_body, .. } => {
+                        assign(body, function, functions);
+                        if let Some(else_body) = else_body {
+                            assign(else_body, function, functions);
+                        }
+                    }
+                    Stmt::Match { arms, .. } => {
+                        for (_, body) in arms {
+                            assign(body, function, functions);
+                        }
+                    }
+                    Stmt::Loop { body, .. } | Stmt::Join { body, .. } => {
+                        assign(body, function, functions);
+                    }
+                    Stmt::Crash { block: Some(id), .. } |
+                        Stmt::Reject { block: id, .. } => {
+                        functions[*id] = function;
+                    }
+                    Stmt::Conjunction { parts, block, .. } => {
+                        for &id in parts.iter().filter_map(|x| x.1.as_ref())
+                                .chain(block) {
+                            functions[id] = function;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut functions = vec![self.functions.len(); self.num_blocks];
+        for (index, function) in self.functions.iter().enumerate() {
+            assign(function, index, &mut functions);
+        }
+        functions
+    }
+
+    /// Block every block is nested in, and the conditions guarding it
+    /// there, each of which must hold or fail, indexed by block ID. This is
+    /// the ground truth taint tracking and solvers learn about the branches
+    /// next to the blocks a fuzzer reached. Entry blocks of functions, and
+    /// the IDs of blocks removed from a variant, are nested in themselves,
+    /// and join blocks are reached through other blocks rather than guarded
+    /// by conditions.
+    pub fn block_guards(&self) -> Vec<(usize, Vec<(Cond, bool)>)> {
+        // Add the guards of the blocks nested in `block`
+        fn guard(block: &Block, guards: &mut [(usize, Vec<(Cond, bool)>)]) {
+            let parent = block.id;
+            for stmt in &block.stmts {
+                match stmt {
+                    Stmt::If { cond, body, else_body } => {
+                        guards[body.id] = (parent, vec![(cond.clone(), true)]);
+                        guard(body, guards);
+                        if let Some(else_body) = else_body {
+                            guards[else_body.id] =
+                                (parent, vec![(cond.clone(), false)]);
+                            guard(else_body, guards);
+                        }
+                    }
+                    Stmt::Match { byte, mask, arms } => {
+                        for &(target, ref body) in arms {
+                            let cond = Cond::Bits { byte: *byte, mask: *mask,
+                                                    target };
+                            guards[body.id] = (parent, vec![(cond, true)]);
+                            guard(body, guards);
+                        }
+                    }
+                    Stmt::Loop { byte, mask, body } => {
+                        // The body runs if the bits of the count are not all
+                        // zero
+                        let cond = Cond::Bits { byte: *byte, mask: *mask,
+                                                target: 0 };
+                        guards[body.id] = (parent, vec![(cond, false)]);
+                        guard(body, guards);
+                    }
+                    Stmt::Join { body, .. } => {
+                        guards[body.id] = (parent, Vec::new());
+                        guard(body, guards);
+                    }
+                    Stmt::Crash { cond, block: Some(id), .. } |
+                        Stmt::Reject { cond, block: id } => {
+                        guards[*id] = (parent, vec![(cond.clone(), true)]);
+                    }
+                    Stmt::Conjunction { parts, block, .. } => {
+                        for (cond, part) in parts {
+                            if let Some(part) = *part {
+                                guards[part] =
+                                    (parent, vec![(cond.clone(), true)]);
+                            }
+                        }
+                        if let Some(id) = *block {
+                            guards[id] = (parent, parts.iter()
+                                .map(|(cond, _)| (cond.clone(), true))
+                                .collect());
+                        }
+                    }
+                    Stmt::Crash { block: None, .. } | Stmt::Call { .. } |
+                        Stmt::Records { .. } | Stmt::SetJoin { .. } => {}
+                }
+            }
+        }
+
+        let mut guards: Vec<(usize, Vec<(Cond, bool)>)> = (0..self.num_blocks)
+            .map(|id| (id, Vec::new())).collect();
+        for function in self.functions.iter().chain(Some(&self.entry)) {
+            guard(function, &mut guards);
+        }
+        guards
+    }
+
+    /// Entry points reaching every block, indexed by block ID, as a bitmask
+    /// with bit `n` set if entry point `n` reaches the block, see
+    /// [`Params::entry_points`]. Entry point 0 is `crashme`.
+    pub fn block_entries(&self) -> Vec<u64> {
+        // Functions called by `block` and every block nested in it
+        fn calls(block: &Block, callees: &mut Vec<usize>) {
+            for stmt in &block.stmts {
+                match stmt {
+                    Stmt::If { body, else_body, .. } => {
+                        calls(body, callees);
+                        if let Some(else_body) = else_body {
+                            calls(else_body, callees);
+                        }
+                    }
+                    Stmt::Match { arms, .. } => {
+                        for (_, body) in arms {
+                            calls(body, callees);
+                        }
+                    }
+                    Stmt::Loop { body, .. } | Stmt::Join { body, .. } => {
+                        calls(body, callees);
+                    }
+                    Stmt::Call { function } => callees.push(*function),
+                    Stmt::Records { arms } => {
+                        callees.extend(arms.iter().map(|x| x.1));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Functions called by every function, with the entry last
+        let callees: Vec<Vec<usize>> = self.functions.iter()
+            .chain(Some(&self.entry)).map(|function| {
+                let mut callees = Vec::new();
+                calls(function, &mut callees);
+                callees
+            }).collect();
+
+        // Mark the functions every entry point reaches
+        let mut reaches = vec![0u64; callees.len()];
+        let starts = Some(self.functions.len()).into_iter()
+            .chain(self.entry_points.iter().copied());
+        for (entry, start) in starts.enumerate() {
+            let mut todo = vec![start];
+            while let Some(function) = todo.pop() {
+                if reaches[function] & (1 << entry) == 0 {
+                    reaches[function] |= 1 << entry;
+                    todo.extend(&callees[function]);
+                }
+            }
+        }
+
+        self.block_functions().into_iter()
+            .map(|function| reaches[function]).collect()
+    }
+
+    /// Stable ID of every block, indexed by block ID, which unlike the block
+    /// ID doesn't depend on the order blocks are generated in. It's a hash
+    /// of the block's path from the start of `crashme`: the conditions
+    /// holding or failing to enter every block on the way, and the first
+    /// call to every function on the way. Blocks with the same path in
+    /// programs generated with other parameters, or in other variants of
+    /// the program, get the same ID, so which blocks get found can be
+    /// compared across them. Blocks with the same path in one program, as
+    /// conditions which are reused can give, are told apart by the order
+    /// they are walked in. Blocks removed from a variant get zero.
+    pub fn stable_ids(&self) -> Vec<u64> {
+        let mut ids   = vec![0; self.num_blocks];
+        let mut seen  = HashSet::new();
+        let mut calls = vec![None; self.functions.len()];
+        let root = unique(fnv1a(0xcbf29ce484222325, b"crashme"), &mut seen);
+        stable_block(&self.entry, root, &mut ids, &mut seen, &mut calls);
+
+        // Functions only call functions generated before them, so their
+        // first call has been walked when they are
+        for (ii, function) in self.functions.iter().enumerate().rev() {
+            let root = calls[ii].unwrap_or_else(|| {
+                let root = fnv1a(0xcbf29ce484222325,
+                                 format!("func_{}", ii).as_bytes());
+                unique(root, &mut seen)
+            });
+            stable_block(function, root, &mut ids, &mut seen, &mut calls);
+        }
+        ids
+    }
+}
+
+/// FNV-1a hash of `data`, continuing from `hash`
+pub fn fnv1a(hash: u64, data: &[u8]) -> u64 {
+    data.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `hash`, hashed again until it isn't in `seen`, which then holds it
+fn unique(mut hash: u64, seen: &mut HashSet<u64>) -> u64 {
+    while !seen.insert(hash) {
+        hash = fnv1a(hash, b"'");
+    }
+    hash
+}
+
+/// Set the stable ID of `block` to `id`, and of every block nested in it to
+/// the hash of the step into it continuing from `id`, and the stable ID of
+/// the first call to every function it calls in `calls`. See
+/// [`Program::stable_ids`].
+fn stable_block(block: &Block, id: u64, ids: &mut [u64],
+                seen: &mut HashSet<u64>, calls: &mut [Option<u64>]) {
+    ids[block.id] = id;
+    for stmt in &block.stmts {
+        // Stable ID of the step `step` from the block
+        let mut step = |step: String| unique(fnv1a(id, step.as_bytes()), seen);
+
+        match stmt {
+            Stmt::If { cond, body, else_body } => {
+                let body_id = step(format!("if {}", cond));
+                let else_id = else_body.as_ref()
+                    .map(|_| step(format!("else {}", cond)));
+                stable_block(body, body_id, ids, seen, calls);
+                if let (Some(else_body), Some(else_id)) =
+                        (else_body, else_id) {
+                    stable_block(else_body, else_id, ids, seen, calls);
+                }
+            }
+            Stmt::Match { byte, mask, arms } => {
+                let arm_ids: Vec<u64> = arms.iter().map(|&(target, _)| {
+                    step(format!("if {}", Cond::Bits {
+                        byte: *byte, mask: *mask, target,
+                    }))
+                }).collect();
+                for ((_, body), arm_id) in arms.iter().zip(arm_ids) {
+                    stable_block(body, arm_id, ids, seen, calls);
+                }
+            }
+            Stmt::Loop { byte, mask, body } => {
+                let body_id = step(format!("loop _input[{}] & {:#010b}", byte,
+                                           mask));
+                stable_block(body, body_id, ids, seen, calls);
+            }
+            Stmt::Join { join, body } => {
+                // The steps into the join's sources, rather than their IDs
+                let sets = |source: &Block| source.stmts.iter().any(|x| {
+                    matches!(x, Stmt::SetJoin { join: x } if x == join)
+                });
+                let mut sources = Vec::new();
+                for stmt in &block.stmts {
+                    if let Stmt::If { cond, body, else_body } = stmt {
+                        if sets(body) {
+                            sources.push(format!("if {}", cond));
+                        }
+                        if else_body.as_ref().is_some_and(sets) {
+                            sources.push(format!("else {}", cond));
+                        }
+                    }
+                }
+                let body_id = step(format!("join {}", sources.join(", ")));
+                stable_block(body, body_id, ids, seen, calls);
+            }
+            Stmt::Call { function } => {
+                let call_id = step("call".into());
+                calls[*function].get_or_insert(call_id);
+            }
+            Stmt::Records { arms } => {
+                for &(tag, function) in arms {
+                    let call_id = step(format!("record {:#04x}", tag));
+                    calls[function].get_or_insert(call_id);
+                }
+            }
+            Stmt::Crash { cond, block, .. } => {
+                if let Some(block) = *block {
+                    ids[block] = step(format!("crash {}", cond));
+                }
+            }
+            Stmt::Conjunction { parts, block, .. } => {
+                for (cond, part) in parts {
+                    if let Some(part) = *part {
+                        ids[part] = step(format!("part {}", cond));
+                    }
+                }
+                if let Some(block) = *block {
+                    let all: Vec<String> = parts.iter()
+                        .map(|(cond, _)| cond.to_string()).collect();
+                    ids[block] = step(format!("crash {}", all.join(" && ")));
+                }
+            }
+            Stmt::Reject { cond, block } => {
+                ids[*block] = step(format!("reject {}", cond));
+            }
+            Stmt::SetJoin { .. } => {}
+        }
+    }
+}
+
+/// Add the ground truth of `block`, in `function` and nested `depth` blocks
+/// deep in it below `parent`, and of everything nested in it to `blocks` and
+/// `crashes` as JSON keyed by ID, left open for the probability. The block
+/// is reached if all of `path` holds. See [`Program::ground_truth`].
+fn truth_block(block: &Block, function: Option<usize>, parent: Option<usize>,
+               depth: usize, path: &[String],
+               blocks: &mut Vec<(usize, String)>,
+               crashes: &mut Vec<(usize, String)>) {
+    // JSON of an optional number
+    fn opt(x: Option<usize>) -> String {
+        x.map_or("null".into(), |x| x.to_string())
+    }
+
+    // JSON of a block, as the coverage event of `crash` if set
+    let block_json = |id: usize, parent: Option<usize>, depth: usize,
+                      flaky: bool, crash: Option<usize>, calls: &[usize],
+                      path: &[String]| {
+        (id, format!(
+            "{{\"id\":{},\"function\":{},\"parent\":{},\"depth\":{},\
+             \"flaky\":{},\"crash\":{},\"calls\":{:?},\
+             \"constraints\":[{}]", id, opt(function), opt(parent), depth,
+            flaky, opt(crash), calls, path.join(",")))
+    };
+
+    // A constraint that `cond` holds, or does not
+    let holds = |cond: &Cond, holds: bool| {
+        format!("{{\"cond\":{},\"holds\":{}}}", cond.json(), holds)
+    };
+
+    let mut calls = Vec::new();
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::Call { function } => calls.push(*function),
+            Stmt::Records { arms } => {
+                calls.extend(arms.iter().map(|&(_, function)| function));
+            }
+            _ => {}
+        }
+    }
+    blocks.push(block_json(block.id, parent, depth, block.flaky, None, &calls,
+                           path));
+
+    // Rejects passed so far, which must have failed
+    let mut rejected = Vec::new();
+    for stmt in &block.stmts {
+        // `path` with the rejects passed and more constraints
+        let extend = |more: &[String]| {
+            path.iter().chain(&rejected).chain(more).cloned()
+                .collect::<Vec<String>>()
+        };
+
+        match stmt {
+            Stmt::If { cond, body, else_body } => {
+                truth_block(body, function, Some(block.id), depth + 1,
+                            &extend(&[holds(cond, true)]), blocks, crashes);
+                if let Some(else_body) = else_body {
+                    truth_block(else_body, function, Some(block.id),
+                                depth + 1, &extend(&[holds(cond, false)]),
+                                blocks, crashes);
+                }
+            }
+            Stmt::Match { byte, mask, arms } => {
+                for &(target, ref body) in arms {
+                    let cond = Cond::Bits { byte: *byte, mask: *mask, target };
+                    truth_block(body, function, Some(block.id), depth + 1,
+                                &extend(&[holds(&cond, true)]), blocks,
+                                crashes);
+                }
+            }
+            Stmt::Loop { byte, mask, body } => {
+                // The body runs if the bits of the count are not all zero
+                let cond = Cond::Bits { byte: *byte, mask: *mask, target: 0 };
+                truth_block(body, function, Some(block.id), depth + 1,
+                            &extend(&[holds(&cond, false)]), blocks, crashes);
+            }
+            Stmt::Join { join, body } => {
+                let any_of = format!("{{\"any_of\":{:?}}}",
+                                     block.join_sources(*join));
+                truth_block(body, function, Some(block.id), depth + 1,
+                            &extend(&[any_of]), blocks, crashes);
+            }
+            Stmt::Crash { cond, id, class, block: coverage } => {
+                let path = extend(&[holds(cond, true)]);
+                if let Some(coverage) = *coverage {
+                    blocks.push(block_json(coverage, Some(block.id),
+                                           depth + 1, false, Some(*id), &[],
+                                           &path));
+                }
+                crashes.push((*id, format!(
+                    "{{\"id\":{},\"class\":\"{}\",\"block\":{},\
+                     \"coverage\":{},\"depth\":{},\"constraints\":[{}]",
+                    id, class.name(), block.id, opt(*coverage), depth + 1,
+                    path.join(","))));
+            }
+            Stmt::Conjunction { parts, id, class, block: coverage } => {
+                let all: Vec<String> = parts.iter()
+                    .map(|(cond, _)| holds(cond, true)).collect();
+                for ((_, part), constraint) in parts.iter().zip(&all) {
+                    if let Some(part) = *part {
+                        blocks.push(block_json(
+                            part, Some(block.id), depth + 1, false, Some(*id),
+                            &[], &extend(std::slice::from_ref(constraint))));
+                    }
+                }
+                let path = extend(&all);
+                if let Some(coverage) = *coverage {
+                    blocks.push(block_json(coverage, Some(block.id),
+                                           depth + 1, false, Some(*id), &[],
+                                           &path));
+                }
+                crashes.push((*id, format!(
+                    "{{\"id\":{},\"class\":\"{}\",\"block\":{},\
+                     \"coverage\":{},\"depth\":{},\"constraints\":[{}]",
+                    id, class.name(), block.id, opt(*coverage), depth + 1,
+                    path.join(","))));
+            }
+            Stmt::Reject { cond, block: coverage } => {
+                blocks.push(block_json(*coverage, Some(block.id), depth + 1,
+                                       false, None, &[],
+                                       &extend(&[holds(cond, true)])));
+                rejected.push(holds(cond, false));
+            }
+            Stmt::Call { .. } | Stmt::Records { .. } |
+                Stmt::SetJoin { .. } => {}
+        }
+    }
+}
+
+/// Emit the contents of `block` at a tab depth of `depth`, labeling the
+/// coverage of every block with its stable ID in `ids`
+fn emit_rust_block(program: &mut String, block: &Block, depth: usize,
+                   ids: &[u64]) {
+    // Tab in the program by `depth` tabs
+    let tab = |program: &mut String, depth: usize| {
+        for _ in 0..depth { *program += "    "; }
+    };
+
+    // Generate a coverage record based on the unique block ID, flaky blocks
+    // only record it by chance
+    tab(program, depth);
+    if block.flaky {
+        *program += &format!("if _flaky() {{ _hit({}); }} // {:016x}\n",
+                             block.id, ids[block.id]);
+    } else {
+        *program += &format!("_hit({}); // {:016x}\n", block.id,
+                             ids[block.id]);
+    }
+
+    // Declare the flags of the block's joins
+    for stmt in &block.stmts {
+        if let Stmt::Join { join, .. } = stmt {
+            tab(program, depth);
+            *program += &format!("let mut _join_{} = false;\n", join);
+        }
+    }
+
+    // Compute the values derived from the input which the block's
+    // conditions test
+    for cond in block.derived() {
+        for stmt in cond.derivation() {
+            tab(program, depth);
+            *program += &stmt;
+            *program += "\n";
+        }
+    }
+
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::If { cond, body, else_body } => {
+                tab(program, depth);
+                *program += &format!("if {} {{\n", cond.rust());
+                emit_rust_block(program, body, depth + 1, ids);
+                if let Some(else_body) = else_body {
+                    tab(program, depth);
+                    *program += "} else {\n";
+                    emit_rust_block(program, else_body, depth + 1, ids);
+                }
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Match { byte, mask, arms } => {
+                tab(program, depth);
+                *program += &format!("match _input[{}] & {:#010b} {{\n",
+                                     byte, mask);
+                for (value, body) in arms {
+                    tab(program, depth + 1);
+                    *program += &format!("{:#010b} => {{\n", value);
+                    emit_rust_block(program, body, depth + 2, ids);
+                    tab(program, depth + 1);
+                    *program += "}\n";
+                }
+                tab(program, depth + 1);
+                *program += "_ => {}\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Call { function } => {
+                tab(program, depth);
+                *program += &format!(
+                    "if let Some(crash) = func_{}(_input, _hit) {{ \
+                     return Some(crash); }}\n", function);
+            }
+            Stmt::SetJoin { join } => {
+                tab(program, depth);
+                *program += &format!("_join_{} = true;\n", join);
+            }
+            Stmt::Join { join, body } => {
+                tab(program, depth);
+                *program += &format!("if _join_{} {{\n", join);
+                emit_rust_block(program, body, depth + 1, ids);
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Records { arms } => {
+                tab(program, depth);
+                *program += "let mut _pos = 0;\n";
+                tab(program, depth);
+                *program += "while _pos + 2 <= _input.len() {\n";
+                tab(program, depth + 1);
+                *program += "let _end = (_pos + 2 + _input[_pos + 1] as \
+                             usize).min(_input.len());\n";
+                tab(program, depth + 1);
+                *program += "match _input[_pos] {\n";
+                for (tag, function) in arms {
+                    tab(program, depth + 2);
+                    *program += &format!(
+                        "{:#04x} => if let Some(crash) = func_{}(\
+                         &_input[_pos + 2.._end], _hit) {{ \
+                         return Some(crash); }},\n", tag, function);
+                }
+                tab(program, depth + 2);
+                *program += "_ => {}\n";
+                tab(program, depth + 1);
+                *program += "}\n";
+                tab(program, depth + 1);
+                *program += "_pos = _end;\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Loop { byte, mask, body } => {
+                tab(program, depth);
+                *program += &format!(
+                    "for _ in 0..(_input[{}] & {:#010b}) >> {} {{\n",
+                    byte, mask, mask.trailing_zeros());
+                emit_rust_block(program, body, depth + 1, ids);
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Crash { cond, id, class, block } => {
+                tab(program, depth);
+                *program += &format!("if {} {{\n", cond.rust());
+                if let Some(block) = block {
+                    tab(program, depth + 1);
+                    *program += &format!("_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                }
+                tab(program, depth + 1);
+                *program += &format!("return Some({}); // {}\n", id,
+                                     class.name());
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Reject { cond, block } => {
+                tab(program, depth);
+                *program += &format!("if {} {{\n", cond.rust());
+                tab(program, depth + 1);
+                *program += &format!("_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                tab(program, depth + 1);
+                *program += "return None;\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+            Stmt::Conjunction { parts, id, class, block } => {
+                // Count the parts which hold in a scope of their own
+                tab(program, depth);
+                *program += "{\n";
+                tab(program, depth + 1);
+                *program += "let mut _parts = 0;\n";
+                for (cond, block) in parts {
+                    tab(program, depth + 1);
+                    *program += &format!("if {} {{\n", cond.rust());
+                    if let Some(block) = block {
+                        tab(program, depth + 2);
+                        *program += &format!("_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                    }
+                    tab(program, depth + 2);
+                    *program += "_parts += 1;\n";
+                    tab(program, depth + 1);
+                    *program += "}\n";
+                }
+                tab(program, depth + 1);
+                *program += &format!("if _parts == {} {{\n", parts.len());
+                if let Some(block) = block {
+                    tab(program, depth + 2);
+                    *program += &format!("_hit({}); // {:016x}\n", block,
+                             ids[*block]);
+                }
+                tab(program, depth + 2);
+                *program += &format!("return Some({}); // {}\n", id,
+                                     class.name());
+                tab(program, depth + 1);
+                *program += "}\n";
+                tab(program, depth);
+                *program += "}\n";
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Leveled diagnostics
+//!
+//! All diagnostics go to stderr through the [`info!`], [`verbose!`], and
+//! [`trace!`] macros, and are filtered by a single process-wide [`Level`].
+//! Result files and the output of `perf` are not diagnostics and are not
+//! affected.
+//!
+//! [`info!`]: crate::info
+//! [`verbose!`]: crate::verbose
+//! [`trace!`]: crate::trace
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How much diagnostic output to print
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    /// Only errors
+    Quiet,
+
+    /// Progress of every datapoint, the default
+    Normal,
+
+    /// Per-datapoint results and generation summaries
+    Verbose,
+
+    /// Per-trial throughput and every generation decision
+    Trace,
+}
+
+impl Level {
+    /// All levels, from least to most output
+    pub const ALL: [Level; 4] =
+        [Level::Quiet, Level::Normal, Level::Verbose, Level::Trace];
+
+    /// Name of the level, as accepted by [`FromStr`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Quiet   => "quiet",
+            Level::Normal  => "normal",
+            Level::Verbose => "verbose",
+            Level::Trace   => "trace",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Level::ALL.iter().copied().find(|level| level.name() == s)
+            .ok_or_else(|| format!("Unknown log level `{}` (known: quiet, \
+                                    normal, verbose, trace)", s))
+    }
+}
+
+/// Current level, as the index of the level in [`Level::ALL`]
+static LEVEL: AtomicUsize = AtomicUsize::new(Level::Normal as usize);
+
+/// Set the level of diagnostics to print
+pub fn set_level(level: Level) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+/// Get the level of diagnostics being printed
+pub fn level() -> Level {
+    Level::ALL[LEVEL.load(Ordering::Relaxed)]
+}
+
+/// Returns `true` if messages at `level` are printed
+pub fn enabled(level: Level) -> bool {
+    level != Level::Quiet && level <= self::level()
+}
+
+/// Print a diagnostic at `$level` to stderr
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::log::enabled($level) {
+            eprintln!($($arg)*);
+        }
+    }
+}
+
+/// Print a diagnostic at [`Level::Normal`]
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log!($crate::log::Level::Normal, $($arg)*)
+    }
+}
+
+/// Print a diagnostic at [`Level::Verbose`]
+#[macro_export]
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        $crate::log!($crate::log::Level::Verbose, $($arg)*)
+    }
+}
+
+/// Print a diagnostic at [`Level::Trace`]
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::log!($crate::log::Level::Trace, $($arg)*)
+    }
+}
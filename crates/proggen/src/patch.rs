@@ -0,0 +1,187 @@
+//! Patched variants of generated programs
+//!
+//! [`Program::patch`] evolves a program like a code base evolves between
+//! releases: a few branches are added, removed, or check other values, and
+//! everything else is left alone. Every block keeps its ID, so coverage and
+//! corpora of one variant carry over to the next, which is what incremental
+//! and continuous fuzzing strategies rely on. Added blocks get new IDs past
+//! the existing ones, and the IDs of removed blocks are never reused, they
+//! are just never reached.
+
+use crate::rng::Rng;
+use crate::{Block, Cond, Program, Stmt};
+
+/// Call `visit` on `block` and every block nested in it, depth first, until
+/// it returns true. Returns whether it did.
+fn visit(block: &mut Block, visit_fn: &mut dyn FnMut(&mut Block) -> bool)
+        -> bool {
+    if visit_fn(block) {
+        return true;
+    }
+    block.stmts.iter_mut().any(|stmt| match stmt {
+        Stmt::If { body, else_body, .. } => {
+            visit(body, visit_fn) ||
+                else_body.as_mut().is_some_and(|x| visit(x, visit_fn))
+        }
+        Stmt::Match { arms, .. } => {
+            arms.iter_mut().any(|(_, body)| visit(body, visit_fn))
+        }
+        Stmt::Loop { body, .. } | Stmt::Join { body, .. } => {
+            visit(body, visit_fn)
+        }
+        _ => false,
+    })
+}
+
+/// Whether `stmt` is a branch which can be removed
+fn is_branch(stmt: &Stmt) -> bool {
+    matches!(stmt, Stmt::If { .. })
+}
+
+/// Whether `stmt` is a branch on bits of an input byte, which can be
+/// retargeted or get a sibling checking the same bits
+fn is_bits_branch(stmt: &Stmt) -> bool {
+    matches!(stmt, Stmt::If { cond: Cond::Bits { .. }, .. })
+}
+
+impl Program {
+    /// Variant number `variant` of the program, with
+    /// [`Params::patches`](crate::Params::patches) random patches drawn from
+    /// stream `variant` of the program's seed. Each patch either adds an
+    /// empty branch next to a branch on bits of an input byte, checking
+    /// other values of the same bits, removes a branch along with
+    /// everything nested in it, or changes the value a branch on bits of
+    /// an input byte checks for. Patches leaving any block or crash
+    /// unreachable are skipped.
+    pub fn patch(&self, variant: usize) -> Program {
+        let mut program = self.clone();
+        program.variant = variant;
+        let mut rng = Rng::derive(self.params.seed as u64, variant as u64);
+
+        for _ in 0..self.params.patches {
+            let kind = rng.rand() % 3;
+            let sites = program.count(if kind == 1 {
+                is_branch
+            } else {
+                is_bits_branch
+            });
+            if sites == 0 {
+                continue;
+            }
+            let site = rng.rand() % sites;
+
+            // Where conditions can contradict each other, patches leaving
+            // anything unreachable are undone, see `Program::verify`
+            let before = self.params.conditions_overlap()
+                .then(|| program.clone());
+
+            match kind {
+                0 => {
+                    // Add a branch on other values of the same bits
+                    let id = program.num_blocks;
+                    let mut added = false;
+                    program.edit(is_bits_branch, site, |block, ii| {
+                        let Stmt::If {
+                            cond: Cond::Bits { byte, mask, .. }, ..
+                        } = block.stmts[ii] else { unreachable!() };
+                        let taken: Vec<u8> = block.stmts.iter()
+                            .filter_map(|stmt| match *stmt {
+                                Stmt::If {
+                                    cond: Cond::Bits { byte: x, mask: y,
+                                                       target },
+                                    ..
+                                } if x == byte && y == mask => Some(target),
+                                _ => None,
+                            }).collect();
+
+                        // Leave the block alone if every value is taken
+                        let free: Vec<u8> = (0..=255u8).filter(|&x| {
+                            x & mask == x && !taken.contains(&x)
+                        }).collect();
+                        if free.is_empty() {
+                            return;
+                        }
+                        let target = free[rng.rand() % free.len()];
+                        block.stmts.insert(ii + 1, Stmt::If {
+                            cond: Cond::Bits { byte, mask, target },
+                            body: Block { id, flaky: false,
+                                          stmts: Vec::new() },
+                            else_body: None,
+                        });
+                        added = true;
+                    });
+                    program.num_blocks += added as usize;
+                }
+                1 => {
+                    program.edit(is_branch, site, |block, ii| {
+                        block.stmts.remove(ii);
+                    });
+                }
+                _ => {
+                    // Check for another value of the same bits
+                    program.edit(is_bits_branch, site, |block, ii| {
+                        let Stmt::If {
+                            cond: Cond::Bits { mask, target, .. }, ..
+                        } = &mut block.stmts[ii] else { unreachable!() };
+                        let old = *target;
+                        if *mask == 0 {
+                            return;
+                        }
+                        while *target == old {
+                            *target = rng.rand() as u8 & *mask;
+                        }
+                    });
+                }
+            }
+
+            if let Some(before) = before {
+                if program.verify().is_err() {
+                    program = before;
+                }
+            }
+        }
+
+        // Removed branches may have taken flaky blocks with them
+        let mut num_flaky = 0;
+        program.visit_all(&mut |block| {
+            num_flaky += block.flaky as usize;
+            false
+        });
+        program.num_flaky = num_flaky;
+        program
+    }
+
+    /// Call `visit_fn` on every block of the program, see [`visit`]
+    fn visit_all(&mut self, visit_fn: &mut dyn FnMut(&mut Block) -> bool)
+            -> bool {
+        self.functions.iter_mut().any(|function| visit(function, visit_fn))
+            || visit(&mut self.entry, visit_fn)
+    }
+
+    /// Number of statements in the program for which `is_site` holds
+    fn count(&mut self, is_site: fn(&Stmt) -> bool) -> usize {
+        let mut count = 0;
+        self.visit_all(&mut |block| {
+            count += block.stmts.iter().filter(|x| is_site(x)).count();
+            false
+        });
+        count
+    }
+
+    /// Call `edit` with the block holding the `site`th statement for which
+    /// `is_site` holds and the statement's index in it
+    fn edit(&mut self, is_site: fn(&Stmt) -> bool, mut site: usize,
+            edit: impl FnOnce(&mut Block, usize)) {
+        let mut edit = Some(edit);
+        self.visit_all(&mut |block| {
+            let sites: Vec<usize> = (0..block.stmts.len())
+                .filter(|&ii| is_site(&block.stmts[ii])).collect();
+            if site >= sites.len() {
+                site -= sites.len();
+                return false;
+            }
+            (edit.take().unwrap())(block, sites[site]);
+            true
+        });
+    }
+}
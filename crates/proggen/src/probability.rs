@@ -0,0 +1,321 @@
+//! Probabilities of random inputs reaching blocks and crashes
+//!
+//! A block is reached by a uniformly random input of the program's input
+//! size if all conditions on one of the ways to it hold, or fail for else
+//! blocks. The ways to a block are the call sites of its function and the
+//! sources of the joins on the path to it. Their probabilities are combined
+//! by inclusion-exclusion. The bits of conditions are disjoint unless
+//! reused, so the probability of a way is the product of the probability of
+//! every input byte's bits, counted exactly, and of every magic value,
+//! range, comparison, and checksum, which have bytes of their own.
+//!
+//! This is exact, except that the crashes a random input could hit on the
+//! way are ignored, as are rejects other than those earlier in the same
+//! block, only the [`MAX_WAYS`] most likely ways to a block are combined,
+//! and the bytes past length fields are taken as independent of the other
+//! conditions, which they only overlap by chance. Blocks in record handlers
+//! are reached if the first record of the input reaches them, with the
+//! record's body as long as its length byte allows.
+
+use std::collections::BTreeMap;
+
+use crate::{Block, Cond, Program, Relation, Stmt};
+
+/// Most ways to reach a block which are combined, beyond this only the most
+/// likely are
+pub const MAX_WAYS: usize = 4;
+
+/// Conditions which must hold, or fail
+type Conds = Vec<(Cond, bool)>;
+
+/// A way to reach a block, as the tag of the record it is in, if the block
+/// is in a record handler, and its conditions
+type Way = (Option<u8>, Conds);
+
+/// Bit slices of an input byte, as `(mask, target, holds)`
+type Slices = Vec<(u8, u8, bool)>;
+
+impl Program {
+    /// Probability of a uniformly random input reaching every block and
+    /// crash, indexed by block and crash ID. Flaky blocks are reached, but
+    /// only record their coverage by chance.
+    pub fn probabilities(&self) -> (Vec<f64>, Vec<f64>) {
+        let mut blocks  = vec![Vec::new(); self.num_blocks];
+        let mut crashes = vec![Vec::new(); self.num_crashes];
+        let mut calls   = vec![Vec::new(); self.functions.len()];
+
+        // Functions only call functions generated before them, so all of
+        // their callers have been walked when they are
+        self.ways(&self.entry, &[(None, Vec::new())], &mut calls,
+                  &mut blocks, &mut crashes);
+        for function in (0..self.functions.len()).rev() {
+            let ways = self.prune(std::mem::take(&mut calls[function]));
+            self.ways(&self.functions[function], &ways, &mut calls,
+                      &mut blocks, &mut crashes);
+        }
+
+        let odds = |ways: Vec<Way>| self.union(&ways);
+        (blocks.into_iter().map(odds).collect(),
+         crashes.into_iter().map(odds).collect())
+    }
+
+    /// Collect the ways to reach `block`, which is reached through `ways`,
+    /// and everything nested in it, into `blocks` and `crashes` by ID, and
+    /// the ways to reach the functions it calls into `calls`
+    fn ways(&self, block: &Block, ways: &[Way], calls: &mut [Vec<Way>],
+            blocks: &mut [Vec<Way>], crashes: &mut [Vec<Way>]) {
+        blocks[block.id] = ways.to_vec();
+        let mut ways = ways.to_vec();
+        for stmt in &block.stmts {
+            // `ways` with more conditions
+            let extend = |more: &[(Cond, bool)]| -> Vec<Way> {
+                ways.iter().map(|(tag, conds)| {
+                    (*tag, conds.iter().chain(more).cloned().collect())
+                }).collect()
+            };
+
+            match stmt {
+                Stmt::If { cond, body, else_body } => {
+                    self.ways(body, &extend(&[(cond.clone(), true)]), calls,
+                              blocks, crashes);
+                    if let Some(else_body) = else_body {
+                        self.ways(else_body,
+                                  &extend(&[(cond.clone(), false)]), calls,
+                                  blocks, crashes);
+                    }
+                }
+                Stmt::Match { byte, mask, arms } => {
+                    for &(target, ref body) in arms {
+                        let cond = Cond::Bits { byte: *byte, mask: *mask,
+                                                target };
+                        self.ways(body, &extend(&[(cond, true)]), calls,
+                                  blocks, crashes);
+                    }
+                }
+                Stmt::Loop { byte, mask, body } => {
+                    // The body runs if the bits of the count aren't all zero
+                    let cond = Cond::Bits { byte: *byte, mask: *mask,
+                                            target: 0 };
+                    self.ways(body, &extend(&[(cond, false)]), calls, blocks,
+                              crashes);
+                }
+                Stmt::Join { join, body } => {
+                    // Every source of the join is another way to reach it
+                    let sets = |source: &Block| source.stmts.iter().any(|x| {
+                        matches!(x, Stmt::SetJoin { join: x } if x == join)
+                    });
+                    let mut joined = Vec::new();
+                    for stmt in &block.stmts {
+                        if let Stmt::If { cond, body, else_body } = stmt {
+                            if sets(body) {
+                                joined.extend(extend(&[(cond.clone(),
+                                                        true)]));
+                            }
+                            if else_body.as_ref().is_some_and(sets) {
+                                joined.extend(extend(&[(cond.clone(),
+                                                        false)]));
+                            }
+                        }
+                    }
+                    self.ways(body, &self.prune(joined), calls, blocks,
+                              crashes);
+                }
+                Stmt::Call { function } => {
+                    calls[*function].extend_from_slice(&ways);
+                }
+                Stmt::Records { arms } => {
+                    // The parsing loop is all there is to the entry block
+                    for &(tag, function) in arms {
+                        calls[function].push((Some(tag), Vec::new()));
+                    }
+                }
+                Stmt::Crash { cond, id, block: coverage, .. } => {
+                    let ways = extend(&[(cond.clone(), true)]);
+                    if let Some(coverage) = *coverage {
+                        blocks[coverage] = ways.clone();
+                    }
+                    crashes[*id] = ways;
+                }
+                Stmt::Conjunction { parts, id, block: coverage, .. } => {
+                    for (cond, part) in parts {
+                        if let Some(part) = *part {
+                            blocks[part] = extend(&[(cond.clone(), true)]);
+                        }
+                    }
+                    let all: Conds = parts.iter()
+                        .map(|(cond, _)| (cond.clone(), true)).collect();
+                    let ways = extend(&all);
+                    if let Some(coverage) = *coverage {
+                        blocks[coverage] = ways.clone();
+                    }
+                    crashes[*id] = ways;
+                }
+                Stmt::Reject { cond, block: coverage } => {
+                    // The rest of the block is only reached if it fails
+                    blocks[*coverage] = extend(&[(cond.clone(), true)]);
+                    ways = extend(&[(cond.clone(), false)]);
+                }
+                Stmt::SetJoin { .. } => {}
+            }
+        }
+    }
+
+    /// Keep only the [`MAX_WAYS`] most likely of `ways`
+    fn prune(&self, mut ways: Vec<Way>) -> Vec<Way> {
+        if ways.len() > MAX_WAYS {
+            let mut odds: Vec<(f64, Way)> = ways.into_iter()
+                .map(|way| (self.way(way.0, &way.1), way)).collect();
+            odds.sort_by(|x, y| y.0.total_cmp(&x.0));
+            ways = odds.into_iter().take(MAX_WAYS).map(|x| x.1).collect();
+        }
+        ways
+    }
+
+    /// Probability of any of `ways` being taken. Ways in different record
+    /// handlers are exclusive, as the first record has a single tag.
+    fn union(&self, ways: &[Way]) -> f64 {
+        let mut tags: BTreeMap<Option<u8>, Vec<&Conds>> = BTreeMap::new();
+        for (tag, conds) in ways {
+            tags.entry(*tag).or_default().push(conds);
+        }
+
+        let mut odds = 0.;
+        for (tag, ways) in tags {
+            // Inclusion-exclusion over every combination of the ways
+            let ways = &ways[..ways.len().min(MAX_WAYS)];
+            for set in 1..1usize << ways.len() {
+                let conds: Conds = ways.iter().enumerate()
+                    .filter(|(ii, _)| set & (1 << ii) != 0)
+                    .flat_map(|(_, conds)| conds.iter().cloned()).collect();
+                let sign = if set.count_ones() % 2 == 1 { 1. } else { -1. };
+                odds += sign * self.way(tag, &conds);
+            }
+        }
+        odds.clamp(0., 1.)
+    }
+
+    /// Probability of all of `conds` holding or failing as required, in the
+    /// record with `tag` if set
+    fn way(&self, tag: Option<u8>, conds: &[(Cond, bool)]) -> f64 {
+        let Some(_) = tag else {
+            return conds_odds(conds, self.input_bytes);
+        };
+
+        // The tag byte matches, and the body is as long as the length byte
+        // says, cut short at the end of the input
+        let max = self.input_bytes.saturating_sub(2);
+        let mut odds = 0.;
+        for len in 0..=max.min(255) {
+            let count = if len == max { 256 - len } else { 1 };
+            odds += count as f64 * conds_odds(conds, len);
+        }
+        odds / 256. / 256.
+    }
+}
+
+/// Probability of all bit slices of an input byte holding or failing as
+/// required
+fn byte_odds(slices: &[(u8, u8, bool)]) -> f64 {
+    if let [(mask, _, holds)] = *slices {
+        let hold = 0.5f64.powi(mask.count_ones() as i32);
+        return if holds { hold } else { 1. - hold };
+    }
+    let count = (0..=255u8).filter(|&x| {
+        slices.iter().all(|&(mask, target, holds)| {
+            (x & mask == target) == holds
+        })
+    }).count();
+    count as f64 / 256.
+}
+
+/// Probability of all of `conds` holding or failing as required for a
+/// uniformly random input of `len` bytes
+fn conds_odds(conds: &[(Cond, bool)], len: usize) -> f64 {
+    let mut odds = 1.;
+    let mut bytes:  BTreeMap<usize, Slices> = BTreeMap::new();
+    let mut fields: BTreeMap<usize, BTreeMap<usize, Slices>> = BTreeMap::new();
+    for (ii, (cond, holds)) in conds.iter().enumerate() {
+        // Bytes past the end of the input fail the condition
+        if cond.input_len() > len && !matches!(cond, Cond::Length { .. }) {
+            if *holds {
+                return 0.;
+            }
+            continue;
+        }
+
+        // Probability of the condition holding, if it has bytes of its own
+        let hold = match *cond {
+            Cond::Bits { byte, mask, target } => {
+                let slices = bytes.entry(byte).or_default();
+                if !slices.contains(&(mask, target, *holds)) {
+                    slices.push((mask, target, *holds));
+                }
+                continue;
+            }
+            Cond::Field { field, offset, mask, target } => {
+                let offsets = fields.entry(field).or_default();
+                let slices  = offsets.entry(offset).or_default();
+                if !slices.contains(&(mask, target, *holds)) {
+                    slices.push((mask, target, *holds));
+                }
+                continue;
+            }
+            Cond::Length { len: x } => {
+                if (len >= x) != *holds {
+                    return 0.;
+                }
+                continue;
+            }
+            Cond::Magic { size, .. } => 0.5f64.powi(8 * size as i32),
+            // The bits are disjoint from those of other conditions, so this
+            // is exact like for bytes
+            Cond::Span { mask, .. } => 0.5f64.powi(mask.count_ones() as i32),
+            Cond::Range { size, low, high, .. } => {
+                ((high - low) as f64 + 1.) * 0.5f64.powi(8 * size as i32)
+            }
+            Cond::Compare { relation: Relation::Equal, .. } => 1. / 256.,
+            Cond::Compare { relation: Relation::Less, .. } => 255. / 512.,
+            Cond::Checksum { .. } => 1. / 256.,
+
+            // The value is uniform, as every step of the derivation is a
+            // bijection of the base byte
+            Cond::Derived { mask, .. } => {
+                0.5f64.powi(mask.count_ones() as i32)
+            }
+        };
+
+        // The same condition is only counted once
+        match conds[..ii].iter().find(|(x, _)| x == cond) {
+            Some((_, x)) if x == holds => {}
+            Some(_) => return 0.,
+            None => odds *= if *holds { hold } else { 1. - hold },
+        }
+    }
+
+    for slices in bytes.values() {
+        odds *= byte_odds(slices);
+    }
+
+    // Average the bits past every length field over the field's values.
+    // Checks at the same offset past the record check the same byte, which
+    // fails all of them if it's past the end of the input.
+    for (&field, offsets) in &fields {
+        let offsets: Vec<(usize, f64, bool)> = offsets.iter()
+            .map(|(&offset, slices)| {
+                (offset, byte_odds(slices), slices.iter().any(|x| x.2))
+            }).collect();
+        let field_odds = (0..256usize).map(|value| {
+            offsets.iter().map(|&(offset, odds, holds)| {
+                if field + 1 + value + offset < len {
+                    odds
+                } else if holds {
+                    0.
+                } else {
+                    1.
+                }
+            }).product::<f64>()
+        }).sum::<f64>();
+        odds *= field_odds / 256.;
+    }
+    odds
+}
@@ -0,0 +1,79 @@
+//! Random number generation shared by the generator and the simulator
+
+/// The splitmix64 finalizer, a bijective mixing of all bits of `x`
+pub fn splitmix64(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9e3779b97f4a7c15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Derive the seed of stream `index` of `seed`, never zero
+pub fn derive(seed: u64, index: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(index)).max(1)
+}
+
+/// Get a seed which differs between calls from the time stamp counter
+#[cfg(target_arch = "x86_64")]
+fn entropy() -> u64 {
+    unsafe { std::arch::x86_64::_rdtsc() }
+}
+
+/// Get a seed which differs between calls, mixing the randomly keyed std
+/// hasher with the time and a counter
+#[cfg(not(target_arch = "x86_64"))]
+fn entropy() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = std::collections::hash_map::RandomState::new()
+        .build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|x| x.as_nanos()).unwrap_or(0));
+    hasher.finish()
+}
+
+/// Get an unpredictable, well mixed seed, for runs which were not given one
+pub fn random_seed() -> u64 {
+    splitmix64(entropy())
+}
+
+/// A fast xorshift64 random number generator
+///
+/// Both program generation and the simulator burn through a huge number of
+/// random values, so this only aims to be fast and "random enough". The state
+/// is 64 bits on every target so seeded streams are the same everywhere.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a new RNG from a fixed `seed`
+    pub fn new(seed: usize) -> Self {
+        Rng(seed as u64)
+    }
+
+    /// Create a new RNG with an unpredictable seed. This is the time stamp
+    /// counter on x86_64, and a slower but portable source elsewhere.
+    pub fn from_entropy() -> Self {
+        Rng(entropy().max(1))
+    }
+
+    /// Create a new RNG for stream `index` of the streams derived from
+    /// `seed`. Streams of the same seed are independent of each other.
+    pub fn derive(seed: u64, index: u64) -> Self {
+        Rng(derive(seed, index))
+    }
+
+    /// Get the next random number
+    pub fn rand(&mut self) -> usize {
+        let orig = self.0;
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 43;
+        orig as usize
+    }
+}
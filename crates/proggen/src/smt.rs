@@ -0,0 +1,404 @@
+//! SMT-LIB export of the path constraints of generated programs
+//!
+//! Every block and crash gets a boolean function of the input, named after
+//! it like the ground truth corpus, which holds for the inputs reaching it.
+//! A block is reached when the block it is nested in is, at the statement
+//! guarding it, its condition holds or fails as needed, and no reject before
+//! it in its function returned early. A function is reached from any of its
+//! calls, and a join block from any of its sources. The functions are
+//! defined in terms of each other, so the constraints of the whole program
+//! stay about as large as the program. The input is an array of bytes with
+//! a length, so the formulas are in `QF_ABV`, and a solver finds an input
+//! reaching a block with `(assert block_<id>)`, `(check-sat)`, and
+//! `(get-model)`.
+//!
+//! Crashes are taken not to stop the program, so the function of a block
+//! also holds for inputs crashing before they get to it. In a parsing loop
+//! the input is a single record with the tag of a handler, like the inputs
+//! of the ground truth corpus, and only the blocks reached from `crashme`
+//! are reachable, not those reached from other entry points only.
+
+use crate::{Block, Cond, Operation, Program, Relation, Stmt};
+
+/// Input byte `index`, a bit-vector of 8 bits
+fn byte(index: &str) -> String {
+    format!("(select input {})", index)
+}
+
+/// A 32-bit constant, as used for offsets and lengths
+fn word(value: usize) -> String {
+    format!("#x{:08x}", value)
+}
+
+/// A constant `value` of `bits` bits, a multiple of 8
+fn constant(value: u64, bits: usize) -> String {
+    format!("#x{:0width$x}", value, width = bits / 4)
+}
+
+/// Conjunction of `terms`
+fn and(terms: &[String]) -> String {
+    match terms {
+        []     => "true".into(),
+        [term] => term.clone(),
+        terms  => format!("(and {})", terms.join(" ")),
+    }
+}
+
+/// Disjunction of `terms`
+fn or(terms: &[String]) -> String {
+    match terms {
+        []     => "false".into(),
+        [term] => term.clone(),
+        terms  => format!("(or {})", terms.join(" ")),
+    }
+}
+
+/// Negation of `term`
+fn not(term: String) -> String {
+    format!("(not {})", term)
+}
+
+/// Functions called by `block` and every block nested in it, including the
+/// record handlers of parsing loops
+fn callees(block: &Block, functions: &mut Vec<usize>) {
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::If { body, else_body, .. } => {
+                callees(body, functions);
+                if let Some(else_body) = else_body {
+                    callees(else_body, functions);
+                }
+            }
+            Stmt::Match { arms, .. } => {
+                for (_, body) in arms {
+                    callees(body, functions);
+                }
+            }
+            Stmt::Loop { body, .. } | Stmt::Join { body, .. } => {
+                callees(body, functions);
+            }
+            Stmt::Call { function } => functions.push(*function),
+            Stmt::Records { arms } => {
+                functions.extend(arms.iter().map(|x| x.1));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Encoding of conditions on the bytes `base` bytes into the input, of
+/// which there are `len`, a 32-bit expression
+#[derive(Clone)]
+struct Encoder {
+    /// Offset of the bytes conditions index, past the tag and length of a
+    /// record
+    base: usize,
+
+    /// Length of the bytes conditions see
+    len: String,
+
+    /// Whether conditions check bytes are within the length first, which
+    /// inputs of a fixed length always are
+    bounded: bool,
+}
+
+impl Encoder {
+    /// Byte `index` of what conditions see
+    fn byte(&self, index: usize) -> String {
+        byte(&word(self.base + index))
+    }
+
+    /// The `size` bytes at `index` as a little endian integer
+    fn le(&self, index: usize, size: usize) -> String {
+        if size == 1 {
+            return self.byte(index);
+        }
+        let bytes: Vec<String> = (index..index + size).rev()
+            .map(|x| self.byte(x)).collect();
+        format!("(concat {})", bytes.join(" "))
+    }
+
+    /// Formula holding when `cond` holds, failing when bytes it reads are
+    /// past the end like the emitted code does
+    fn cond(&self, cond: &Cond) -> String {
+        let mut terms = Vec::new();
+        if self.bounded && cond.input_len() != 0 {
+            terms.push(format!("(bvule {} {})", word(cond.input_len()),
+                               self.len));
+        }
+        terms.push(match *cond {
+            Cond::Bits { byte, mask, target } => {
+                format!("(= (bvand {} {}) {})", self.byte(byte),
+                        constant(mask as u64, 8), constant(target as u64, 8))
+            }
+            Cond::Magic { byte, size, value } => {
+                format!("(= {} {})", self.le(byte, size),
+                        constant(value, size * 8))
+            }
+            Cond::Span { byte, mask, target } => {
+                format!("(= (bvand {} {}) {})", self.le(byte, 2),
+                        constant(mask as u64, 16),
+                        constant(target as u64, 16))
+            }
+            Cond::Range { byte, size, low, high } => {
+                let value = self.le(byte, size);
+                format!("(and (bvuge {} {}) (bvule {} {}))", value,
+                        constant(low, size * 8), value,
+                        constant(high, size * 8))
+            }
+            Cond::Compare { lhs, rhs, relation } => {
+                let operator = match relation {
+                    Relation::Equal => "=",
+                    Relation::Less  => "bvult",
+                };
+                format!("({} {} {})", operator, self.byte(lhs),
+                        self.byte(rhs))
+            }
+            Cond::Checksum { start, len, byte } => {
+                let sum = match len {
+                    0 => constant(0, 8),
+                    1 => self.byte(start),
+                    _ => {
+                        let bytes: Vec<String> = (start..start + len)
+                            .map(|x| self.byte(x)).collect();
+                        format!("(bvadd {})", bytes.join(" "))
+                    }
+                };
+                format!("(= {} {})", sum, self.byte(byte))
+            }
+            Cond::Length { len } => {
+                format!("(bvuge {} {})", self.len, word(len))
+            }
+            Cond::Field { field, offset, mask, target } => {
+                // The checked byte is past the record the field gives the
+                // length of
+                let at = format!("(bvadd {} ((_ zero_extend 24) {}))",
+                                 word(self.base + field + 1 + offset),
+                                 self.byte(field));
+                let check = format!("(= (bvand {} {}) {})", byte(&at),
+                                    constant(mask as u64, 8),
+                                    constant(target as u64, 8));
+                if self.bounded {
+                    let at = format!("(bvsub {} {})", at, word(self.base));
+                    format!("(and (bvult {} {}) {})", at, self.len, check)
+                } else {
+                    check
+                }
+            }
+            Cond::Derived { base, ref terms, mask, target, .. } => {
+                let value = terms.iter().fold(self.byte(base),
+                        |value, &(op, byte, shift)| {
+                    let operator = match op {
+                        Operation::Add => "bvadd",
+                        Operation::Sub => "bvsub",
+                        Operation::Xor => "bvxor",
+                    };
+                    let term = if shift == 0 {
+                        self.byte(byte)
+                    } else {
+                        format!("(bvshl {} {})", self.byte(byte),
+                                constant(shift as u64, 8))
+                    };
+                    format!("({} {} {})", operator, value, term)
+                });
+                format!("(= (bvand {} {}) {})", value,
+                        constant(mask as u64, 8), constant(target as u64, 8))
+            }
+        });
+        and(&terms)
+    }
+}
+
+/// The SMT-LIB script of a program being written
+struct Writer {
+    /// The script so far
+    smt: String,
+
+    /// Conditions of reaching every call of every function found so far
+    calls: Vec<Vec<String>>,
+
+    /// Encoding of the conditions of every function, that of its first call
+    encoders: Vec<Option<Encoder>>,
+
+    /// Conditions of reaching the sources of every join found so far,
+    /// indexed by join
+    joins: Vec<Vec<String>>,
+}
+
+impl Writer {
+    /// Define the boolean function `name` as `value`
+    fn define(&mut self, name: &str, value: &str) {
+        self.smt += &format!("(define-fun {} () Bool {})\n", name, value);
+    }
+
+    /// Define the crash `id`, which is reached when `reached` holds, through
+    /// its coverage event `block` if it has one
+    fn crash(&mut self, id: usize, block: Option<usize>, reached: &str) {
+        match block {
+            Some(block) => {
+                let name = format!("block_{}", block);
+                self.define(&name, reached);
+                self.define(&format!("crash_{}", id), &name);
+            }
+            None => self.define(&format!("crash_{}", id), reached),
+        }
+    }
+
+    /// Define `block`, which is reached when `reached` holds, and everything
+    /// nested in it, with conditions encoded by `encoder`. Returns the IDs
+    /// of the blocks of the rejects in it and everything nested in it.
+    fn block(&mut self, block: &Block, reached: &str, encoder: &Encoder)
+            -> Vec<usize> {
+        let name = format!("block_{}", block.id);
+        self.define(&name, reached);
+
+        // Rejects before the current statement, none of which may return
+        let mut passed: Vec<usize> = Vec::new();
+        for stmt in &block.stmts {
+            let here = and(&std::iter::once(name.clone())
+                .chain(passed.iter().map(|x| not(format!("block_{}", x))))
+                .collect::<Vec<_>>());
+
+            match stmt {
+                Stmt::If { cond, body, else_body } => {
+                    let cond = encoder.cond(cond);
+                    passed.extend(self.block(body, &and(&[here.clone(),
+                        cond.clone()]), encoder));
+                    if let Some(else_body) = else_body {
+                        passed.extend(self.block(else_body,
+                            &and(&[here, not(cond)]), encoder));
+                    }
+                }
+                Stmt::Match { byte, mask, arms } => {
+                    for &(target, ref body) in arms {
+                        let cond = encoder.cond(&Cond::Bits {
+                            byte: *byte, mask: *mask, target });
+                        passed.extend(self.block(body,
+                            &and(&[here.clone(), cond]), encoder));
+                    }
+                }
+                Stmt::Loop { byte, mask, body } => {
+                    // The body runs if the bits of the count aren't all zero
+                    let cond = encoder.cond(&Cond::Bits {
+                        byte: *byte, mask: *mask, target: 0 });
+                    passed.extend(self.block(body, &and(&[here, not(cond)]),
+                                             encoder));
+                }
+                Stmt::Join { join, body } => {
+                    let sources = std::mem::take(&mut self.joins[*join]);
+                    passed.extend(self.block(body, &and(&[here,
+                        or(&sources)]), encoder));
+                }
+                Stmt::SetJoin { join } => {
+                    if self.joins.len() <= *join {
+                        self.joins.resize(*join + 1, Vec::new());
+                    }
+                    self.joins[*join].push(here);
+                }
+                Stmt::Call { function } => {
+                    self.calls[*function].push(here);
+                    self.encoders[*function].get_or_insert(encoder.clone());
+                }
+                Stmt::Records { arms } => {
+                    // A record with the tag, as long as its length byte says
+                    let len = format!("((_ zero_extend 24) {})",
+                                      byte(&word(1)));
+                    let record = format!("(bvule (bvadd {} {}) len)",
+                                         word(2), len);
+                    for &(tag, function) in arms {
+                        let tag = format!("(= {} {})", byte(&word(0)),
+                                          constant(tag as u64, 8));
+                        self.calls[function].push(and(&[here.clone(), tag,
+                                                        record.clone()]));
+                        self.encoders[function].get_or_insert(Encoder {
+                            base:    2,
+                            len:     len.clone(),
+                            bounded: true,
+                        });
+                    }
+                }
+                Stmt::Crash { cond, id, block, .. } => {
+                    self.crash(*id, *block, &and(&[here, encoder.cond(cond)]));
+                }
+                Stmt::Conjunction { parts, id, block, .. } => {
+                    // Every part is checked on its own, recording coverage
+                    // of its block when it holds
+                    let mut all = vec![here.clone()];
+                    for (cond, part) in parts {
+                        let cond = encoder.cond(cond);
+                        if let Some(part) = part {
+                            self.define(&format!("block_{}", part),
+                                        &and(&[here.clone(), cond.clone()]));
+                        }
+                        all.push(cond);
+                    }
+                    self.crash(*id, *block, &and(&all));
+                }
+                Stmt::Reject { cond, block } => {
+                    self.define(&format!("block_{}", block),
+                                &and(&[here, encoder.cond(cond)]));
+                    passed.push(*block);
+                }
+            }
+        }
+        passed
+    }
+}
+
+impl Program {
+    /// SMT-LIB script defining the constraint on the input of reaching every
+    /// block and crash, as `block_<id>` and `crash_<id>`, to check one with
+    /// a solver
+    pub fn to_smt(&self) -> String {
+        let mut writer = Writer {
+            smt:      String::new(),
+            calls:    vec![Vec::new(); self.functions.len()],
+            encoders: vec![None; self.functions.len()],
+            joins:    Vec::new(),
+        };
+        writer.smt += "; Constraints on the input of reaching every block \
+                       and crash\n";
+        writer.smt += "(set-logic QF_ABV)\n";
+        writer.smt += "(declare-const input (Array (_ BitVec 32) \
+                       (_ BitVec 8)))\n";
+        writer.smt += "(declare-const len (_ BitVec 32))\n";
+        writer.smt += &if self.params.variable_length {
+            format!("(assert (bvule len {}))\n", word(self.input_bytes))
+        } else {
+            format!("(assert (= len {}))\n", word(self.input_bytes))
+        };
+
+        let encoder = Encoder {
+            base:    0,
+            len:     "len".into(),
+            bounded: self.params.variable_length,
+        };
+        writer.block(&self.entry, "true", &encoder);
+
+        // A function is defined once all of its calls are, which they are
+        // once every function calling it is
+        let mut callers = vec![0usize; self.functions.len()];
+        let callees: Vec<Vec<usize>> = self.functions.iter().map(|x| {
+            let mut functions = Vec::new();
+            callees(x, &mut functions);
+            for &function in &functions {
+                callers[function] += 1;
+            }
+            functions
+        }).collect();
+        let mut done = vec![false; self.functions.len()];
+        while let Some(function) = (0..self.functions.len())
+                .filter(|&x| !done[x])
+                .min_by_key(|&x| (callers[x], x)) {
+            done[function] = true;
+            let reached = or(&writer.calls[function]);
+            let encoder = writer.encoders[function].clone()
+                .unwrap_or_else(|| encoder.clone());
+            writer.block(&self.functions[function], &reached, &encoder);
+            for &callee in &callees[function] {
+                callers[callee] -= 1;
+            }
+        }
+        writer.smt
+    }
+}
@@ -0,0 +1,581 @@
+//! Inputs reaching the blocks and crashes of generated programs
+//!
+//! The generator knows every condition on the path to every block, so an
+//! input reaching a block is built by making the conditions on its path hold,
+//! or fail for else blocks, one after the other, and the conditions of the
+//! rejects passed on the way fail. Derived values are made to hold by trying
+//! every value of their base byte. Checksums are fixed up after the bytes
+//! they sum are settled. Every input is checked against its whole path
+//! before it is returned, so goals the solver can't satisfy, like ones
+//! behind conditions reusing the same bits with different targets, are
+//! reported rather than given a wrong input.
+
+use crate::{Block, Cond, Program, Relation, Stmt};
+
+/// Something an input can reach
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Goal {
+    /// The block with this ID
+    Block(usize),
+
+    /// The crash with this ID
+    Crash(usize),
+}
+
+impl Goal {
+    /// File name of the input reaching the goal
+    pub fn name(&self) -> String {
+        match self {
+            Goal::Block(id) => format!("block_{}", id),
+            Goal::Crash(id) => format!("crash_{}", id),
+        }
+    }
+}
+
+/// An input reaching a goal
+#[derive(Clone, Debug)]
+pub struct Solution {
+    /// What the input reaches
+    pub goal: Goal,
+
+    /// The input, or `None` if the conditions on the path to the goal could
+    /// not be satisfied together
+    pub input: Option<Vec<u8>>,
+}
+
+/// A goal with the conditions on the path to it, each of which must hold or
+/// fail, and the tag of the record it is parsed from in a parsing loop
+type Path = (Goal, Vec<(Cond, bool)>, Option<u8>);
+
+impl Program {
+    /// Build an input reaching every leaf block, which has no blocks nested
+    /// in it, and every crash. Reaching a leaf also reaches every block on
+    /// the path to it. Functions are solved from the first call to them
+    /// which is found.
+    pub fn solve(&self) -> Vec<Solution> {
+        self.all_paths().into_iter().map(|(goal, path, tag)| {
+            Solution { goal, input: self.solve_path(&path, tag) }
+        }).collect()
+    }
+
+    /// Check that every block and crash of the program is reachable, by
+    /// finding an input reaching every leaf block and crash, see
+    /// [`Program::solve`]. Otherwise, describe the first goal no input was
+    /// found for, with the condition on the path to it which can't hold, or
+    /// fail, along with the ones before it, and the earlier conditions on
+    /// the same input bytes.
+    pub fn verify(&self) -> Result<(), String> {
+        for (goal, path, tag) in self.all_paths() {
+            if self.solve_path(&path, tag).is_some() {
+                continue;
+            }
+
+            // The shortest prefix of the path which can't be satisfied ends
+            // with the culprit
+            let len = (1..=path.len())
+                .find(|&len| self.solve_path(&path[..len], tag).is_none())
+                .unwrap_or(path.len());
+            let (cond, holds) = &path[len - 1];
+            let bytes = cond.bytes();
+            let conflicts: Vec<String> = path[..len - 1].iter()
+                .filter(|(x, _)| x.bytes().iter().any(|x| bytes.contains(x)))
+                .map(|(x, holds)| {
+                    format!("`{}` {}", x, if *holds { "holds" } else {
+                        "fails" })
+                }).collect();
+            let mut err = format!("{} is unreachable: `{}` can't {}",
+                                  goal.name(), cond,
+                                  if *holds { "hold" } else { "fail" });
+            if !conflicts.is_empty() {
+                err += &format!(" where {}", conflicts.join(", "));
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Paths to every leaf block and crash of the program
+    fn all_paths(&self) -> Vec<Path> {
+        let mut paths   = Vec::new();
+        let mut visited = vec![false; self.functions.len()];
+        self.paths(&self.entry, &mut Vec::new(), &mut Vec::new(), None,
+                   &mut visited, &mut paths);
+        paths
+    }
+
+    /// Build an input for which every condition of `path` holds or fails as
+    /// required, in the record with `tag` if any
+    fn solve_path(&self, path: &[(Cond, bool)], tag: Option<u8>)
+            -> Option<Vec<u8>> {
+        // Records are parsed from the input after their tag and length bytes
+        let fixed_len = if self.params.variable_length {
+            0
+        } else {
+            self.input_bytes
+        };
+        let record_len = self.input_bytes.saturating_sub(2).min(255);
+
+        match tag {
+            None => solve_path(path, fixed_len, self.input_bytes),
+            Some(tag) => {
+                solve_path(path, 0, record_len).map(|body| {
+                    let mut input = vec![tag, body.len() as u8];
+                    input.extend(body);
+                    input
+                })
+            }
+        }
+    }
+
+    /// Collect the paths to the leaf blocks, crashes, and rejects in
+    /// `block`, which is reached through `path`, in the record with `tag` if
+    /// any. The conditions of `rejects`, every reject passed in the function
+    /// so far, must fail, as must those of the rejects in `block` for
+    /// anything after them. Rejects in blocks the path doesn't go through
+    /// are made to fail too, which they can, as nothing else checks their
+    /// bits.
+    fn paths(&self, block: &Block, path: &mut Vec<(Cond, bool)>,
+             rejects: &mut Vec<(Cond, bool)>, tag: Option<u8>,
+             visited: &mut [bool], paths: &mut Vec<Path>) {
+        // `path` with the rejects passed so far, and more conditions
+        let full = |path: &[(Cond, bool)], rejects: &[(Cond, bool)],
+                    more: &[(Cond, bool)]| {
+            path.iter().chain(rejects).chain(more).cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let passed = rejects.len();
+        let mut leaf = true;
+        for stmt in &block.stmts {
+            // Descend into a block reached when `conds` hold or fail
+            let mut nested = |conds: &[(Cond, bool)], body: &Block,
+                              rejects: &mut Vec<(Cond, bool)>,
+                              visited: &mut [bool],
+                              paths: &mut Vec<Path>| {
+                let len = path.len();
+                path.extend_from_slice(conds);
+                self.paths(body, path, rejects, tag, visited, paths);
+                path.truncate(len);
+            };
+
+            match stmt {
+                Stmt::If { cond, body, else_body } => {
+                    leaf = false;
+                    nested(&[(cond.clone(), true)], body, rejects, visited,
+                           paths);
+                    if let Some(else_body) = else_body {
+                        nested(&[(cond.clone(), false)], else_body, rejects,
+                               visited, paths);
+                    }
+                }
+                Stmt::Match { byte, mask, arms } => {
+                    leaf = false;
+                    for &(target, ref body) in arms {
+                        let cond = Cond::Bits { byte: *byte, mask: *mask,
+                                                target };
+                        nested(&[(cond, true)], body, rejects, visited,
+                               paths);
+                    }
+                }
+                Stmt::Loop { byte, mask, body } => {
+                    // The body runs if the bits of the count aren't all zero
+                    leaf = false;
+                    let cond = Cond::Bits { byte: *byte, mask: *mask,
+                                            target: 0 };
+                    nested(&[(cond, false)], body, rejects, visited, paths);
+                }
+                Stmt::Join { join, body } => {
+                    // Reach the join through the first of its sources
+                    leaf = false;
+                    let source = block.stmts.iter().find_map(|stmt| {
+                        let Stmt::If { cond, body, else_body } = stmt else {
+                            return None;
+                        };
+                        let sets = |source: &Block| {
+                            source.stmts.iter().any(|x| matches!(x,
+                                Stmt::SetJoin { join: x } if x == join))
+                        };
+                        if sets(body) {
+                            Some((cond.clone(), true))
+                        } else if else_body.as_ref().is_some_and(sets) {
+                            Some((cond.clone(), false))
+                        } else {
+                            None
+                        }
+                    });
+                    if let Some(source) = source {
+                        nested(&[source], body, rejects, visited, paths);
+                    }
+                }
+                Stmt::Call { function } => {
+                    leaf = false;
+                    // The function's rejects only return from it
+                    if !std::mem::replace(&mut visited[*function], true) {
+                        let len = rejects.len();
+                        nested(&[], &self.functions[*function], rejects,
+                               visited, paths);
+                        rejects.truncate(len);
+                    }
+                }
+                Stmt::Records { arms } => {
+                    leaf = false;
+                    for &(tag, function) in arms {
+                        if !std::mem::replace(&mut visited[function], true) {
+                            self.paths(&self.functions[function],
+                                       &mut Vec::new(), &mut Vec::new(),
+                                       Some(tag), visited, paths);
+                        }
+                    }
+                }
+                Stmt::Crash { cond, id, .. } => {
+                    let path = full(path, rejects, &[(cond.clone(), true)]);
+                    paths.push((Goal::Crash(*id), path, tag));
+                }
+                Stmt::Conjunction { parts, id, .. } => {
+                    let all: Vec<(Cond, bool)> = parts.iter()
+                        .map(|(x, _)| (x.clone(), true)).collect();
+                    let path = full(path, rejects, &all);
+                    paths.push((Goal::Crash(*id), path, tag));
+                }
+                Stmt::Reject { cond, block } => {
+                    leaf = false;
+                    let path = full(path, rejects, &[(cond.clone(), true)]);
+                    paths.push((Goal::Block(*block), path, tag));
+                    rejects.push((cond.clone(), false));
+                }
+                Stmt::SetJoin { .. } => {}
+            }
+        }
+
+        if leaf {
+            let path = full(path, &rejects[..passed], &[]);
+            paths.push((Goal::Block(block.id), path, tag));
+        }
+    }
+}
+
+impl Cond {
+    /// Check whether the condition holds for `input`, like the emitted code
+    /// does. Bytes past the end of the input fail the condition.
+    pub fn holds(&self, input: &[u8]) -> bool {
+        let byte = |x: usize| input.get(x).copied();
+        match *self {
+            Cond::Bits { byte: x, mask, target } => {
+                byte(x).is_some_and(|x| x & mask == target)
+            }
+            Cond::Magic { byte: x, size, value } => {
+                input.get(x..x + size).is_some_and(|bytes| {
+                    bytes.iter().rev().fold(0u64, |acc, &x| {
+                        (acc << 8) | x as u64
+                    }) == value
+                })
+            }
+            Cond::Span { byte: x, mask, target } => {
+                input.get(x..x + 2).is_some_and(|bytes| {
+                    u16::from_le_bytes([bytes[0], bytes[1]]) & mask == target
+                })
+            }
+            Cond::Range { byte: x, size, low, high } => {
+                input.get(x..x + size).is_some_and(|bytes| {
+                    let value = bytes.iter().rev().fold(0u64, |acc, &x| {
+                        (acc << 8) | x as u64
+                    });
+                    (low..=high).contains(&value)
+                })
+            }
+            Cond::Compare { lhs, rhs, relation } => {
+                match (byte(lhs), byte(rhs), relation) {
+                    (Some(lhs), Some(rhs), Relation::Equal) => lhs == rhs,
+                    (Some(lhs), Some(rhs), Relation::Less)  => lhs < rhs,
+                    _ => false,
+                }
+            }
+            Cond::Checksum { start, len, byte: x } => {
+                input.get(start..start + len).zip(byte(x))
+                    .is_some_and(|(range, x)| checksum(range) == x)
+            }
+            Cond::Length { len } => input.len() >= len,
+            Cond::Field { field, offset, mask, target } => {
+                byte(field).and_then(|len| {
+                    byte(field + 1 + len as usize + offset)
+                }).is_some_and(|x| x & mask == target)
+            }
+            Cond::Derived { mask, target, .. } => {
+                self.derive(input).is_some_and(|x| x & mask == target)
+            }
+        }
+    }
+
+    /// Input bytes the condition reads, other than the ones picked by length
+    /// fields, which is what taint tracking would report for it
+    pub fn bytes(&self) -> Vec<usize> {
+        match *self {
+            Cond::Bits { byte, .. }          => vec![byte],
+            Cond::Magic { byte, size, .. }   => (byte..byte + size).collect(),
+            Cond::Span { byte, .. }          => vec![byte, byte + 1],
+            Cond::Range { byte, size, .. }   => (byte..byte + size).collect(),
+            Cond::Compare { lhs, rhs, .. }   => vec![lhs, rhs],
+            Cond::Checksum { start, len, byte } => {
+                (start..start + len).chain([byte]).collect()
+            }
+            Cond::Length { .. }              => Vec::new(),
+            Cond::Field { field, .. }        => vec![field],
+            Cond::Derived { base, ref terms, .. } => {
+                std::iter::once(base).chain(terms.iter().map(|x| x.1))
+                    .collect()
+            }
+        }
+    }
+
+    /// Change `input` so the condition holds, or fails if `holds` is false,
+    /// extending variable length inputs up to `max_len` bytes as needed.
+    /// Length fields are set to place the bytes they pick outside of `used`,
+    /// which then holds them too.
+    fn satisfy(&self, holds: bool, input: &mut Vec<u8>, max_len: usize,
+               used: &mut [bool]) {
+        // Make sure the input has byte `x`, if it can
+        let grow = |input: &mut Vec<u8>, x: usize| {
+            if input.len() <= x && x < max_len {
+                input.resize(x + 1, 0);
+            }
+        };
+        // Set the bits of byte `x` selected by `mask` to `target`
+        let set = |input: &mut Vec<u8>, x: usize, mask: u8, target: u8| {
+            if let Some(x) = input.get_mut(x) {
+                *x = (*x & !mask) | target;
+            }
+        };
+
+        if self.holds(input) == holds {
+            return;
+        }
+        match *self {
+            Cond::Bits { byte, mask, target } => {
+                grow(input, byte);
+                let target = if holds {
+                    target
+                } else {
+                    target ^ (mask & mask.wrapping_neg())
+                };
+                set(input, byte, mask, target);
+            }
+            Cond::Magic { byte, size, value } => {
+                grow(input, byte + size - 1);
+                let value = if holds { value } else { value ^ 1 };
+                for ii in 0..size {
+                    set(input, byte + ii, !0, (value >> (ii * 8)) as u8);
+                }
+            }
+            Cond::Span { byte, mask, target } => {
+                grow(input, byte + 1);
+                let target = if holds {
+                    target
+                } else {
+                    target ^ (mask & mask.wrapping_neg())
+                };
+                set(input, byte, mask as u8, target as u8);
+                set(input, byte + 1, (mask >> 8) as u8, (target >> 8) as u8);
+            }
+            Cond::Range { byte, size, low, high } => {
+                // Fail with the value just past either end of the range, if
+                // there is one
+                grow(input, byte + size - 1);
+                let max = u64::MAX >> (64 - size * 8);
+                let value = if holds {
+                    low
+                } else if low != 0 {
+                    low - 1
+                } else if high != max {
+                    high + 1
+                } else {
+                    return;
+                };
+                for ii in 0..size {
+                    set(input, byte + ii, !0, (value >> (ii * 8)) as u8);
+                }
+            }
+            Cond::Compare { lhs, rhs, relation } => {
+                grow(input, lhs.max(rhs));
+                let (Some(&x), Some(&y)) = (input.get(lhs), input.get(rhs))
+                        else { return };
+                match (relation, holds) {
+                    (Relation::Equal, true)  => input[rhs] = x,
+                    (Relation::Equal, false) => input[rhs] = x.wrapping_add(1),
+                    (Relation::Less, true)   => {
+                        input[lhs] = 0;
+                        input[rhs] = y.max(1);
+                    }
+                    (Relation::Less, false)  => input[lhs] = y,
+                }
+            }
+            Cond::Checksum { start, len, byte } => {
+                grow(input, (start + len - 1).max(byte));
+                if let Some(range) = input.get(start..start + len) {
+                    let sum = checksum(range);
+                    let sum = if holds { sum } else { sum.wrapping_add(1) };
+                    set(input, byte, !0, sum);
+                }
+            }
+            Cond::Length { len } => {
+                if holds {
+                    grow(input, len - 1);
+                } else {
+                    input.truncate(len - 1);
+                }
+            }
+            Cond::Field { field, offset, mask, target } => {
+                // Prefer the record the field already gives, and otherwise
+                // the shortest one ending before a byte nothing else reads
+                grow(input, field);
+                let Some(&len) = input.get(field) else { return };
+                let free = |len: u8| {
+                    let at = field + 1 + len as usize + offset;
+                    at < input.len().max(max_len) &&
+                        !used.get(at).copied().unwrap_or(false)
+                };
+                let Some(len) = std::iter::once(len).chain(0..=255)
+                        .find(|&x| free(x)) else { return };
+                set(input, field, !0, len);
+                let at = field + 1 + len as usize + offset;
+                if let Some(used) = used.get_mut(at) {
+                    *used = true;
+                }
+                grow(input, at);
+                let target = if holds {
+                    target
+                } else {
+                    target ^ (mask & mask.wrapping_neg())
+                };
+                set(input, at, mask, target);
+            }
+            Cond::Derived { base, .. } => {
+                // Every step of the derivation is a bijection of the base
+                // byte, so some value of it gives any value
+                grow(input, self.input_len() - 1);
+                if input.len() < self.input_len() {
+                    return;
+                }
+                for value in 0..=255 {
+                    input[base] = value;
+                    if self.holds(input) == holds {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Wrapping sum of `bytes`, see [`Cond::Checksum`]
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |x, y| x.wrapping_add(*y))
+}
+
+/// Build an input for which every condition of `path` holds or fails as
+/// required. Inputs are `fixed_len` bytes, or if that is zero as short as
+/// possible up to `max_len` bytes.
+pub(crate) fn solve_path(path: &[(Cond, bool)], fixed_len: usize,
+                         max_len: usize) -> Option<Vec<u8>> {
+    let max_len = if fixed_len != 0 { fixed_len } else { max_len };
+    solve_input(path, vec![0u8; fixed_len], max_len)
+}
+
+/// Change `input` so every condition of `path` holds or fails as required,
+/// leaving the bytes no condition reads alone, like a concolic solver
+/// flipping a branch of a concrete input. Inputs shorter than `max_len`
+/// bytes are extended as needed. Returns `None` if the conditions could not
+/// be satisfied together.
+pub fn solve_input(path: &[(Cond, bool)], mut input: Vec<u8>,
+                   max_len: usize) -> Option<Vec<u8>> {
+    let mut used = vec![false; max_len];
+    for byte in path.iter().flat_map(|(cond, _)| cond.bytes()) {
+        if let Some(used) = used.get_mut(byte) {
+            *used = true;
+        }
+    }
+
+    // Checksums go last, once the bytes they sum are settled. Another round
+    // fixes up conditions broken by a later one on the same bytes.
+    let is_checksum = |cond: &Cond| matches!(cond, Cond::Checksum { .. });
+    for _ in 0..2 {
+        for (cond, holds) in path.iter().filter(|x| !is_checksum(&x.0)) {
+            cond.satisfy(*holds, &mut input, max_len, &mut used);
+        }
+        for (cond, holds) in path.iter().filter(|x| is_checksum(&x.0)) {
+            cond.satisfy(*holds, &mut input, max_len, &mut used);
+        }
+    }
+
+    path.iter().all(|(cond, holds)| cond.holds(&input) == *holds)
+        .then_some(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::{generate, Params};
+
+    /// Conditions on the path from the entry to every block of `program`,
+    /// outermost first
+    fn paths(program: &Program) -> Vec<Vec<(Cond, bool)>> {
+        let guards = program.block_guards();
+        (0..guards.len()).map(|mut id| {
+            let mut path = Vec::new();
+            while guards[id].0 != id {
+                path.splice(0..0, guards[id].1.iter().cloned());
+                id = guards[id].0;
+            }
+            path
+        }).collect()
+    }
+
+    #[test]
+    fn solved_inputs_satisfy_their_path() {
+        let program = generate(&Params {
+            max_input_size_bits: 512,
+            min_blocks:          200,
+            else_chance:         4,
+            magic_chance:        8,
+            compare_chance:      8,
+            range_chance:        8,
+            checksum_chance:     16,
+            derived_chance:      8,
+            ..Params::default()
+        });
+
+        let mut rng = Rng::new(1);
+        for path in paths(&program) {
+            let input: Vec<u8> = (0..program.input_bytes)
+                .map(|_| rng.rand() as u8).collect();
+            let solved = solve_input(&path, input.clone(),
+                                     program.input_bytes).unwrap();
+            assert_eq!(solved.len(), input.len());
+            for (cond, holds) in &path {
+                assert_eq!(cond.holds(&solved), *holds, "{}", cond);
+            }
+
+            // Bytes no condition reads are left alone
+            for (ii, (&x, &y)) in input.iter().zip(&solved).enumerate() {
+                if !path.iter().any(|(cond, _)| cond.bytes().contains(&ii)) {
+                    assert_eq!(x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contradictions_are_unsolvable() {
+        let cond = Cond::Bits { byte: 3, mask: 0x0f, target: 5 };
+        let path = [(cond.clone(), true), (cond, false)];
+        assert_eq!(solve_input(&path, vec![0; 8], 8), None);
+    }
+
+    #[test]
+    fn short_inputs_are_extended() {
+        let path = [(Cond::Bits { byte: 6, mask: 0xff, target: 0x41 }, true)];
+        let solved = solve_input(&path, Vec::new(), 8).unwrap();
+        assert!(solved.len() >= 7 && solved.len() <= 8);
+        assert_eq!(solved[6], 0x41);
+    }
+}
@@ -0,0 +1,258 @@
+//! Statistics of generated programs
+//!
+//! [`Program::stats`] summarizes the shape of a program beyond its block and
+//! crash counts: how its blocks and crashes are spread over nesting depths,
+//! how many bits its conditions check, how many crashes coverage feedback
+//! can't see coming, how many rejects bail out early, and how much of the
+//! input the program looks at at all. The rest of the input is dead, no
+//! mutation of it changes what the program does.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{Block, Cond, Program, Stmt};
+
+/// Statistics of a program, see [`Program::stats`]
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    /// Number of blocks at every nesting depth in their function, indexed by
+    /// depth, counting the coverage events of crashes like the ground truth
+    pub blocks_by_depth: Vec<usize>,
+
+    /// Number of crashes at every nesting depth in their function, indexed
+    /// by depth
+    pub crashes_by_depth: Vec<usize>,
+
+    /// Number of conditions checking every number of input bits, indexed by
+    /// the number of bits. Matches and loops count as a condition on the
+    /// bits they select, checks of the input length as zero bits.
+    pub condition_bits: Vec<usize>,
+
+    /// Number of crashes recording no coverage event
+    pub invisible_crashes: usize,
+
+    /// Number of rejects, returning early from their function
+    pub rejects: usize,
+
+    /// Number of crashes recording no coverage event at every nesting depth
+    /// in their function, indexed by depth
+    pub invisible_crashes_by_depth: Vec<usize>,
+
+    /// Number of distinct input bits checked by any condition. In a parsing
+    /// loop these are bits of record bodies, counted once for all records.
+    pub used_bits: usize,
+
+    /// Size of the input in bits
+    pub input_bits: usize,
+
+    /// Number of input bytes none of whose bits any condition checks
+    pub dead_bytes: usize,
+}
+
+/// Add one to `histogram[index]`, growing it as needed
+fn count(histogram: &mut Vec<usize>, index: usize) {
+    if histogram.len() <= index {
+        histogram.resize(index + 1, 0);
+    }
+    histogram[index] += 1;
+}
+
+/// Add `block`, nested `depth` blocks deep in its function, and everything
+/// nested in it to `stats`, and the input bits its conditions check to
+/// `used_bits`
+fn stats_block(block: &Block, depth: usize, stats: &mut Stats,
+               used_bits: &mut BTreeSet<usize>) {
+    // Count a condition on `mask` of the input byte `byte`
+    fn bits(stats: &mut Stats, used_bits: &mut BTreeSet<usize>, byte: usize,
+            mask: u8) {
+        count(&mut stats.condition_bits, mask.count_ones() as usize);
+        used_bits.extend((0..8).filter(|bit| mask & (1 << bit) != 0)
+                         .map(|bit| byte * 8 + bit));
+    }
+
+    // Count a condition
+    fn cond(stats: &mut Stats, used_bits: &mut BTreeSet<usize>, cond: &Cond) {
+        // Every bit of the input bytes `bytes`
+        let bytes = |used_bits: &mut BTreeSet<usize>,
+                     bytes: &mut dyn Iterator<Item = usize>| {
+            for byte in bytes {
+                used_bits.extend(byte * 8..byte * 8 + 8);
+            }
+        };
+
+        match *cond {
+            Cond::Bits { byte, mask, .. } => {
+                bits(stats, used_bits, byte, mask)
+            }
+            Cond::Magic { byte, size, .. } => {
+                count(&mut stats.condition_bits, size * 8);
+                bytes(used_bits, &mut (byte..byte + size));
+            }
+            Cond::Span { byte, mask, .. } => {
+                count(&mut stats.condition_bits, mask.count_ones() as usize);
+                used_bits.extend((0..16).filter(|bit| mask & (1 << bit) != 0)
+                                 .map(|bit| byte * 8 + bit));
+            }
+            Cond::Range { byte, size, .. } => {
+                count(&mut stats.condition_bits, size * 8);
+                bytes(used_bits, &mut (byte..byte + size));
+            }
+            Cond::Compare { lhs, rhs, .. } => {
+                count(&mut stats.condition_bits, 8);
+                bytes(used_bits, &mut [lhs, rhs].iter().copied());
+            }
+            Cond::Checksum { start, len, byte } => {
+                count(&mut stats.condition_bits, 8);
+                bytes(used_bits, &mut (start..start + len).chain([byte]));
+            }
+            Cond::Length { .. } => count(&mut stats.condition_bits, 0),
+            Cond::Derived { base, ref terms, mask, .. } => {
+                count(&mut stats.condition_bits, mask.count_ones() as usize);
+                bytes(used_bits, &mut std::iter::once(base)
+                      .chain(terms.iter().map(|x| x.1)));
+            }
+            Cond::Field { field, mask, .. } => {
+                // Where the checked bits are depends on the input, only the
+                // length field is at a fixed place
+                count(&mut stats.condition_bits, mask.count_ones() as usize);
+                bytes(used_bits, &mut std::iter::once(field));
+            }
+        }
+    }
+
+    count(&mut stats.blocks_by_depth, depth);
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::If { cond: x, body, else_body } => {
+                cond(stats, used_bits, x);
+                stats_block(body, depth + 1, stats, used_bits);
+                if let Some(else_body) = else_body {
+                    stats_block(else_body, depth + 1, stats, used_bits);
+                }
+            }
+            Stmt::Match { byte, mask, arms } => {
+                bits(stats, used_bits, *byte, *mask);
+                for (_, body) in arms {
+                    stats_block(body, depth + 1, stats, used_bits);
+                }
+            }
+            Stmt::Loop { byte, mask, body } => {
+                bits(stats, used_bits, *byte, *mask);
+                stats_block(body, depth + 1, stats, used_bits);
+            }
+            Stmt::Join { body, .. } => {
+                stats_block(body, depth + 1, stats, used_bits);
+            }
+            Stmt::Crash { cond: x, block, .. } => {
+                cond(stats, used_bits, x);
+                if block.is_some() {
+                    count(&mut stats.blocks_by_depth, depth + 1);
+                } else {
+                    stats.invisible_crashes += 1;
+                    count(&mut stats.invisible_crashes_by_depth, depth + 1);
+                }
+                count(&mut stats.crashes_by_depth, depth + 1);
+            }
+            Stmt::Conjunction { parts, block, .. } => {
+                for (x, part) in parts {
+                    cond(stats, used_bits, x);
+                    if part.is_some() {
+                        count(&mut stats.blocks_by_depth, depth + 1);
+                    }
+                }
+                if block.is_some() {
+                    count(&mut stats.blocks_by_depth, depth + 1);
+                } else {
+                    stats.invisible_crashes += 1;
+                    count(&mut stats.invisible_crashes_by_depth, depth + 1);
+                }
+                count(&mut stats.crashes_by_depth, depth + 1);
+            }
+            Stmt::Reject { cond: x, .. } => {
+                cond(stats, used_bits, x);
+                count(&mut stats.blocks_by_depth, depth + 1);
+                stats.rejects += 1;
+            }
+            Stmt::Call { .. } | Stmt::Records { .. } |
+                Stmt::SetJoin { .. } => {}
+        }
+    }
+}
+
+impl Program {
+    /// Statistics of the program's shape
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            input_bits: self.params.max_input_size_bits,
+            ..Stats::default()
+        };
+        let mut used_bits = BTreeSet::new();
+        for function in self.functions.iter().chain([&self.entry]) {
+            stats_block(function, 0, &mut stats, &mut used_bits);
+        }
+        stats.used_bits = used_bits.len();
+
+        let used_bytes: BTreeSet<usize> = used_bits.iter()
+            .map(|bit| bit / 8).collect();
+        stats.dead_bytes = self.input_bytes.saturating_sub(used_bytes.len());
+        stats
+    }
+}
+
+impl Stats {
+    /// Fraction of the input bits no condition checks
+    pub fn dead_fraction(&self) -> f64 {
+        if self.input_bits == 0 {
+            return 0.;
+        }
+        1. - self.used_bits as f64 / self.input_bits as f64
+    }
+
+    /// JSON object of the statistics, with the histograms as arrays
+    pub fn json(&self) -> String {
+        format!("{{\"blocks_by_depth\":{:?},\"crashes_by_depth\":{:?},\
+                 \"condition_bits\":{:?},\"invisible_crashes\":{},\
+                 \"invisible_crashes_by_depth\":{:?},\"rejects\":{},\
+                 \"used_bits\":{},\"input_bits\":{},\"dead_bytes\":{},\
+                 \"dead_fraction\":{}}}",
+                self.blocks_by_depth, self.crashes_by_depth,
+                self.condition_bits, self.invisible_crashes,
+                self.invisible_crashes_by_depth, self.rejects,
+                self.used_bits, self.input_bits, self.dead_bytes,
+                self.dead_fraction())
+    }
+}
+
+impl fmt::Display for Stats {
+    /// Write out the statistics as a report, one line each, listing the
+    /// non-empty entries of histograms as `index: count`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Non-empty entries of a histogram
+        let histogram = |histogram: &[usize]| {
+            let entries: Vec<String> = histogram.iter().enumerate()
+                .filter(|(_, &count)| count != 0)
+                .map(|(index, count)| format!("{}: {}", index, count))
+                .collect();
+            if entries.is_empty() { "none".into() } else { entries.join(", ") }
+        };
+
+        let crashes: usize = self.crashes_by_depth.iter().sum();
+        writeln!(f, "Blocks:            {}",
+                 self.blocks_by_depth.iter().sum::<usize>())?;
+        writeln!(f, "Crashes:           {} ({} without coverage)", crashes,
+                 self.invisible_crashes)?;
+        writeln!(f, "Rejects:           {}", self.rejects)?;
+        writeln!(f, "Input bits used:   {} of {}", self.used_bits,
+                 self.input_bits)?;
+        writeln!(f, "Dead input:        {:.1}% of bits, {} of {} bytes",
+                 self.dead_fraction() * 100., self.dead_bytes,
+                 self.input_bits.div_ceil(8))?;
+        writeln!(f, "Blocks by depth:   {}",
+                 histogram(&self.blocks_by_depth))?;
+        writeln!(f, "Crashes by depth:  {}",
+                 histogram(&self.crashes_by_depth))?;
+        writeln!(f, "Hidden by depth:   {}",
+                 histogram(&self.invisible_crashes_by_depth))?;
+        write!(f, "Condition bits:    {}", histogram(&self.condition_bits))
+    }
+}
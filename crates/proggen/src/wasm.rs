@@ -0,0 +1,617 @@
+//! WebAssembly backend for generated programs
+//!
+//! [`Program::to_wasm`] emits the same program as [`Program::to_rust`] as a
+//! self-contained WebAssembly module, so it can be run in sandboxed and
+//! browser environments and shared as a portable benchmark. The module has a
+//! fixed interface, which doesn't depend on the program:
+//!
+//! ```text
+//! (import "fuzztheory" "hit" (func (param i32)))
+//! (export "memory"      (memory 0))
+//! (export "input_bytes" (global i32))
+//! (export "crashme"     (func (param i32) (result i32)))
+//! ```
+//!
+//! The input is written to the start of `memory`, which has room for
+//! `input_bytes` bytes, and `crashme` is called with its length. It invokes
+//! `hit` with the ID of every block as it is reached, and returns the ID of
+//! the crash the input triggered, or -1 if it did not crash. Fixed size
+//! inputs are zero padded to the program's input size first, and longer
+//! inputs are cut to it. The program's [`Program::info`] JSON is embedded in
+//! the `fuzztheory` custom section. Programs with several entry points also
+//! export a `crashme_<n>` like `crashme` for every other entry point.
+
+use std::collections::BTreeMap;
+
+use crate::{Block, Cond, Operation, Program, Relation, Stmt};
+
+/// Index of the imported `hit` function
+const HIT: u32 = 0;
+
+/// Index of the function drawing whether a flaky block records coverage
+const FLAKY: u32 = 1;
+
+/// Index of the function summing the bytes of the input, for checksums
+const SUM: u32 = 2;
+
+/// Index of the first of the program's functions, followed by the entry
+/// block, `crashme`, and the `crashme_<n>` of the other entry points
+const FUNCS: u32 = 3;
+
+/// Local holding the offset of the function's input in memory
+const BASE: u32 = 0;
+
+/// Local holding the length of the function's input
+const LEN: u32 = 1;
+
+/// Local holding the result of the last call
+const RESULT: u32 = 2;
+
+/// Local holding the position of the current record
+const POS: u32 = 3;
+
+/// Local holding the end of the current record
+const END: u32 = 4;
+
+/// Local counting the parts of a conjunction which hold
+const PARTS: u32 = 5;
+
+/// Locals every function of the program has, past its parameters
+const SCRATCH: u32 = 6;
+
+/// Opcodes used by the module
+mod op {
+    pub const BLOCK:     u8 = 0x02;
+    pub const LOOP:      u8 = 0x03;
+    pub const IF:        u8 = 0x04;
+    pub const ELSE:      u8 = 0x05;
+    pub const END:       u8 = 0x0b;
+    pub const BR:        u8 = 0x0c;
+    pub const BR_IF:     u8 = 0x0d;
+    pub const RETURN:    u8 = 0x0f;
+    pub const CALL:      u8 = 0x10;
+    pub const SELECT:    u8 = 0x1b;
+    pub const LOCAL_GET: u8 = 0x20;
+    pub const LOCAL_SET: u8 = 0x21;
+    pub const LOCAL_TEE: u8 = 0x22;
+    pub const GLOBAL_GET: u8 = 0x23;
+    pub const GLOBAL_SET: u8 = 0x24;
+    pub const LOAD8_U:   u8 = 0x2d;
+    pub const I32_CONST: u8 = 0x41;
+    pub const I64_CONST: u8 = 0x42;
+    pub const I32_EQZ:   u8 = 0x45;
+    pub const I32_EQ:    u8 = 0x46;
+    pub const I32_NE:    u8 = 0x47;
+    pub const I32_LT_U:  u8 = 0x49;
+    pub const I32_GT_U:  u8 = 0x4b;
+    pub const I32_GE_U:  u8 = 0x4f;
+    pub const I64_EQZ:   u8 = 0x50;
+    pub const I32_ADD:   u8 = 0x6a;
+    pub const I32_SUB:   u8 = 0x6b;
+    pub const I32_AND:   u8 = 0x71;
+    pub const I32_OR:    u8 = 0x72;
+    pub const I32_XOR:   u8 = 0x73;
+    pub const I32_SHL:   u8 = 0x74;
+    pub const I32_SHR_U: u8 = 0x76;
+    pub const I64_REM_U: u8 = 0x82;
+    pub const I64_XOR:   u8 = 0x85;
+    pub const I64_SHL:   u8 = 0x86;
+    pub const I64_SHR_U: u8 = 0x88;
+    pub const MISC:      u8 = 0xfc;
+
+    /// `memory.fill`, following [`MISC`]
+    pub const MEMORY_FILL: u8 = 0x0b;
+
+    /// Block types
+    pub const EMPTY: u8 = 0x40;
+    pub const I32:   u8 = 0x7f;
+    pub const I64:   u8 = 0x7e;
+}
+
+/// Append `x` to `out` as unsigned LEB128
+fn uleb(out: &mut Vec<u8>, mut x: u64) {
+    loop {
+        let byte = x as u8 & 0x7f;
+        x >>= 7;
+        if x == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Append `x` to `out` as signed LEB128
+fn sleb(out: &mut Vec<u8>, mut x: i64) {
+    loop {
+        let byte = x as u8 & 0x7f;
+        x >>= 6;
+        if x == 0 || x == -1 {
+            out.push(byte);
+            return;
+        }
+        x >>= 1;
+        out.push(byte | 0x80);
+    }
+}
+
+/// Append the vector `items`, prefixed with its length, to `out`
+fn vec(out: &mut Vec<u8>, len: usize, items: &[u8]) {
+    uleb(out, len as u64);
+    out.extend_from_slice(items);
+}
+
+/// Append the section `id` with the contents `contents` to `out`
+fn section(out: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    out.push(id);
+    vec(out, contents.len(), contents);
+}
+
+/// Append the name `name` to `out`
+fn name(out: &mut Vec<u8>, name: &str) {
+    vec(out, name.len(), name.as_bytes());
+}
+
+/// Code of a function being emitted
+#[derive(Default)]
+struct Func {
+    /// Instructions of the function
+    code: Vec<u8>,
+
+    /// Number of locals past the scratch ones, all `i32`
+    locals: u32,
+
+    /// Locals of the flags of the joins in the function
+    joins: BTreeMap<usize, u32>,
+}
+
+impl Func {
+    /// Append the opcodes `ops`
+    fn op(&mut self, ops: &[u8]) -> &mut Self {
+        self.code.extend_from_slice(ops);
+        self
+    }
+
+    /// Append an opcode with the unsigned immediate `x`
+    fn imm(&mut self, op: u8, x: u32) -> &mut Self {
+        self.code.push(op);
+        uleb(&mut self.code, x as u64);
+        self
+    }
+
+    /// Push the constant `x`
+    fn i32(&mut self, x: i32) -> &mut Self {
+        self.code.push(op::I32_CONST);
+        sleb(&mut self.code, x as i64);
+        self
+    }
+
+    /// Push the input byte `offset` bytes past the address on the stack
+    fn load(&mut self, offset: usize) -> &mut Self {
+        self.code.extend_from_slice(&[op::LOAD8_U, 0]);
+        uleb(&mut self.code, offset as u64);
+        self
+    }
+
+    /// Push the input byte `byte` of the function
+    fn byte(&mut self, byte: usize) -> &mut Self {
+        self.imm(op::LOCAL_GET, BASE).load(byte)
+    }
+
+    /// Allocate a new local
+    fn local(&mut self) -> u32 {
+        self.locals += 1;
+        SCRATCH + self.locals - 1
+    }
+
+    /// Return the crash ID on the stack if it isn't -1
+    fn propagate(&mut self) {
+        self.imm(op::LOCAL_TEE, RESULT).i32(-1).op(&[op::I32_NE])
+            .op(&[op::IF, op::EMPTY]).imm(op::LOCAL_GET, RESULT)
+            .op(&[op::RETURN, op::END]);
+    }
+
+    /// Push whether `cond` holds
+    fn cond(&mut self, cond: &Cond) {
+        match *cond {
+            Cond::Bits { byte, mask, target } => {
+                self.byte(byte).i32(mask as i32).op(&[op::I32_AND])
+                    .i32(target as i32).op(&[op::I32_EQ]);
+            }
+            Cond::Magic { byte, size, value } => {
+                for ii in 0..size {
+                    self.byte(byte + ii).i32((value >> (ii * 8)) as u8 as i32)
+                        .op(&[op::I32_EQ]);
+                    if ii != 0 {
+                        self.op(&[op::I32_AND]);
+                    }
+                }
+            }
+            Cond::Span { byte, mask, target } => {
+                self.byte(byte).byte(byte + 1).i32(8)
+                    .op(&[op::I32_SHL, op::I32_OR]).i32(mask as i32)
+                    .op(&[op::I32_AND]).i32(target as i32)
+                    .op(&[op::I32_EQ]);
+            }
+            Cond::Range { byte, size, low, high } => {
+                // The value is in the range if it is at most `high - low`
+                // past `low`, with wrapping subtraction
+                self.byte(byte);
+                for ii in 1..size {
+                    self.byte(byte + ii).i32(ii as i32 * 8)
+                        .op(&[op::I32_SHL, op::I32_OR]);
+                }
+                self.i32(low as i32).op(&[op::I32_SUB])
+                    .i32((high - low) as i32)
+                    .op(&[op::I32_GT_U, op::I32_EQZ]);
+            }
+            Cond::Compare { lhs, rhs, relation } => {
+                self.byte(lhs).byte(rhs).op(&[match relation {
+                    Relation::Equal => op::I32_EQ,
+                    Relation::Less  => op::I32_LT_U,
+                }]);
+            }
+            Cond::Checksum { start, len, byte } => {
+                self.imm(op::LOCAL_GET, BASE).i32(start as i32)
+                    .op(&[op::I32_ADD]).i32(len as i32).imm(op::CALL, SUM)
+                    .byte(byte).op(&[op::I32_EQ]);
+            }
+            Cond::Length { len } => {
+                self.imm(op::LOCAL_GET, LEN).i32(len as i32)
+                    .op(&[op::I32_GE_U]);
+            }
+            Cond::Field { field, offset, mask, target } => {
+                // The byte is only read if it's in the input
+                self.byte(field).i32((field + 1 + offset) as i32)
+                    .op(&[op::I32_ADD]).imm(op::LOCAL_GET, LEN)
+                    .op(&[op::I32_LT_U, op::IF, op::I32])
+                    .imm(op::LOCAL_GET, BASE).byte(field).op(&[op::I32_ADD])
+                    .load(field + 1 + offset).i32(mask as i32)
+                    .op(&[op::I32_AND]).i32(target as i32)
+                    .op(&[op::I32_EQ, op::ELSE]).i32(0).op(&[op::END]);
+            }
+            Cond::Derived { base, ref terms, mask, target, .. } => {
+                // Bits past the low 8 of the value are masked off at the
+                // end, as they don't change the ones below them
+                self.byte(base);
+                for &(operation, byte, shift) in terms {
+                    self.byte(byte).i32(shift as i32).op(&[op::I32_SHL]);
+                    self.op(&[match operation {
+                        Operation::Add => op::I32_ADD,
+                        Operation::Sub => op::I32_SUB,
+                        Operation::Xor => op::I32_XOR,
+                    }]);
+                }
+                self.i32(mask as i32).op(&[op::I32_AND])
+                    .i32(target as i32).op(&[op::I32_EQ]);
+            }
+        }
+    }
+
+    /// Record coverage of the block `id`
+    fn hit(&mut self, id: usize) {
+        self.i32(id as i32).imm(op::CALL, HIT);
+    }
+
+    /// Append the code of `block` and everything nested in it
+    fn block(&mut self, block: &Block) {
+        // Flaky blocks only record their coverage by chance
+        if block.flaky {
+            self.imm(op::CALL, FLAKY).op(&[op::IF, op::EMPTY]);
+            self.hit(block.id);
+            self.op(&[op::END]);
+        } else {
+            self.hit(block.id);
+        }
+
+        // Clear the flags of the block's joins
+        for stmt in &block.stmts {
+            if let Stmt::Join { join, .. } = stmt {
+                let local = self.local();
+                self.joins.insert(*join, local);
+                self.i32(0).imm(op::LOCAL_SET, local);
+            }
+        }
+
+        for stmt in &block.stmts {
+            match stmt {
+                Stmt::If { cond, body, else_body } => {
+                    self.cond(cond);
+                    self.op(&[op::IF, op::EMPTY]);
+                    self.block(body);
+                    if let Some(else_body) = else_body {
+                        self.op(&[op::ELSE]);
+                        self.block(else_body);
+                    }
+                    self.op(&[op::END]);
+                }
+                Stmt::Match { byte, mask, arms } => {
+                    // The arms' values are distinct, so at most one is taken
+                    for (target, body) in arms {
+                        self.cond(&Cond::Bits {
+                            byte: *byte, mask: *mask, target: *target,
+                        });
+                        self.op(&[op::IF, op::EMPTY]);
+                        self.block(body);
+                        self.op(&[op::END]);
+                    }
+                }
+                Stmt::Loop { byte, mask, body } => {
+                    let count = self.local();
+                    self.byte(*byte).i32(*mask as i32).op(&[op::I32_AND])
+                        .i32(mask.trailing_zeros() as i32)
+                        .op(&[op::I32_SHR_U]).imm(op::LOCAL_SET, count)
+                        .op(&[op::BLOCK, op::EMPTY, op::LOOP, op::EMPTY])
+                        .imm(op::LOCAL_GET, count).op(&[op::I32_EQZ])
+                        .imm(op::BR_IF, 1);
+                    self.block(body);
+                    self.imm(op::LOCAL_GET, count).i32(1)
+                        .op(&[op::I32_SUB]).imm(op::LOCAL_SET, count)
+                        .imm(op::BR, 0).op(&[op::END, op::END]);
+                }
+                Stmt::Call { function } => {
+                    self.imm(op::LOCAL_GET, BASE).imm(op::LOCAL_GET, LEN)
+                        .imm(op::CALL, FUNCS + *function as u32);
+                    self.propagate();
+                }
+                Stmt::SetJoin { join } => {
+                    let local = self.joins[join];
+                    self.i32(1).imm(op::LOCAL_SET, local);
+                }
+                Stmt::Join { join, body } => {
+                    let local = self.joins[join];
+                    self.imm(op::LOCAL_GET, local).op(&[op::IF, op::EMPTY]);
+                    self.block(body);
+                    self.op(&[op::END]);
+                }
+                Stmt::Records { arms } => {
+                    // Walk the records while there's a tag and length left
+                    self.i32(0).imm(op::LOCAL_SET, POS)
+                        .op(&[op::BLOCK, op::EMPTY, op::LOOP, op::EMPTY])
+                        .imm(op::LOCAL_GET, POS).i32(2).op(&[op::I32_ADD])
+                        .imm(op::LOCAL_GET, LEN).op(&[op::I32_GT_U])
+                        .imm(op::BR_IF, 1);
+
+                    // The body ends after the length byte, or at the end of
+                    // the input
+                    self.imm(op::LOCAL_GET, POS).i32(2).op(&[op::I32_ADD])
+                        .imm(op::LOCAL_GET, BASE).imm(op::LOCAL_GET, POS)
+                        .op(&[op::I32_ADD]).load(1).op(&[op::I32_ADD])
+                        .imm(op::LOCAL_SET, END)
+                        .imm(op::LOCAL_GET, END).imm(op::LOCAL_GET, LEN)
+                        .imm(op::LOCAL_GET, END).imm(op::LOCAL_GET, LEN)
+                        .op(&[op::I32_LT_U, op::SELECT])
+                        .imm(op::LOCAL_SET, END);
+
+                    // Tags are distinct, so at most one handler is called
+                    for &(tag, function) in arms {
+                        self.imm(op::LOCAL_GET, BASE)
+                            .imm(op::LOCAL_GET, POS).op(&[op::I32_ADD])
+                            .load(0).i32(tag as i32)
+                            .op(&[op::I32_EQ, op::IF, op::EMPTY])
+                            .imm(op::LOCAL_GET, BASE).imm(op::LOCAL_GET, POS)
+                            .op(&[op::I32_ADD]).i32(2).op(&[op::I32_ADD])
+                            .imm(op::LOCAL_GET, END).imm(op::LOCAL_GET, POS)
+                            .op(&[op::I32_SUB]).i32(2).op(&[op::I32_SUB])
+                            .imm(op::CALL, FUNCS + function as u32);
+                        self.propagate();
+                        self.op(&[op::END]);
+                    }
+
+                    self.imm(op::LOCAL_GET, END).imm(op::LOCAL_SET, POS)
+                        .imm(op::BR, 0).op(&[op::END, op::END]);
+                }
+                Stmt::Crash { cond, id, block, .. } => {
+                    self.cond(cond);
+                    self.op(&[op::IF, op::EMPTY]);
+                    if let Some(block) = block {
+                        self.hit(*block);
+                    }
+                    self.i32(*id as i32).op(&[op::RETURN, op::END]);
+                }
+                Stmt::Reject { cond, block } => {
+                    self.cond(cond);
+                    self.op(&[op::IF, op::EMPTY]);
+                    self.hit(*block);
+                    self.i32(-1).op(&[op::RETURN, op::END]);
+                }
+                Stmt::Conjunction { parts, id, block, .. } => {
+                    // Count the parts which hold
+                    self.i32(0).imm(op::LOCAL_SET, PARTS);
+                    for (cond, block) in parts {
+                        self.cond(cond);
+                        self.op(&[op::IF, op::EMPTY]);
+                        if let Some(block) = block {
+                            self.hit(*block);
+                        }
+                        self.imm(op::LOCAL_GET, PARTS).i32(1)
+                            .op(&[op::I32_ADD]).imm(op::LOCAL_SET, PARTS)
+                            .op(&[op::END]);
+                    }
+                    self.imm(op::LOCAL_GET, PARTS).i32(parts.len() as i32)
+                        .op(&[op::I32_EQ, op::IF, op::EMPTY]);
+                    if let Some(block) = block {
+                        self.hit(*block);
+                    }
+                    self.i32(*id as i32).op(&[op::RETURN, op::END]);
+                }
+            }
+        }
+    }
+
+    /// Encode the function as an entry of the code section, with `locals`
+    /// declarations of its locals past its parameters
+    fn finish(mut self, locals: &[(u32, u8)]) -> Vec<u8> {
+        self.code.push(op::END);
+        let mut body = Vec::new();
+        uleb(&mut body, locals.len() as u64);
+        for &(count, kind) in locals {
+            uleb(&mut body, count as u64);
+            body.push(kind);
+        }
+        body.extend_from_slice(&self.code);
+
+        let mut out = Vec::new();
+        vec(&mut out, body.len(), &body);
+        out
+    }
+
+    /// Encode one of the program's functions, see [`Func::finish`]
+    fn finish_program(self) -> Vec<u8> {
+        let locals = SCRATCH - 2 + self.locals;
+        self.finish(&[(locals, op::I32)])
+    }
+}
+
+impl Program {
+    /// Emit the program as a WebAssembly module, see the [module
+    /// documentation](self) for its interface
+    pub fn to_wasm(&self) -> Vec<u8> {
+        // Function types: `hit`, `flaky`, `sum` and the program's functions,
+        // and `crashme`
+        let mut types = Vec::new();
+        uleb(&mut types, 4);
+        types.extend_from_slice(&[0x60, 1, op::I32, 0]);
+        types.extend_from_slice(&[0x60, 0, 1, op::I32]);
+        types.extend_from_slice(&[0x60, 2, op::I32, op::I32, 1, op::I32]);
+        types.extend_from_slice(&[0x60, 1, op::I32, 1, op::I32]);
+
+        let mut imports = Vec::new();
+        uleb(&mut imports, 1);
+        name(&mut imports, "fuzztheory");
+        name(&mut imports, "hit");
+        imports.extend_from_slice(&[0x00, 0]);
+
+        // `flaky`, `sum`, the program's functions, its entry block, and
+        // `crashme` of every entry point
+        let entries = 1 + self.entry_points.len();
+        let mut funcs = Vec::new();
+        let count = 2 + self.functions.len() + 1 + entries;
+        uleb(&mut funcs, count as u64);
+        funcs.extend_from_slice(&[1, 2]);
+        funcs.extend(std::iter::repeat_n(2, self.functions.len() + 1));
+        funcs.extend(std::iter::repeat_n(3, entries));
+
+        // Enough 64 KiB pages to hold the input
+        let pages = self.input_bytes.div_ceil(65536).max(1) as u64;
+        let mut memory = Vec::new();
+        memory.extend_from_slice(&[1, 0x01]);
+        uleb(&mut memory, pages);
+        uleb(&mut memory, pages);
+
+        // The flaky RNG's state, and the input size
+        let mut globals = Vec::new();
+        uleb(&mut globals, 2);
+        globals.extend_from_slice(&[op::I64, 1, op::I64_CONST]);
+        sleb(&mut globals, 0x2545f4914f6cdd1d);
+        globals.extend_from_slice(&[op::END, op::I32, 0, op::I32_CONST]);
+        sleb(&mut globals, self.input_bytes as i64);
+        globals.push(op::END);
+
+        let mut exports = Vec::new();
+        uleb(&mut exports, 2 + entries as u64);
+        name(&mut exports, "memory");
+        exports.extend_from_slice(&[0x02, 0]);
+        name(&mut exports, "input_bytes");
+        exports.extend_from_slice(&[0x03, 1]);
+        for entry in 0..entries {
+            if entry == 0 {
+                name(&mut exports, "crashme");
+            } else {
+                name(&mut exports, &format!("crashme_{}", entry));
+            }
+            exports.push(0x00);
+            uleb(&mut exports, (FUNCS as usize + self.functions.len() + 1 +
+                                entry) as u64);
+        }
+
+        let mut code = Vec::new();
+        uleb(&mut code, count as u64);
+        code.extend(self.wasm_flaky());
+        code.extend(wasm_sum());
+        for function in self.functions.iter().chain(Some(&self.entry)) {
+            let mut func = Func::default();
+            func.block(function);
+            func.i32(-1);
+            code.extend(func.finish_program());
+        }
+        code.extend(self.wasm_crashme(FUNCS + self.functions.len() as u32));
+        for &function in &self.entry_points {
+            code.extend(self.wasm_crashme(FUNCS + function as u32));
+        }
+
+        let mut custom = Vec::new();
+        name(&mut custom, "fuzztheory");
+        custom.extend_from_slice(self.info().as_bytes());
+
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        section(&mut module, 1, &types);
+        section(&mut module, 2, &imports);
+        section(&mut module, 3, &funcs);
+        section(&mut module, 5, &memory);
+        section(&mut module, 6, &globals);
+        section(&mut module, 7, &exports);
+        section(&mut module, 10, &code);
+        section(&mut module, 0, &custom);
+        module
+    }
+
+    /// Code of the function drawing whether a flaky block records its
+    /// coverage, from the same xorshift RNG as [`Program::to_rust`]
+    fn wasm_flaky(&self) -> Vec<u8> {
+        let mut func = Func::default();
+        func.imm(op::GLOBAL_GET, 0).imm(op::LOCAL_SET, 0);
+        for (shift, left) in [(13, true), (7, false), (17, true)] {
+            func.imm(op::LOCAL_GET, 0).imm(op::LOCAL_GET, 0)
+                .op(&[op::I64_CONST, shift])
+                .op(&[if left { op::I64_SHL } else { op::I64_SHR_U }])
+                .op(&[op::I64_XOR]).imm(op::LOCAL_SET, 0);
+        }
+        func.imm(op::LOCAL_GET, 0).imm(op::GLOBAL_SET, 0)
+            .imm(op::LOCAL_GET, 0).op(&[op::I64_CONST]);
+        sleb(&mut func.code, self.params.flaky_hit_chance as i64);
+        func.op(&[op::I64_REM_U, op::I64_EQZ]);
+        func.finish(&[(1, op::I64)])
+    }
+
+    /// Code of `crashme`, which fits the input to the program's input size
+    /// and runs the function at index `function` on it, the entry block or
+    /// the function of another entry point
+    fn wasm_crashme(&self, function: u32) -> Vec<u8> {
+        let size = self.input_bytes as i32;
+        let mut func = Func::default();
+        if self.params.variable_length {
+            func.i32(0).imm(op::LOCAL_GET, 0).i32(size).imm(op::LOCAL_GET, 0)
+                .i32(size).op(&[op::I32_LT_U, op::SELECT]);
+        } else {
+            func.imm(op::LOCAL_GET, 0).i32(size)
+                .op(&[op::I32_LT_U, op::IF, op::EMPTY])
+                .imm(op::LOCAL_GET, 0).i32(0).i32(size)
+                .imm(op::LOCAL_GET, 0).op(&[op::I32_SUB])
+                .op(&[op::MISC, op::MEMORY_FILL, 0, op::END])
+                .i32(0).i32(size);
+        }
+        func.imm(op::CALL, function);
+        func.finish(&[])
+    }
+}
+
+/// Code of the function summing the bytes of the input at its first
+/// parameter, as long as its second one says, wrapping to a byte
+fn wasm_sum() -> Vec<u8> {
+    /// Local holding the sum so far
+    const ACC: u32 = 2;
+
+    let mut func = Func::default();
+    func.op(&[op::BLOCK, op::EMPTY, op::LOOP, op::EMPTY])
+        .imm(op::LOCAL_GET, LEN).op(&[op::I32_EQZ]).imm(op::BR_IF, 1)
+        .imm(op::LOCAL_GET, ACC).imm(op::LOCAL_GET, BASE).load(0)
+        .op(&[op::I32_ADD]).imm(op::LOCAL_SET, ACC)
+        .imm(op::LOCAL_GET, BASE).i32(1).op(&[op::I32_ADD])
+        .imm(op::LOCAL_SET, BASE)
+        .imm(op::LOCAL_GET, LEN).i32(1).op(&[op::I32_SUB])
+        .imm(op::LOCAL_SET, LEN)
+        .imm(op::BR, 0).op(&[op::END, op::END])
+        .imm(op::LOCAL_GET, ACC).i32(0xff).op(&[op::I32_AND]);
+    func.finish(&[(1, op::I32)])
+}
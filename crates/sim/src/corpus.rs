@@ -0,0 +1,111 @@
+//! Sparse storage of corpus inputs
+//!
+//! Every simulated worker keeps a corpus of its own unless inputs are shared,
+//! so storing each input in full takes `workers * entries * input_size`
+//! bytes, which rules out large inputs. Inputs start out as all zeros and
+//! mutations only ever change a few bytes at a time, so a [`Corpus`] stores
+//! just the non-zero bytes of every input instead. The memory it takes then
+//! grows with the bytes fuzzing actually changed, not with the input size.
+
+/// The inputs saved by a worker, as the offsets and values of their non-zero
+/// bytes
+#[derive(Clone, Debug, Default)]
+pub struct Corpus {
+    /// Offsets of the non-zero bytes of all inputs, back to back
+    offsets: Vec<u32>,
+
+    /// Values of the non-zero bytes of all inputs, matching `offsets`
+    values: Vec<u8>,
+
+    /// Index of the first non-zero byte of every input in `offsets`, and the
+    /// length of the input
+    entries: Vec<(usize, usize)>,
+}
+
+impl Corpus {
+    /// Number of inputs in the corpus
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the corpus holds no inputs
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove every input
+    pub fn clear(&mut self) {
+        self.offsets.clear();
+        self.values.clear();
+        self.entries.clear();
+    }
+
+    /// Add `input` to the corpus
+    pub fn push(&mut self, input: &[u8]) {
+        self.entries.push((self.offsets.len(), input.len()));
+        for (offset, &value) in input.iter().enumerate() {
+            if value != 0 {
+                self.offsets.push(offset as u32);
+                self.values.push(value);
+            }
+        }
+    }
+
+    /// Length of input `index`
+    pub fn input_len(&self, index: usize) -> usize {
+        self.entries[index].1
+    }
+
+    /// Copy input `index` to the start of `input`, returning its length.
+    /// Bytes of `input` past the length are left alone.
+    pub fn load(&self, index: usize, input: &mut [u8]) -> usize {
+        let (start, len) = self.entries[index];
+        let end = self.entries.get(index + 1).map_or(self.offsets.len(),
+                                                      |x| x.0);
+        input[..len].fill(0);
+        for (&offset, &value) in self.offsets[start..end].iter()
+                .zip(&self.values[start..end]) {
+            input[offset as usize] = value;
+        }
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_round_trip() {
+        let inputs: [&[u8]; 5] = [
+            &[],
+            &[0; 16],
+            &[1, 0, 0, 2, 0],
+            &[0, 0, 0xff, 0, 0, 0, 0],
+            &[7; 3],
+        ];
+
+        let mut corpus = Corpus::default();
+        for input in inputs {
+            corpus.push(input);
+        }
+        assert_eq!(corpus.len(), inputs.len());
+
+        // Load over stale bytes, which must be cleared up to the length of
+        // the input and left alone past it
+        for (index, input) in inputs.iter().enumerate() {
+            let mut buffer = [0xaa; 32];
+            assert_eq!(corpus.input_len(index), input.len());
+            assert_eq!(corpus.load(index, &mut buffer), input.len());
+            assert_eq!(&buffer[..input.len()], *input);
+            assert!(buffer[input.len()..].iter().all(|&x| x == 0xaa));
+        }
+
+        corpus.clear();
+        assert!(corpus.is_empty());
+        corpus.push(&[0, 3]);
+        let mut buffer = [0xaa; 2];
+        assert_eq!(corpus.load(0, &mut buffer), 2);
+        assert_eq!(buffer, [0, 3]);
+    }
+}
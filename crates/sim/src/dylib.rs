@@ -0,0 +1,212 @@
+//! Targets loaded from dynamic libraries at runtime
+//!
+//! The `cdylib` build of `fuzztheory-target` exports the generated program
+//! with a small C ABI:
+//!
+//! ```c
+//! int64_t fuzztheory_run(const uint8_t *input, size_t len,
+//!                        void (*hit)(void *ctx, uint32_t block), void *ctx);
+//! const char *fuzztheory_info(void);
+//! int64_t fuzztheory_run_entry(size_t entry, const uint8_t *input,
+//!                              size_t len,
+//!                              void (*hit)(void *ctx, uint32_t block),
+//!                              void *ctx);
+//! ```
+//!
+//! where `len` is the input size from the metadata, or at most that for
+//! programs taking variable length inputs, `hit` is invoked with the ID of
+//! every block as it is reached, `fuzztheory_run` returns the ID of the crash
+//! the input triggered or -1 if it did not crash, and `fuzztheory_info`
+//! returns the program's [`Metadata`] JSON as a NUL terminated string.
+//! Libraries without `fuzztheory_info` have their metadata read from their
+//! [`sidecar`] file instead. `fuzztheory_run_entry` runs the program from
+//! entry point `entry` instead of the first, libraries without it only run
+//! the first.
+//!
+//! Loading these lets a single simulator process fuzz any number of
+//! generated programs without recompiling the harness.
+
+use std::io;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+
+use crate::Target;
+use crate::metadata::{sidecar, Metadata};
+
+/// Resolve symbols when the library is loaded rather than lazily
+const RTLD_NOW: c_int = 2;
+
+#[link(name = "dl")]
+extern "C" {
+    fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlclose(handle: *mut c_void) -> c_int;
+    fn dlerror() -> *mut c_char;
+}
+
+/// Get the last `dlopen()` family error as an I/O error
+fn dl_error(context: &str) -> io::Error {
+    let err = unsafe { dlerror() };
+    let msg = if err.is_null() {
+        "unknown error".into()
+    } else {
+        unsafe { CStr::from_ptr(err) }.to_string_lossy().into_owned()
+    };
+    io::Error::other(format!("{}: {}", context, msg))
+}
+
+/// A generated program loaded from a dynamic library
+pub struct DylibTarget {
+    /// Handle returned by `dlopen()`
+    handle: *mut c_void,
+
+    /// The `fuzztheory_run` export
+    run: RunFn,
+
+    /// The `fuzztheory_run_entry` export, if any
+    run_entry: Option<RunEntryFn>,
+
+    /// Metadata of the program
+    metadata: Metadata,
+}
+
+/// Callback invoked by the program for every block reached
+type HitFn = unsafe extern "C" fn(*mut c_void, u32);
+
+/// Signature of the `fuzztheory_run` export
+type RunFn = unsafe extern "C" fn(*const u8, usize, HitFn, *mut c_void)
+    -> i64;
+
+/// Signature of the `fuzztheory_run_entry` export
+type RunEntryFn = unsafe extern "C" fn(usize, *const u8, usize, HitFn,
+                                       *mut c_void) -> i64;
+
+/// [`HitFn`] appending blocks to the `Vec<u32>` trace pointed to by `ctx`
+unsafe extern "C" fn push_hit(ctx: *mut c_void, block: u32) {
+    (*(ctx as *mut Vec<u32>)).push(block);
+}
+
+// The handle is only used to close the library, and the program itself is a
+// pure function of its arguments
+unsafe impl Send for DylibTarget {}
+unsafe impl Sync for DylibTarget {}
+
+impl DylibTarget {
+    /// Load the generated program in the library at `path`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        // `dlopen()` searches the library path for bare file names
+        let cpath = if path.components().count() == 1 {
+            Path::new(".").join(path)
+        } else {
+            path.to_path_buf()
+        };
+        let cpath = CString::new(cpath.to_string_lossy().as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput,
+                "library path contains a NUL byte"))?;
+
+        let handle = unsafe { dlopen(cpath.as_ptr(), RTLD_NOW) };
+        if handle.is_null() {
+            return Err(dl_error(&format!("failed to load {}",
+                                         path.display())));
+        }
+
+        // Look up a symbol in the library
+        let sym = |name: &str| {
+            let cname = CString::new(name).unwrap();
+            let addr = unsafe { dlsym(handle, cname.as_ptr()) };
+            if addr.is_null() {
+                Err(dl_error(&format!("{} has no `{}`", path.display(),
+                                      name)))
+            } else {
+                Ok(addr)
+            }
+        };
+
+        let lookup = || -> io::Result<Self> {
+            let run = unsafe {
+                std::mem::transmute::<*mut c_void, RunFn>(
+                    sym("fuzztheory_run")?)
+            };
+
+            // Prefer the metadata embedded in the library
+            let cname = CString::new("fuzztheory_info").unwrap();
+            let info = unsafe { dlsym(handle, cname.as_ptr()) };
+            let metadata = if info.is_null() {
+                Metadata::load(&sidecar(path))?
+            } else {
+                let info: extern "C" fn() -> *const c_char =
+                    unsafe { std::mem::transmute(info) };
+                let info = unsafe { CStr::from_ptr(info()) }
+                    .to_string_lossy().into_owned();
+                Metadata::parse(&info).map_err(|err| {
+                    io::Error::new(io::ErrorKind::InvalidData,
+                                   format!("{}: {}", path.display(), err))
+                })?
+            };
+
+            let cname = CString::new("fuzztheory_run_entry").unwrap();
+            let run_entry = unsafe { dlsym(handle, cname.as_ptr()) };
+            let run_entry = (!run_entry.is_null()).then(|| unsafe {
+                std::mem::transmute::<*mut c_void, RunEntryFn>(run_entry)
+            });
+
+            Ok(DylibTarget { handle, run, run_entry, metadata })
+        };
+
+        lookup().inspect_err(|_| unsafe { dlclose(handle); })
+    }
+}
+
+impl Drop for DylibTarget {
+    fn drop(&mut self) {
+        unsafe { dlclose(self.handle); }
+    }
+}
+
+impl Target for DylibTarget {
+    fn input_size(&self)   -> usize { self.metadata.input_size }
+    fn variable_length(&self) -> bool { self.metadata.variable_length }
+    fn num_coverage(&self) -> usize { self.metadata.num_coverage }
+    fn num_crashes(&self)  -> usize { self.metadata.num_crashes }
+    fn info(&self) -> Option<&str> { Some(&self.metadata.info) }
+
+    fn run(&self, input: &[u8], trace: &mut Vec<u32>) -> Option<u32> {
+        if self.metadata.variable_length {
+            assert!(input.len() <= self.metadata.input_size);
+        } else {
+            assert!(input.len() == self.metadata.input_size);
+        }
+        let crash = unsafe {
+            (self.run)(input.as_ptr(), input.len(), push_hit,
+                       trace as *mut Vec<u32> as *mut c_void)
+        };
+        (crash >= 0).then_some(crash as u32)
+    }
+
+    fn entry_points(&self) -> usize {
+        if self.run_entry.is_some() {
+            1 + self.metadata.entry_points.len()
+        } else {
+            1
+        }
+    }
+
+    fn run_entry(&self, entry: usize, input: &[u8], trace: &mut Vec<u32>)
+            -> Option<u32> {
+        let Some(run_entry) = self.run_entry.filter(|_| entry != 0) else {
+            return self.run(input, trace);
+        };
+        assert!(entry < self.entry_points());
+        if self.metadata.variable_length {
+            assert!(input.len() <= self.metadata.input_size);
+        } else {
+            assert!(input.len() == self.metadata.input_size);
+        }
+        let crash = unsafe {
+            run_entry(entry, input.as_ptr(), input.len(), push_hit,
+                      trace as *mut Vec<u32> as *mut c_void)
+        };
+        (crash >= 0).then_some(crash as u32)
+    }
+}
@@ -0,0 +1,272 @@
+//! Entry point for compiled harness binaries
+//!
+//! The generator emits a program as Rust source, which the
+//! `fuzztheory-target` crate compiles into a harness binary. The driver then
+//! invokes that binary with a mode (`sweep`, `heatmap`, or `perf`) followed
+//! by the sweep parameters as options, which are handled by [`main`].
+//! Targets loaded in the driver's own process are run with [`run`] instead.
+
+use std::io;
+
+use fuzztheory_proggen::log;
+
+use crate::{interrupt, Registry, Target};
+use crate::manifest::{Manifest, Status};
+use crate::sweep::{self, Params};
+
+/// Encode `params` as the options understood by [`main`]
+pub fn args(params: &Params) -> Vec<String> {
+    let mut args = vec![
+        "--max-cores".into(),    params.max_cores.to_string(),
+        "--x-resolution".into(), params.max_x_resolution.to_string(),
+        "--averages".into(),     params.averages.to_string(),
+        "--threads".into(),      params.threads.to_string(),
+        "--y-resolution".into(), params.max_y_resolution.to_string(),
+        "--y-max".into(),        params.max_y_point.to_string(),
+    ];
+    if let Some(time_constraint) = params.time_constraint {
+        args.push("--time-constraint".into());
+        args.push(time_constraint.to_string());
+    }
+    for (flag, values) in [("--guided",         &params.guided),
+                           ("--shared-inputs",  &params.shared_inputs),
+                           ("--shared-results", &params.shared_results),
+                           ("--deterministic",  &params.deterministic)] {
+        args.push(flag.into());
+        args.push(values.iter().map(bool::to_string).collect::<Vec<_>>()
+            .join(","));
+    }
+    for mutator in &params.mutators {
+        args.push("--mutator".into());
+        args.push(mutator.clone());
+    }
+    args.push("--scheduler".into());
+    args.push(params.scheduler.clone());
+    for metric in &params.metrics {
+        args.push("--metric".into());
+        args.push(metric.clone());
+    }
+    args.push("--havoc-stack".into());
+    args.push(params.havoc_stack.to_string());
+    for op in &params.havoc_ops {
+        args.push("--havoc-op".into());
+        args.push(op.clone());
+    }
+    for count in &params.mutation_counts {
+        args.push("--mutation-count".into());
+        args.push(count.clone());
+    }
+    args.push("--mopt-period".into());
+    args.push(params.mopt_period.to_string());
+    args.push("--dictionary-completeness".into());
+    args.push(params.dictionary_completeness.to_string());
+    args.push("--map-size".into());
+    args.push(params.map_size.to_string());
+    args.push("--taint-chance".into());
+    args.push(params.taint_chance.to_string());
+    args.push("--taint-cost".into());
+    args.push(params.taint_cost.to_string());
+    args.push("--solver-period".into());
+    args.push(params.solver_period.to_string());
+    args.push("--dedup".into());
+    args.push(params.dedup.clone());
+    args.push("--dedup-bucket-size".into());
+    args.push(params.dedup_bucket_size.to_string());
+    if !params.instrumented_modules.is_empty() {
+        args.push("--instrumented-modules".into());
+        args.push(params.instrumented_modules.iter().map(usize::to_string)
+            .collect::<Vec<_>>().join(","));
+    }
+    args.push("--entry-points".into());
+    args.push(params.entry_points.iter().map(usize::to_string)
+        .collect::<Vec<_>>().join(","));
+    args.push("--log-level".into());
+    args.push(log::level().name().into());
+    if let Some(seed) = params.seed {
+        args.push("--seed".into());
+        args.push(seed.to_string());
+    }
+    if let Some(trial) = params.trial {
+        args.push("--trial".into());
+        args.push(trial.to_string());
+    }
+    if !params.output_dir.as_os_str().is_empty() {
+        args.push("--output-dir".into());
+        args.push(params.output_dir.to_string_lossy().into_owned());
+    }
+    if params.resume {
+        args.push("--resume".into());
+    }
+    args
+}
+
+/// Run the harness `mode` (`sweep`, `heatmap`, or `perf`) on `target`.
+/// Ctrl-C stops the run at the next datapoint, see [`interrupt`]. Sweeps
+/// record a [`manifest`] next to their results, including the seed picked
+/// by [`Params::prepare`] for unseeded runs.
+pub fn run<T: Target + ?Sized>(mode: &str, target: &T, params: &Params)
+        -> io::Result<()> {
+    interrupt::install();
+    let params = &params.prepare();
+
+    let sweep: fn(&T, &Params) -> io::Result<()> = match mode {
+        "sweep"   => |target, params| {
+            sweep::run(target, params, params.time_constraint)
+        },
+        "heatmap" => sweep::heatmap,
+        "perf"    => return sweep::perf(target, params),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            format!("Unknown harness mode {:?}", mode))),
+    };
+
+    let manifest = Manifest::new(mode, target, params);
+    manifest.write(Status::Running)?;
+    let result = sweep(target, params);
+    manifest.write(match &result {
+        Ok(())  => Status::Complete,
+        Err(err) if err.kind() == io::ErrorKind::Interrupted =>
+            Status::Interrupted,
+        Err(_)  => Status::Failed,
+    })?;
+    result
+}
+
+/// Parse the harness command line and run the requested mode on `target`
+pub fn main<T: Target + ?Sized>(target: &T) -> io::Result<()> {
+    main_with(target, Registry::default())
+}
+
+/// Like [`main`], but strategy names are looked up in `registry`
+pub fn main_with<T: Target + ?Sized>(target: &T, registry: Registry)
+        -> io::Result<()> {
+    // The driver passes the mode as the first argument followed by any
+    // options for that mode
+    let mut args = std::env::args().skip(1);
+    let mode = args.next().unwrap_or_else(|| "perf".into());
+
+    let mut params = Params { registry, ..Params::default() };
+
+    // Mutators, metrics, havoc operations, and mutation counts given on the
+    // command line replace the defaults
+    let mut mutators = Vec::new();
+    let mut metrics  = Vec::new();
+    let mut havoc_ops = Vec::new();
+    let mut mutation_counts = Vec::new();
+
+    // Get the value for an option, parsed into the type of `$field`
+    macro_rules! value {
+        ($arg:expr, $field:expr) => {
+            $field = args.next().and_then(|x| x.parse().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("{} expects a number", $arg)))?
+        }
+    }
+
+    // Get the value for an option which takes a comma separated list of
+    // booleans
+    macro_rules! bools {
+        ($arg:expr) => {
+            args.next().and_then(|list| {
+                list.split(',').map(|x| x.parse().ok()).collect()
+            }).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} expects a list of booleans", $arg)))?
+        }
+    }
+
+    // Get the value for an option which takes a comma separated list of
+    // numbers
+    macro_rules! numbers {
+        ($arg:expr) => {
+            args.next().and_then(|list| {
+                list.split(',').map(|x| x.parse().ok()).collect()
+            }).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} expects a list of numbers", $arg)))?
+        }
+    }
+
+    // Get the value for an option which takes a string
+    macro_rules! string {
+        ($arg:expr) => {
+            args.next().ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} expects a value", $arg)))?
+        }
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-cores"    => value!(arg, params.max_cores),
+            "--x-resolution" => value!(arg, params.max_x_resolution),
+            "--averages"     => value!(arg, params.averages),
+            "--threads"      => value!(arg, params.threads),
+            "--y-resolution" => value!(arg, params.max_y_resolution),
+            "--y-max"        => value!(arg, params.max_y_point),
+            "--time-constraint" => {
+                let time_constraint: f64;
+                value!(arg, time_constraint);
+                params.time_constraint = Some(time_constraint);
+            }
+            "--mutator"   => mutators.push(string!(arg)),
+            "--scheduler" => params.scheduler = string!(arg),
+            "--metric"    => metrics.push(string!(arg)),
+            "--map-size"  => value!(arg, params.map_size),
+            "--taint-chance" => value!(arg, params.taint_chance),
+            "--taint-cost" => value!(arg, params.taint_cost),
+            "--solver-period" => value!(arg, params.solver_period),
+            "--havoc-stack" => value!(arg, params.havoc_stack),
+            "--havoc-op"  => havoc_ops.push(string!(arg)),
+            "--mopt-period" => value!(arg, params.mopt_period),
+            "--mutation-count" => mutation_counts.push(string!(arg)),
+            "--dictionary-completeness" => {
+                value!(arg, params.dictionary_completeness);
+            }
+            "--dedup"     => params.dedup = string!(arg),
+            "--dedup-bucket-size" => value!(arg, params.dedup_bucket_size),
+            "--resume"    => params.resume = true,
+            "--output-dir" => params.output_dir = string!(arg).into(),
+            "--guided"         => params.guided = bools!(arg),
+            "--shared-inputs"  => params.shared_inputs = bools!(arg),
+            "--shared-results" => params.shared_results = bools!(arg),
+            "--deterministic"  => params.deterministic = bools!(arg),
+            "--instrumented-modules" => {
+                params.instrumented_modules = numbers!(arg);
+            }
+            "--entry-points" => params.entry_points = numbers!(arg),
+            "--seed" => {
+                let seed: u64;
+                value!(arg, seed);
+                params.seed = Some(seed);
+            }
+            "--trial" => {
+                let trial: usize;
+                value!(arg, trial);
+                params.trial = Some(trial);
+            }
+            "--log-level" => {
+                let level = string!(arg);
+                log::set_level(level.parse().map_err(|err: String| {
+                    io::Error::new(io::ErrorKind::InvalidInput, err)
+                })?);
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("Unknown harness option {:?}", arg))),
+        }
+    }
+
+    if !mutators.is_empty() {
+        params.mutators = mutators;
+    }
+    if !metrics.is_empty() {
+        params.metrics = metrics;
+    }
+    if !havoc_ops.is_empty() {
+        params.havoc_ops = havoc_ops;
+    }
+    if !mutation_counts.is_empty() {
+        params.mutation_counts = mutation_counts;
+    }
+
+    run(&mode, target, &params)
+}
@@ -0,0 +1,54 @@
+//! Ctrl-C handling
+//!
+//! Sweeps can run for days, so rather than dying on SIGINT they poll
+//! [`interrupted`] between datapoints and write out what they have collected
+//! so far. A second Ctrl-C kills the process as usual.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once SIGINT has been received
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+mod imp {
+    use std::os::raw::c_int;
+    use std::sync::atomic::Ordering;
+
+    /// Signal number of SIGINT
+    const SIGINT: c_int = 2;
+
+    /// Default signal disposition
+    const SIG_DFL: usize = 0;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: usize) -> usize;
+    }
+
+    /// Record the interrupt, and restore the default handler so another
+    /// Ctrl-C terminates immediately
+    extern "C" fn handler(_signum: c_int) {
+        super::INTERRUPTED.store(true, Ordering::SeqCst);
+        unsafe { signal(SIGINT, SIG_DFL); }
+    }
+
+    pub fn install() {
+        let handler: extern "C" fn(c_int) = handler;
+        unsafe { signal(SIGINT, handler as usize); }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    pub fn install() {}
+}
+
+/// Catch SIGINT, setting the [`interrupted`] flag instead of terminating.
+/// Does nothing on targets other than unix.
+pub fn install() {
+    imp::install()
+}
+
+/// Returns `true` if SIGINT has been received since [`install`]
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,80 @@
+//! Targets interpreted from the IR of generated programs
+//!
+//! `fuzztheory proggen --emit-ir` writes the program as
+//! `fuzztheory_proggen::ir::Ir`, with its metadata embedded. Loading it needs
+//! neither a compiler nor a dynamic library, so a single simulator process
+//! can fuzz thousands of generated programs on any platform, at the cost of
+//! interpreting them.
+
+use std::io;
+use std::path::Path;
+
+use fuzztheory_proggen::ir::Ir;
+
+use crate::Target;
+use crate::metadata::Metadata;
+
+/// Whether the program at `path` is IR rather than a dynamic library, by its
+/// `.ir` extension
+pub fn is_ir(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "ir")
+}
+
+/// A generated program interpreted from its IR
+pub struct IrTarget {
+    /// The program
+    ir: Ir,
+
+    /// Metadata of the program, parsed from the IR
+    metadata: Metadata,
+}
+
+impl IrTarget {
+    /// Wrap the program `ir`
+    pub fn new(ir: Ir) -> Result<Self, String> {
+        let metadata = Metadata::parse(&ir.info)?;
+        Ok(IrTarget { ir, metadata })
+    }
+
+    /// Load the program in the IR file at `path`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Ir::decode(&bytes).and_then(IrTarget::new).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData,
+                           format!("{}: {}", path.display(), err))
+        })
+    }
+}
+
+impl Target for IrTarget {
+    fn input_size(&self)   -> usize { self.metadata.input_size }
+    fn variable_length(&self) -> bool { self.metadata.variable_length }
+    fn num_coverage(&self) -> usize { self.metadata.num_coverage }
+    fn num_crashes(&self)  -> usize { self.metadata.num_crashes }
+    fn info(&self) -> Option<&str> { Some(&self.metadata.info) }
+
+    fn run(&self, input: &[u8], trace: &mut Vec<u32>) -> Option<u32> {
+        if self.metadata.variable_length {
+            assert!(input.len() <= self.metadata.input_size);
+        } else {
+            assert!(input.len() == self.metadata.input_size);
+        }
+        self.ir.run(input, &mut |block| trace.push(block))
+    }
+
+    fn entry_points(&self) -> usize { 1 + self.metadata.entry_points.len() }
+
+    fn run_entry(&self, entry: usize, input: &[u8], trace: &mut Vec<u32>)
+            -> Option<u32> {
+        if entry == 0 {
+            return self.run(input, trace);
+        }
+        if self.metadata.variable_length {
+            assert!(input.len() <= self.metadata.input_size);
+        } else {
+            assert!(input.len() == self.metadata.input_size);
+        }
+        self.ir.run_function(self.metadata.entry_points[entry - 1], input,
+                             &mut |block| trace.push(block))
+    }
+}
@@ -0,0 +1,689 @@
+//! The fuzzer simulation
+//!
+//! A [`Fuzzer`] simulates a number of cores fuzzing a [`Target`] in lock
+//! step, optionally sharing their corpus and coverage databases with each
+//! other. The [`sweep`] drivers run it over many configurations, and
+//! [`harness`] is the entry point of compiled harness binaries. Programs can
+//! also be loaded into an existing process from a dynamic library with
+//! `dylib`, or interpreted from their IR with [`ir`].
+
+use std::collections::VecDeque;
+
+use fuzztheory_proggen::Cond;
+use fuzztheory_proggen::rng::Rng;
+use fuzztheory_proggen::solve::solve_input;
+
+pub mod corpus;
+pub mod harness;
+pub mod interrupt;
+pub mod ir;
+pub mod manifest;
+pub mod metadata;
+pub mod progress;
+pub mod registry;
+pub mod strategy;
+pub mod sweep;
+
+pub use corpus::Corpus;
+pub use metadata::Metadata;
+pub use registry::Registry;
+pub use strategy::{CoverageMetric, Mutator, Scheduler, Strategies};
+
+#[cfg(unix)]
+pub mod dylib;
+
+/// A program which can be fuzzed by the simulator
+pub trait Target: Sync {
+    /// Size of the input to the program in bytes, or the largest size if it
+    /// takes variable length inputs
+    fn input_size(&self) -> usize;
+
+    /// Whether the program takes inputs of any length up to `input_size`
+    fn variable_length(&self) -> bool { false }
+
+    /// Number of blocks (coverage events) in the program
+    fn num_coverage(&self) -> usize;
+
+    /// Number of distinct crashes in the program
+    fn num_crashes(&self) -> usize;
+
+    /// Run the program on `input`, appending the ID of every block reached
+    /// to `trace` in the order they were reached. Returns the ID of the
+    /// crash `input` triggered, if any.
+    fn run(&self, input: &[u8], trace: &mut Vec<u32>) -> Option<u32>;
+
+    /// Number of entry points of the program, see [`Target::run_entry`]
+    fn entry_points(&self) -> usize { 1 }
+
+    /// Run the program from entry point `entry` like [`Target::run`], which
+    /// runs entry point 0
+    fn run_entry(&self, entry: usize, input: &[u8], trace: &mut Vec<u32>)
+            -> Option<u32> {
+        debug_assert_eq!(entry, 0);
+        self.run(input, trace)
+    }
+
+    /// JSON object describing how the program was generated, as produced by
+    /// `fuzztheory_proggen::Program::info`, if known
+    fn info(&self) -> Option<&str> { None }
+}
+
+/// A [`Target`] backed by a plain function, as emitted by the generator
+pub struct FnTarget {
+    /// Size of the input to the program in bytes
+    pub input_size: usize,
+
+    /// Whether the program takes inputs of any length up to `input_size`
+    pub variable_length: bool,
+
+    /// Number of blocks (coverage events) in the program
+    pub num_coverage: usize,
+
+    /// Number of distinct crashes in the program
+    pub num_crashes: usize,
+
+    /// The program itself, see [`Target::run`]
+    pub run: fn(&[u8], &mut Vec<u32>) -> Option<u32>,
+
+    /// Number of entry points of the program
+    pub entry_points: usize,
+
+    /// The program from any entry point, see [`Target::run_entry`]
+    pub run_entry: fn(usize, &[u8], &mut Vec<u32>) -> Option<u32>,
+
+    /// Description of the program, see [`Target::info`]
+    pub info: Option<&'static str>,
+}
+
+impl Target for FnTarget {
+    fn input_size(&self)   -> usize { self.input_size }
+    fn variable_length(&self) -> bool { self.variable_length }
+    fn num_coverage(&self) -> usize { self.num_coverage }
+    fn num_crashes(&self)  -> usize { self.num_crashes }
+    fn info(&self) -> Option<&str> { self.info }
+
+    fn run(&self, input: &[u8], trace: &mut Vec<u32>) -> Option<u32> {
+        (self.run)(input, trace)
+    }
+
+    fn entry_points(&self) -> usize { self.entry_points }
+
+    fn run_entry(&self, entry: usize, input: &[u8], trace: &mut Vec<u32>)
+            -> Option<u32> {
+        (self.run_entry)(entry, input, trace)
+    }
+}
+
+/// A simulated fuzzer, running `workers` simulated cores against a target
+pub struct Fuzzer<'a, T: Target + ?Sized> {
+    /// The program being fuzzed
+    target: &'a T,
+
+    /// A random number generator
+    rng: Rng,
+
+    /// Should the fuzzer use input corpus data to build upon. Eg. should it be
+    /// a coverage guided fuzzer
+    pub coverage_guided: bool,
+
+    /// Should the fuzzer share inputs between simulated cores. This allows
+    /// the cores to collaboratively share coverage information and build off
+    /// eachothers progress.
+    pub shared_inputs: bool,
+
+    /// Should the fuzzer share results between simulated cores. This allows
+    /// the coverage databases to be shared between the cores, thus making
+    /// them work together towards the same goal.
+    pub shared_results: bool,
+
+    /// How many simulated cores should run the fuzzer. This is used to
+    /// evaluate the properties of scaling the fuzzer, but does not actually
+    /// cause any parallelism to be used.
+    pub workers: usize,
+
+    /// Mutates inputs before they are run
+    mutator: Box<dyn Mutator>,
+
+    /// Picks corpus entries to mutate
+    scheduler: Box<dyn Scheduler>,
+
+    /// Feedback deciding which inputs are saved to the corpus
+    metric: Box<dyn CoverageMetric>,
+
+    /// Run AFL's deterministic stage on every input saved to the corpus.
+    /// The fuzz cases after it is saved walk every bit of it, flipping each
+    /// one once, and then every byte, before going back to the scheduler
+    /// and mutator. Workers sharing their inputs share the walk.
+    pub deterministic: bool,
+
+    /// Corpus entries whose deterministic stage is not done yet, per input
+    /// database, oldest first, with the next step of their stage
+    stages: Vec<VecDeque<(usize, usize)>>,
+
+    /// Chance of querying the taint oracle when a corpus entry is picked
+    /// for mutation and nothing is known about its taint yet. The oracle
+    /// runs the entry, picks a random branch next to the blocks it reached
+    /// which was not covered yet, and learns the input bytes its condition
+    /// depends on from the program's ground truth. Mutation of the entry is
+    /// then restricted to those bytes, for free, until the branch is
+    /// covered. Zero never queries it, like a fuzzer without taint tracking.
+    pub taint_chance: f64,
+
+    /// Fuzz cases every query of the taint oracle costs, the overhead of
+    /// running an input with taint tracking
+    pub taint_cost: u64,
+
+    /// Fuzz cases of uptime the solver worker takes to solve a branch, zero
+    /// for no solver. It runs beside the workers of coverage guided
+    /// fuzzers, like the concolic executor of a hybrid fuzzer: it runs a
+    /// random corpus entry of each worker in turn, picks a random branch
+    /// next to the blocks it reached which was not covered yet, and solves
+    /// the conditions on the path to it from the program's ground truth,
+    /// changing only the bytes they read. The workers run the inputs it
+    /// solves before going back to mutating their own.
+    pub solver_period: u64,
+
+    /// Parent of every block, see [`Metadata::block_parents`]
+    block_parents: Vec<usize>,
+
+    /// Branches guarded by input bytes in every block, indexed by block ID,
+    /// from [`Metadata::block_parents`]
+    block_children: Vec<Vec<usize>>,
+
+    /// Conditions guarding every block, see [`Metadata::block_guards`]
+    block_guards: Vec<Vec<(Cond, bool)>>,
+
+    /// Input bytes guarding every block, see [`Metadata::block_taint`]
+    block_taint: Vec<Vec<usize>>,
+
+    /// Branch the taint oracle picked for every corpus entry, per input
+    /// database, if it was queried for the entry
+    taints: Vec<Vec<Option<usize>>>,
+
+    /// Inputs the solver worker solved which the workers have not run yet,
+    /// per input database, oldest first
+    solved: Vec<VecDeque<Vec<u8>>>,
+
+    /// Number of entries of the coverage map the `metric` hashes blocks and
+    /// edges into, see [`CoverageMetric::map_size`]. Zero tracks them
+    /// exactly.
+    pub map_size: usize,
+
+    /// Database used to keep track of per-worker block coverage frequencies.
+    /// This is the ground truth progress of the fuzzer, independent of the
+    /// feedback `metric`. It grows to the number of databases needed by the
+    /// largest trial run so far, so memory is only paid for the workers which
+    /// are simulated.
+    coverage: Vec<Vec<u64>>,
+
+    /// Number of distinct blocks found in each of the `coverage` databases
+    found: Vec<usize>,
+
+    /// Database used to keep track of per-worker crash frequencies, indexed
+    /// by crash ID. It is shared between the workers like `coverage`.
+    crashes: Vec<Vec<u64>>,
+
+    /// Number of distinct crashes found in each of the `crashes` databases
+    crashes_found: Vec<usize>,
+
+    /// Bucket every crash is deduplicated into, indexed by crash ID, so
+    /// crashes in the same bucket count as one. `None` counts every crash
+    /// ID apart.
+    pub crash_buckets: Option<Vec<usize>>,
+
+    /// Result database of the worker the result of the last trial was
+    /// taken from
+    result_db: usize,
+
+    /// Database used to keep track of per-worker input databases. Inputs are
+    /// stored sparsely, so large inputs only cost the bytes which were
+    /// mutated. Grows just like `coverage`.
+    inputs: Vec<Corpus>,
+
+    /// Trace of the most recent fuzz case
+    trace: Vec<u32>,
+
+    /// Blocks reporting coverage to the `metric` and `scheduler`, indexed by
+    /// block ID, to simulate a partially instrumented program. The others
+    /// still count toward the ground truth coverage. `None` instruments
+    /// every block.
+    pub instrumented: Option<Vec<bool>>,
+
+    /// Entry points of the target the workers fuzz, taking turns: worker
+    /// `n` fuzzes `entries[n % entries.len()]`. Full coverage is every
+    /// block these entry points reach.
+    pub entries: Vec<usize>,
+
+    /// Entry points reaching every block, see [`Metadata::block_entries`]
+    block_entries: Vec<u64>,
+
+    /// Total number of invocations of the target
+    pub fuzz_cases: u64,
+
+    /// Maximum amount of time to execute for
+    pub time_constraint: Option<f64>,
+}
+
+impl<'a, T: Target + ?Sized> Fuzzer<'a, T> {
+    /// Create a new fuzzer for `target` using the default strategies, with a
+    /// single worker which is not coverage guided
+    pub fn new(target: &'a T) -> Self {
+        Self::with_strategies(target, &Strategies::default())
+    }
+
+    /// Create a new fuzzer for `target` using `strategies`, with a single
+    /// worker which is not coverage guided
+    pub fn with_strategies(target: &'a T, strategies: &Strategies) -> Self {
+        let mut mutator = (strategies.mutator)();
+        let mut metric  = (strategies.metric)();
+        mutator.mutation_count(strategies.mutation_count);
+        let metadata = target.info().and_then(|x| Metadata::parse(x).ok());
+        if let Some(metadata) = &metadata {
+            mutator.target(metadata);
+            metric.target(metadata);
+        }
+
+        // Blocks guarded by input bytes are branches of their parents
+        let mut block_children = Vec::new();
+        let mut block_parents  = Vec::new();
+        let mut block_guards   = Vec::new();
+        let mut block_taint    = Vec::new();
+        if let Some(metadata) = &metadata {
+            block_children = vec![Vec::new(); metadata.block_parents.len()];
+            for (block, &parent) in metadata.block_parents.iter().enumerate() {
+                let guarded = metadata.block_taint.get(block)
+                    .is_some_and(|x| !x.is_empty());
+                if parent != block && guarded {
+                    block_children[parent].push(block);
+                }
+            }
+            block_parents = metadata.block_parents.clone();
+            block_guards  = metadata.block_guards.clone();
+            block_taint   = metadata.block_taint.clone();
+        }
+
+        Fuzzer {
+            target,
+            rng:             Rng::from_entropy(),
+            coverage_guided: false,
+            shared_inputs:   false,
+            shared_results:  false,
+            workers:         1,
+            mutator,
+            scheduler:       (strategies.scheduler)(),
+            metric,
+            deterministic:   strategies.deterministic,
+            stages:          Vec::new(),
+            taint_chance:    0.0,
+            taint_cost:      10,
+            solver_period:   0,
+            block_parents,
+            block_children,
+            block_guards,
+            block_taint,
+            taints:          Vec::new(),
+            solved:          Vec::new(),
+            map_size:        0,
+            fuzz_cases:      0,
+            coverage:        Vec::new(),
+            found:           Vec::new(),
+            crashes:         Vec::new(),
+            crashes_found:   Vec::new(),
+            crash_buckets:   None,
+            result_db:       0,
+            inputs:          Vec::new(),
+            trace:           Vec::new(),
+            instrumented:    None,
+            entries:         vec![0],
+            block_entries:   metadata.map(|x| x.block_entries)
+                .unwrap_or_default(),
+            time_constraint: None,
+        }
+    }
+
+    /// Replace the RNG with one seeded from `seed`, making all following
+    /// trials deterministic
+    pub fn reseed(&mut self, seed: usize) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Number of inputs saved to the corpora by the last trial, which grows
+    /// with how fine-grained the feedback of the metric is
+    pub fn corpus_len(&self) -> usize {
+        let num_input_dbs = if self.shared_inputs { 1 } else { self.workers };
+        self.inputs.iter().take(num_input_dbs).map(Corpus::len).sum()
+    }
+
+    /// Number of distinct crashes found by the last trial, in the results
+    /// database its coverage was taken from, deduplicated by
+    /// [`Fuzzer::crash_buckets`]
+    pub fn crashes_found(&self) -> usize {
+        self.crashes_found.get(self.result_db).copied().unwrap_or(0)
+    }
+
+    /// Run a single trial of fuzzing from scratch
+    ///
+    /// Returns `Ok(uptime)` with the uptime it took to find all coverage, or
+    /// `Err(coverage)` with the amount of coverage found if the trial hit the
+    /// time constraint first. The crashes found along the way are counted by
+    /// [`Fuzzer::crashes_found`].
+    pub fn start(&mut self) -> Result<f64, usize> {
+        assert!(self.workers > 0, "Invalid number of workers");
+
+        // Get access to the RNG
+        let rng = &mut self.rng;
+
+        // If the workers are collaborative, share a single database.
+        let num_input_dbs  = if self.shared_inputs  { 1 } else { self.workers };
+        let num_output_dbs = if self.shared_results { 1 } else { self.workers };
+
+        // Fuzz input starts as all zeros, or empty if the target takes
+        // variable length inputs
+        let input_size   = self.target.input_size();
+        let num_coverage = self.target.num_coverage();
+        let num_crashes  = match &self.crash_buckets {
+            Some(buckets) => buckets.iter().max().map_or(0, |x| x + 1),
+            None          => self.target.num_crashes(),
+        };
+        assert!(!self.entries.is_empty(), "No entry points to fuzz");
+        let variable     = self.target.variable_length();
+        let mut input = vec![0u8; input_size];
+        let mut len   = if variable { 0 } else { input_size };
+
+        // Corpus handed to the mutator by fuzzers which are not coverage
+        // guided
+        let empty = Corpus::default();
+
+        // Number of fuzz cases performed, shared between all workers.
+        let mut cases = 0u64;
+
+        // Blocks which can be found from the fuzzed entry points
+        let entries = self.entries.iter().fold(0u64, |acc, &x| acc | 1 << x);
+        let goal = if self.block_entries.is_empty() {
+            num_coverage
+        } else {
+            self.block_entries.iter().filter(|&&x| x & entries != 0).count()
+        };
+
+        // Make sure there are enough databases for this many workers
+        if self.inputs.len() < num_input_dbs {
+            self.inputs.resize(num_input_dbs, Corpus::default());
+        }
+        if self.coverage.len() < num_output_dbs {
+            self.coverage.resize(num_output_dbs, vec![0; num_coverage]);
+            self.found.resize(num_output_dbs, 0);
+            self.crashes_found.resize(num_output_dbs, 0);
+        }
+        self.crashes.resize(num_output_dbs, Vec::new());
+
+        // Clear input databases
+        self.stages.resize(num_input_dbs, VecDeque::new());
+        self.taints.resize(num_input_dbs, Vec::new());
+        self.solved.resize(num_input_dbs, VecDeque::new());
+        for idb in 0..num_input_dbs {
+            self.inputs[idb].clear();
+            self.stages[idb].clear();
+            self.taints[idb].clear();
+            self.solved[idb].clear();
+        }
+
+        // Clear result databases
+        for odb in 0..num_output_dbs {
+            self.coverage[odb].iter_mut().for_each(|x| *x = 0);
+            self.found[odb] = 0;
+            self.crashes[odb].clear();
+            self.crashes[odb].resize(num_crashes, 0);
+            self.crashes_found[odb] = 0;
+        }
+
+        // Reset the strategies
+        self.scheduler.reset(num_input_dbs);
+        self.mutator.reset();
+        self.metric.map_size(self.map_size);
+        self.metric.reset(num_output_dbs, num_coverage);
+
+        // Uptime at which the solver worker is done with its next branch,
+        // and the number of branches it tried to solve so far
+        let mut next_solve = self.solver_period as f64;
+        let mut solves = 0usize;
+
+        // Fuzz loop
+        loop {
+            for worker in 0..self.workers {
+                // Update number of cases (shared between all workers)
+                cases += 1;
+
+                // Get access to the worker-specfic database
+                let idb      = worker % num_input_dbs;
+                let odb      = worker % num_output_dbs;
+                let input_db = &mut self.inputs[idb];
+                let coverage = &mut self.coverage[odb];
+
+                // Run the oldest input the solver solved, if any, and
+                // otherwise take the next step of the oldest deterministic
+                // stage, if any entry is still in one
+                let solved = self.solved[idb].pop_front();
+                let stages = &mut self.stages[idb];
+                let step = match stages.front_mut() {
+                    Some((idx, step))
+                            if self.coverage_guided && solved.is_none() => {
+                        len = input_db.load(*idx, &mut input);
+                        *step += 1;
+                        Some(*step - 1)
+                    }
+                    _ => None,
+                };
+
+                if let Some(solved) = &solved {
+                    len = solved.len();
+                    input[..len].copy_from_slice(solved);
+                } else if let Some(step) = step {
+                    // Flip every bit, then every byte
+                    if step < len * 8 {
+                        input[step / 8] ^= 1 << (step % 8);
+                    } else {
+                        input[step - len * 8] ^= 0xff;
+                    }
+                    if step + 1 == len * 9 {
+                        stages.pop_front();
+                    }
+                } else {
+                    // Select an input from the input database, if it is not
+                    // empty, along with the branch its taint points at
+                    let mut branch = None;
+                    if self.coverage_guided && !input_db.is_empty() {
+                        let idx = self.scheduler.select(rng, idb,
+                                                        input_db.len());
+                        len = input_db.load(idx, &mut input);
+
+                        // Reuse what the taint oracle learned about the
+                        // entry until its branch is covered, or query it
+                        let taints = &mut self.taints[idb];
+                        taints.resize(input_db.len(), None);
+                        branch = taints[idx]
+                            .filter(|&x| coverage[x] == 0);
+                        if branch.is_none() && self.taint_chance > 0.0 &&
+                                (rng.rand() as f64 / usize::MAX as f64) <
+                                self.taint_chance {
+                            cases += self.taint_cost;
+                            self.trace.clear();
+                            let entry =
+                                self.entries[worker % self.entries.len()];
+                            self.target.run_entry(entry, &input[..len],
+                                                  &mut self.trace);
+                            self.fuzz_cases += 1;
+
+                            let frontier = frontier(&self.block_children,
+                                                    &self.trace, coverage);
+                            if !frontier.is_empty() {
+                                branch = Some(frontier[
+                                    rng.rand() % frontier.len()]);
+                            }
+                            taints[idx] = branch;
+                        }
+                    }
+
+                    // Change the length of the input, zeroing any new bytes
+                    if variable {
+                        let new_len = self.mutator.resize(rng, &input[..len],
+                                                          input_size);
+                        if new_len > len {
+                            input[len..new_len].fill(0);
+                        }
+                        len = new_len;
+                    }
+
+                    // Bytes of the input the branch depends on, if any
+                    let block_taint = &self.block_taint;
+                    let tainted: Vec<usize> = branch
+                        .map_or(&[][..], |x| &block_taint[x])
+                        .iter().copied().filter(|&x| x < len).collect();
+
+                    if !tainted.is_empty() {
+                        // Mutate only the tainted bytes, as if they were the
+                        // whole input
+                        let mut bytes: Vec<u8> = tainted.iter()
+                            .map(|&x| input[x]).collect();
+                        self.mutator.mutate(rng, &mut bytes);
+                        for (&x, byte) in tainted.iter().zip(bytes) {
+                            input[x] = byte;
+                        }
+                    } else {
+                        // Mutate the input, only fuzzers guided by their
+                        // corpus may combine it with other entries
+                        let corpus = if self.coverage_guided { &*input_db }
+                                     else { &empty };
+                        self.mutator.mutate_with_corpus(rng,
+                            &mut input[..len], corpus);
+                    }
+                }
+
+                // Invoke the "program" we're fuzzing
+                self.trace.clear();
+                let entry = self.entries[worker % self.entries.len()];
+                let crash = self.target.run_entry(entry, &input[..len],
+                                                  &mut self.trace);
+                self.fuzz_cases += 1;
+
+                // Record the crash, if the input triggered one, in its bucket
+                if let Some(crash) = crash {
+                    let bucket = match &self.crash_buckets {
+                        Some(buckets) => buckets[crash as usize],
+                        None          => crash as usize,
+                    };
+                    let hits = &mut self.crashes[odb][bucket];
+                    if *hits == 0 {
+                        self.crashes_found[odb] += 1;
+                    }
+                    *hits += 1;
+                }
+
+                // Update the ground truth coverage
+                for &block in &self.trace {
+                    let hits = &mut coverage[block as usize];
+                    if *hits == 0 {
+                        self.found[odb] += 1;
+                    }
+                    *hits += 1;
+                }
+
+                // Get the uptime (assuming workers are parallel we compute
+                // this by dividing fuzz cases by number of workers)
+                let uptime = cases as f64 / self.workers as f64;
+
+                if self.time_constraint.is_some() &&
+                        Some(uptime) >= self.time_constraint {
+                    // Return the number of known coverage
+                    self.result_db = odb;
+                    return Err(self.found[odb]);
+                }
+
+                // Feedback only sees the instrumented blocks
+                if let Some(instrumented) = &self.instrumented {
+                    self.trace.retain(|&block| instrumented[block as usize]);
+                }
+
+                // Save the input if the feedback considers it novel, and let
+                // the mutator know how it did
+                let novel = self.metric.observe(odb, &self.trace);
+                if step.is_none() && solved.is_none() {
+                    self.mutator.feedback(novel);
+                }
+                if novel {
+                    // Save this input as we caused new coverage
+                    input_db.push(&input[..len]);
+                    self.scheduler.added(idb, input_db.len() - 1,
+                                         &self.trace);
+
+                    // Empty inputs have nothing to walk
+                    if self.deterministic && len > 0 {
+                        self.stages[idb].push_back((input_db.len() - 1, 0));
+                    }
+                }
+
+                // Fuzzing complete if we found all coverage
+                if self.found[odb] == goal {
+                    self.result_db = odb;
+                    return Ok(uptime);
+                }
+
+                // Let the solver worker solve a branch for the next worker
+                // in turn whenever it is done with the last one
+                if self.solver_period == 0 || !self.coverage_guided ||
+                        uptime < next_solve {
+                    continue;
+                }
+                next_solve += self.solver_period as f64;
+                let helped = solves % self.workers;
+                solves += 1;
+                let solver_idb = helped % num_input_dbs;
+                let solver_odb = helped % num_output_dbs;
+                let input_db = &self.inputs[solver_idb];
+                if input_db.is_empty() {
+                    continue;
+                }
+
+                // Run a random entry, and pick a branch next to it
+                let mut solved = vec![0u8; input_size];
+                let solved_len = input_db.load(rng.rand() % input_db.len(),
+                                               &mut solved);
+                solved.truncate(solved_len);
+                self.trace.clear();
+                let entry = self.entries[helped % self.entries.len()];
+                self.target.run_entry(entry, &solved, &mut self.trace);
+                self.fuzz_cases += 1;
+                let frontier = frontier(&self.block_children, &self.trace,
+                                        &self.coverage[solver_odb]);
+                if frontier.is_empty() {
+                    continue;
+                }
+                let mut branch = frontier[rng.rand() % frontier.len()];
+
+                // Solve the conditions on the path to the branch in its
+                // function, outermost first
+                let mut path = Vec::new();
+                loop {
+                    path.extend(self.block_guards[branch].iter().rev()
+                        .cloned());
+                    let parent = self.block_parents[branch];
+                    if parent == branch {
+                        break;
+                    }
+                    branch = parent;
+                }
+                path.reverse();
+                if let Some(solved) = solve_input(&path, solved, input_size) {
+                    self.solved[solver_idb].push_back(solved);
+                }
+            }
+        }
+    }
+}
+
+/// Branches guarded by input bytes next to the blocks of `trace`, from
+/// `children`, which are not in `coverage` yet
+fn frontier(children: &[Vec<usize>], trace: &[u32], coverage: &[u64])
+        -> Vec<usize> {
+    trace.iter().flat_map(|&x| &children[x as usize]).copied()
+        .filter(|&x| coverage[x] == 0).collect()
+}
@@ -0,0 +1,153 @@
+//! Experiment manifests
+//!
+//! Result files only hold the measurements, so every `sweep` and `heatmap`
+//! run also writes `<output_prefix>manifest.json` to its output directory,
+//! recording everything needed to reproduce them: how the program was
+//! generated, the sweep parameters, the datapoints, and when the run started
+//! and finished. The manifest is written when the run starts and rewritten
+//! with its outcome when it ends.
+
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fuzztheory_analysis::json::Json;
+
+use crate::Target;
+use crate::sweep::{self, Params};
+
+/// Seconds since the unix epoch
+pub fn unix_time() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs_f64()).unwrap_or(0.)
+}
+
+/// State of the run a manifest describes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+    Running,
+    Complete,
+    Interrupted,
+    Failed,
+}
+
+impl Status {
+    /// Name of the status as written to the manifest
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Running     => "running",
+            Status::Complete    => "complete",
+            Status::Interrupted => "interrupted",
+            Status::Failed      => "failed",
+        }
+    }
+}
+
+/// A manifest being kept up to date over the course of a run
+pub struct Manifest<'a, T: Target + ?Sized> {
+    /// Harness mode being run
+    mode: &'a str,
+
+    /// Program being fuzzed
+    target: &'a T,
+
+    /// Parameters of the run
+    params: &'a Params,
+
+    /// Time the run started, see [`unix_time`]
+    started: f64,
+}
+
+impl<'a, T: Target + ?Sized> Manifest<'a, T> {
+    /// Start a manifest for running `mode` on `target` with `params`
+    pub fn new(mode: &'a str, target: &'a T, params: &'a Params) -> Self {
+        Manifest { mode, target, params, started: unix_time() }
+    }
+
+    /// Path the manifest is written to
+    pub fn path(&self) -> PathBuf {
+        self.params.output_dir.join(format!("{}manifest.json",
+                                            self.params.output_prefix))
+    }
+
+    /// Build the manifest for a run which is in state `status`
+    pub fn to_json(&self, status: Status) -> io::Result<Json> {
+        let params = self.params;
+        let strategies = params.strategies()?;
+        let points = sweep::datapoints(params, strategies.len());
+
+        // All runs, including heatmap rows completed by an earlier run
+        let runs = sweep::runs(self.mode,
+                               &Params { resume: false, ..params.clone() })?;
+        let time_constraints: Vec<Option<f64>> = runs.iter()
+            .map(|x| x.1).collect();
+
+        // Worker counts and result files, without duplicates
+        let mut workers: Vec<usize> = points.iter().map(|x| x.workers)
+            .collect();
+        workers.sort_unstable();
+        workers.dedup();
+        let mut results: Vec<String> = runs.iter().flat_map(|(params, _)| {
+            points.iter().map(|x| x.filename(params, &strategies))
+                .collect::<Vec<_>>()
+        }).collect();
+        results.dedup();
+
+        let finished = (status != Status::Running).then(unix_time);
+
+        Ok(Json::object(vec![
+            ("mode",    self.mode.into()),
+            ("schema_version",
+             (fuzztheory_analysis::SCHEMA_VERSION as usize).into()),
+            ("status",  status.name().into()),
+            ("program", self.target.info()
+                .map_or(Json::Null, |x| Json::Raw(x.into()))),
+            ("input_size",   self.target.input_size().into()),
+            ("variable_length", self.target.variable_length().into()),
+            ("num_coverage", self.target.num_coverage().into()),
+            ("num_crashes",  self.target.num_crashes().into()),
+            ("sweep", Json::object(vec![
+                ("max_cores",        params.max_cores.into()),
+                ("max_x_resolution", params.max_x_resolution.into()),
+                ("averages",         params.averages.into()),
+                ("time_constraint",  params.time_constraint.into()),
+                ("max_y_resolution", params.max_y_resolution.into()),
+                ("max_y_point",      params.max_y_point.into()),
+                ("guided",           params.guided.clone().into()),
+                ("shared_inputs",    params.shared_inputs.clone().into()),
+                ("shared_results",   params.shared_results.clone().into()),
+                ("mutators",         params.mutators.clone().into()),
+                ("scheduler",        params.scheduler.as_str().into()),
+                ("metrics",          params.metrics.clone().into()),
+                ("havoc_stack",      params.havoc_stack.into()),
+                ("havoc_ops",        params.havoc_ops.clone().into()),
+                ("mopt_period",      params.mopt_period.into()),
+                ("dictionary_completeness",
+                 params.dictionary_completeness.into()),
+                ("deterministic",    params.deterministic.clone().into()),
+                ("mutation_counts",  params.mutation_counts.clone().into()),
+                ("map_size",         params.map_size.into()),
+                ("taint_chance",     params.taint_chance.into()),
+                ("taint_cost",       params.taint_cost.into()),
+                ("solver_period",    params.solver_period.into()),
+                ("dedup",            params.dedup.as_str().into()),
+                ("dedup_bucket_size", params.dedup_bucket_size.into()),
+                ("instrumented_modules",
+                 params.instrumented_modules.clone().into()),
+                ("entry_points",     params.entry_points.clone().into()),
+                ("seed",             params.seed.into()),
+                ("trial",            params.trial.into()),
+            ])),
+            ("time_constraints", time_constraints.into()),
+            ("workers",  workers.into()),
+            ("results",  results.into()),
+            ("started",  self.started.into()),
+            ("finished", finished.into()),
+        ]))
+    }
+
+    /// Write the manifest for a run in state `status`
+    pub fn write(&self, status: Status) -> io::Result<()> {
+        std::fs::write(self.path(), format!("{:#}\n", self.to_json(status)?))
+    }
+}
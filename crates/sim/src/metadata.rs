@@ -0,0 +1,188 @@
+//! Program metadata read at runtime
+//!
+//! Generated programs carry no constants describing themselves. Their
+//! `fuzztheory_proggen::Program::info` JSON is written next to the source as
+//! a [`sidecar`] file and embedded in the harness builds, and is parsed here
+//! when the program is loaded. This keeps the harness code the same for
+//! every program.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fuzztheory_analysis::json::Json;
+use fuzztheory_proggen::Cond;
+use fuzztheory_proggen::ir::decode_guards;
+
+/// What a harness needs to know about a program to run it
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    /// Size of the input to the program in bytes
+    pub input_size: usize,
+
+    /// Whether the program takes inputs of any length up to `input_size`,
+    /// false for programs generated before variable length inputs existed
+    pub variable_length: bool,
+
+    /// Size of the config buffer at the start of the input of programs
+    /// taking two buffers, with the data buffer in the rest of it. Zero if
+    /// the program takes a single buffer.
+    pub config_bytes: usize,
+
+    /// Number of blocks (coverage events) in the program
+    pub num_coverage: usize,
+
+    /// Number of distinct crashes in the program, zero for programs
+    /// generated before crashes existed
+    pub num_crashes: usize,
+
+    /// Root cause of every crash, indexed by crash ID, empty for programs
+    /// generated before root causes existed
+    pub root_causes: Vec<usize>,
+
+    /// Number of modules the program is partitioned into, one for programs
+    /// generated before modules existed
+    pub modules: usize,
+
+    /// Module of every block, indexed by block ID, empty if the program is
+    /// a single module
+    pub block_modules: Vec<usize>,
+
+    /// Number of functions the program calls, besides its entry
+    pub functions: usize,
+
+    /// Function of every block, indexed by block ID, with the entry after
+    /// every called function. Empty if the program calls no functions.
+    pub block_functions: Vec<usize>,
+
+    /// Functions which are entry points after the first, so entry point `n`
+    /// is function `entry_points[n - 1]`. Empty if the program has a single
+    /// entry point.
+    pub entry_points: Vec<usize>,
+
+    /// Entry points reaching every block, indexed by block ID, as a bitmask
+    /// with bit `n` set if entry point `n` reaches the block. Empty if the
+    /// program has a single entry point.
+    pub block_entries: Vec<u64>,
+
+    /// Block every block is nested in, indexed by block ID, with entry
+    /// blocks of functions nested in themselves. Empty for programs
+    /// generated before the guards of blocks were listed.
+    pub block_parents: Vec<usize>,
+
+    /// Conditions guarding every block in its parent, each of which must
+    /// hold or fail, indexed by block ID. Empty for programs generated
+    /// before the guards of blocks were listed.
+    pub block_guards: Vec<Vec<(Cond, bool)>>,
+
+    /// Input bytes the conditions of `block_guards` depend on, sorted and
+    /// without duplicates, as taint tracking would report them
+    pub block_taint: Vec<Vec<usize>>,
+
+    /// Magic values the program compares its input against, as the bytes
+    /// the input has to hold, empty if it compares none. This is the
+    /// dictionary of its input format.
+    pub magic_values: Vec<Vec<u8>>,
+
+    /// The full JSON the metadata was parsed from, see [`Target::info`]
+    ///
+    /// [`Target::info`]: crate::Target::info
+    pub info: String,
+}
+
+impl Metadata {
+    /// Parse the metadata from a program's info JSON
+    pub fn parse(info: &str) -> Result<Self, String> {
+        let json = Json::parse(info)
+            .map_err(|err| format!("invalid program metadata: {}", err))?;
+        let field = |key: &str| {
+            json.get(key).and_then(Json::as_u64).map(|x| x as usize)
+                .ok_or_else(|| format!("program metadata lacks `{}`", key))
+        };
+        let list = |key: &str| -> Result<Vec<usize>, String> {
+            json.get(key).and_then(Json::as_array).unwrap_or(&[]).iter()
+                .map(|x| x.as_u64().map(|x| x as usize).ok_or_else(|| {
+                    format!("program metadata has an invalid `{}`", key)
+                })).collect()
+        };
+
+        let block_guards: Vec<Vec<(Cond, bool)>> = json.get("block_guards")
+            .and_then(Json::as_array).unwrap_or(&[]).iter().map(|x| {
+                x.as_str().and_then(parse_hex)
+                    .and_then(|x| decode_guards(&x).ok())
+                    .ok_or_else(|| {
+                        "program metadata has an invalid `block_guards`"
+                            .to_string()
+                    })
+            }).collect::<Result<_, _>>()?;
+        let block_taint = block_guards.iter().map(|guards| {
+            let mut bytes: Vec<usize> = guards.iter()
+                .flat_map(|(cond, _)| cond.bytes()).collect();
+            bytes.sort_unstable();
+            bytes.dedup();
+            bytes
+        }).collect();
+
+        Ok(Metadata {
+            input_size:   field("input_bytes")?,
+            variable_length: json.get("params")
+                .and_then(|x| x.get("variable_length"))
+                .and_then(Json::as_bool).unwrap_or(false),
+            config_bytes: json.get("params")
+                .and_then(|x| x.get("config_bytes"))
+                .and_then(Json::as_u64).unwrap_or(0) as usize,
+            num_coverage: field("blocks")?,
+            num_crashes:  field("crashes").unwrap_or(0),
+            root_causes:  list("root_causes")?,
+            modules:      field("modules").unwrap_or(1),
+            block_modules: list("block_modules")?,
+            functions:    field("functions").unwrap_or(0),
+            block_functions: list("block_functions")?,
+            entry_points: list("entry_points")?,
+            block_entries: list("block_entries")?.into_iter()
+                .map(|x| x as u64).collect(),
+            block_parents: list("block_parents")?,
+            block_taint,
+            block_guards,
+            magic_values: json.get("magic_values").and_then(Json::as_array)
+                .unwrap_or(&[]).iter().map(|x| {
+                    x.as_str().and_then(parse_hex).ok_or_else(|| {
+                        "program metadata has an invalid `magic_values`"
+                            .to_string()
+                    })
+                }).collect::<Result<_, _>>()?,
+            info:         info.trim().into(),
+        })
+    }
+
+    /// Load the metadata from the sidecar file at `path`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Metadata::parse(&contents).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData,
+                           format!("{}: {}", path.display(), err))
+        })
+    }
+}
+
+/// Parse the hex string `hex` into the bytes it spells out
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2)
+        .map(|ii| u8::from_str_radix(hex.get(ii..ii + 2)?, 16).ok())
+        .collect()
+}
+
+/// Path of the metadata sidecar of the program at `path`, which is the
+/// program's source, binary, or library with a `.json` extension
+pub fn sidecar(path: &Path) -> PathBuf {
+    path.with_extension("json")
+}
+
+/// Path of the ground truth of the program at `path`, as written by
+/// `fuzztheory_proggen::Program::ground_truth`, which is the program's source
+/// with a `.truth.json` extension
+pub fn ground_truth(path: &Path) -> PathBuf {
+    path.with_extension("truth.json")
+}
@@ -0,0 +1,119 @@
+//! Progress reporting for sweeps
+//!
+//! Full sweeps run for hours, so [`sweep::run`] reports every completed
+//! datapoint along with the recent simulator throughput and an estimate of
+//! the time left. Datapoints take very different amounts of time, so the
+//! estimate weighs each one by the fuzz cases it is expected to take, which
+//! grows with its number of workers and trials.
+//!
+//! [`sweep::run`]: crate::sweep::run
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use fuzztheory_proggen::info;
+
+/// Number of recent datapoints the fuzz case rate is computed over
+const RATE_WINDOW: usize = 16;
+
+/// Format `duration` compactly, like `1h02m03s`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h{:02}m{:02}s", hours, mins, secs)
+    } else if mins > 0 {
+        format!("{}m{:02}s", mins, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Progress of a sweep
+pub struct Progress {
+    /// Name of the sweep, prefixed to every report
+    name: String,
+
+    /// Number of datapoints in the sweep
+    total: usize,
+
+    /// Number of datapoints completed
+    done: usize,
+
+    /// Number of trials completed
+    trials: u64,
+
+    /// Total weight of all datapoints
+    total_weight: f64,
+
+    /// Weight of the completed datapoints
+    done_weight: f64,
+
+    /// Total fuzz cases run
+    fuzz_cases: u64,
+
+    /// Time the sweep started
+    started: Instant,
+
+    /// Completion time and total fuzz cases run of the most recent
+    /// datapoints, for the rolling fuzz case rate
+    window: VecDeque<(Instant, u64)>,
+}
+
+impl Progress {
+    /// Start tracking the progress of the sweep `name`, made up of
+    /// datapoints with the weights `weights`
+    pub fn new(name: &str, weights: impl Iterator<Item = f64>) -> Self {
+        let mut total = 0;
+        let mut total_weight = 0.;
+        for weight in weights {
+            total += 1;
+            total_weight += weight;
+        }
+
+        let started = Instant::now();
+        Progress {
+            name: name.into(),
+            total,
+            done: 0,
+            trials: 0,
+            total_weight,
+            done_weight: 0.,
+            fuzz_cases: 0,
+            started,
+            window: VecDeque::from(vec![(started, 0)]),
+        }
+    }
+
+    /// Record a completed datapoint of `weight` which ran `trials` trials
+    /// and `fuzz_cases` fuzz cases, and report the progress
+    pub fn complete(&mut self, weight: f64, trials: u64, fuzz_cases: u64) {
+        let now = Instant::now();
+        self.done        += 1;
+        self.trials      += trials;
+        self.done_weight += weight;
+        self.fuzz_cases  += fuzz_cases;
+
+        self.window.push_back((now, self.fuzz_cases));
+        if self.window.len() > RATE_WINDOW + 1 {
+            self.window.pop_front();
+        }
+        let (then, cases_then) = self.window[0];
+        let rate = (self.fuzz_cases - cases_then) as f64 /
+            (now - then).as_secs_f64().max(1e-9);
+
+        let elapsed = now - self.started;
+        let eta = if self.done_weight > 0. {
+            let left = (self.total_weight - self.done_weight).max(0.);
+            format_duration(elapsed.mul_f64(left / self.done_weight))
+        } else {
+            "unknown".into()
+        };
+
+        info!("{}: {}/{} datapoints ({:.1}%), {} trials, {:.0} fuzz \
+               cases/second, {} elapsed, ETA {}",
+              self.name, self.done, self.total,
+              100. * self.done as f64 / self.total.max(1) as f64,
+              self.trials, rate, format_duration(elapsed), eta);
+    }
+}
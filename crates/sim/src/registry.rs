@@ -0,0 +1,157 @@
+//! Named fuzzing strategies
+//!
+//! Sweeps refer to their strategies by name so they can be selected from the
+//! configuration file and the command line. A [`Registry`] maps those names
+//! to factories. It starts out with the built-in strategies, and external
+//! code can register its own before handing the registry to
+//! `harness::main_with` or the `fuzztheory` driver:
+//!
+//! ```ignore
+//! let mut registry = Registry::default();
+//! registry.register_mutator("mine", || Box::new(MyMutator::default()));
+//! fuzztheory::cli::main_with(registry);
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::collections::BTreeMap;
+
+use crate::strategy::*;
+
+/// Maps strategy names to factories creating them
+#[derive(Clone)]
+pub struct Registry {
+    /// Registered mutators
+    mutators: BTreeMap<String, Factory<dyn Mutator>>,
+
+    /// Registered corpus schedulers
+    schedulers: BTreeMap<String, Factory<dyn Scheduler>>,
+
+    /// Registered coverage metrics
+    metrics: BTreeMap<String, Factory<dyn CoverageMetric>>,
+}
+
+impl Registry {
+    /// Create a registry without any strategies
+    pub fn empty() -> Self {
+        Registry {
+            mutators:   BTreeMap::new(),
+            schedulers: BTreeMap::new(),
+            metrics:    BTreeMap::new(),
+        }
+    }
+
+    /// Register a mutator as `name`, replacing any mutator with that name
+    pub fn register_mutator<F>(&mut self, name: &str, factory: F)
+            where F: Fn() -> Box<dyn Mutator> + Send + Sync + 'static {
+        self.mutators.insert(name.into(), Arc::new(factory));
+    }
+
+    /// Register a scheduler as `name`, replacing any scheduler with that name
+    pub fn register_scheduler<F>(&mut self, name: &str, factory: F)
+            where F: Fn() -> Box<dyn Scheduler> + Send + Sync + 'static {
+        self.schedulers.insert(name.into(), Arc::new(factory));
+    }
+
+    /// Register a coverage metric as `name`, replacing any metric with that
+    /// name
+    pub fn register_metric<F>(&mut self, name: &str, factory: F)
+            where F: Fn() -> Box<dyn CoverageMetric> + Send + Sync + 'static {
+        self.metrics.insert(name.into(), Arc::new(factory));
+    }
+
+    /// Names of all registered mutators
+    pub fn mutators(&self) -> impl Iterator<Item = &str> {
+        self.mutators.keys().map(String::as_str)
+    }
+
+    /// Names of all registered schedulers
+    pub fn schedulers(&self) -> impl Iterator<Item = &str> {
+        self.schedulers.keys().map(String::as_str)
+    }
+
+    /// Names of all registered coverage metrics
+    pub fn metrics(&self) -> impl Iterator<Item = &str> {
+        self.metrics.keys().map(String::as_str)
+    }
+
+    /// Look up strategies by name. The resulting [`Strategies`] is named
+    /// after the mutator, and has no deterministic stage and the default
+    /// [`MutationCount`].
+    pub fn strategies(&self, mutator: &str, scheduler: &str, metric: &str)
+            -> Result<Strategies, String> {
+        // Look up a name in one of the maps, listing the options on failure
+        fn get<T: ?Sized>(map: &BTreeMap<String, Factory<T>>, kind: &str,
+                          name: &str) -> Result<Factory<T>, String> {
+            map.get(name).cloned().ok_or_else(|| {
+                let known: Vec<&str> = map.keys().map(String::as_str)
+                    .collect();
+                format!("Unknown {} `{}` (known: {})", kind, name,
+                        known.join(", "))
+            })
+        }
+
+        Ok(Strategies {
+            name:      mutator.into(),
+            mutator:   get(&self.mutators,   "mutator",   mutator)?,
+            scheduler: get(&self.schedulers, "scheduler", scheduler)?,
+            metric:    get(&self.metrics,    "metric",    metric)?,
+            deterministic: false,
+            mutation_count: MutationCount::default(),
+        })
+    }
+}
+
+impl Default for Registry {
+    /// A registry holding the built-in strategies
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register_mutator("replace",
+            || Box::new(ByteReplace::default()));
+        registry.register_mutator("resize",
+            || Box::new(ByteResize::default()));
+        registry.register_mutator("bitflip",
+            || Box::new(BitFlip::default()));
+        registry.register_mutator("split",
+            || Box::new(SplitReplace::default()));
+        registry.register_mutator("arith",
+            || Box::new(ArithDelta::default()));
+        registry.register_mutator("havoc",   || Box::new(Havoc::default()));
+        registry.register_mutator("splice",  || Box::new(Splice::default()));
+        registry.register_mutator("dictionary",
+            || Box::new(Dictionary::default()));
+        registry.register_mutator("mopt",
+            || Box::new(AdaptiveHavoc::default()));
+        registry.register_mutator("interesting",
+            || Box::new(InterestingValue::default()));
+        registry.register_scheduler("uniform", || Box::new(UniformScheduler));
+        registry.register_scheduler("queue",
+            || Box::new(QueueScheduler::default()));
+        registry.register_scheduler("newest", || Box::new(NewestScheduler));
+        registry.register_scheduler("rare",
+            || Box::new(RarityScheduler::default()));
+        registry.register_metric("block",
+            || Box::new(BlockCoverage::default()));
+        registry.register_metric("function",
+            || Box::new(FunctionCoverage::default()));
+        registry.register_metric("edge",
+            || Box::new(EdgeCoverage::default()));
+        registry.register_metric("block-hits",
+            || Box::new(BlockHitCoverage::default()));
+        registry.register_metric("edge-hits",
+            || Box::new(EdgeHitCoverage::default()));
+        registry.register_metric("path",
+            || Box::new(PathCoverage::default()));
+        registry
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("mutators",   &self.mutators.keys().collect::<Vec<_>>())
+            .field("schedulers", &self.schedulers.keys().collect::<Vec<_>>())
+            .field("metrics",    &self.metrics.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
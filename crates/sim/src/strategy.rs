@@ -0,0 +1,1242 @@
+//! Pluggable fuzzing strategies
+//!
+//! Every fuzz case of a simulated worker picks an input from its corpus with
+//! a [`Scheduler`], mutates it with a [`Mutator`], runs it, and hands the
+//! trace of blocks it reached to a [`CoverageMetric`] which decides whether
+//! the input is interesting enough to be saved to the corpus.
+//!
+//! The built-in metrics see the same trace at different granularities:
+//! [`FunctionCoverage`] only the functions reached, [`BlockCoverage`] the
+//! blocks, and [`EdgeCoverage`] the transitions between blocks.
+//! [`BlockHitCoverage`] and [`EdgeHitCoverage`] also tell apart how many
+//! times a case reached a block or took an edge, in AFL's hit count
+//! buckets, and [`PathCoverage`] sees the whole sequence of blocks reached.
+//! Every program can be fuzzed with each of them, to compare the feedback
+//! they give on the exact same target.
+//!
+//! A [`Strategies`] bundles factories for all three so the sweep drivers can
+//! create a fresh set for every fuzzer they run, and compare several
+//! strategies against each other in a single sweep.
+
+use std::fmt;
+use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+
+use fuzztheory_proggen::rng::{self, Rng};
+
+use crate::{Corpus, Metadata};
+
+/// Mutates inputs taken from the corpus
+pub trait Mutator {
+    /// Mutate `input` in place
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]);
+
+    /// Mutate `input`, which was taken from `corpus` or is the first input
+    /// of a trial, in place. Mutators combining inputs pick other entries
+    /// of the corpus here, which is empty for fuzzers which are not coverage
+    /// guided. The default mutates with [`Mutator::mutate`].
+    fn mutate_with_corpus(&mut self, rng: &mut Rng, input: &mut [u8],
+                          _corpus: &Corpus) {
+        self.mutate(rng, input);
+    }
+
+    /// Forget all state learned from [`Mutator::feedback`], a new trial is
+    /// starting
+    fn reset(&mut self) {}
+
+    /// The input mutated last was run, and the metric found it `novel` or
+    /// not. Mutators adapting to what finds coverage learn from this.
+    fn feedback(&mut self, _novel: bool) {}
+
+    /// Pick a new length of at most `max` bytes for `input`, before it is
+    /// mutated. Bytes added to the end are zeros. This is only used for
+    /// targets taking variable length inputs, the default keeps the length.
+    fn resize(&mut self, _rng: &mut Rng, input: &[u8], _max: usize)
+            -> usize {
+        input.len()
+    }
+
+    /// Learn about the target from its `metadata`, once before the first
+    /// trial. This is only called for targets whose metadata is known.
+    fn target(&mut self, _metadata: &Metadata) {}
+
+    /// Draw the number of mutations made in every fuzz case from `count`,
+    /// once before the first trial. Mutators which don't make a number of
+    /// mutations of the same kind ignore it.
+    fn mutation_count(&mut self, _count: MutationCount) {}
+}
+
+/// Distribution of the number of mutations a mutator makes in a fuzz case,
+/// how aggressively it mutates
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MutationCount {
+    /// Always `n` mutations
+    Fixed(usize),
+
+    /// 1 to `n` mutations, all equally likely
+    Uniform(usize),
+
+    /// 1 or more mutations, with a 1 in `n` chance of stopping after every
+    /// one, so `n` on average. Few mutations are the likeliest, with a long
+    /// tail of aggressive ones.
+    Geometric(usize),
+}
+
+impl MutationCount {
+    /// Parse a distribution named like `fixed-4`, `uniform-8`, or
+    /// `geometric-4`, with a non-zero `n`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (kind, n) = name.split_once('-')?;
+        let n = n.parse().ok().filter(|&n| n != 0)?;
+        match kind {
+            "fixed"     => Some(MutationCount::Fixed(n)),
+            "uniform"   => Some(MutationCount::Uniform(n)),
+            "geometric" => Some(MutationCount::Geometric(n)),
+            _ => None,
+        }
+    }
+
+    /// Name of the distribution, which [`MutationCount::parse`] parses
+    pub fn name(self) -> String {
+        match self {
+            MutationCount::Fixed(n)     => format!("fixed-{}", n),
+            MutationCount::Uniform(n)   => format!("uniform-{}", n),
+            MutationCount::Geometric(n) => format!("geometric-{}", n),
+        }
+    }
+
+    /// Draw a number of mutations
+    pub fn draw(self, rng: &mut Rng) -> usize {
+        match self {
+            MutationCount::Fixed(n)   => n,
+            MutationCount::Uniform(n) => rng.rand() % n + 1,
+            MutationCount::Geometric(n) => {
+                let mut count = 1;
+                while !rng.rand().is_multiple_of(n) {
+                    count += 1;
+                }
+                count
+            }
+        }
+    }
+}
+
+impl Default for MutationCount {
+    /// 1 to 8 mutations, the classic behavior of the simulator
+    fn default() -> Self {
+        MutationCount::Uniform(8)
+    }
+}
+
+/// Picks which corpus entry to build upon for each fuzz case
+pub trait Scheduler {
+    /// Forget all state, a new trial with `databases` corpora is starting
+    fn reset(&mut self, _databases: usize) {}
+
+    /// Entry `index` was added to corpus `db`, reaching the blocks in `trace`
+    fn added(&mut self, _db: usize, _index: usize, _trace: &[u32]) {}
+
+    /// Pick an entry from corpus `db`, which holds `len` (non-zero) entries
+    fn select(&mut self, rng: &mut Rng, db: usize, len: usize) -> usize;
+}
+
+/// Decides which fuzz cases are novel, eg. the feedback of the fuzzer
+pub trait CoverageMetric {
+    /// Forget all state, a new trial with `databases` coverage databases is
+    /// starting for a target with `num_coverage` blocks
+    fn reset(&mut self, databases: usize, num_coverage: usize);
+
+    /// Learn about the target from its `metadata`, once before the first
+    /// trial. This is only called for targets whose metadata is known.
+    fn target(&mut self, _metadata: &Metadata) {}
+
+    /// Record the `trace` of blocks reached by a fuzz case into database
+    /// `db`, returning `true` if the case was novel
+    fn observe(&mut self, db: usize, trace: &[u32]) -> bool;
+
+    /// Hash what the metric tracks into a coverage map of `size` entries,
+    /// like real fuzzers do, so distinct blocks or edges can collide and
+    /// look the same, see [`map_slot`]. Zero tracks them exactly. This is
+    /// called before every trial, before [`CoverageMetric::reset`].
+    /// Metrics tracking nothing a map would hold ignore it.
+    fn map_size(&mut self, _size: usize) {}
+}
+
+/// Entry of a coverage map of `map_size` entries which `block` is hashed
+/// to, like the random IDs AFL gives blocks when instrumenting them.
+/// Distinct blocks can share an entry. A map size of zero is an exact map
+/// with an entry for every block.
+pub fn map_slot(block: u32, map_size: usize) -> usize {
+    if map_size == 0 {
+        block as usize
+    } else {
+        rng::splitmix64(block as u64) as usize % map_size
+    }
+}
+
+/// Number of the `num_coverage` blocks sharing their [`map_slot`] in a
+/// coverage map of `map_size` entries with a block before them, which the
+/// map can't tell apart from it
+pub fn map_collisions(num_coverage: usize, map_size: usize) -> usize {
+    let slots: HashSet<usize> = (0..num_coverage as u32)
+        .map(|block| map_slot(block, map_size)).collect();
+    num_coverage - slots.len()
+}
+
+/// Entry of a coverage map of `map_size` entries which the edge from block
+/// `from` to block `to` is hashed to, AFL's `to ^ from >> 1` of their
+/// [`map_slot`]s. A map size of zero is an exact map, with the source block
+/// in the upper and the destination in the lower 32 bits.
+pub fn map_edge(from: u32, to: u32, map_size: usize) -> u64 {
+    if map_size == 0 {
+        (from as u64) << 32 | to as u64
+    } else {
+        ((map_slot(to, map_size) ^ map_slot(from, map_size) >> 1) % map_size)
+            as u64
+    }
+}
+
+/// Randomly replace bytes with a random value at random locations, up to 8
+/// of them by default
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ByteReplace {
+    /// Number of bytes replaced in every case
+    pub count: MutationCount,
+}
+
+impl Mutator for ByteReplace {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        if input.is_empty() {
+            return;
+        }
+        for _ in 0..self.count.draw(rng) {
+            input[rng.rand() % input.len()] = rng.rand() as u8;
+        }
+    }
+
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.count = count;
+    }
+}
+
+/// Flip single random bits of the input, up to 8 of them by default. Every
+/// generated condition compares bits of the input under a mask, so this is
+/// the finest grained mutation, a reference point for how much whole byte
+/// overwrites overstate the difficulty of conditions.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BitFlip {
+    /// Number of bits flipped in every case
+    pub count: MutationCount,
+}
+
+impl Mutator for BitFlip {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        if input.is_empty() {
+            return;
+        }
+        for _ in 0..self.count.draw(rng) {
+            let bit = rng.rand() % (input.len() * 8);
+            input[bit / 8] ^= 1 << (bit % 8);
+        }
+    }
+
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.count = count;
+    }
+}
+
+/// Like [`ByteReplace`], but first has a 1 in 4 chance of truncating or
+/// extending the input to a random length
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ByteResize {
+    /// Replaces the bytes of the resized input
+    pub replace: ByteReplace,
+}
+
+impl Mutator for ByteResize {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        self.replace.mutate(rng, input);
+    }
+
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.replace.count = count;
+    }
+
+    fn resize(&mut self, rng: &mut Rng, input: &[u8], max: usize) -> usize {
+        if rng.rand().is_multiple_of(4) {
+            rng.rand() % (max + 1)
+        } else {
+            input.len()
+        }
+    }
+}
+
+/// Like [`ByteReplace`], but for programs taking two input buffers, see
+/// [`Metadata::config_bytes`], only mutates one of them in every case: the
+/// config buffer with a one in `config_chance` chance, and the data buffer
+/// otherwise. Inputs too short to reach the data buffer mutate the config
+/// buffer, and inputs of other programs are mutated like [`ByteReplace`].
+#[derive(Clone, Copy, Debug)]
+pub struct SplitReplace {
+    /// Chance of mutating the config buffer rather than the data buffer
+    pub config_chance: usize,
+
+    /// Replaces the bytes of the buffer mutated
+    pub replace: ByteReplace,
+
+    /// Size of the config buffer, zero for a single buffer
+    config_bytes: usize,
+}
+
+impl Default for SplitReplace {
+    /// Mutate the config buffer in one in 8 cases
+    fn default() -> Self {
+        SplitReplace {
+            config_chance: 8,
+            replace:       ByteReplace::default(),
+            config_bytes:  0,
+        }
+    }
+}
+
+impl Mutator for SplitReplace {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        let split = self.config_bytes.min(input.len());
+        if split == 0 {
+            self.replace.mutate(rng, input);
+        } else if split == input.len() ||
+                rng.rand().is_multiple_of(self.config_chance) {
+            self.replace.mutate(rng, &mut input[..split]);
+        } else {
+            self.replace.mutate(rng, &mut input[split..]);
+        }
+    }
+
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.replace.count = count;
+    }
+
+    fn target(&mut self, metadata: &Metadata) {
+        self.config_bytes = metadata.config_bytes;
+    }
+}
+
+/// Like [`ByteReplace`], but every replacement has an even chance of
+/// writing a token from a dictionary at a random offset instead, like AFL's
+/// dictionaries. The dictionary holds the `completeness` fraction of the
+/// program's magic values, see [`Metadata::magic_values`], the same ones in
+/// every trial, to simulate partial knowledge of the input format.
+#[derive(Clone, Debug)]
+pub struct Dictionary {
+    /// Fraction of the magic values known, from 0 to 1
+    pub completeness: f64,
+
+    /// Number of bytes replaced or tokens written in every case
+    pub count: MutationCount,
+
+    /// The magic values known
+    tokens: Vec<Vec<u8>>,
+}
+
+impl Dictionary {
+    /// A dictionary knowing the `completeness` fraction of the magic values
+    pub fn new(completeness: f64) -> Self {
+        Dictionary {
+            completeness,
+            count:  MutationCount::default(),
+            tokens: Vec::new(),
+        }
+    }
+}
+
+impl Default for Dictionary {
+    /// Know every magic value
+    fn default() -> Self {
+        Dictionary::new(1.0)
+    }
+}
+
+impl Mutator for Dictionary {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        if input.is_empty() {
+            return;
+        }
+        for _ in 0..self.count.draw(rng) {
+            if !self.tokens.is_empty() && rng.rand().is_multiple_of(2) {
+                let token = &self.tokens[rng.rand() % self.tokens.len()];
+                if token.len() <= input.len() {
+                    let offset = rng.rand() % (input.len() - token.len() + 1);
+                    input[offset..offset + token.len()]
+                        .copy_from_slice(token);
+                }
+            } else {
+                input[rng.rand() % input.len()] = rng.rand() as u8;
+            }
+        }
+    }
+
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.count = count;
+    }
+
+    fn target(&mut self, metadata: &Metadata) {
+        // Which values are known is decided by their hash, so partial
+        // dictionaries are the same in every trial, and grow with the
+        // completeness
+        let hash = |token: &Vec<u8>| token.iter()
+            .fold(token.len() as u64, |hash, &x| {
+                rng::splitmix64(hash ^ x as u64)
+            });
+        let mut tokens = metadata.magic_values.clone();
+        tokens.sort_by_key(hash);
+        let known = (tokens.len() as f64 * self.completeness).round();
+        tokens.truncate(known as usize);
+        self.tokens = tokens;
+    }
+}
+
+/// Copy a random range of bytes over from another random entry of the
+/// corpus, then mutate like [`ByteReplace`], like AFL's splicing. Inputs are
+/// only crossed over where both have bytes, so their length is kept. This
+/// is how inputs found by one worker combine with those found by others
+/// when they share their corpus.
+#[derive(Clone, Default, Debug)]
+pub struct Splice {
+    /// Replaces bytes of the spliced input
+    pub replace: ByteReplace,
+
+    /// The entry crossed over with, loaded from the corpus
+    other: Vec<u8>,
+}
+
+impl Mutator for Splice {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        self.replace.mutate(rng, input);
+    }
+
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.replace.count = count;
+    }
+
+    fn mutate_with_corpus(&mut self, rng: &mut Rng, input: &mut [u8],
+                          corpus: &Corpus) {
+        if !corpus.is_empty() {
+            let index = rng.rand() % corpus.len();
+            self.other.resize(corpus.input_len(index), 0);
+            corpus.load(index, &mut self.other);
+
+            let common = input.len().min(self.other.len());
+            if common > 0 {
+                let start = rng.rand() % common;
+                let end = start + rng.rand() % (common - start) + 1;
+                input[start..end].copy_from_slice(&self.other[start..end]);
+            }
+        }
+        self.mutate(rng, input);
+    }
+}
+
+/// An operation stacked by the [`Havoc`] mutator
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HavocOp {
+    /// Overwrite a random byte with a random value
+    Overwrite,
+
+    /// Flip a random bit
+    Flip,
+
+    /// Swap two random bytes
+    Swap,
+
+    /// Copy a random block of up to 32 bytes over another place in the input
+    Copy,
+}
+
+impl HavocOp {
+    /// Every operation, in the order of their names
+    pub const ALL: [HavocOp; 4] =
+        [HavocOp::Overwrite, HavocOp::Flip, HavocOp::Swap, HavocOp::Copy];
+
+    /// Name of the operation, as listed in `sweep.havoc_ops`
+    pub fn name(self) -> &'static str {
+        match self {
+            HavocOp::Overwrite => "overwrite",
+            HavocOp::Flip      => "flip",
+            HavocOp::Swap      => "swap",
+            HavocOp::Copy      => "copy",
+        }
+    }
+
+    /// The operation called `name`, if any
+    pub fn from_name(name: &str) -> Option<Self> {
+        HavocOp::ALL.iter().copied().find(|x| x.name() == name)
+    }
+
+    /// Apply the operation to `input`, which must not be empty
+    fn apply(self, rng: &mut Rng, input: &mut [u8]) {
+        let len = input.len();
+        match self {
+            HavocOp::Overwrite => input[rng.rand() % len] = rng.rand() as u8,
+            HavocOp::Flip => {
+                let bit = rng.rand() % (len * 8);
+                input[bit / 8] ^= 1 << (bit % 8);
+            }
+            HavocOp::Swap => input.swap(rng.rand() % len, rng.rand() % len),
+            HavocOp::Copy => {
+                let size = rng.rand() % len.min(32) + 1;
+                let src = rng.rand() % (len - size + 1);
+                let dst = rng.rand() % (len - size + 1);
+                input.copy_within(src..src + size, dst);
+            }
+        }
+    }
+}
+
+/// Stack 1 to `stack` operations, each picked at random from `ops`, like
+/// AFL's havoc stage. The stack sets the intensity of the mutation, how far
+/// from its corpus entry an input lands.
+#[derive(Clone, Debug)]
+pub struct Havoc {
+    /// Largest number of operations stacked in a single case
+    pub stack: usize,
+
+    /// Operations to pick from, listing one several times makes it likelier
+    pub ops: Vec<HavocOp>,
+}
+
+impl Default for Havoc {
+    /// Stack up to 8 of every operation
+    fn default() -> Self {
+        Havoc { stack: 8, ops: HavocOp::ALL.to_vec() }
+    }
+}
+
+impl Mutator for Havoc {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        if input.is_empty() || self.ops.is_empty() {
+            return;
+        }
+        for _ in 0..rng.rand() % self.stack.max(1) + 1 {
+            self.ops[rng.rand() % self.ops.len()].apply(rng, input);
+        }
+    }
+}
+
+/// Like [`Havoc`], but picks its operations with probabilities adapting to
+/// which of them recently found new coverage, like MOpt. Every `period`
+/// fuzz cases, every operation is weighed by the fraction of the cases
+/// using it which the metric found novel, plus a tenth of the mean of those
+/// fractions so no operation is ever ruled out. Operations start out
+/// equally likely, and keep their weight over periods they weren't used in.
+#[derive(Clone, Debug)]
+pub struct AdaptiveHavoc {
+    /// Largest number of operations stacked in a single case
+    pub stack: usize,
+
+    /// Operations to pick from
+    pub ops: Vec<HavocOp>,
+
+    /// Number of fuzz cases between updates of the probabilities
+    pub period: usize,
+
+    /// Weight of every operation, relative to the others
+    weights: Vec<f64>,
+
+    /// Number of cases every operation was used in during this period
+    uses: Vec<u64>,
+
+    /// Number of novel cases every operation was used in during this period
+    finds: Vec<u64>,
+
+    /// Operations used by the last case, as indices into `ops`
+    used: Vec<usize>,
+
+    /// Number of cases in this period so far
+    cases: usize,
+}
+
+impl AdaptiveHavoc {
+    /// Adapt the probabilities of `ops` every `period` cases, stacking up
+    /// to `stack` of them
+    pub fn new(stack: usize, ops: Vec<HavocOp>, period: usize) -> Self {
+        AdaptiveHavoc {
+            stack, ops, period,
+            weights: Vec::new(),
+            uses:    Vec::new(),
+            finds:   Vec::new(),
+            used:    Vec::new(),
+            cases:   0,
+        }
+    }
+}
+
+impl Default for AdaptiveHavoc {
+    /// Stack up to 8 of every operation, adapting every 1000 cases
+    fn default() -> Self {
+        AdaptiveHavoc::new(8, HavocOp::ALL.to_vec(), 1000)
+    }
+}
+
+impl Mutator for AdaptiveHavoc {
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        self.used.clear();
+        if input.is_empty() || self.ops.is_empty() {
+            return;
+        }
+        if self.weights.len() != self.ops.len() {
+            self.reset();
+        }
+
+        let total: f64 = self.weights.iter().sum();
+        for _ in 0..rng.rand() % self.stack.max(1) + 1 {
+            // Pick an operation with a probability following its weight
+            let mut pick = rng.rand() as f64 / usize::MAX as f64 * total;
+            let op = self.weights.iter().position(|&weight| {
+                pick -= weight;
+                pick < 0.0
+            }).unwrap_or(self.ops.len() - 1);
+            self.ops[op].apply(rng, input);
+            self.used.push(op);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.weights = vec![1.0; self.ops.len()];
+        self.uses    = vec![0; self.ops.len()];
+        self.finds   = vec![0; self.ops.len()];
+        self.used.clear();
+        self.cases = 0;
+    }
+
+    fn feedback(&mut self, novel: bool) {
+        if self.used.is_empty() {
+            return;
+        }
+        self.used.sort_unstable();
+        self.used.dedup();
+        for &op in &self.used {
+            self.uses[op]  += 1;
+            self.finds[op] += novel as u64;
+        }
+
+        self.cases += 1;
+        if self.cases < self.period {
+            return;
+        }
+
+        // Weigh the operations by their recent success rate
+        for (op, weight) in self.weights.iter_mut().enumerate() {
+            if self.uses[op] != 0 {
+                *weight = self.finds[op] as f64 / self.uses[op] as f64;
+            }
+        }
+        let floor = self.weights.iter().sum::<f64>() /
+            self.weights.len() as f64 / 10.0;
+        for weight in &mut self.weights {
+            *weight += floor.max(f64::MIN_POSITIVE);
+        }
+        self.uses.iter_mut().for_each(|x| *x = 0);
+        self.finds.iter_mut().for_each(|x| *x = 0);
+        self.cases = 0;
+    }
+}
+
+/// Widths in bytes of the integers mutated by [`ArithDelta`] and
+/// [`InterestingValue`], the sizes of the integers generated programs
+/// compare, in little endian
+const INT_WIDTHS: [usize; 4] = [1, 2, 4, 8];
+
+/// Pick an integer at a random offset in `input` with a random one of the
+/// [`INT_WIDTHS`] which fit, returning its offset and width. `input` must
+/// not be empty.
+fn pick_int(rng: &mut Rng, input: &[u8]) -> (usize, usize) {
+    let fits = INT_WIDTHS.iter().filter(|&&x| x <= input.len()).count();
+    let width = INT_WIDTHS[rng.rand() % fits];
+    (rng.rand() % (input.len() - width + 1), width)
+}
+
+/// Largest delta [`ArithDelta`] adds or subtracts, AFL's `ARITH_MAX`
+pub const ARITH_MAX: u64 = 35;
+
+/// Add or subtract a small delta, of 1 to [`ARITH_MAX`], to integers at
+/// random locations, up to 8 of them by default, like AFL's arithmetic
+/// stage. This walks values across the bounds of range conditions a little
+/// at a time, where replacing bytes jumps anywhere.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ArithDelta {
+    /// Number of integers changed in every case
+    pub count: MutationCount,
+}
+
+impl Mutator for ArithDelta {
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.count = count;
+    }
+
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        if input.is_empty() {
+            return;
+        }
+        for _ in 0..self.count.draw(rng) {
+            let (offset, width) = pick_int(rng, input);
+            let bytes = &mut input[offset..offset + width];
+            let mut value = [0u8; 8];
+            value[..width].copy_from_slice(bytes);
+            let value = u64::from_le_bytes(value);
+            let delta = rng.rand() as u64 % ARITH_MAX + 1;
+            let value = if rng.rand().is_multiple_of(2) {
+                value.wrapping_add(delta)
+            } else {
+                value.wrapping_sub(delta)
+            };
+            bytes.copy_from_slice(&value.to_le_bytes()[..width]);
+        }
+    }
+}
+
+/// AFL's interesting values, which are the boundaries of signed and unsigned
+/// integers, and common sizes and counts
+pub const INTERESTING_VALUES: [i64; 27] = [
+    -128, -1, 0, 1, 16, 32, 64, 100, 127,
+    -32768, -129, 128, 255, 256, 512, 1000, 1024, 4096, 32767,
+    -2147483648, -100663046, -32769, 32768, 65535, 65536, 100663045,
+    2147483647,
+];
+
+/// Replace integers at random locations, up to 8 of them by default, with
+/// one of the [`INTERESTING_VALUES`] which fits them, like AFL's interesting
+/// value stage. Values are truncated to the integer, so 8 byte integers
+/// also see the 32-bit boundaries sign extended. This hits the edges of
+/// range conditions which random bytes rarely do.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct InterestingValue {
+    /// Number of integers replaced in every case
+    pub count: MutationCount,
+}
+
+impl Mutator for InterestingValue {
+    fn mutation_count(&mut self, count: MutationCount) {
+        self.count = count;
+    }
+
+    fn mutate(&mut self, rng: &mut Rng, input: &mut [u8]) {
+        if input.is_empty() {
+            return;
+        }
+        for _ in 0..self.count.draw(rng) {
+            let (offset, width) = pick_int(rng, input);
+
+            // The values fitting in a byte, in 16 bits, and in 32 bits
+            let fits = match width {
+                1 => 9,
+                2 => 19,
+                _ => INTERESTING_VALUES.len(),
+            };
+            let value = INTERESTING_VALUES[rng.rand() % fits];
+            input[offset..offset + width]
+                .copy_from_slice(&value.to_le_bytes()[..width]);
+        }
+    }
+}
+
+/// Pick corpus entries uniformly at random
+#[derive(Clone, Copy, Default, Debug)]
+pub struct UniformScheduler;
+
+impl Scheduler for UniformScheduler {
+    fn select(&mut self, rng: &mut Rng, _db: usize, len: usize) -> usize {
+        rng.rand() % len
+    }
+}
+
+/// Cycle through the corpus entries in the order they were added, like
+/// AFL's queue. Entries added during a cycle are picked before the next
+/// cycle starts over at the oldest.
+#[derive(Clone, Default, Debug)]
+pub struct QueueScheduler {
+    /// Per-database index of the entry picked next
+    next: Vec<usize>,
+}
+
+impl Scheduler for QueueScheduler {
+    fn reset(&mut self, databases: usize) {
+        self.next = vec![0; databases];
+    }
+
+    fn select(&mut self, _rng: &mut Rng, db: usize, len: usize) -> usize {
+        let index = self.next[db] % len;
+        self.next[db] = index + 1;
+        index
+    }
+}
+
+/// Always pick the newest corpus entry, building on the latest progress
+/// depth first
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NewestScheduler;
+
+impl Scheduler for NewestScheduler {
+    fn select(&mut self, _rng: &mut Rng, _db: usize, len: usize) -> usize {
+        len - 1
+    }
+}
+
+/// Prefer corpus entries reaching blocks which few other entries of their
+/// corpus reach. A block reached by any entry is picked uniformly at random,
+/// and then one of the entries reaching it, so every entry is weighed by the
+/// sum of the inverse of the number of entries reaching each of its blocks.
+#[derive(Clone, Default, Debug)]
+pub struct RarityScheduler {
+    /// Per-database entries reaching every block, indexed by block ID
+    entries: Vec<Vec<Vec<usize>>>,
+
+    /// Per-database blocks reached by any entry
+    reached: Vec<Vec<usize>>,
+}
+
+impl Scheduler for RarityScheduler {
+    fn reset(&mut self, databases: usize) {
+        self.entries = vec![Vec::new(); databases];
+        self.reached = vec![Vec::new(); databases];
+    }
+
+    fn added(&mut self, db: usize, index: usize, trace: &[u32]) {
+        let entries = &mut self.entries[db];
+        for &block in trace {
+            let block = block as usize;
+            if entries.len() <= block {
+                entries.resize(block + 1, Vec::new());
+            }
+
+            // Blocks reached several times only count once
+            if entries[block].last() != Some(&index) {
+                if entries[block].is_empty() {
+                    self.reached[db].push(block);
+                }
+                entries[block].push(index);
+            }
+        }
+    }
+
+    fn select(&mut self, rng: &mut Rng, db: usize, len: usize) -> usize {
+        // Entries reaching no instrumented block can only be picked if no
+        // entry reaches one
+        let reached = &self.reached[db];
+        if reached.is_empty() {
+            return rng.rand() % len;
+        }
+        let entries = &self.entries[db][reached[rng.rand() % reached.len()]];
+        entries[rng.rand() % entries.len()]
+    }
+}
+
+/// A fuzz case is novel if it reaches a block no earlier case in the same
+/// database has reached
+#[derive(Clone, Default, Debug)]
+pub struct BlockCoverage {
+    /// Per-database flags of which entries of the map have been reached
+    reached: Vec<Vec<bool>>,
+
+    /// Size of the coverage map, zero for an entry for every block
+    map_size: usize,
+}
+
+impl CoverageMetric for BlockCoverage {
+    fn reset(&mut self, databases: usize, num_coverage: usize) {
+        let size = if self.map_size != 0 { self.map_size }
+                   else { num_coverage };
+        self.reached.resize(databases, Vec::new());
+        for db in &mut self.reached[..databases] {
+            db.clear();
+            db.resize(size, false);
+        }
+    }
+
+    fn observe(&mut self, db: usize, trace: &[u32]) -> bool {
+        let reached = &mut self.reached[db];
+        let mut novel = false;
+        for &block in trace {
+            let slot = map_slot(block, self.map_size);
+            novel |= !std::mem::replace(&mut reached[slot], true);
+        }
+        novel
+    }
+
+    fn map_size(&mut self, size: usize) {
+        self.map_size = size;
+    }
+}
+
+/// A fuzz case is novel if it reaches a function no earlier case in the
+/// same database has reached. Without the target's metadata every block is
+/// taken to be in the same function.
+#[derive(Clone, Default, Debug)]
+pub struct FunctionCoverage {
+    /// Function of every block, see [`Metadata::block_functions`]
+    block_functions: Vec<usize>,
+
+    /// Number of functions, including the entry
+    functions: usize,
+
+    /// Per-database flags of which functions have been reached
+    reached: Vec<Vec<bool>>,
+}
+
+impl CoverageMetric for FunctionCoverage {
+    fn reset(&mut self, databases: usize, _num_coverage: usize) {
+        let functions = self.functions.max(1);
+        self.reached.resize(databases, Vec::new());
+        for db in &mut self.reached[..databases] {
+            db.clear();
+            db.resize(functions, false);
+        }
+    }
+
+    fn target(&mut self, metadata: &Metadata) {
+        self.block_functions = metadata.block_functions.clone();
+        self.functions = metadata.functions + 1;
+    }
+
+    fn observe(&mut self, db: usize, trace: &[u32]) -> bool {
+        let reached = &mut self.reached[db];
+        let mut novel = false;
+        for &block in trace {
+            let function = self.block_functions.get(block as usize)
+                .copied().unwrap_or(0);
+            novel |= !std::mem::replace(&mut reached[function], true);
+        }
+        novel
+    }
+}
+
+/// A fuzz case is novel if it goes from one block straight to another in a
+/// way no earlier case in the same database has, or starts in a block no
+/// earlier case started in. Edges are tracked exactly with a map size of
+/// zero, and otherwise hashed into a map of that size with [`map_edge`],
+/// where distinct edges can collide.
+#[derive(Clone, Default, Debug)]
+pub struct EdgeCoverage {
+    /// Per-database sets of the edges which have been taken, as their
+    /// [`map_edge`]. Edges into the first block of a trace come from
+    /// `u32::MAX`.
+    reached: Vec<HashSet<u64>>,
+
+    /// Size of the coverage map, zero to track edges exactly
+    map_size: usize,
+}
+
+impl CoverageMetric for EdgeCoverage {
+    fn reset(&mut self, databases: usize, _num_coverage: usize) {
+        self.reached.resize(databases, HashSet::new());
+        for db in &mut self.reached[..databases] {
+            db.clear();
+        }
+    }
+
+    fn observe(&mut self, db: usize, trace: &[u32]) -> bool {
+        let reached = &mut self.reached[db];
+        let mut novel = false;
+        let mut from = u32::MAX;
+        for &block in trace {
+            novel |= reached.insert(map_edge(from, block, self.map_size));
+            from = block;
+        }
+        novel
+    }
+
+    fn map_size(&mut self, size: usize) {
+        self.map_size = size;
+    }
+}
+
+/// The hit count bucket of a block or edge reached `hits` times in a fuzz
+/// case, as a bit: 1, 2, 3, 4-7, 8-15, 16-31, 32-127, or 128 and more hits,
+/// like AFL's. Zero hits are in no bucket.
+pub fn hit_bucket(hits: u32) -> u8 {
+    match hits {
+        0        => 0,
+        1        => 1 << 0,
+        2        => 1 << 1,
+        3        => 1 << 2,
+        4..=7    => 1 << 3,
+        8..=15   => 1 << 4,
+        16..=31  => 1 << 5,
+        32..=127 => 1 << 6,
+        _        => 1 << 7,
+    }
+}
+
+/// Like [`BlockCoverage`], but a fuzz case is also novel if it reaches a
+/// block a number of times in a [`hit_bucket`] no earlier case in the same
+/// database has reached it with, like AFL's hit counts
+#[derive(Clone, Default, Debug)]
+pub struct BlockHitCoverage {
+    /// Per-database buckets every entry of the map has been reached with,
+    /// as bits
+    reached: Vec<Vec<u8>>,
+
+    /// Hits of every entry of the map in the fuzz case being observed
+    hits: Vec<u32>,
+
+    /// Entries of the map reached by the fuzz case being observed
+    slots: Vec<usize>,
+
+    /// Size of the coverage map, zero for an entry for every block
+    map_size: usize,
+}
+
+impl CoverageMetric for BlockHitCoverage {
+    fn reset(&mut self, databases: usize, num_coverage: usize) {
+        let size = if self.map_size != 0 { self.map_size }
+                   else { num_coverage };
+        self.reached.resize(databases, Vec::new());
+        for db in &mut self.reached[..databases] {
+            db.clear();
+            db.resize(size, 0);
+        }
+        self.hits.clear();
+        self.hits.resize(size, 0);
+    }
+
+    fn observe(&mut self, db: usize, trace: &[u32]) -> bool {
+        for &block in trace {
+            let slot = map_slot(block, self.map_size);
+            if self.hits[slot] == 0 {
+                self.slots.push(slot);
+            }
+            self.hits[slot] += 1;
+        }
+
+        let reached = &mut self.reached[db];
+        let mut novel = false;
+        for slot in self.slots.drain(..) {
+            let bucket = hit_bucket(std::mem::take(&mut self.hits[slot]));
+            novel |= reached[slot] & bucket == 0;
+            reached[slot] |= bucket;
+        }
+        novel
+    }
+
+    fn map_size(&mut self, size: usize) {
+        self.map_size = size;
+    }
+}
+
+/// Like [`EdgeCoverage`], but a fuzz case is also novel if it takes an edge
+/// a number of times in a [`hit_bucket`] no earlier case in the same
+/// database has taken it. With a map size this is AFL's feedback, collisions
+/// of its bitmap included, and without one the same with every edge exact.
+#[derive(Clone, Default, Debug)]
+pub struct EdgeHitCoverage {
+    /// Per-database buckets every edge has been taken with, as bits, with
+    /// edges as in [`EdgeCoverage`]
+    reached: Vec<HashMap<u64, u8>>,
+
+    /// Hits of every edge taken by the fuzz case being observed
+    hits: HashMap<u64, u32>,
+
+    /// Size of the coverage map, zero to track edges exactly
+    map_size: usize,
+}
+
+impl CoverageMetric for EdgeHitCoverage {
+    fn reset(&mut self, databases: usize, _num_coverage: usize) {
+        self.reached.resize(databases, HashMap::new());
+        for db in &mut self.reached[..databases] {
+            db.clear();
+        }
+    }
+
+    fn observe(&mut self, db: usize, trace: &[u32]) -> bool {
+        let mut from = u32::MAX;
+        for &block in trace {
+            *self.hits.entry(map_edge(from, block, self.map_size))
+                .or_insert(0) += 1;
+            from = block;
+        }
+
+        let reached = &mut self.reached[db];
+        let mut novel = false;
+        for (edge, hits) in self.hits.drain() {
+            let buckets = reached.entry(edge).or_insert(0);
+            novel |= *buckets & hit_bucket(hits) == 0;
+            *buckets |= hit_bucket(hits);
+        }
+        novel
+    }
+
+    fn map_size(&mut self, size: usize) {
+        self.map_size = size;
+    }
+}
+
+/// A fuzz case is novel if the whole sequence of blocks it reached, its
+/// path, differs from that of every earlier case in the same database.
+/// Paths are told apart by a 64-bit hash of the trace, which practically
+/// never collides. Every way through a loop and every order of calls is a
+/// path of its own, so the corpus grows far past the number of blocks.
+/// Paths are not hashed into the coverage map, so the map size is ignored.
+#[derive(Clone, Default, Debug)]
+pub struct PathCoverage {
+    /// Per-database sets of the hashes of the paths which have been taken
+    reached: Vec<HashSet<u64>>,
+}
+
+impl CoverageMetric for PathCoverage {
+    fn reset(&mut self, databases: usize, _num_coverage: usize) {
+        self.reached.resize(databases, HashSet::new());
+        for db in &mut self.reached[..databases] {
+            db.clear();
+        }
+    }
+
+    fn observe(&mut self, db: usize, trace: &[u32]) -> bool {
+        let hash = trace.iter().fold(trace.len() as u64, |hash, &block| {
+            rng::splitmix64(hash ^ block as u64)
+        });
+        self.reached[db].insert(hash)
+    }
+}
+
+/// A factory creating a fresh strategy of type `T`
+pub type Factory<T> = Arc<dyn Fn() -> Box<T> + Send + Sync>;
+
+/// Factories for the strategies of a fuzzer
+#[derive(Clone)]
+pub struct Strategies {
+    /// Name of this set of strategies, used to label results
+    pub name: String,
+
+    /// Creates the mutator
+    pub mutator: Factory<dyn Mutator>,
+
+    /// Creates the corpus scheduler
+    pub scheduler: Factory<dyn Scheduler>,
+
+    /// Creates the coverage metric
+    pub metric: Factory<dyn CoverageMetric>,
+
+    /// Run AFL's deterministic stage on every new corpus entry before it is
+    /// mutated randomly, see [`Fuzzer::deterministic`]
+    ///
+    /// [`Fuzzer::deterministic`]: crate::Fuzzer::deterministic
+    pub deterministic: bool,
+
+    /// Distribution of the number of mutations the mutator makes, see
+    /// [`Mutator::mutation_count`]
+    pub mutation_count: MutationCount,
+}
+
+impl Default for Strategies {
+    /// [`ByteReplace`], [`UniformScheduler`], and [`BlockCoverage`], which
+    /// is the classic behavior of the simulator
+    fn default() -> Self {
+        Strategies {
+            name:      "default".into(),
+            mutator:   Arc::new(|| Box::new(ByteReplace::default())),
+            scheduler: Arc::new(|| Box::new(UniformScheduler)),
+            metric:    Arc::new(|| Box::new(BlockCoverage::default())),
+            deterministic: false,
+            mutation_count: MutationCount::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Strategies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Strategies").field("name", &self.name)
+            .field("deterministic", &self.deterministic)
+            .field("mutation_count", &self.mutation_count)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_entries_are_in_bounds() {
+        for map_size in [1, 7, 1 << 16] {
+            for block in (0..10000).chain([u32::MAX - 1, u32::MAX]) {
+                assert!(map_slot(block, map_size) < map_size);
+                assert!(map_edge(u32::MAX, block, map_size) <
+                        map_size as u64);
+                assert!(map_edge(block, block / 3, map_size) <
+                        map_size as u64);
+            }
+        }
+
+        // An exact map tells every block and edge apart
+        assert_eq!(map_slot(12345, 0), 12345);
+        assert_ne!(map_edge(1, 2, 0), map_edge(2, 1, 0));
+        assert_ne!(map_edge(u32::MAX, 0, 0), map_edge(0, u32::MAX, 0));
+        assert_eq!(map_collisions(1000, 0), 0);
+        assert_eq!(map_collisions(1000, 1), 999);
+    }
+
+    #[test]
+    fn hit_buckets() {
+        assert_eq!(hit_bucket(0), 0);
+        let mut last = 0;
+        for hits in 1..300 {
+            let bucket = hit_bucket(hits);
+            assert_eq!(bucket.count_ones(), 1);
+            assert!(bucket >= last);
+            last = bucket;
+        }
+        assert_eq!(hit_bucket(4), hit_bucket(7));
+        assert_ne!(hit_bucket(7), hit_bucket(8));
+        assert_eq!(hit_bucket(u32::MAX), 1 << 7);
+    }
+
+    #[test]
+    fn queue_cycles_through_every_entry() {
+        let mut rng = Rng::new(1);
+        let mut queue = QueueScheduler::default();
+        queue.reset(2);
+        let picks: Vec<usize> =
+            (0..7).map(|_| queue.select(&mut rng, 0, 3)).collect();
+        assert_eq!(picks, [0, 1, 2, 0, 1, 2, 0]);
+
+        // Databases are independent, and new entries join the cycle
+        assert_eq!(queue.select(&mut rng, 1, 3), 0);
+        assert_eq!(queue.select(&mut rng, 0, 5), 1);
+        assert_eq!(queue.select(&mut rng, 0, 5), 2);
+        assert_eq!(queue.select(&mut rng, 0, 5), 3);
+        assert_eq!(queue.select(&mut rng, 0, 5), 4);
+        assert_eq!(queue.select(&mut rng, 0, 5), 0);
+
+        queue.reset(1);
+        assert_eq!(queue.select(&mut rng, 0, 3), 0);
+    }
+
+    #[test]
+    fn rarity_prefers_rare_blocks() {
+        let mut rng = Rng::new(1);
+        let mut rarity = RarityScheduler::default();
+        rarity.reset(2);
+
+        // Nothing reached yet, so any entry
+        assert!(rarity.select(&mut rng, 0, 3) < 3);
+
+        // Every entry reaches block 0, several times, and only entry 4 also
+        // reaches block 9, so it is picked 1/2 + 1/2 * 1/5 of the time
+        for index in 0..5 {
+            rarity.added(0, index, &[0, 0, 0]);
+        }
+        rarity.added(0, 4, &[9]);
+        let mut picks = [0usize; 5];
+        for _ in 0..10000 {
+            picks[rarity.select(&mut rng, 0, 5)] += 1;
+        }
+        assert!((5500..6500).contains(&picks[4]), "{:?}", picks);
+        for &count in &picks[..4] {
+            assert!((700..1300).contains(&count), "{:?}", picks);
+        }
+
+        // Databases are independent
+        rarity.added(1, 0, &[9]);
+        assert_eq!(rarity.select(&mut rng, 1, 1), 0);
+    }
+}
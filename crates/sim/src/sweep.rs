@@ -0,0 +1,1115 @@
+//! Sweep drivers which run the simulator over many configurations
+//!
+//! [`run`] sweeps the number of simulated workers for each fuzzer
+//! configuration, averaging many trials per datapoint, and writes the results
+//! to one file per configuration. [`heatmap`] repeats that sweep for a range
+//! of time constraints, writing each row of the heatmap to its own files, and
+//! [`perf`] measures raw simulator throughput.
+
+use std::io::{self, Write};
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, BTreeSet};
+
+use fuzztheory_analysis::{Record, Stats};
+use fuzztheory_proggen::{info, rng, trace, verbose};
+
+use crate::{Fuzzer, Metadata, Strategies, Target};
+use crate::interrupt;
+use crate::progress::Progress;
+use crate::strategy::{AdaptiveHavoc, Dictionary, Havoc, HavocOp,
+                      MutationCount, map_collisions};
+use crate::registry::Registry;
+
+/// Parameters controlling the simulation sweeps
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// Maximum number of simulated cores, the largest worker count swept
+    pub max_cores: usize,
+
+    /// Number of datapoints to generate along the worker count axis. If the
+    /// exponential spacing is used, the base is computed such that
+    /// expbase^max_x_resolution = max_cores
+    pub max_x_resolution: usize,
+
+    /// Number of iterations of each fuzz attempt to perform, to generate an
+    /// average value per data point.
+    pub averages: usize,
+
+    /// Stop each trial of a [`run`] after this much uptime
+    pub time_constraint: Option<f64>,
+
+    /// Number of time constraint rows generated by [`heatmap`]
+    pub max_y_resolution: usize,
+
+    /// Largest time constraint used by [`heatmap`]
+    pub max_y_point: f64,
+
+    /// Values of [`Fuzzer::coverage_guided`] to sweep
+    pub guided: Vec<bool>,
+
+    /// Values of [`Fuzzer::shared_inputs`] to sweep
+    pub shared_inputs: Vec<bool>,
+
+    /// Values of [`Fuzzer::shared_results`] to sweep
+    pub shared_results: Vec<bool>,
+
+    /// Directory result files, manifests, and checkpoints are written to.
+    /// Empty for the working directory.
+    pub output_dir: PathBuf,
+
+    /// Prefix prepended to the name of every result file, used to keep the
+    /// results of different programs apart
+    pub output_prefix: String,
+
+    /// Names of the mutators to compare. If there is more than one, every
+    /// configuration is swept with each of them and the name of the mutator
+    /// is included in the result file names.
+    pub mutators: Vec<String>,
+
+    /// Name of the corpus scheduler used with every mutator
+    pub scheduler: String,
+
+    /// Names of the coverage metrics to compare, each of which is used with
+    /// every mutator. If there is more than one, the name of the metric is
+    /// included in the result file names after the mutator's.
+    pub metrics: Vec<String>,
+
+    /// Largest number of operations the `havoc` mutator stacks in a single
+    /// fuzz case, see [`Havoc::stack`]
+    pub havoc_stack: usize,
+
+    /// Names of the operations the `havoc` mutator picks from, see
+    /// [`HavocOp::name`]
+    pub havoc_ops: Vec<String>,
+
+    /// Number of fuzz cases between updates of the probabilities of the
+    /// operations of the `mopt` mutator, which stacks them like `havoc`,
+    /// see [`AdaptiveHavoc::period`]
+    pub mopt_period: usize,
+
+    /// Fraction of the program's magic values the `dictionary` mutator
+    /// knows, from 0 to 1, see [`Dictionary::completeness`]
+    pub dictionary_completeness: f64,
+
+    /// Whether the strategies run a deterministic stage on every new corpus
+    /// entry, see [`Fuzzer::deterministic`]. Every mutator and metric is
+    /// swept with each value, and if there are several, result file names
+    /// end their strategy name with `deterministic` or `random`.
+    pub deterministic: Vec<bool>,
+
+    /// Distributions of the number of mutations made in every fuzz case,
+    /// named like `fixed-4`, `uniform-8`, or `geometric-4`, see
+    /// [`MutationCount::parse`]. Every mutator and metric is swept with
+    /// each, and if there are several, result file names end their strategy
+    /// name with the distribution. `havoc` and `mopt` stack
+    /// [`Params::havoc_stack`] operations instead, so they are only swept
+    /// once, with the first.
+    pub mutation_counts: Vec<String>,
+
+    /// Number of entries of the coverage map the metrics hash blocks and
+    /// edges into, so distinct ones collide like they do in real fuzzers,
+    /// see [`Fuzzer::map_size`]. Zero tracks them exactly.
+    pub map_size: usize,
+
+    /// Chance of querying the taint oracle for a corpus entry picked for
+    /// mutation, which then restricts its mutation to the input bytes a
+    /// branch next to its blocks depends on, see [`Fuzzer::taint_chance`].
+    /// Zero fuzzes without taint tracking.
+    pub taint_chance: f64,
+
+    /// Fuzz cases every query of the taint oracle costs, see
+    /// [`Fuzzer::taint_cost`]
+    pub taint_cost: u64,
+
+    /// Fuzz cases of uptime the solver worker takes to solve a branch of
+    /// the corpus of coverage guided fuzzers, see
+    /// [`Fuzzer::solver_period`]. Zero runs no solver.
+    pub solver_period: u64,
+
+    /// How crashes are deduplicated when counting the unique crashes found:
+    /// `exact` counts every crash ID, `bucket` counts buckets of
+    /// `dedup_bucket_size` neighboring IDs, and `root-cause` counts the
+    /// root causes of the program's ground truth, see
+    /// [`Params::crash_buckets`]
+    pub dedup: String,
+
+    /// Number of neighboring crash IDs deduplicated together by the
+    /// `bucket` policy
+    pub dedup_bucket_size: usize,
+
+    /// Modules of the program reporting coverage to the metric and
+    /// scheduler, see [`Fuzzer::instrumented`]. Empty instruments every
+    /// module.
+    pub instrumented_modules: Vec<usize>,
+
+    /// Entry points of the program fuzzed, split between the workers of
+    /// every fuzzer, see [`Fuzzer::entries`]
+    pub entry_points: Vec<usize>,
+
+    /// Registry the strategy names are looked up in
+    pub registry: Registry,
+
+    /// Seed for the simulated workers. Every trial of every datapoint
+    /// derives its own RNG stream from it, so seeded sweeps are reproducible
+    /// regardless of the order datapoints are run in, and single trials can
+    /// be re-run with [`Params::trial`]. Unseeded sweeps pick a seed from
+    /// the time stamp counter.
+    pub seed: Option<u64>,
+
+    /// Number of threads running datapoints in parallel. Every thread has
+    /// its own [`Fuzzer`], and seeded results don't depend on it.
+    pub threads: usize,
+
+    /// Only run this trial of each datapoint instead of all `averages` of
+    /// them, to reproduce an outlier reported by a seeded sweep
+    pub trial: Option<usize>,
+
+    /// Keep the records in existing result files and only run the
+    /// datapoints missing from them. The existing results must have been
+    /// produced with the same parameters.
+    pub resume: bool,
+}
+
+impl Params {
+    /// Indices of the trials run for every datapoint
+    pub fn trials(&self) -> std::ops::Range<usize> {
+        match self.trial {
+            Some(trial) => trial..trial + 1,
+            None        => 0..self.averages,
+        }
+    }
+
+    /// The parameters a run actually uses. Unseeded runs pick a seed here so
+    /// it can be recorded, and the results of a single [`Params::trial`] are
+    /// written to `trial_<n>_` prefixed files instead of the full results.
+    pub fn prepare(&self) -> Params {
+        let mut params = self.clone();
+        params.seed.get_or_insert_with(rng::random_seed);
+        if let Some(trial) = params.trial {
+            params.output_prefix = format!("{}trial_{}_",
+                                           params.output_prefix, trial);
+        }
+        params
+    }
+
+    /// The `havoc` mutator configured by [`Params::havoc_stack`] and
+    /// [`Params::havoc_ops`]
+    pub fn havoc(&self) -> io::Result<Havoc> {
+        let ops = self.havoc_ops.iter().map(|name| {
+            HavocOp::from_name(name).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown havoc operation `{}`", name)))
+        }).collect::<io::Result<Vec<_>>>()?;
+        if self.havoc_stack == 0 || ops.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "havoc needs at least one operation to stack"));
+        }
+        Ok(Havoc { stack: self.havoc_stack, ops })
+    }
+
+    /// The distributions of [`Params::mutation_counts`]
+    pub fn mutation_counts(&self) -> io::Result<Vec<MutationCount>> {
+        self.mutation_counts.iter().map(|name| {
+            MutationCount::parse(name).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown mutation count distribution `{}`", name)))
+        }).collect()
+    }
+
+    /// Look up the strategies to sweep in the registry, every mutator with
+    /// every metric, every value of [`Params::deterministic`], and every
+    /// distribution of [`Params::mutation_counts`], named after the mutator
+    /// and whichever of the others have several values. `havoc` and `mopt`
+    /// ignore the distribution, and only get the first.
+    /// The built-in `havoc` mutator is configured by [`Params::havoc`],
+    /// `mopt` also by [`Params::mopt_period`], and `dictionary` by
+    /// [`Params::dictionary_completeness`].
+    pub fn strategies(&self) -> io::Result<Vec<Strategies>> {
+        let counts = self.mutation_counts()?;
+        let mut strategies = Vec::new();
+        for mutator in &self.mutators {
+            // Sweeping these with every distribution would only repeat the
+            // same runs under different names
+            let swept = if matches!(mutator.as_str(), "havoc" | "mopt") {
+                &counts[..counts.len().min(1)]
+            } else {
+                &counts[..]
+            };
+            for metric in &self.metrics {
+                for (&deterministic, &count) in self.deterministic.iter()
+                        .flat_map(|x| swept.iter().map(move |y| (x, y))) {
+                    let mut strategy = self.registry
+                        .strategies(mutator, &self.scheduler, metric)
+                        .map_err(|err| io::Error::new(
+                            io::ErrorKind::InvalidInput, err))?;
+                    strategy.deterministic = deterministic;
+                    strategy.mutation_count = count;
+                    match mutator.as_str() {
+                        "havoc" => {
+                            let havoc = self.havoc()?;
+                            strategy.mutator = Arc::new(move || {
+                                Box::new(havoc.clone())
+                            });
+                        }
+                        "mopt" => {
+                            let Havoc { stack, ops } = self.havoc()?;
+                            let period = self.mopt_period.max(1);
+                            strategy.mutator = Arc::new(move || {
+                                Box::new(AdaptiveHavoc::new(stack,
+                                    ops.clone(), period))
+                            });
+                        }
+                        "dictionary" => {
+                            let completeness = self.dictionary_completeness;
+                            strategy.mutator = Arc::new(move || {
+                                Box::new(Dictionary::new(completeness))
+                            });
+                        }
+                        _ => {}
+                    }
+                    if self.metrics.len() > 1 {
+                        strategy.name = format!("{}_{}", strategy.name,
+                                                metric);
+                    }
+                    if self.deterministic.len() > 1 {
+                        strategy.name = format!("{}_{}", strategy.name,
+                            if deterministic { "deterministic" }
+                            else { "random" });
+                    }
+                    if swept.len() > 1 {
+                        strategy.name = format!("{}_{}", strategy.name,
+                                                count.name());
+                    }
+                    strategies.push(strategy);
+                }
+            }
+        }
+        Ok(strategies)
+    }
+
+    /// The blocks of `target` in [`Params::instrumented_modules`], for
+    /// [`Fuzzer::instrumented`]
+    pub fn instrumented<T: Target + ?Sized>(&self, target: &T)
+            -> io::Result<Option<Vec<bool>>> {
+        if self.instrumented_modules.is_empty() {
+            return Ok(None);
+        }
+
+        let invalid = |err: String| {
+            io::Error::new(io::ErrorKind::InvalidInput, err)
+        };
+        let metadata = Metadata::parse(target.info().ok_or_else(|| {
+            invalid("instrumented modules need the program's metadata".into())
+        })?).map_err(invalid)?;
+        if let Some(module) = self.instrumented_modules.iter()
+                .find(|&&x| x >= metadata.modules) {
+            return Err(invalid(format!("can't instrument module {}, the \
+                                        program has {} modules", module,
+                                       metadata.modules)));
+        }
+
+        // Programs in a single module have all their blocks in module 0
+        let module = |block: usize| {
+            metadata.block_modules.get(block).copied().unwrap_or(0)
+        };
+        Ok(Some((0..target.num_coverage())
+            .map(|block| self.instrumented_modules.contains(&module(block)))
+            .collect()))
+    }
+
+    /// Make sure the taint oracle and solver worker can learn about the
+    /// branches of `target` if [`Params::taint_chance`] or
+    /// [`Params::solver_period`] enable them, which needs the guards of its
+    /// blocks in its metadata
+    pub fn check_guards<T: Target + ?Sized>(&self, target: &T)
+            -> io::Result<()> {
+        if self.taint_chance == 0.0 && self.solver_period == 0 {
+            return Ok(());
+        }
+        let guarded = target.info().and_then(|x| Metadata::parse(x).ok())
+            .is_some_and(|x| x.block_guards.len() == target.num_coverage());
+        if !guarded {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "the taint oracle and solver need the guards of the \
+                 program's blocks in its metadata, regenerate it"));
+        }
+        if self.taint_chance != 0.0 {
+            verbose!("sweep: taint oracle queried for {} of entries, \
+                      costing {} fuzz cases", self.taint_chance,
+                     self.taint_cost);
+        }
+        if self.solver_period != 0 {
+            verbose!("sweep: solver worker solving a branch every {} fuzz \
+                      cases", self.solver_period);
+        }
+        Ok(())
+    }
+
+    /// The bucket every crash of `target` is deduplicated into by the
+    /// [`Params::dedup`] policy, for [`Fuzzer::crash_buckets`]
+    pub fn crash_buckets<T: Target + ?Sized>(&self, target: &T)
+            -> io::Result<Option<Vec<usize>>> {
+        let invalid = |err: String| {
+            io::Error::new(io::ErrorKind::InvalidInput, err)
+        };
+        match self.dedup.as_str() {
+            "exact"  => Ok(None),
+            "bucket" => {
+                if self.dedup_bucket_size == 0 {
+                    return Err(invalid("crash buckets must hold at least \
+                                        one crash".into()));
+                }
+                Ok(Some((0..target.num_crashes())
+                    .map(|crash| crash / self.dedup_bucket_size).collect()))
+            }
+            "root-cause" => {
+                let metadata = Metadata::parse(target.info().ok_or_else(|| {
+                    invalid("root cause deduplication needs the program's \
+                             metadata".into())
+                })?).map_err(invalid)?;
+                if metadata.root_causes.len() != target.num_crashes() {
+                    return Err(invalid("the program's metadata lacks the \
+                                        root causes of its crashes".into()));
+                }
+                Ok(Some(metadata.root_causes))
+            }
+            dedup => Err(invalid(format!("unknown crash deduplication \
+                                          policy `{}`, expected `exact`, \
+                                          `bucket`, or `root-cause`",
+                                         dedup))),
+        }
+    }
+
+    /// Check that [`Params::entry_points`] are entry points of `target`,
+    /// returning them for [`Fuzzer::entries`]
+    pub fn entries<T: Target + ?Sized>(&self, target: &T)
+            -> io::Result<Vec<usize>> {
+        let invalid = |err: String| {
+            io::Error::new(io::ErrorKind::InvalidInput, err)
+        };
+        if self.entry_points.is_empty() {
+            return Err(invalid("no entry points to fuzz".into()));
+        }
+        if let Some(entry) = self.entry_points.iter()
+                .find(|&&x| x >= target.entry_points()) {
+            return Err(invalid(format!("can't fuzz entry point {}, the \
+                                        program has {} entry points", entry,
+                                       target.entry_points())));
+        }
+        Ok(self.entry_points.clone())
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            max_cores:        2001,
+            max_x_resolution: 100,
+            averages:         1000,
+            time_constraint:  None,
+            max_y_resolution: 100,
+            max_y_point:      1.0,
+            guided:           vec![true],
+            shared_inputs:    vec![false, true],
+            shared_results:   vec![true],
+            output_dir:       PathBuf::new(),
+            output_prefix:    String::new(),
+            mutators:         vec!["replace".into()],
+            scheduler:        "uniform".into(),
+            metrics:          vec!["block".into()],
+            havoc_stack:      8,
+            havoc_ops:        HavocOp::ALL.iter()
+                .map(|x| x.name().into()).collect(),
+            mopt_period:      1000,
+            dictionary_completeness: 1.0,
+            deterministic:    vec![false],
+            mutation_counts:  vec![MutationCount::default().name()],
+            map_size:         0,
+            taint_chance:     0.0,
+            taint_cost:       10,
+            solver_period:    0,
+            dedup:            "exact".into(),
+            dedup_bucket_size: 4,
+            instrumented_modules: Vec::new(),
+            entry_points:     vec![0],
+            registry:         Registry::default(),
+            seed:             None,
+            trial:            None,
+            threads:          physical_cores(),
+            resume:           false,
+        }
+    }
+}
+
+/// Number of physical cores of the machine, which is the default number of
+/// [`Params::threads`]. Hyperthreads don't speed up the simulator much, so
+/// they are not counted where the topology is known.
+pub fn physical_cores() -> usize {
+    let logical = std::thread::available_parallelism()
+        .map(|x| x.get()).unwrap_or(1);
+
+    // Count the distinct (package, core) pairs listed by Linux
+    let cpuinfo = match std::fs::read_to_string("/proc/cpuinfo") {
+        Ok(cpuinfo) => cpuinfo,
+        Err(_)      => return logical,
+    };
+    let mut cores = BTreeSet::new();
+    let mut package = None;
+    for line in cpuinfo.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        match key {
+            "physical id" => package = Some(value.to_string()),
+            "core id"     => { cores.insert((package.clone(), value)); }
+            _ => {}
+        }
+    }
+
+    // Don't use more threads than we're allowed to run on
+    if cores.is_empty() { logical } else { cores.len().min(logical) }
+}
+
+/// A single datapoint of a sweep, which is run `averages` times. Datapoints
+/// are ordered by their fields, in declaration order.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Datapoint {
+    /// Index of the strategies used, into [`Params::strategies`]
+    pub strategy: usize,
+
+    /// Whether the fuzzer is coverage guided
+    pub guided: bool,
+
+    /// Whether the workers share their input databases
+    pub shared_inputs: bool,
+
+    /// Whether the workers share their coverage databases
+    pub shared_results: bool,
+
+    /// Number of simulated workers
+    pub workers: usize,
+}
+
+impl Datapoint {
+    /// Seed of the RNG streams of this datapoint of a sweep seeded with
+    /// `seed`, run with `time_constraint`. Trial `n` of the datapoint uses
+    /// stream `n` of this seed.
+    pub fn seed(&self, seed: u64, time_constraint: Option<f64>) -> u64 {
+        [self.strategy as u64, self.guided as u64, self.shared_inputs as u64,
+         self.shared_results as u64, self.workers as u64,
+         time_constraint.map_or(u64::MAX, f64::to_bits)]
+            .iter().fold(seed, |seed, &x| rng::derive(seed, x))
+    }
+
+    /// Name of the result file this datapoint is recorded in, given the
+    /// `strategies` of the sweep
+    pub fn filename(&self, params: &Params, strategies: &[Strategies])
+            -> String {
+        let strategy_name = if strategies.len() > 1 {
+            format!("{}_", strategies[self.strategy].name)
+        } else {
+            String::new()
+        };
+        format!("{}{}coverage_{}_inputshare_{}_resultshare_{}.txt",
+            params.output_prefix, strategy_name, self.guided,
+            self.shared_inputs, self.shared_results)
+    }
+}
+
+/// All datapoints of a [`run`] comparing `num_strategies` strategies, in
+/// ascending order
+pub fn datapoints(params: &Params, num_strategies: usize) -> Vec<Datapoint> {
+    let mut todo = BTreeSet::new();
+    for strategy in 0..num_strategies {
+        for &shared_inputs in &params.shared_inputs {
+            for &shared_results in &params.shared_results {
+                for &guided in &params.guided {
+                    for x in (1..=params.max_x_resolution).step_by(1) {
+                        let num_workers = if false {
+                            let expbase = (params.max_cores as f64)
+                                .powf(1. / params.max_x_resolution as f64);
+                            expbase.powf(x as f64)
+                        } else {
+                            (x as f64 / params.max_x_resolution as f64) *
+                                params.max_cores as f64
+                        } as usize;
+
+                        // Small core counts round down to zero workers
+                        let workers = num_workers.max(1);
+                        todo.insert(Datapoint {
+                            strategy, guided, shared_inputs, shared_results,
+                            workers,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    todo.into_iter().collect()
+}
+
+/// The time constraints of the sweeps run by [`heatmap`], in ascending order
+pub fn time_constraints(params: &Params) -> Vec<f64> {
+    (1..=params.max_y_resolution).map(|timeout| {
+        if false {
+            let expbase = 2f64.powf(1.0 / params.max_y_resolution as f64);
+            expbase.powf(timeout as f64) - 1.
+        } else {
+            (timeout as f64 / params.max_y_resolution as f64) *
+                params.max_y_point
+        }
+    }).collect()
+}
+
+/// What the results of a [`run`] with `time_constraint` measure, for the
+/// result file header
+pub fn describe(time_constraint: Option<f64>) -> String {
+    match time_constraint {
+        Some(time) => format!("coverage found within {} uptime", time),
+        None       => "uptime to find all coverage".into(),
+    }
+}
+
+/// Trials further than this many standard deviations from the mean of their
+/// datapoint are reported as outliers
+const OUTLIER_SIGMAS: f64 = 3.0;
+
+/// Sweep the number of workers for each fuzzer configuration, writing the
+/// `(workers, mean, stddev, exhausted)` results to a file per configuration.
+/// Outlier trials are logged along with their index and the sweep seed, which
+/// reproduce them with [`Params::trial`].
+///
+/// With a `time_constraint` the results are the amount of coverage found in
+/// the time budget, otherwise they are the uptime needed to find all
+/// coverage.
+pub fn run<T: Target + ?Sized>(target: &T, params: &Params,
+                               time_constraint: Option<f64>)
+        -> io::Result<()> {
+    // Resolve the strategies to compare, and the blocks they see
+    let strategies = params.strategies()?;
+    let instrumented = params.instrumented(target)?;
+    let entries = params.entries(target)?;
+    let crash_buckets = params.crash_buckets(target)?;
+    if let Some(buckets) = &crash_buckets {
+        verbose!("sweep: {} crashes deduplicated into {} buckets",
+                 buckets.len(), buckets.iter().max().map_or(0, |x| x + 1));
+    }
+    if params.map_size != 0 {
+        verbose!("sweep: coverage map of {} entries, {} of {} blocks share \
+                  an entry", params.map_size,
+                 map_collisions(target.num_coverage(), params.map_size),
+                 target.num_coverage());
+    }
+    params.check_guards(target)?;
+
+    // Every trial derives its RNG from the seed, pick one if there is none
+    let seed = params.seed.unwrap_or_else(rng::random_seed);
+    verbose!("sweep: seed {}", seed);
+
+    // Generate a list of things to do
+    let mut todo = datapoints(params, strategies.len());
+    let total = todo.len();
+    match time_constraint {
+        Some(time) => info!("sweep: {} datapoints, time constraint {}",
+                            total, time),
+        None => info!("sweep: {} datapoints", total),
+    }
+
+    // The results which map filenames to records which can be sorted before
+    // writing to a file
+    let mut results = BTreeMap::new();
+
+    // Pick up the records of an earlier run, skipping their datapoints
+    if params.resume {
+        results = resume(params, &strategies, &mut todo)?;
+        info!("sweep: resuming, {} of {} datapoints already done",
+              total - todo.len(), total);
+    }
+
+    // Datapoints are weighed by the fuzz cases they take, which grow with
+    // the number of workers
+    let weight = |point: &Datapoint| {
+        (point.workers * params.trials().len()) as f64
+    };
+    let progress = Mutex::new(Progress::new("sweep",
+        todo.iter().map(weight)));
+
+    // Wrap up the todo in a mutex so we can share it between workers
+    let todo = Mutex::new(todo);
+    let results = Mutex::new(results);
+
+    std::thread::scope(|scope| {
+        for _ in 0..params.threads.max(1) {
+            scope.spawn(|| {
+                // Get some work to do, until there is no more work or we
+                // were interrupted
+                loop {
+                    // Pop in a statement of its own, so the lock is released
+                    // before the work runs
+                    let work = todo.lock().unwrap().pop();
+                    let Some(point) = work else {
+                        break;
+                    };
+
+                    if interrupt::interrupted() {
+                        break;
+                    }
+
+                    let workers = point.workers;
+                    let mut fuzzer = Fuzzer::with_strategies(target,
+                        &strategies[point.strategy]);
+                    fuzzer.coverage_guided = point.guided;
+                    fuzzer.shared_inputs   = point.shared_inputs;
+                    fuzzer.shared_results  = point.shared_results;
+                    fuzzer.workers         = workers;
+                    fuzzer.time_constraint = time_constraint;
+                    fuzzer.instrumented    = instrumented.clone();
+                    fuzzer.entries         = entries.clone();
+                    fuzzer.map_size        = params.map_size;
+                    fuzzer.taint_chance    = params.taint_chance;
+                    fuzzer.taint_cost      = params.taint_cost;
+                    fuzzer.solver_period   = params.solver_period;
+                    fuzzer.crash_buckets   = crash_buckets.clone();
+                    let point_seed = point.seed(seed, time_constraint);
+                    let it = Instant::now();
+
+                    // Generate the filename we're going to use for this data
+                    // point.
+                    let fname = point.filename(params, &strategies);
+
+                    // Track if any of the tests found all possible coverage
+                    // during a time constrained mode. This will indicate that
+                    // the data is invalid and should not be used.
+                    let mut exhaust = false;
+
+                    // Run the worker multiple times, generating the averages
+                    let mut stats = Stats::new();
+                    let mut crashes = Stats::new();
+                    let mut samples = Vec::new();
+                    for trial in params.trials() {
+                        // Run the fuzz case!
+                        fuzzer.reseed(rng::derive(point_seed, trial as u64)
+                                      as usize);
+                        let tmp = fuzzer.start();
+
+                        let elapsed = (Instant::now() - it).as_secs_f64();
+                        trace!("sweep: trial done, {:10.0} fuzz cases/second, \
+                                {} corpus entries, {} crashes",
+                               fuzzer.fuzz_cases as f64 / elapsed,
+                               fuzzer.corpus_len(), fuzzer.crashes_found());
+
+                        let ret = if time_constraint.is_some() {
+                            match tmp {
+                                // We ran out of coverage to gain, stop early
+                                Ok(_) => {
+                                    exhaust = true;
+                                    break;
+                                }
+
+                                // Get the number of coverage records at the
+                                // timeout
+                                Err(coverage) => coverage as f64,
+                            }
+                        } else {
+                            // Get the time it took to get full coverage
+                            tmp.unwrap()
+                        };
+
+                        stats.push(ret);
+                        crashes.push(fuzzer.crashes_found() as f64);
+                        samples.push((trial, ret));
+                    }
+
+                    progress.lock().unwrap().complete(weight(&point),
+                        samples.len() as u64, fuzzer.fuzz_cases);
+
+                    // Exhausted by its first trial, there's no result to
+                    // record
+                    if samples.is_empty() {
+                        verbose!("sweep: {} workers {:6}: exhausted before \
+                                  any trial finished, not recorded",
+                                 fname, workers);
+                        continue;
+                    }
+
+                    // Report the trials far from the rest
+                    let (mean, stddev) = (stats.mean(), stats.stddev());
+                    for &(trial, ret) in &samples {
+                        if (ret - mean).abs() > OUTLIER_SIGMAS * stddev {
+                            verbose!("sweep: {} workers {:6}: trial {} is an \
+                                      outlier, {:.4} ({:+.1} sigma), rerun \
+                                      with --seed {} --trial {}",
+                                     fname, workers, trial, ret,
+                                     (ret - mean) / stddev, seed, trial);
+                        }
+                    }
+
+                    verbose!("sweep: {} workers {:6}: {:.4} +- {:.4}, {:.2} \
+                              crashes{}",
+                             fname, workers, stats.mean(), stats.stddev(),
+                             crashes.mean(),
+                             if exhaust { " (exhausted)" } else { "" });
+
+                    // Record the results
+                    results.lock().unwrap().entry(fname)
+                        .or_insert_with(Vec::new)
+                        .push(Record {
+                            workers,
+                            mean:      stats.mean(),
+                            stddev:    stats.stddev(),
+                            exhausted: exhaust,
+                            crashes:   crashes.mean(),
+                        });
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+
+    // Sort and log the results, marking them if the sweep didn't finish
+    let truncated = interrupt::interrupted();
+    for (filename, records) in results.iter_mut() {
+        records.sort_by_key(|x| x.workers);
+        let mut file = File::create(params.output_dir.join(filename))?;
+        fuzztheory_analysis::write_header(&mut file,
+                                          &describe(time_constraint))?;
+        fuzztheory_analysis::write_records(&mut file, records)?;
+        if truncated {
+            writeln!(file, "{}", fuzztheory_analysis::TRUNCATED_MARKER)?;
+        }
+    }
+
+    if truncated {
+        return Err(io::Error::new(io::ErrorKind::Interrupted,
+            "interrupted, wrote truncated results"));
+    }
+
+    Ok(())
+}
+
+/// Load the records of the result files `points` are written to which
+/// already exist, and remove the datapoints they already hold from `points`.
+/// Files which don't exist yet are skipped.
+fn resume(params: &Params, strategies: &[Strategies],
+          points: &mut Vec<Datapoint>)
+        -> io::Result<BTreeMap<String, Vec<Record>>> {
+    let mut results = BTreeMap::new();
+    for point in points.iter() {
+        let fname = point.filename(params, strategies);
+        if results.contains_key(&fname) {
+            continue;
+        }
+
+        let path = params.output_dir.join(&fname);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        let records = fuzztheory_analysis::parse_records(&contents)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err)))?;
+        results.insert(fname, records);
+    }
+
+    points.retain(|point| {
+        !results.get(&point.filename(params, strategies))
+            .is_some_and(|records: &Vec<Record>| {
+                records.iter().any(|x| x.workers == point.workers)
+            })
+    });
+    Ok(results)
+}
+
+/// Parameters of row `row` of a [`heatmap`], whose results are written to
+/// their own files
+pub fn heatmap_row(params: &Params, row: usize) -> Params {
+    Params {
+        output_prefix: format!("{}heatmap_{:03}_", params.output_prefix, row),
+        ..params.clone()
+    }
+}
+
+/// Path of the file recording the completed rows of a [`heatmap`]
+pub fn checkpoint_path(params: &Params) -> PathBuf {
+    params.output_dir.join(format!("{}heatmap_checkpoint.txt",
+                                   params.output_prefix))
+}
+
+/// Rows of a [`heatmap`] with time constraints `timeouts` recorded as
+/// complete in its checkpoint
+fn completed_rows(params: &Params, timeouts: &[f64])
+        -> io::Result<BTreeSet<usize>> {
+    let path = checkpoint_path(params);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    let mut rows = BTreeSet::new();
+    for (lineno, line) in contents.lines().enumerate() {
+        let err = |msg: String| io::Error::new(io::ErrorKind::InvalidData,
+            format!("{}: line {}: {}", path.display(), lineno + 1, msg));
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let (row, timeout) = match fields[..] {
+            [row, timeout] => (row.parse::<usize>().ok(),
+                               timeout.parse::<f64>().ok()),
+            _ => (None, None),
+        };
+        let (row, timeout) = row.zip(timeout).ok_or_else(|| {
+            err(format!("malformed checkpoint `{}`", line))
+        })?;
+
+        // Make sure the checkpoint is from a heatmap with the same rows
+        if timeouts.get(row) != Some(&timeout) {
+            return Err(err(format!("row {} was run with time constraint {}, \
+                                    which is not part of this heatmap",
+                                   row, timeout)));
+        }
+        rows.insert(row);
+    }
+    Ok(rows)
+}
+
+/// Run a time constrained [`run`] for each of the `max_y_resolution` time
+/// constraints up to `max_y_point`, one row of the heatmap each. Every
+/// completed row is checkpointed, and with `resume` the heatmap picks up
+/// after the rows which were completed.
+pub fn heatmap<T: Target + ?Sized>(target: &T, params: &Params)
+        -> io::Result<()> {
+    let timeouts = time_constraints(params);
+    let path = checkpoint_path(params);
+
+    let done = if params.resume {
+        completed_rows(params, &timeouts)?
+    } else {
+        std::fs::write(&path, "")?;
+        BTreeSet::new()
+    };
+    if !done.is_empty() {
+        info!("heatmap: resuming, {} of {} rows already done", done.len(),
+              timeouts.len());
+    }
+
+    for (row, &timeout) in timeouts.iter().enumerate() {
+        if done.contains(&row) {
+            continue;
+        }
+
+        run(target, &heatmap_row(params, row), Some(timeout))?;
+
+        // Checkpoint the row
+        let mut checkpoint = std::fs::OpenOptions::new()
+            .create(true).append(true).open(&path)?;
+        writeln!(checkpoint, "{} {}", row, timeout)?;
+    }
+
+    Ok(())
+}
+
+/// The [`run`]s making up the harness `mode` (`sweep` or `heatmap`), as the
+/// parameters and time constraint of each. With `resume`, completed
+/// [`heatmap`] rows are left out.
+pub fn runs(mode: &str, params: &Params)
+        -> io::Result<Vec<(Params, Option<f64>)>> {
+    match mode {
+        "sweep"   => Ok(vec![(params.clone(), params.time_constraint)]),
+        "heatmap" => {
+            let timeouts = time_constraints(params);
+            let done = if params.resume {
+                completed_rows(params, &timeouts)?
+            } else {
+                BTreeSet::new()
+            };
+            Ok(timeouts.iter().enumerate()
+                .filter(|(row, _)| !done.contains(row))
+                .map(|(row, &timeout)| {
+                    (heatmap_row(params, row), Some(timeout))
+                }).collect())
+        }
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+            format!("{:?} is not a sweep", mode))),
+    }
+}
+
+/// Print every datapoint the harness `mode` (`sweep` or `heatmap`) would run
+/// with `params`, along with an upper bound on the number of fuzz cases it
+/// would take, without running anything
+pub fn dry_run(mode: &str, params: &Params) -> io::Result<()> {
+    let params = &params.prepare();
+    let strategies = params.strategies()?;
+
+    // The strategy column fits the longest name, like `replace_edge-hits`
+    let width = strategies.iter().map(|x| x.name.len()).max().unwrap_or(0)
+        .max(12);
+    println!("{:>width$} {:>6} {:>11} {:>12} {:>8} {:>10} {:>16}",
+             "strategy", "guided", "inputshare", "resultshare", "workers",
+             "timeout", "fuzz cases");
+
+    // A time constrained trial runs at most `timeout` uptime on every worker,
+    // unconstrained ones run until all coverage is found
+    let mut total = Some(0u64);
+    let mut count = 0;
+    for (params, timeout) in runs(mode, params)? {
+        let mut points = datapoints(&params, strategies.len());
+        if params.resume {
+            resume(&params, &strategies, &mut points)?;
+        }
+        count += points.len();
+
+        for point in &points {
+            let cases = timeout.map(|timeout| {
+                (timeout * point.workers as f64).ceil() as u64 *
+                    params.trials().len() as u64
+            });
+            total = total.zip(cases).map(|(total, cases)| total + cases);
+
+            println!("{:>width$} {:>6} {:>11} {:>12} {:>8} {:>10} {:>16}",
+                     strategies[point.strategy].name, point.guided,
+                     point.shared_inputs, point.shared_results,
+                     point.workers,
+                     timeout.map_or("none".into(), |x| x.to_string()),
+                     cases.map_or("unbounded".into(), |x| x.to_string()));
+        }
+    }
+
+    let trials = count as u64 * params.trials().len() as u64;
+    match total {
+        Some(total) => println!("{} datapoints, {} trials, at most {} fuzz \
+                                 cases", count, trials, total),
+        None => println!("{} datapoints, {} trials, fuzz cases unbounded \
+                          without a time constraint", count, trials),
+    }
+
+    Ok(())
+}
+
+/// Repeatedly run single worker trials with the first of the strategies in
+/// `params`, printing the fuzz case throughput of the simulator. Runs until
+/// interrupted.
+pub fn perf<T: Target + ?Sized>(target: &T, params: &Params)
+        -> io::Result<()> {
+    let strategies = params.strategies()?;
+    let strategy = strategies.first().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "no mutator given")
+    })?;
+    let mut fuzzer = Fuzzer::with_strategies(target, strategy);
+    fuzzer.instrumented  = params.instrumented(target)?;
+    fuzzer.entries       = params.entries(target)?;
+    fuzzer.map_size      = params.map_size;
+    fuzzer.taint_chance  = params.taint_chance;
+    fuzzer.taint_cost    = params.taint_cost;
+    fuzzer.solver_period = params.solver_period;
+    fuzzer.crash_buckets = params.crash_buckets(target)?;
+    params.check_guards(target)?;
+    if let Some(seed) = params.seed {
+        fuzzer.reseed(seed as usize);
+    }
+
+    let it = Instant::now();
+    while !interrupt::interrupted() {
+        fuzzer.coverage_guided = true;
+        fuzzer.shared_inputs   = false;
+        fuzzer.shared_results  = false;
+        fuzzer.workers         = 1;
+        let _ = fuzzer.start();
+
+        let elapsed = (Instant::now() - it).as_secs_f64();
+        println!("{:12.2} fuzz cases/second",
+                 fuzzer.fuzz_cases as f64 / elapsed);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Target reaching its only block on every input, slowly enough for the
+    /// datapoints on other threads to overlap with it, recording the most
+    /// runs which did
+    #[derive(Default)]
+    struct Overlap {
+        running: AtomicUsize,
+        most:    AtomicUsize,
+    }
+
+    impl Target for Overlap {
+        fn input_size(&self)   -> usize { 4 }
+        fn num_coverage(&self) -> usize { 1 }
+        fn num_crashes(&self)  -> usize { 0 }
+
+        fn run(&self, _input: &[u8], trace: &mut Vec<u32>) -> Option<u32> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(running, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(2));
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            trace.push(0);
+            None
+        }
+    }
+
+    /// Parameters of a small sweep writing to a fresh directory for `test`
+    fn params(test: &str) -> Params {
+        let output_dir = std::env::temp_dir().join(format!(
+            "fuzztheory-sweep-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&output_dir);
+        std::fs::create_dir_all(&output_dir).unwrap();
+        Params {
+            max_cores:        4,
+            max_x_resolution: 4,
+            averages:         2,
+            threads:          4,
+            seed:             Some(1),
+            output_dir,
+            ..Params::default()
+        }
+    }
+
+    #[test]
+    fn datapoints_run_in_parallel() {
+        let target = Overlap::default();
+        let params = params("parallel");
+        run(&target, &params, None).unwrap();
+        assert!(target.most.load(Ordering::SeqCst) > 1);
+        std::fs::remove_dir_all(&params.output_dir).unwrap();
+    }
+
+    #[test]
+    fn exhausted_datapoints_are_not_recorded() {
+        // Every datapoint reaches full coverage long before the constraint,
+        // in its first trial
+        let target = Overlap::default();
+        let params = params("exhausted");
+        run(&target, &params, Some(1e9)).unwrap();
+        assert_eq!(std::fs::read_dir(&params.output_dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&params.output_dir).unwrap();
+    }
+
+    #[test]
+    fn havoc_is_swept_with_one_mutation_count() {
+        let params = Params {
+            mutators:        vec!["replace".into(), "havoc".into(),
+                                  "mopt".into()],
+            mutation_counts: vec!["fixed-1".into(), "uniform-8".into()],
+            ..Params::default()
+        };
+        let names: Vec<String> = params.strategies().unwrap().into_iter()
+            .map(|x| x.name).collect();
+        assert_eq!(names, ["replace_fixed-1", "replace_uniform-8", "havoc",
+                           "mopt"]);
+    }
+}
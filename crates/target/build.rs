@@ -0,0 +1,49 @@
+//! Pull the generated program named by `FUZZTHEORY_PROGRAM` and its metadata
+//! sidecar into the build
+//!
+//! Without it a single block placeholder program is built instead, so the
+//! workspace builds before anything has been generated.
+
+use std::path::{Path, PathBuf};
+
+/// Program used when `FUZZTHEORY_PROGRAM` is not set
+const PLACEHOLDER: &str = "
+#[inline(never)] pub fn crashme<F: FnMut(u32)>(_input: &[u8], _hit: &mut F) -> Option<u32> {
+    _hit(0);
+    None
+}
+";
+
+/// Metadata of [`PLACEHOLDER`]
+const PLACEHOLDER_METADATA: &str = "{\"blocks\":1,\"crashes\":0,\"input_bytes\":1}";
+
+/// Read `path`, marking it as an input of the build
+fn read(path: &Path) -> String {
+    println!("cargo:rerun-if-changed={}", path.display());
+    std::fs::read_to_string(path).unwrap_or_else(|err| {
+        panic!("failed to read {}: {}", path.display(), err)
+    })
+}
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=FUZZTHEORY_PROGRAM");
+
+    let out = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+
+    let (program, metadata) = match std::env::var_os("FUZZTHEORY_PROGRAM") {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            (read(&path), read(&path.with_extension("json")))
+        }
+        None => (PLACEHOLDER.into(), PLACEHOLDER_METADATA.into()),
+    };
+
+    // Programs with several entry points can run any of them
+    println!("cargo:rustc-check-cfg=cfg(fuzztheory_entry_points)");
+    if program.contains("fn crashme_entry") {
+        println!("cargo:rustc-cfg=fuzztheory_entry_points");
+    }
+
+    std::fs::write(out.join("program.rs"), program).unwrap();
+    std::fs::write(out.join("program.json"), metadata).unwrap();
+}
@@ -0,0 +1,104 @@
+//! A generated program, built into a harness
+//!
+//! `build.rs` includes the program source and its metadata. The metadata is
+//! only parsed at runtime, which this wraps up as a [`FnTarget`] for the
+//! harness binary, and exports with the C ABI described in
+//! `fuzztheory_sim::dylib` for the `cdylib` build.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::sync::OnceLock;
+
+use fuzztheory_sim::{FnTarget, Metadata};
+
+mod program {
+    include!(concat!(env!("OUT_DIR"), "/program.rs"));
+}
+
+/// Metadata JSON of the program, see [`Metadata`]
+pub const METADATA: &str = include_str!(concat!(env!("OUT_DIR"),
+                                                "/program.json"));
+
+/// The parsed [`METADATA`]
+pub fn metadata() -> &'static Metadata {
+    static METADATA_PARSED: OnceLock<Metadata> = OnceLock::new();
+    METADATA_PARSED.get_or_init(|| {
+        Metadata::parse(METADATA).expect("invalid embedded metadata")
+    })
+}
+
+/// Run the program on `input`, appending every block reached to `trace` and
+/// returning the crash it triggered, if any
+fn run(input: &[u8], trace: &mut Vec<u32>) -> Option<u32> {
+    program::crashme(input, &mut |block| trace.push(block))
+}
+
+/// Run the program from entry point `entry`. `build.rs` sets
+/// `fuzztheory_entry_points` for programs with several of them.
+#[cfg(fuzztheory_entry_points)]
+fn crashme_entry<F: FnMut(u32)>(entry: usize, input: &[u8], hit: &mut F)
+        -> Option<u32> {
+    program::crashme_entry(entry, input, hit)
+}
+
+/// Run the program from its only entry point
+#[cfg(not(fuzztheory_entry_points))]
+fn crashme_entry<F: FnMut(u32)>(_entry: usize, input: &[u8], hit: &mut F)
+        -> Option<u32> {
+    program::crashme(input, hit)
+}
+
+/// Run the program from entry point `entry` like [`run`]
+fn run_entry(entry: usize, input: &[u8], trace: &mut Vec<u32>)
+        -> Option<u32> {
+    crashme_entry(entry, input, &mut |block| trace.push(block))
+}
+
+/// The program as a [`fuzztheory_sim::Target`]
+pub fn target() -> FnTarget {
+    let metadata = metadata();
+    FnTarget {
+        input_size:   metadata.input_size,
+        variable_length: metadata.variable_length,
+        num_coverage: metadata.num_coverage,
+        num_crashes:  metadata.num_crashes,
+        run,
+        entry_points: 1 + metadata.entry_points.len(),
+        run_entry,
+        info:         Some(&metadata.info),
+    }
+}
+
+/// # Safety
+///
+/// `input` must point to `len` readable bytes, which is the input size in the
+/// metadata unless the program takes variable length inputs, and `hit` must
+/// be safe to call with `ctx`
+#[no_mangle]
+pub unsafe extern "C" fn fuzztheory_run(input: *const u8, len: usize,
+        hit: unsafe extern "C" fn(*mut c_void, u32), ctx: *mut c_void)
+        -> i64 {
+    let input = std::slice::from_raw_parts(input, len);
+    program::crashme(input, &mut |block| hit(ctx, block))
+        .map_or(-1, i64::from)
+}
+
+/// # Safety
+///
+/// Like [`fuzztheory_run`], and `entry` must be below the number of entry
+/// points in the metadata
+#[no_mangle]
+pub unsafe extern "C" fn fuzztheory_run_entry(entry: usize, input: *const u8,
+        len: usize, hit: unsafe extern "C" fn(*mut c_void, u32),
+        ctx: *mut c_void) -> i64 {
+    let input = std::slice::from_raw_parts(input, len);
+    crashme_entry(entry, input, &mut |block| hit(ctx, block))
+        .map_or(-1, i64::from)
+}
+
+#[no_mangle]
+pub extern "C" fn fuzztheory_info() -> *const c_char {
+    // Built once so the pointer stays valid for the life of the library
+    static INFO: OnceLock<CString> = OnceLock::new();
+    INFO.get_or_init(|| CString::new(METADATA).unwrap()).as_ptr()
+}
@@ -0,0 +1,5 @@
+//! Harness binary for the generated program in the `fuzztheory-target` lib
+
+fn main() -> std::io::Result<()> {
+    fuzztheory_sim::harness::main(&fuzztheory_target::target())
+}
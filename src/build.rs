@@ -0,0 +1,376 @@
+//! Building harnesses for generated programs
+//!
+//! A generated program is compiled by building the `fuzztheory-target` crate
+//! with cargo, which pulls in the program named by `FUZZTHEORY_PROGRAM`. The
+//! `[build]` table picks the cargo to run, the profile and target to build
+//! with, extra flags for `rustc`, and where the harness binary goes.
+//!
+//! Building takes far longer than generating, and the same program is often
+//! generated again, as by every run with the same seed. Built harnesses are
+//! cached in the target directory, keyed by a hash of the program and the
+//! build parameters, and reused instead of building them again.
+//!
+//! [`write_crate`] writes a program out as a standalone crate instead, the
+//! `fuzztheory-target` crate with the program in it, which builds with plain
+//! `cargo build` anywhere the `fuzztheory-sim` crate can be found.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use fuzztheory_proggen::{fnv1a, verbose};
+
+use crate::sim;
+
+/// Parameters of harness builds, the `[build]` table
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// Cargo to build with. Empty uses the cargo which built the driver if
+    /// it runs under it, and `cargo` from the `PATH` otherwise.
+    pub cargo: String,
+
+    /// Cargo profile to build with, like `release` or `dev`
+    pub profile: String,
+
+    /// Target triple to build for. Empty builds for the host.
+    pub target: String,
+
+    /// Extra flags for `rustc`, like `-C target-cpu=native`. They are passed
+    /// to cargo in `CARGO_ENCODED_RUSTFLAGS`, so they apply to the crates
+    /// the harness depends on too. Empty leaves `RUSTFLAGS` alone.
+    pub rustflags: Vec<String>,
+
+    /// Path the harness binary is copied to, unless `--binary` is given
+    pub binary: PathBuf,
+
+    /// Reuse harnesses built before for the same program and parameters
+    pub cache: bool,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            cargo:     String::new(),
+            profile:   "release".into(),
+            target:    String::new(),
+            rustflags: Vec::new(),
+            binary:    PathBuf::from("./test"),
+            cache:     true,
+        }
+    }
+}
+
+/// Root of the workspace containing the `fuzztheory-target` crate
+fn workspace_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Name of the directory in the target directory holding cached harnesses
+const CACHE_DIR: &str = "fuzztheory-harnesses";
+
+/// Key of the harness `params` build from the program at `source`, hashing
+/// everything which goes into the build: the program and its metadata, the
+/// build parameters, and the driver, whose `fuzztheory-target` crate it is
+/// built with
+fn cache_key(params: &Params, source: &Path, cargo: &std::ffi::OsStr)
+        -> io::Result<u64> {
+    let mut hash = 0xcbf29ce484222325;
+    hash = fnv1a(hash, &std::fs::read(source)?);
+    hash = fnv1a(hash, &std::fs::read(sim::metadata::sidecar(source))?);
+
+    // A rebuilt driver may come with a changed target crate
+    let driver = std::env::current_exe()
+        .and_then(|x| x.metadata())
+        .and_then(|x| x.modified())
+        .ok();
+    let fields = [
+        format!("{:?}", cargo),
+        params.profile.clone(),
+        params.target.clone(),
+        params.rustflags.join("\x1f"),
+        format!("{} {:?}", env!("CARGO_PKG_VERSION"), driver),
+    ];
+    for field in &fields {
+        hash = fnv1a(hash, field.as_bytes());
+        hash = fnv1a(hash, &[0]);
+    }
+    Ok(hash)
+}
+
+/// Build the harness binary for the program source at `source` with the
+/// `fuzztheory-target` crate as `params` say, and copy it to `binary`. The
+/// program's metadata [`sidecar`](sim::metadata::sidecar) must be next to
+/// `source`. If `dylib` is given, the dynamic library build of the program
+/// and its sidecar are copied there too.
+///
+/// If [`Params::cache`] is set and a harness was built for the same program
+/// and parameters before, it is copied out of the cache instead.
+///
+/// Compiler diagnostics are captured and returned as the error on failure.
+pub fn harness(params: &Params, source: &Path, binary: &Path,
+               dylib: Option<&Path>) -> io::Result<()> {
+    let root = workspace_root();
+
+    // Build in the workspace's target directory, honoring an override
+    let target_dir = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join("target"));
+
+    // Prefer the cargo which built the driver, if we're running under it
+    let cargo = if params.cargo.is_empty() {
+        std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into())
+    } else {
+        params.cargo.clone().into()
+    };
+
+    // Look for a harness built before
+    let cached = if params.cache {
+        let key = cache_key(params, source, &cargo)?;
+        Some(target_dir.join(CACHE_DIR).join(format!("{:016x}", key)))
+    } else {
+        None
+    };
+    match &cached {
+        Some(dir) if dir.is_dir() => {
+            verbose!("Reusing cached harness {}", dir.display());
+            return copy_artifacts(dir, source, binary, dylib);
+        }
+        _ => {}
+    }
+
+    let mut command = Command::new(&cargo);
+    command.current_dir(&root)
+        .env("FUZZTHEORY_PROGRAM", std::fs::canonicalize(source)?)
+        .arg("build")
+        .arg("--profile").arg(&params.profile)
+        .arg("--package").arg("fuzztheory-target")
+        .arg("--target-dir").arg(&target_dir);
+    if !params.target.is_empty() {
+        command.arg("--target").arg(&params.target);
+    }
+    if !params.rustflags.is_empty() {
+        command.env("CARGO_ENCODED_RUSTFLAGS", params.rustflags.join("\x1f"));
+    }
+    let output = command.output().map_err(|err| {
+        io::Error::new(err.kind(), format!("failed to run {}: {}",
+                                           Path::new(&cargo).display(), err))
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "failed to build harness for {}:\n{}", source.display(),
+            String::from_utf8_lossy(&output.stderr).trim_end())));
+    }
+
+    // Artifacts of cross builds go in a directory named after the target,
+    // and those of the built-in `dev` and `test` profiles in `debug`
+    let mut built_dir = target_dir;
+    if !params.target.is_empty() {
+        built_dir.push(&params.target);
+    }
+    built_dir.push(match params.profile.as_str() {
+        "dev" | "test" => "debug",
+        "bench"        => "release",
+        profile        => profile,
+    });
+
+    // Cache both artifacts, whichever are asked for now
+    if let Some(dir) = cached {
+        cache_artifacts(&built_dir, &dir)?;
+        built_dir = dir;
+    }
+
+    copy_artifacts(&built_dir, source, binary, dylib)
+}
+
+/// Copy the artifacts built in `built_dir` to the cache directory `dir`.
+/// They are copied to a temporary directory of this process first, so an
+/// interrupted copy is never reused, and processes building the same harness
+/// at once don't copy over each other. The first to finish wins the race to
+/// the cache, and the others use its copy.
+fn cache_artifacts(built_dir: &Path, dir: &Path) -> io::Result<()> {
+    let partial = dir.with_extension(
+        format!("partial-{}", std::process::id()));
+    std::fs::create_dir_all(&partial)?;
+    for name in artifacts() {
+        std::fs::copy(built_dir.join(&name), partial.join(&name))?;
+    }
+    if let Err(err) = std::fs::rename(&partial, dir) {
+        if !dir.is_dir() {
+            return Err(err);
+        }
+        std::fs::remove_dir_all(&partial)?;
+    }
+    Ok(())
+}
+
+/// File names of the harness binary and dynamic library build artifacts
+fn artifacts() -> [String; 2] {
+    use std::env::consts::{DLL_PREFIX, DLL_SUFFIX, EXE_SUFFIX};
+    [format!("fuzztheory-target{}", EXE_SUFFIX),
+     format!("{}fuzztheory_target{}", DLL_PREFIX, DLL_SUFFIX)]
+}
+
+/// Copy the harness binary built in `dir` to `binary`, and the dynamic
+/// library to `dylib` along with the sidecar of the program at `source`
+fn copy_artifacts(dir: &Path, source: &Path, binary: &Path,
+                  dylib: Option<&Path>) -> io::Result<()> {
+    // Copy a build artifact out of `dir`
+    let copy = |name: String, to: &Path| {
+        let built = dir.join(name);
+        std::fs::copy(&built, to).map(|_| ()).map_err(|err| {
+            io::Error::new(err.kind(), format!("failed to copy {} to {}: {}",
+                built.display(), to.display(), err))
+        })
+    };
+
+    let [binary_name, dylib_name] = artifacts();
+    copy(binary_name, binary)?;
+    if let Some(dylib) = dylib {
+        copy(dylib_name, dylib)?;
+        std::fs::copy(sim::metadata::sidecar(source),
+                      sim::metadata::sidecar(dylib))?;
+    }
+
+    Ok(())
+}
+
+/// Build script of crates written by [`write_crate`], pulling the program and
+/// its metadata next to the manifest into the build where the
+/// `fuzztheory-target` sources expect them
+const CRATE_BUILD_SCRIPT: &str = "\
+//! Pull the program and its metadata sidecar into the build
+
+use std::path::PathBuf;
+
+fn main() {
+    let out = PathBuf::from(std::env::var_os(\"OUT_DIR\").unwrap());
+    for name in [\"program.rs\", \"program.json\"] {
+        println!(\"cargo:rerun-if-changed={}\", name);
+        std::fs::copy(name, out.join(name)).unwrap_or_else(|err| {
+            panic!(\"failed to copy {}: {}\", name, err)
+        });
+    }
+
+    // Programs with several entry points can run any of them
+    println!(\"cargo:rustc-check-cfg=cfg(fuzztheory_entry_points)\");
+    let program = std::fs::read_to_string(\"program.rs\").unwrap();
+    if program.contains(\"fn crashme_entry\") {
+        println!(\"cargo:rustc-cfg=fuzztheory_entry_points\");
+    }
+}
+";
+
+/// Write the program at `source` as a standalone cargo crate to the
+/// directory `dir`, named after it. The program, its metadata, ground truth,
+/// and statistics go next to the manifest, and the sources are those of the
+/// `fuzztheory-target` crate, so the crate builds the same harness binary
+/// and dynamic library. It depends on `fuzztheory-sim` in this workspace by
+/// path.
+pub fn write_crate(source: &Path, dir: &Path) -> io::Result<()> {
+    // Package names are restricted to alphanumerics, `-`, and `_`
+    let stem = dir.file_name().unwrap_or_default().to_string_lossy();
+    let name: String = format!("fuzztheory-{}", stem).chars()
+        .map(|x| if x.is_ascii_alphanumeric() || x == '_' { x } else { '-' })
+        .collect();
+
+    std::fs::create_dir_all(dir.join("src"))?;
+    std::fs::copy(source, dir.join("program.rs"))?;
+    for (from, to) in [
+        (sim::metadata::sidecar(source), "program.json"),
+        (sim::metadata::ground_truth(source), "program.truth.json"),
+        (source.with_extension("stats.json"), "program.stats.json"),
+    ] {
+        std::fs::copy(from, dir.join(to))?;
+    }
+
+    let sim = workspace_root().join("crates").join("sim");
+    std::fs::write(dir.join("Cargo.toml"), format!("\
+[package]
+name = \"{}\"
+version = \"0.1.0\"
+edition = \"2018\"
+build = \"build.rs\"
+
+[lib]
+name = \"fuzztheory_target\"
+crate-type = [\"rlib\", \"cdylib\"]
+
+[dependencies]
+fuzztheory-sim = {{ path = {:?} }}
+
+# Keep the crate out of any workspace it is copied into
+[workspace]
+", name, sim.display().to_string()))?;
+    std::fs::write(dir.join("build.rs"), CRATE_BUILD_SCRIPT)?;
+    std::fs::write(dir.join("src").join("lib.rs"),
+                   include_str!("../crates/target/src/lib.rs"))?;
+    std::fs::write(dir.join("src").join("main.rs"),
+                   include_str!("../crates/target/src/main.rs"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh temporary directory for `test`
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "fuzztheory-build-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn cache_key_covers_the_program_and_parameters() {
+        let dir = temp_dir("key");
+        let source = dir.join("program.rs");
+        std::fs::write(&source, "fn main() {}").unwrap();
+        std::fs::write(sim::metadata::sidecar(&source), "{}").unwrap();
+
+        let params = Params::default();
+        let key = |params: &Params| {
+            cache_key(params, &source, "cargo".as_ref()).unwrap()
+        };
+        let base = key(&params);
+        assert_eq!(key(&params), base);
+        assert_ne!(cache_key(&params, &source, "other".as_ref()).unwrap(),
+                   base);
+        assert_ne!(key(&Params { profile: "dev".into(), ..params.clone() }),
+                   base);
+        assert_ne!(key(&Params { rustflags: vec!["-g".into()],
+                                 ..params.clone() }), base);
+
+        std::fs::write(sim::metadata::sidecar(&source), "{ }").unwrap();
+        assert_ne!(key(&params), base);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn losing_the_race_to_the_cache_keeps_the_winner() {
+        let dir = temp_dir("race");
+        let built = dir.join("built");
+        std::fs::create_dir(&built).unwrap();
+        for name in artifacts() {
+            std::fs::write(built.join(name), "ours").unwrap();
+        }
+
+        // Nothing cached yet, our copy wins
+        let cached = dir.join("0123");
+        cache_artifacts(&built, &cached).unwrap();
+        for name in artifacts() {
+            assert_eq!(std::fs::read(cached.join(name)).unwrap(), b"ours");
+        }
+
+        // Another build finished first, its copy stays
+        for name in artifacts() {
+            std::fs::write(cached.join(name), "theirs").unwrap();
+        }
+        cache_artifacts(&built, &cached).unwrap();
+        for name in artifacts() {
+            assert_eq!(std::fs::read(cached.join(name)).unwrap(), b"theirs");
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
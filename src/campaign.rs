@@ -0,0 +1,102 @@
+//! Campaigns running the same sweep over many programs
+//!
+//! Conclusions drawn from a single synthetic program are fragile. A campaign
+//! generates a family of programs, or takes existing ones, runs the same
+//! `sweep` on each of them, and reduces the results of every fuzzer
+//! configuration across the programs with [`analysis::aggregate`]. The
+//! per-program results are kept next to the aggregated ones.
+
+use std::io;
+
+use fuzztheory_proggen::info;
+
+use crate::{analysis, proggen, rng, sweep};
+
+/// Prefix of the result files aggregated over all programs of a campaign
+pub const PREFIX: &str = "campaign_";
+
+/// Parameters of a campaign, the `[campaign]` table
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// Number of programs to generate
+    pub programs: usize,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            programs: 8,
+        }
+    }
+}
+
+/// Generation parameters of program `index` of a campaign whose programs are
+/// generated with `params`. Each program derives its own seed from the seed
+/// in `params`, so the whole family is reproducible.
+pub fn program_params(params: &proggen::Params, index: usize)
+        -> proggen::Params {
+    proggen::Params {
+        seed: rng::derive(params.seed as u64, index as u64) as usize,
+        ..params.clone()
+    }
+}
+
+/// Name of generated program `index`, used for its files
+pub fn program_name(index: usize) -> String {
+    format!("program_{:03}", index)
+}
+
+/// One line summary of a series of `records`: the result at the smallest
+/// and largest worker counts, and the ratio between them
+fn summary(records: &[analysis::Record]) -> String {
+    match (records.first(), records.last()) {
+        (Some(first), Some(last)) => {
+            format!("{:6} workers {:14.4}  {:6} workers {:14.4}  {:8.2}x",
+                    first.workers, first.mean, last.workers, last.mean,
+                    first.mean / last.mean)
+        }
+        _ => "no records".into(),
+    }
+}
+
+/// Aggregate the results of a campaign which ran the sweep `params` on the
+/// programs whose result files are prefixed with `prefixes`. Every result
+/// file is reduced across the programs into a [`PREFIX`]ed file, and a
+/// summary of each program and of the aggregate is printed.
+pub fn aggregate(params: &sweep::Params, prefixes: &[String])
+        -> io::Result<()> {
+    let strategies = params.strategies()?;
+    let mut files: Vec<String> = sweep::datapoints(params, strategies.len())
+        .iter().map(|x| x.filename(params, &strategies)).collect();
+    files.dedup();
+
+    for file in &files {
+        let mut runs = Vec::new();
+        for prefix in prefixes {
+            let path = params.output_dir.join(format!("{}{}", prefix, file));
+            let contents = std::fs::read_to_string(&path)?;
+            let mut records = analysis::parse_records(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), err)))?;
+            records.sort_by_key(|x| x.workers);
+            runs.push(records);
+        }
+
+        let records = analysis::aggregate(&runs);
+        let path = params.output_dir.join(format!("{}{}", PREFIX, file));
+        let mut out = std::fs::File::create(path)?;
+        analysis::write_header(&mut out, &format!(
+            "mean over {} programs of the {}, stddev between programs",
+            runs.len(), sweep::describe(params.time_constraint)))?;
+        analysis::write_records(&mut out, &records)?;
+
+        info!("{}:", file);
+        for (prefix, run) in prefixes.iter().zip(&runs) {
+            info!("    {:>16}  {}", prefix.trim_end_matches('_'),
+                  summary(run));
+        }
+        info!("    {:>16}  {}", "all programs", summary(&records));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1067 @@
+//! The `fuzztheory` command line driver
+//!
+//! `proggen` generates a program and builds a harness binary for it, the
+//! other commands invoke that harness binary to run the simulations, or load
+//! the programs given with `--program` into the driver itself. With
+//! `--generate` they generate a program and interpret it in the driver
+//! without building it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeSet;
+use std::process::Command;
+
+use fuzztheory_proggen::info;
+use fuzztheory_proggen::c::Harness;
+
+use crate::config::{self, Config, Document};
+use crate::log::{self, Level};
+use crate::{build, campaign, harness, proggen, rng, sweep};
+use crate::sim::{self, Registry};
+
+/// Usage text printed for `--help` and on argument errors
+pub const USAGE: &str = "\
+Usage: fuzztheory <command> [options]
+
+Commands:
+    proggen     Generate a new target program and build the harness for it
+    sweep       Run the worker scaling sweep using the built harness
+    heatmap     Run the time constrained sweeps used for the coverage heatmap
+    perf        Measure the fuzz case throughput of the built harness
+    campaign    Generate several programs, run the sweep on each, and
+                aggregate the results across them
+
+Options:
+    --config <path>          Load experiment parameters from a TOML file
+    --preset <name>          Start from a built-in experiment, one of
+                             scaling-sweep, sharing-heatmap, or
+                             single-core-baseline. `--config` overrides it.
+    --set <table.key=value>  Override a single configuration key, on top of
+                             `--preset` and `--config`, may be given
+                             multiple times
+    --out-dir <dir>          Write generated programs to `dir/programs`,
+                             results to `dir/results`, and keep `dir/plots`
+                             for plots, instead of the working directory.
+                             Relative `--source`, `--binary`, `--dylib`, and
+                             `--program` paths are in `dir/programs`.
+    --source <path>          Generated program source (default: program.rs),
+                             its metadata goes next to it as `.json`
+    --binary <path>          Built harness binary, overriding the
+                             configuration (default: ./test)
+    --no-build               proggen: only write the source, don't compile
+                             it, campaign: interpret the programs' IR
+                             instead of building them
+    --corpus                 proggen: also write an input reaching every leaf
+                             block and crash to `ground_truth_corpus/` next
+                             to the source
+    --emit-cfg               proggen: also write the program's control flow
+                             graph as Graphviz DOT next to the source, as
+                             `.dot`
+    --emit-c                 proggen: also write the program as C next to
+                             the source, as `.c`, to fuzz it with real
+                             fuzzers
+    --emit-libfuzzer         proggen: also write the program as C with a
+                             libFuzzer harness next to the source, as
+                             `.libfuzzer.c`
+    --emit-afl               proggen: also write the program as C with a
+                             `main` for AFL++ persistent mode next to the
+                             source, as `.afl.c`, crashing with `abort()`
+    --emit-ir                proggen: also write the program as IR next to
+                             the source, as `.ir`, to load it with
+                             `--program` without compiling it
+    --emit-wasm              proggen: also write the program as a
+                             WebAssembly module next to the source, as
+                             `.wasm`
+    --emit-smt               proggen: also write the constraint on the input
+                             of reaching every block and crash as SMT-LIB
+                             next to the source, as `.smt2`
+    --emit-decisions         proggen: also write the trace of the decisions
+                             made generating the program as JSON next to
+                             the source, as `.decisions.json`
+    --emit-crate             proggen: also write the program as a standalone
+                             cargo crate building its harness, in a
+                             directory named after the source next to it
+    --variants <n>           proggen: also write `n` variants of the program
+                             next to the source, as `_v<k>`, each with
+                             `proggen.patches` branches patched in the one
+                             before, along with the same emitted files
+    --dylib <path>           proggen: also copy the program as a dynamic
+                             library to `path`
+    --program <path>         sweep, heatmap, perf, campaign: load a program
+                             built with `--dylib`, or interpret one written
+                             with `--emit-ir`, instead of running the
+                             harness binary (or generating programs), may be
+                             given multiple times
+    --generate               sweep, heatmap, perf: generate a program from
+                             the `[proggen]` table and interpret its IR in
+                             this process, without writing it out or
+                             building it
+    --keep-source            With `--generate`, also write out the program
+                             source and its metadata to `--source`
+    --count <n>              campaign: number of programs to generate
+    --time-constraint <t>    sweep, campaign: stop each trial after `t` units
+                             of uptime
+    --max-cores <n>          sweep, heatmap: largest number of simulated cores
+    --threads <n>            sweep, heatmap, campaign: datapoints to run in
+                             parallel (default: number of physical cores)
+    --seed <n>               proggen, campaign, `--generate`: seed program
+                             generation, sweep, heatmap, perf, campaign: seed
+                             the simulated workers so results are
+                             reproducible
+    --random-seed            proggen, campaign, `--generate`: generate
+                             programs from a random seed, which is printed
+                             and recorded in the program metadata
+    --trial <n>              sweep, heatmap: only run trial `n` of each
+                             datapoint, to reproduce an outlier reported by
+                             `-v` along with its seed
+    --resume                 sweep, heatmap: keep the records in existing
+                             result files and only run the missing datapoints
+    --dry-run                sweep, heatmap: print the datapoints which would
+                             be run and the fuzz cases they'd take, instead
+                             of running them
+    --mutator <name>         sweep, heatmap, perf: mutator to use, may be
+                             given multiple times to compare mutators
+    --scheduler <name>       sweep, heatmap, perf: corpus scheduler to use
+    --metric <name>          sweep, heatmap, perf: coverage metric to use,
+                             may be given multiple times to compare metrics
+    --log-level <level>      Diagnostics to print: quiet, normal (default),
+                             verbose, or trace
+    -q, --quiet              Same as `--log-level quiet`
+    -v, --verbose            Same as `--log-level verbose`, give it twice for
+                             `--log-level trace`
+    -h, --help               Print this message
+";
+
+/// Subcommand selected on the command line
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// Generate (and build) a new target program
+    Proggen,
+
+    /// Run the scaling sweep, `sweep::run`
+    Sweep,
+
+    /// Run the time constrained heatmap sweeps, `sweep::heatmap`
+    Heatmap,
+
+    /// Run the throughput benchmark, `sweep::perf`
+    Perf,
+
+    /// Run the scaling sweep on many programs, see [`crate::campaign`]
+    Campaign,
+}
+
+impl Mode {
+    /// Name of the mode as it is passed to the harness binary
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Proggen  => "proggen",
+            Mode::Sweep    => "sweep",
+            Mode::Heatmap  => "heatmap",
+            Mode::Perf     => "perf",
+            Mode::Campaign => "campaign",
+        }
+    }
+}
+
+/// Fully parsed command line
+#[derive(Debug)]
+pub struct Options {
+    /// Subcommand to run
+    pub mode: Mode,
+
+    /// Experiment configuration file
+    pub config: Option<PathBuf>,
+
+    /// Built-in experiment the configuration starts from
+    pub preset: Option<String>,
+
+    /// Configuration keys overridden with `--set`, applied last
+    pub overrides: Document,
+
+    /// Directory all artifacts are organized under, see [`Options::dir`]
+    pub out_dir: Option<PathBuf>,
+
+    /// Path to write the generated program source to
+    pub source: PathBuf,
+
+    /// Path of the compiled harness binary, overriding the configuration
+    pub binary: Option<PathBuf>,
+
+    /// Skip compiling the generated source, and interpret the IR of
+    /// campaign programs instead
+    pub no_build: bool,
+
+    /// Write the ground truth corpus next to the generated source
+    pub corpus: bool,
+
+    /// Write the control flow graph next to the generated source
+    pub emit_cfg: bool,
+
+    /// Write the program as C next to the generated source
+    pub emit_c: bool,
+
+    /// Write the program as C with a libFuzzer harness next to the generated
+    /// source
+    pub emit_libfuzzer: bool,
+
+    /// Write the program as C with an AFL++ `main` next to the generated
+    /// source
+    pub emit_afl: bool,
+
+    /// Write the program as IR next to the generated source
+    pub emit_ir: bool,
+
+    /// Write the program as a WebAssembly module next to the generated
+    /// source
+    pub emit_wasm: bool,
+
+    /// Write the path constraints of the program as SMT-LIB next to the
+    /// generated source
+    pub emit_smt: bool,
+
+    /// Write the trace of generating the program as JSON next to the
+    /// generated source
+    pub emit_decisions: bool,
+
+    /// Write the program as a cargo crate next to the generated source
+    pub emit_crate: bool,
+
+    /// Number of patched variants of the program to write
+    pub variants: usize,
+
+    /// Path to copy the program's dynamic library to
+    pub dylib: Option<PathBuf>,
+
+    /// Dynamic library or IR programs to run in-process
+    pub programs: Vec<PathBuf>,
+
+    /// Generate a program and run its IR in-process instead of running the
+    /// harness binary
+    pub generate: bool,
+
+    /// Write out the source of the program run with `--generate`
+    pub keep_source: bool,
+
+    /// Number of programs generated by a campaign, overriding the
+    /// configuration
+    pub count: Option<usize>,
+
+    /// Time constraint passed to the sweep, if any
+    pub time_constraint: Option<f64>,
+
+    /// Maximum number of simulated cores, overriding the configuration
+    pub max_cores: Option<usize>,
+
+    /// Number of sweep threads, overriding the configuration
+    pub threads: Option<usize>,
+
+    /// Seed for program generation or the simulated workers, overriding the
+    /// configuration
+    pub seed: Option<u64>,
+
+    /// Generate programs from a random seed
+    pub random_seed: bool,
+
+    /// Only run this trial of each datapoint
+    pub trial: Option<usize>,
+
+    /// Only run the datapoints missing from existing result files
+    pub resume: bool,
+
+    /// Only print the planned datapoints
+    pub dry_run: bool,
+
+    /// Names of the mutators to compare, overriding the configuration if
+    /// non-empty
+    pub mutators: Vec<String>,
+
+    /// Name of the corpus scheduler, overriding the configuration
+    pub scheduler: Option<String>,
+
+    /// Names of the coverage metrics to compare, overriding the
+    /// configuration if non-empty
+    pub metrics: Vec<String>,
+
+    /// Level of diagnostics to print
+    pub log_level: Level,
+}
+
+/// Subdirectories of `--out-dir` artifacts are organized into
+pub const OUT_SUBDIRS: &[&str] = &["programs", "results", "plots"];
+
+impl Options {
+    /// Directory the artifacts in subdirectory `name` of `--out-dir`
+    /// (one of [`OUT_SUBDIRS`]) are written to. Without `--out-dir` this is
+    /// the working directory.
+    pub fn dir(&self, name: &str) -> PathBuf {
+        debug_assert!(OUT_SUBDIRS.contains(&name));
+        self.out_dir.as_ref().map_or_else(PathBuf::new, |x| x.join(name))
+    }
+
+    /// Path of the generated program source
+    pub fn source(&self) -> PathBuf {
+        self.dir("programs").join(&self.source)
+    }
+
+    /// Path of the compiled harness binary, `--binary` or the one `config`
+    /// builds
+    pub fn binary(&self, config: &Config) -> PathBuf {
+        self.dir("programs")
+            .join(self.binary.as_ref().unwrap_or(&config.build.binary))
+    }
+
+    /// Path the program's dynamic library is copied to, if any
+    pub fn dylib(&self) -> Option<PathBuf> {
+        self.dylib.as_ref().map(|x| self.dir("programs").join(x))
+    }
+
+    /// Paths of the dynamic library programs to run in-process
+    pub fn programs(&self) -> Vec<PathBuf> {
+        self.programs.iter().map(|x| self.dir("programs").join(x)).collect()
+    }
+}
+
+/// Result of parsing the command line
+pub enum Parsed {
+    /// Run with these options
+    Run(Box<Options>),
+
+    /// The user asked for `--help`
+    Help,
+}
+
+/// Parse command line arguments (not including the program name)
+pub fn parse<I: IntoIterator<Item = String>>(args: I)
+        -> Result<Parsed, String> {
+    let mut args = args.into_iter();
+
+    let mode = match args.next().as_deref() {
+        Some("proggen")  => Mode::Proggen,
+        Some("sweep")    => Mode::Sweep,
+        Some("heatmap")  => Mode::Heatmap,
+        Some("perf")     => Mode::Perf,
+        Some("campaign") => Mode::Campaign,
+        Some("-h") | Some("--help") => return Ok(Parsed::Help),
+        Some(other) => return Err(format!("Unknown command `{}`", other)),
+        None => return Err("No command given".into()),
+    };
+
+    let mut options = Options {
+        mode,
+        config:          None,
+        preset:          None,
+        overrides:       Document::new(),
+        out_dir:         None,
+        source:          PathBuf::from("program.rs"),
+        binary:          None,
+        no_build:        false,
+        corpus:          false,
+        emit_cfg:        false,
+        emit_c:          false,
+        emit_libfuzzer:  false,
+        emit_afl:        false,
+        emit_ir:         false,
+        emit_wasm:       false,
+        emit_smt:        false,
+        emit_decisions:  false,
+        emit_crate:      false,
+        variants:        0,
+        dylib:           None,
+        programs:        Vec::new(),
+        generate:        false,
+        keep_source:     false,
+        count:           None,
+        time_constraint: None,
+        max_cores:       None,
+        threads:         None,
+        seed:            None,
+        random_seed:     false,
+        trial:           None,
+        resume:          false,
+        dry_run:         false,
+        mutators:        Vec::new(),
+        scheduler:       None,
+        metrics:         Vec::new(),
+        log_level:       Level::Normal,
+    };
+
+    // Get the value for a flag which takes an argument
+    macro_rules! value {
+        ($flag:expr) => {
+            args.next().ok_or_else(|| format!("`{}` expects a value", $flag))?
+        }
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Parsed::Help),
+            "--config"   => options.config = Some(value!(arg).into()),
+            "--preset"   => options.preset = Some(value!(arg)),
+            "--set"      => {
+                options.overrides.extend(config::parse_override(
+                    &value!(arg))?);
+            }
+            "--out-dir"  => options.out_dir = Some(value!(arg).into()),
+            "--source"   => options.source = value!(arg).into(),
+            "--binary"   => options.binary = Some(value!(arg).into()),
+            "--no-build" => options.no_build = true,
+            "--corpus"   => options.corpus = true,
+            "--emit-cfg" => options.emit_cfg = true,
+            "--emit-c"   => options.emit_c = true,
+            "--emit-libfuzzer" => options.emit_libfuzzer = true,
+            "--emit-afl" => options.emit_afl = true,
+            "--emit-ir"  => options.emit_ir = true,
+            "--emit-wasm" => options.emit_wasm = true,
+            "--emit-smt" => options.emit_smt = true,
+            "--emit-decisions" => options.emit_decisions = true,
+            "--emit-crate" => options.emit_crate = true,
+            "--dry-run"  => options.dry_run = true,
+            "--random-seed" => options.random_seed = true,
+            "--resume"   => options.resume = true,
+            "--dylib"    => options.dylib = Some(value!(arg).into()),
+            "--program"  => options.programs.push(value!(arg).into()),
+            "--generate" => options.generate = true,
+            "--keep-source" => options.keep_source = true,
+            "--mutator"   => options.mutators.push(value!(arg)),
+            "--scheduler" => options.scheduler = Some(value!(arg)),
+            "--metric"    => options.metrics.push(value!(arg)),
+            "--log-level" => options.log_level = value!(arg).parse()?,
+            "-q" | "--quiet" => options.log_level = Level::Quiet,
+            "-v" | "--verbose" => {
+                options.log_level = if options.log_level >= Level::Verbose {
+                    Level::Trace
+                } else {
+                    Level::Verbose
+                };
+            }
+            "-vv" => options.log_level = Level::Trace,
+            "--time-constraint" => {
+                let val = value!(arg);
+                options.time_constraint = Some(val.parse().map_err(|_| {
+                    format!("Invalid time constraint `{}`", val)
+                })?);
+            }
+            "--seed" => {
+                let val = value!(arg);
+                options.seed = Some(val.parse().map_err(|_| {
+                    format!("Invalid seed `{}`", val)
+                })?);
+            }
+            "--trial" => {
+                let val = value!(arg);
+                options.trial = Some(val.parse().map_err(|_| {
+                    format!("Invalid trial `{}`", val)
+                })?);
+            }
+            "--threads" => {
+                let val = value!(arg);
+                options.threads = Some(val.parse().ok()
+                    .filter(|&x| x > 0)
+                    .ok_or_else(|| format!("Invalid thread count `{}`",
+                                           val))?);
+            }
+            "--count" => {
+                let val = value!(arg);
+                options.count = Some(val.parse().ok()
+                    .filter(|&x| x > 0)
+                    .ok_or_else(|| format!("Invalid count `{}`", val))?);
+            }
+            "--variants" => {
+                let val = value!(arg);
+                options.variants = val.parse().map_err(|_| {
+                    format!("Invalid variant count `{}`", val)
+                })?;
+            }
+            "--max-cores" => {
+                let val = value!(arg);
+                options.max_cores = Some(val.parse().ok()
+                    .filter(|&x| x > 0)
+                    .ok_or_else(|| format!("Invalid core count `{}`", val))?);
+            }
+            _ => return Err(format!("Unknown option `{}`", arg)),
+        }
+    }
+
+    // Make sure options are only used with the commands they apply to
+    if options.no_build && !matches!(mode, Mode::Proggen | Mode::Campaign) {
+        return Err("`--no-build` only applies to `proggen` and `campaign`"
+            .into());
+    }
+    if options.corpus && mode != Mode::Proggen {
+        return Err("`--corpus` only applies to `proggen`".into());
+    }
+    if options.emit_cfg && mode != Mode::Proggen {
+        return Err("`--emit-cfg` only applies to `proggen`".into());
+    }
+    if options.emit_c && mode != Mode::Proggen {
+        return Err("`--emit-c` only applies to `proggen`".into());
+    }
+    if options.emit_libfuzzer && mode != Mode::Proggen {
+        return Err("`--emit-libfuzzer` only applies to `proggen`".into());
+    }
+    if options.emit_afl && mode != Mode::Proggen {
+        return Err("`--emit-afl` only applies to `proggen`".into());
+    }
+    if options.emit_ir && mode != Mode::Proggen {
+        return Err("`--emit-ir` only applies to `proggen`".into());
+    }
+    if options.emit_wasm && mode != Mode::Proggen {
+        return Err("`--emit-wasm` only applies to `proggen`".into());
+    }
+    if options.emit_smt && mode != Mode::Proggen {
+        return Err("`--emit-smt` only applies to `proggen`".into());
+    }
+    if options.emit_decisions && mode != Mode::Proggen {
+        return Err("`--emit-decisions` only applies to `proggen`".into());
+    }
+    if options.emit_crate && mode != Mode::Proggen {
+        return Err("`--emit-crate` only applies to `proggen`".into());
+    }
+    if options.variants != 0 && mode != Mode::Proggen {
+        return Err("`--variants` only applies to `proggen`".into());
+    }
+    if options.no_build && options.dylib.is_some() {
+        return Err("`--dylib` requires building the program".into());
+    }
+    if options.dylib.is_some() && mode != Mode::Proggen {
+        return Err("`--dylib` only applies to `proggen`".into());
+    }
+    if !options.programs.is_empty() && mode == Mode::Proggen {
+        return Err("`--program` does not apply to `proggen`".into());
+    }
+    if options.programs.len() > 1 && mode == Mode::Perf {
+        return Err("`perf` runs forever, give it a single `--program`"
+            .into());
+    }
+    if options.time_constraint.is_some() &&
+            !matches!(mode, Mode::Sweep | Mode::Campaign) {
+        return Err("`--time-constraint` only applies to `sweep` and \
+                    `campaign`".into());
+    }
+    if options.max_cores.is_some() &&
+            !matches!(mode, Mode::Sweep | Mode::Heatmap | Mode::Campaign) {
+        return Err("`--max-cores` only applies to `sweep`, `heatmap`, and \
+                    `campaign`".into());
+    }
+    if options.threads.is_some() &&
+            !matches!(mode, Mode::Sweep | Mode::Heatmap | Mode::Campaign) {
+        return Err("`--threads` only applies to `sweep`, `heatmap`, and \
+                    `campaign`".into());
+    }
+    if options.random_seed && !options.generate &&
+            !matches!(mode, Mode::Proggen | Mode::Campaign) {
+        return Err("`--random-seed` only applies to `proggen`, `campaign`, \
+                    and `--generate`".into());
+    }
+    if options.random_seed && options.seed.is_some() {
+        return Err("`--random-seed` conflicts with `--seed`".into());
+    }
+    if options.generate &&
+            !matches!(mode, Mode::Sweep | Mode::Heatmap | Mode::Perf) {
+        return Err("`--generate` only applies to `sweep`, `heatmap`, and \
+                    `perf`".into());
+    }
+    if options.generate && !options.programs.is_empty() {
+        return Err("`--generate` conflicts with `--program`".into());
+    }
+    if options.generate && options.binary.is_some() {
+        return Err("`--generate` conflicts with `--binary`".into());
+    }
+    if options.keep_source && !options.generate {
+        return Err("`--keep-source` requires `--generate`".into());
+    }
+    if options.count.is_some() && mode != Mode::Campaign {
+        return Err("`--count` only applies to `campaign`".into());
+    }
+    if options.count.is_some() && !options.programs.is_empty() {
+        return Err("`--count` conflicts with `--program`".into());
+    }
+    if options.trial.is_some() &&
+            !matches!(mode, Mode::Sweep | Mode::Heatmap) {
+        return Err("`--trial` only applies to `sweep` and `heatmap`".into());
+    }
+    if options.resume &&
+            !matches!(mode, Mode::Sweep | Mode::Heatmap | Mode::Campaign) {
+        return Err("`--resume` only applies to `sweep`, `heatmap`, and \
+                    `campaign`".into());
+    }
+    if options.dry_run && !matches!(mode, Mode::Sweep | Mode::Heatmap) {
+        return Err("`--dry-run` only applies to `sweep` and `heatmap`"
+            .into());
+    }
+    if mode == Mode::Proggen && (!options.mutators.is_empty() ||
+            options.scheduler.is_some() || !options.metrics.is_empty()) {
+        return Err("Strategies do not apply to `proggen`".into());
+    }
+    if options.mutators.len() > 1 && mode == Mode::Perf {
+        return Err("`perf` runs forever, give it a single `--mutator`"
+            .into());
+    }
+    if options.metrics.len() > 1 && mode == Mode::Perf {
+        return Err("`perf` runs forever, give it a single `--metric`"
+            .into());
+    }
+
+    Ok(Parsed::Run(Box::new(options)))
+}
+
+/// Write out the source of `program` to `source`, with its metadata and
+/// ground truth next to it. Programs with unreachable blocks or crashes are
+/// rejected rather than written, see [`proggen::Program::verify`].
+fn write_program(program: &proggen::Program, source: &Path)
+        -> io::Result<()> {
+    program.verify().map_err(|err| {
+        io::Error::new(io::ErrorKind::InvalidData,
+                       format!("{}: {}", source.display(), err))
+    })?;
+    std::fs::write(source, program.to_rust())?;
+    std::fs::write(sim::metadata::sidecar(source), program.info() + "\n")?;
+    std::fs::write(sim::metadata::ground_truth(source),
+                   program.ground_truth() + "\n")?;
+    std::fs::write(source.with_extension("stats.json"),
+                   program.stats().json() + "\n")
+}
+
+/// Write an input reaching every leaf block and crash of `program` to the
+/// directory `dir`, named after what it reaches, see [`proggen::solve`]
+fn write_corpus(program: &proggen::Program, dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let solutions = program.solve();
+    let mut unsolved = Vec::new();
+    for solution in &solutions {
+        match &solution.input {
+            Some(input) => {
+                std::fs::write(dir.join(solution.goal.name()), input)?;
+            }
+            None => unsolved.push(solution.goal.name()),
+        }
+    }
+
+    info!("Wrote {} inputs to {}", solutions.len() - unsolved.len(),
+          dir.display());
+    if !unsolved.is_empty() {
+        info!("Couldn't solve the paths to {}", unsolved.join(", "));
+    }
+    Ok(())
+}
+
+/// Program generation parameters from the configuration, with command line
+/// overrides
+fn proggen_params(options: &Options, config: &Config) -> proggen::Params {
+    let mut params = config.proggen.clone();
+    if let Some(seed) = options.seed {
+        params.seed = seed as usize;
+    }
+    if options.random_seed {
+        params.seed = rng::random_seed() as usize;
+        info!("Generating from random seed {}", params.seed);
+    }
+    params
+}
+
+/// Generate a program, write out its source, and build a harness for it
+fn proggen(options: &Options, config: &Config) -> io::Result<()> {
+    let params = proggen_params(options, config);
+    let program = generate(&params)?;
+
+    // Write out the program and its metadata
+    let source = options.source();
+    write_program(&program, &source)?;
+    if options.corpus {
+        write_corpus(&program, &source.with_file_name("ground_truth_corpus"))?;
+    }
+    write_emitted(options, &program, &source)?;
+
+    // Each variant patches the one before it
+    let mut variant = program.clone();
+    for index in 1..=options.variants {
+        variant = variant.patch(index);
+        let source = variant_source(&source, index);
+        write_program(&variant, &source)?;
+        write_emitted(options, &variant, &source)?;
+        info!("Wrote variant {} to {}, {} blocks", index, source.display(),
+              variant.num_blocks);
+    }
+
+    // Print out the program "complexity"
+    info!("Program complexity:\n\
+        Seed:              {}\n{}", program.params.seed, program.stats());
+
+    if options.no_build {
+        return Ok(());
+    }
+
+    build::harness(&config.build, &source, &options.binary(config),
+                   options.dylib().as_deref())
+}
+
+/// Generate a program meeting the acceptance criteria of `params`, see
+/// [`proggen::generate_accepted`]
+fn generate(params: &proggen::Params) -> io::Result<proggen::Program> {
+    proggen::generate_accepted(params).map_err(|err| {
+        io::Error::other(format!("no acceptable program: {}", err))
+    })
+}
+
+/// Path of the source of variant `index` of the program at `source`, with
+/// `_v<index>` appended to its file stem
+fn variant_source(source: &Path, index: usize) -> PathBuf {
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}_v{}", stem, index);
+    if let Some(ext) = source.extension() {
+        name += &format!(".{}", ext.to_string_lossy());
+    }
+    source.with_file_name(name)
+}
+
+/// Write `program` in every other form requested by `options` next to its
+/// source at `source`
+fn write_emitted(options: &Options, program: &proggen::Program,
+                 source: &Path) -> io::Result<()> {
+    if options.emit_cfg {
+        std::fs::write(source.with_extension("dot"), program.to_dot())?;
+    }
+    if options.emit_c {
+        std::fs::write(source.with_extension("c"),
+                       program.to_c(Harness::None))?;
+    }
+    if options.emit_libfuzzer {
+        std::fs::write(source.with_extension("libfuzzer.c"),
+                       program.to_c(Harness::LibFuzzer))?;
+    }
+    if options.emit_afl {
+        std::fs::write(source.with_extension("afl.c"),
+                       program.to_c(Harness::Afl))?;
+    }
+    if options.emit_ir {
+        std::fs::write(source.with_extension("ir"), program.to_ir().encode())?;
+    }
+    if options.emit_wasm {
+        std::fs::write(source.with_extension("wasm"), program.to_wasm())?;
+    }
+    if options.emit_smt {
+        std::fs::write(source.with_extension("smt2"), program.to_smt())?;
+    }
+    if options.emit_decisions {
+        std::fs::write(source.with_extension("decisions.json"),
+                       program.decisions())?;
+    }
+    if options.emit_crate {
+        build::write_crate(source, &source.with_extension(""))?;
+    }
+    Ok(())
+}
+
+/// Sweep parameters from the configuration, with command line overrides.
+/// Strategy names are looked up in `registry`.
+fn sweep_params(options: &Options, config: &Config, registry: &Registry)
+        -> sweep::Params {
+    let mut params = config.sweep.clone();
+    if let Some(max_cores) = options.max_cores {
+        params.max_cores = max_cores;
+    }
+    if let Some(threads) = options.threads {
+        params.threads = threads;
+    }
+    if let Some(time_constraint) = options.time_constraint {
+        params.time_constraint = Some(time_constraint);
+    }
+    if !options.mutators.is_empty() {
+        params.mutators = options.mutators.clone();
+    }
+    if let Some(scheduler) = &options.scheduler {
+        params.scheduler = scheduler.clone();
+    }
+    if !options.metrics.is_empty() {
+        params.metrics = options.metrics.clone();
+    }
+    if let Some(seed) = options.seed {
+        params.seed = Some(seed);
+    }
+    if let Some(trial) = options.trial {
+        params.trial = Some(trial);
+    }
+    params.registry   = registry.clone();
+    params.resume     = options.resume;
+    params.output_dir = options.dir("results");
+    params
+}
+
+/// Run the built harness binary in the mode requested by `options`. The
+/// harness only knows about the built-in strategies, so names are checked
+/// against `registry` here to fail before spawning it.
+fn run_harness(options: &Options, config: &Config, registry: &Registry)
+        -> io::Result<()> {
+    let params = sweep_params(options, config, registry);
+    params.strategies()?;
+
+    // Ctrl-C reaches the harness too, keep waiting while it writes out its
+    // truncated results
+    sim::interrupt::install();
+
+    let binary = options.binary(config);
+    let status = Command::new(&binary)
+        .arg(options.mode.name())
+        .args(harness::args(&params))
+        .status()?;
+    if sim::interrupt::interrupted() {
+        return Err(io::Error::new(io::ErrorKind::Interrupted,
+            "interrupted, wrote truncated results"));
+    }
+    if !status.success() {
+        return Err(io::Error::other(
+            format!("{} {} failed", binary.display(),
+                    options.mode.name())));
+    }
+
+    Ok(())
+}
+
+/// Prefixes of the result files of each of the `--program`s at `paths`.
+/// Programs are named by their file stem, with a counter appended to tell
+/// apart libraries with the same name in different directories.
+fn program_prefixes(paths: &[PathBuf]) -> Vec<String> {
+    let mut used = BTreeSet::new();
+    paths.iter().map(|path| {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut prefix = format!("{}_", stem);
+        for ii in 2.. {
+            if used.insert(prefix.clone()) {
+                break;
+            }
+            prefix = format!("{}_{}_", stem, ii);
+        }
+        prefix
+    }).collect()
+}
+
+/// Print the datapoints the requested mode would run, for each `--program`
+/// if any were given
+fn dry_run(options: &Options, config: &Config, registry: &Registry)
+        -> io::Result<()> {
+    let params = sweep_params(options, config, registry);
+    if options.programs.is_empty() {
+        return sweep::dry_run(options.mode.name(), &params);
+    }
+
+    let programs = options.programs();
+    let prefixes = program_prefixes(&programs);
+    for (path, prefix) in programs.iter().zip(prefixes) {
+        println!("{}:", path.display());
+        sweep::dry_run(options.mode.name(), &sweep::Params {
+            output_prefix: prefix,
+            ..params.clone()
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Load the program at `path`, interpreting it if it's IR and loading it as
+/// a dynamic library otherwise
+fn load_program(path: &Path) -> io::Result<Box<dyn sim::Target>> {
+    if sim::ir::is_ir(path) {
+        return Ok(Box::new(sim::ir::IrTarget::load(path)?));
+    }
+    load_dylib(path)
+}
+
+/// Load the dynamic library program at `path`
+#[cfg(unix)]
+fn load_dylib(path: &Path) -> io::Result<Box<dyn sim::Target>> {
+    Ok(Box::new(sim::dylib::DylibTarget::load(path)?))
+}
+
+#[cfg(not(unix))]
+fn load_dylib(_path: &Path) -> io::Result<Box<dyn sim::Target>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+        "loading programs from dynamic libraries requires a unix target"))
+}
+
+/// Load each of the programs at `programs` and run the harness `mode` on
+/// them in this process with `params`. Result files are prefixed with the
+/// matching entry of `prefixes`.
+fn run_programs(mode: &str, programs: &[PathBuf], prefixes: &[String],
+                params: &sweep::Params) -> io::Result<()> {
+    for (path, prefix) in programs.iter().zip(prefixes) {
+        let target = load_program(path)?;
+
+        let params = sweep::Params {
+            output_prefix: prefix.clone(),
+            ..params.clone()
+        };
+
+        info!("Running {} on {}", mode, path.display());
+        harness::run(mode, &*target, &params)?;
+    }
+
+    Ok(())
+}
+
+/// Generate a program and run the harness `mode` requested by `options` on
+/// it in this process, interpreting its IR. Nothing is written out or built
+/// unless `--keep-source` asks for the source.
+fn run_generated(options: &Options, config: &Config, registry: &Registry)
+        -> io::Result<()> {
+    let program = generate(&proggen_params(options, config))?;
+    if options.keep_source {
+        write_program(&program, &options.source())?;
+    } else {
+        program.verify().map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData,
+                           format!("generated program: {}", err))
+        })?;
+    }
+
+    let target = sim::ir::IrTarget::new(program.to_ir()).map_err(|err| {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    })?;
+    info!("Running {} on the generated program, {} blocks",
+          options.mode.name(), program.num_blocks);
+    harness::run(options.mode.name(), &target,
+                 &sweep_params(options, config, registry))
+}
+
+/// Generate the programs of a campaign, or take the `--program`s, run the
+/// sweep on each of them, and aggregate the results. With `--no-build` the
+/// generated programs are interpreted from their IR rather than built.
+fn campaign(options: &Options, config: &Config, registry: &Registry)
+        -> io::Result<()> {
+    let programs = if options.programs.is_empty() {
+        let count = options.count.unwrap_or(config.campaign.programs);
+        let dir = options.dir("programs");
+        let base = proggen_params(options, config);
+
+        (0..count).map(|index| {
+            let params = campaign::program_params(&base, index);
+            let program = generate(&params)?;
+            let name = campaign::program_name(index);
+            info!("Generated {}, {} blocks", name, program.num_blocks);
+
+            use std::env::consts::{DLL_SUFFIX, EXE_SUFFIX};
+            let source = dir.join(format!("{}.rs", name));
+            let binary = dir.join(format!("{}{}", name, EXE_SUFFIX));
+            let dylib  = dir.join(format!("{}{}", name, DLL_SUFFIX));
+            write_program(&program, &source)?;
+            if options.no_build {
+                let ir = source.with_extension("ir");
+                std::fs::write(&ir, program.to_ir().encode())?;
+                return Ok(ir);
+            }
+            build::harness(&config.build, &source, &binary, Some(&dylib))?;
+            Ok(dylib)
+        }).collect::<io::Result<Vec<_>>>()?
+    } else {
+        options.programs()
+    };
+
+    let params = sweep_params(options, config, registry);
+    let prefixes = program_prefixes(&programs);
+    run_programs(Mode::Sweep.name(), &programs, &prefixes, &params)?;
+    campaign::aggregate(&params, &prefixes)
+}
+
+/// Run the driver with already parsed `options`, looking up strategy names
+/// in `registry`
+pub fn run(options: &Options, registry: &Registry) -> io::Result<()> {
+    log::set_level(options.log_level);
+
+    // Load the experiment configuration, falling back to the defaults
+    let mut config = match &options.preset {
+        Some(name) => Config::preset(name).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidInput, err)
+        })?,
+        None => Config::default(),
+    };
+    if let Some(path) = &options.config {
+        config.apply_file(path)?;
+    }
+    config.apply(options.overrides.clone()).map_err(|err| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("--set: {}", err))
+    })?;
+
+    // Lay out the output directory
+    if options.out_dir.is_some() && !options.dry_run {
+        for name in OUT_SUBDIRS {
+            std::fs::create_dir_all(options.dir(name))?;
+        }
+    }
+
+    match options.mode {
+        Mode::Proggen => proggen(options, &config),
+        _ if options.dry_run => dry_run(options, &config, registry),
+        Mode::Campaign => campaign(options, &config, registry),
+        _ if options.generate => run_generated(options, &config, registry),
+        _ if !options.programs.is_empty() => {
+            let programs = options.programs();
+            run_programs(options.mode.name(), &programs,
+                         &program_prefixes(&programs),
+                         &sweep_params(options, &config, registry))
+        }
+        _ => run_harness(options, &config, registry),
+    }
+}
+
+/// Parse the process arguments and run the driver, exiting on errors
+pub fn main() {
+    main_with(Registry::default())
+}
+
+/// Like [`main`], but with user-defined strategies in `registry`. These can
+/// be used with `--program`, the harness binary only has the built-ins.
+pub fn main_with(registry: Registry) {
+    let options = match parse(std::env::args().skip(1)) {
+        Ok(Parsed::Run(options)) => options,
+        Ok(Parsed::Help) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprint!("error: {}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&options, &registry) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a command line which must be valid
+    fn options(args: &[&str]) -> Options {
+        match parse(args.iter().map(|x| x.to_string())) {
+            Ok(Parsed::Run(options)) => *options,
+            _ => panic!("{:?} didn't parse", args),
+        }
+    }
+
+    /// Commands which generate programs
+    const GENERATING: [&[&str]; 3] =
+        [&["proggen"], &["campaign"], &["sweep", "--generate"]];
+
+    #[test]
+    fn seed_applies_to_generation() {
+        let config = Config::default();
+        for command in GENERATING {
+            let options = options(&[command, &["--seed", "1234"]].concat());
+            assert_eq!(proggen_params(&options, &config).seed, 1234);
+        }
+        let options = options(&["campaign"]);
+        assert_eq!(proggen_params(&options, &config).seed,
+                   config.proggen.seed);
+    }
+
+    #[test]
+    fn random_seed_conflicts_with_seed() {
+        for command in GENERATING {
+            let args = [command, &["--seed", "1", "--random-seed"]].concat();
+            assert!(parse(args.iter().map(|x| x.to_string())).is_err());
+        }
+    }
+}
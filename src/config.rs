@@ -0,0 +1,807 @@
+//! Experiment configuration files
+//!
+//! An experiment is described by a small TOML file. Only the subset of TOML
+//! which is needed to describe an experiment is supported: `[table]` headers,
+//! `key = value` pairs, `#` comments, and values which are integers, floats,
+//! booleans, strings, or flat arrays of those.
+//!
+//! ```toml
+//! [proggen]
+//! if_chance   = 4
+//! min_blocks  = 5000
+//!
+//! [sweep]
+//! averages        = 1000
+//! time_constraint = 0.5
+//! ```
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+
+use crate::{build, campaign, proggen, sweep};
+
+/// A single value parsed from a configuration file
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// Name of the type of the value, used for error messages
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "integer",
+            Value::Float(_)   => "float",
+            Value::Bool(_)    => "boolean",
+            Value::String(_)  => "string",
+            Value::Array(_)   => "array",
+        }
+    }
+}
+
+/// Parsed TOML document, mapping `(table, key)` to values. Keys outside of
+/// any table live in the table named `""`.
+pub type Document = BTreeMap<(String, String), Value>;
+
+/// Parse a single value starting at the start of `s`, returning the value and
+/// the remaining unparsed string
+fn parse_value(s: &str) -> Result<(Value, &str), String> {
+    let s = s.trim_start();
+
+    if let Some(rest) = s.strip_prefix('"') {
+        // Basic string, supporting the common escapes
+        let mut out = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((ii, ch)) = chars.next() {
+            match ch {
+                '"' => return Ok((Value::String(out), &rest[ii + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, 'n'))  => out.push('\n'),
+                    Some((_, 't'))  => out.push('\t'),
+                    Some((_, '"'))  => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    _ => return Err("Invalid escape in string".into()),
+                },
+                _ => out.push(ch),
+            }
+        }
+        return Err("Unterminated string".into());
+    }
+
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(values), after));
+            }
+
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("Expected `,` or `]` in array".into());
+            }
+        }
+    }
+
+    // Bare value, runs until a delimiter
+    let end = s.find(|c: char| c == ',' || c == ']' || c.is_whitespace())
+        .unwrap_or(s.len());
+    let (token, rest) = s.split_at(end);
+
+    let value = match token {
+        "true"  => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => {
+            let clean = token.replace('_', "");
+            if let Some(hex) = clean.strip_prefix("0x") {
+                Value::Integer(i64::from_str_radix(hex, 16)
+                    .map_err(|_| format!("Invalid integer `{}`", token))?)
+            } else if let Ok(int) = clean.parse::<i64>() {
+                Value::Integer(int)
+            } else if let Ok(float) = clean.parse::<f64>() {
+                Value::Float(float)
+            } else {
+                return Err(format!("Invalid value `{}`", token));
+            }
+        }
+    };
+
+    Ok((value, rest))
+}
+
+/// Strip a trailing comment from a line, ignoring `#`s inside of strings
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped   = false;
+    for (ii, ch) in line.char_indices() {
+        match ch {
+            '\\' if in_string => { escaped = !escaped; continue; }
+            '"' if !escaped => in_string = !in_string,
+            '#' if !in_string => return &line[..ii],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Parse the TOML subset described in the module documentation
+pub fn parse(contents: &str) -> Result<Document, String> {
+    let mut document = Document::new();
+    let mut table = String::new();
+
+    for (lineno, line) in contents.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        let err = |msg: String| format!("line {}: {}", lineno + 1, msg);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            table = name.strip_suffix(']')
+                .ok_or_else(|| err("Unterminated table header".into()))?
+                .trim().to_string();
+            continue;
+        }
+
+        let eq = line.find('=')
+            .ok_or_else(|| err(format!("Expected `key = value`: `{}`", line)))?;
+        let key = line[..eq].trim();
+        if key.is_empty() {
+            return Err(err("Empty key".into()));
+        }
+
+        let (value, rest) = parse_value(&line[eq + 1..]).map_err(err)?;
+        if !rest.trim().is_empty() {
+            return Err(err(format!("Trailing characters `{}`", rest.trim())));
+        }
+
+        if document.insert((table.clone(), key.to_string()), value).is_some() {
+            return Err(err(format!("Duplicate key `{}`", key)));
+        }
+    }
+
+    Ok(document)
+}
+
+/// Parse a single `table.key=value` override, as given to `--set`, into a
+/// document holding just that key
+pub fn parse_override(s: &str) -> Result<Document, String> {
+    let err = || format!("Expected `table.key=value`: `{}`", s);
+    let (name, value) = s.split_once('=').ok_or_else(err)?;
+    let (table, key) = name.trim().split_once('.').ok_or_else(err)?;
+    if key.is_empty() {
+        return Err(err());
+    }
+
+    // Strings don't need quoting on the command line
+    let value = match parse_value(value) {
+        Ok((value, rest)) if rest.trim().is_empty() => value,
+        Ok(_) | Err(_) if !value.contains(['"', '[']) => {
+            Value::String(value.trim().to_string())
+        }
+        Ok((_, rest)) => {
+            return Err(format!("Trailing characters `{}`", rest.trim()));
+        }
+        Err(err) => return Err(err),
+    };
+
+    Ok(Document::from([((table.to_string(), key.to_string()), value)]))
+}
+
+/// Types which can be extracted from a configuration [`Value`]
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl FromValue for usize {
+    fn from_value(value: &Value) -> Option<Self> {
+        match *value {
+            Value::Integer(x) if x >= 0 => Some(x as usize),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for u64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        match *value {
+            Value::Integer(x) if x >= 0 => Some(x as u64),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        match *value {
+            Value::Integer(x) => Some(x as f64),
+            Value::Float(x)   => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Option<Self> {
+        match *value {
+            Value::Bool(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(x) => Some(x.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for PathBuf {
+    fn from_value(value: &Value) -> Option<Self> {
+        String::from_value(value).map(PathBuf::from)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Option<Self> {
+        T::from_value(value).map(Some)
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Array(values) => values.iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Built-in experiments, as `(name, TOML)` pairs. These live in `presets/`.
+pub const PRESETS: &[(&str, &str)] = &[
+    ("scaling-sweep",
+     include_str!("../presets/scaling-sweep.toml")),
+    ("sharing-heatmap",
+     include_str!("../presets/sharing-heatmap.toml")),
+    ("single-core-baseline",
+     include_str!("../presets/single-core-baseline.toml")),
+];
+
+/// Built-in programs, as `(name, TOML)` pairs of `[proggen]` tables with
+/// fixed seeds, selected with `proggen.preset`. These live in
+/// `presets/programs/`.
+pub const PROGRAM_PRESETS: &[(&str, &str)] = &[
+    ("small-1k-blocks",
+     include_str!("../presets/programs/small-1k-blocks.toml")),
+    ("medium-10k-blocks",
+     include_str!("../presets/programs/medium-10k-blocks.toml")),
+    ("large-100k-blocks",
+     include_str!("../presets/programs/large-100k-blocks.toml")),
+    ("deep-narrow",
+     include_str!("../presets/programs/deep-narrow.toml")),
+    ("wide-shallow",
+     include_str!("../presets/programs/wide-shallow.toml")),
+    ("checksum-walled",
+     include_str!("../presets/programs/checksum-walled.toml")),
+    ("sparse-64k-input",
+     include_str!("../presets/programs/sparse-64k-input.toml")),
+    ("plateau-32-bit",
+     include_str!("../presets/programs/plateau-32-bit.toml")),
+];
+
+/// Full description of an experiment
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Program generation parameters, the `[proggen]` table
+    pub proggen: proggen::Params,
+
+    /// Simulation parameters, the `[sweep]` table
+    pub sweep: sweep::Params,
+
+    /// Multi-program campaign parameters, the `[campaign]` table
+    pub campaign: campaign::Params,
+
+    /// Harness build parameters, the `[build]` table
+    pub build: build::Params,
+}
+
+impl Config {
+    /// Build a configuration from a parsed document, starting from the
+    /// defaults. Unknown tables and keys are errors so typos don't silently
+    /// fall back to defaults.
+    pub fn from_document(document: Document) -> Result<Self, String> {
+        let mut config = Config::default();
+        config.apply(document)?;
+        Ok(config)
+    }
+
+    /// Override the parameters set in `document`, leaving the others as they
+    /// are
+    pub fn apply(&mut self, mut document: Document) -> Result<(), String> {
+        let config = self;
+
+        // Move a value out of the document into `$field`, if present
+        macro_rules! take {
+            ($table:expr, $key:ident, $field:expr) => {
+                let key = ($table.to_string(), stringify!($key).to_string());
+                if let Some(value) = document.remove(&key) {
+                    $field = FromValue::from_value(&value).ok_or_else(|| {
+                        format!("Invalid {} for `{}.{}`",
+                                value.type_name(), key.0, key.1)
+                    })?;
+                }
+            }
+        }
+
+        // A program preset replaces every generation parameter, the ones set
+        // next to it apply on top of it
+        let key = ("proggen".to_string(), "preset".to_string());
+        if let Some(value) = document.remove(&key) {
+            let name: String = FromValue::from_value(&value).ok_or_else(|| {
+                format!("Invalid {} for `proggen.preset`", value.type_name())
+            })?;
+            config.proggen = Config::program_preset(&name)?;
+        }
+
+        let proggen = &mut config.proggen;
+        take!("proggen", seed,                proggen.seed);
+        take!("proggen", max_input_size_bits, proggen.max_input_size_bits);
+        take!("proggen", if_chance,           proggen.if_chance);
+        take!("proggen", end_block_chance,    proggen.end_block_chance);
+        take!("proggen", done_chance,         proggen.done_chance);
+        take!("proggen", min_blocks,          proggen.min_blocks);
+        take!("proggen", max_blocks,          proggen.max_blocks);
+        take!("proggen", target_blocks,       proggen.target_blocks);
+        take!("proggen", target_tolerance,    proggen.target_tolerance);
+        take!("proggen", crash_chance,        proggen.crash_chance);
+        take!("proggen", non_coverage_crash_chance,
+              proggen.non_coverage_crash_chance);
+        take!("proggen", non_coverage_crash_fraction,
+              proggen.non_coverage_crash_fraction);
+        take!("proggen", non_coverage_crash_depths,
+              proggen.non_coverage_crash_depths);
+        take!("proggen", min_crashes,         proggen.min_crashes);
+        take!("proggen", max_crashes,         proggen.max_crashes);
+        take!("proggen", min_depth,           proggen.min_depth);
+        take!("proggen", target_depth,        proggen.target_depth);
+        take!("proggen", max_depth,           proggen.max_depth);
+        take!("proggen", max_retries,         proggen.max_retries);
+        take!("proggen", crash_depths,        proggen.crash_depths);
+        take!("proggen", crash_classes,       proggen.crash_classes);
+        take!("proggen", root_cause_chance,   proggen.root_cause_chance);
+        take!("proggen", conjunction_chance,  proggen.conjunction_chance);
+        take!("proggen", conjunction_size,    proggen.conjunction_size);
+        take!("proggen", reject_chance,       proggen.reject_chance);
+        take!("proggen", else_chance,         proggen.else_chance);
+        take!("proggen", match_chance,        proggen.match_chance);
+        take!("proggen", loop_chance,         proggen.loop_chance);
+        take!("proggen", magic_chance,        proggen.magic_chance);
+        take!("proggen", compare_chance,      proggen.compare_chance);
+        take!("proggen", range_chance,        proggen.range_chance);
+        take!("proggen", checksum_chance,     proggen.checksum_chance);
+        take!("proggen", integrity_chance,    proggen.integrity_chance);
+        take!("proggen", derived_chance,      proggen.derived_chance);
+        take!("proggen", checksum_min_depth,  proggen.checksum_min_depth);
+        take!("proggen", stages,              proggen.stages);
+        take!("proggen", plateau_bits,        proggen.plateau_bits);
+        take!("proggen", record_tags,         proggen.record_tags);
+        take!("proggen", function_chance,     proggen.function_chance);
+        take!("proggen", call_chance,         proggen.call_chance);
+        take!("proggen", join_chance,         proggen.join_chance);
+        take!("proggen", reuse_chance,        proggen.reuse_chance);
+        take!("proggen", condition_bits,      proggen.condition_bits);
+        take!("proggen", span_slices,         proggen.span_slices);
+        take!("proggen", dead_fraction,       proggen.dead_fraction);
+        take!("proggen", config_bytes,        proggen.config_bytes);
+        take!("proggen", rarity_exponent,     proggen.rarity_exponent);
+        take!("proggen", flaky_chance,        proggen.flaky_chance);
+        take!("proggen", flaky_hit_chance,    proggen.flaky_hit_chance);
+        take!("proggen", variable_length,     proggen.variable_length);
+        take!("proggen", length_field_chance, proggen.length_field_chance);
+        take!("proggen", max_alloc_failures,  proggen.max_alloc_failures);
+        take!("proggen", patches,             proggen.patches);
+        take!("proggen", modules,             proggen.modules);
+        take!("proggen", entry_points,        proggen.entry_points);
+        take!("proggen", cfg,                 proggen.cfg);
+
+        let sweep = &mut config.sweep;
+        take!("sweep", max_cores,        sweep.max_cores);
+        take!("sweep", max_x_resolution, sweep.max_x_resolution);
+        take!("sweep", averages,         sweep.averages);
+        take!("sweep", time_constraint,  sweep.time_constraint);
+        take!("sweep", max_y_resolution, sweep.max_y_resolution);
+        take!("sweep", max_y_point,      sweep.max_y_point);
+        take!("sweep", guided,           sweep.guided);
+        take!("sweep", shared_inputs,    sweep.shared_inputs);
+        take!("sweep", shared_results,   sweep.shared_results);
+        take!("sweep", mutators,         sweep.mutators);
+        take!("sweep", scheduler,        sweep.scheduler);
+        take!("sweep", metrics,          sweep.metrics);
+        take!("sweep", havoc_stack,      sweep.havoc_stack);
+        take!("sweep", havoc_ops,        sweep.havoc_ops);
+        take!("sweep", mopt_period,      sweep.mopt_period);
+        take!("sweep", dictionary_completeness,
+              sweep.dictionary_completeness);
+        take!("sweep", deterministic,    sweep.deterministic);
+        take!("sweep", mutation_counts,  sweep.mutation_counts);
+        take!("sweep", map_size,         sweep.map_size);
+        take!("sweep", taint_chance,     sweep.taint_chance);
+        take!("sweep", taint_cost,       sweep.taint_cost);
+        take!("sweep", solver_period,    sweep.solver_period);
+        take!("sweep", dedup,            sweep.dedup);
+        take!("sweep", dedup_bucket_size, sweep.dedup_bucket_size);
+        take!("sweep", instrumented_modules, sweep.instrumented_modules);
+        take!("sweep", entry_points,     sweep.entry_points);
+        take!("sweep", seed,             sweep.seed);
+        take!("sweep", threads,          sweep.threads);
+
+        let campaign = &mut config.campaign;
+        take!("campaign", programs, campaign.programs);
+
+        let build = &mut config.build;
+        take!("build", cargo,     build.cargo);
+        take!("build", profile,   build.profile);
+        take!("build", target,    build.target);
+        take!("build", rustflags, build.rustflags);
+        take!("build", binary,    build.binary);
+        take!("build", cache,     build.cache);
+
+        if let Some(((table, key), _)) = document.into_iter().next() {
+            return Err(format!("Unknown configuration key `{}.{}`",
+                               table, key));
+        }
+
+        config.validate()
+    }
+
+    /// Load a configuration from a TOML file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut config = Config::default();
+        config.apply_file(path)?;
+        Ok(config)
+    }
+
+    /// Override the parameters set in the TOML file at `path`
+    pub fn apply_file(&mut self, path: &Path) -> io::Result<()> {
+        let contents = std::fs::read_to_string(path)?;
+        parse(&contents)
+            .and_then(|document| self.apply(document))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err)))
+    }
+
+    /// Load the built-in preset `name`, one of [`PRESETS`]
+    pub fn preset(name: &str) -> Result<Self, String> {
+        let (_, contents) = PRESETS.iter().find(|(x, _)| *x == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = PRESETS.iter().map(|x| x.0).collect();
+                format!("Unknown preset `{}` (known: {})", name,
+                        known.join(", "))
+            })?;
+        parse(contents).and_then(Config::from_document)
+            .map_err(|err| format!("preset {}: {}", name, err))
+    }
+
+    /// Generation parameters of the built-in program `name`, one of
+    /// [`PROGRAM_PRESETS`]
+    pub fn program_preset(name: &str) -> Result<proggen::Params, String> {
+        let (_, contents) = PROGRAM_PRESETS.iter().find(|(x, _)| *x == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> =
+                    PROGRAM_PRESETS.iter().map(|x| x.0).collect();
+                format!("Unknown program preset `{}` (known: {})", name,
+                        known.join(", "))
+            })?;
+        parse(contents).and_then(Config::from_document)
+            .map(|config| config.proggen)
+            .map_err(|err| format!("program preset {}: {}", name, err))
+    }
+
+    /// Sanity check parameters which would otherwise cause a panic or hang
+    pub fn validate(&self) -> Result<(), String> {
+        let proggen = &self.proggen;
+        if proggen.if_chance == 0 || proggen.end_block_chance == 0 ||
+                proggen.done_chance == 0 {
+            return Err("Generation chances must be non-zero".into());
+        }
+        if proggen.min_crashes > 0 && proggen.crash_chance == 0 {
+            return Err("`proggen.min_crashes` requires a non-zero \
+                        `proggen.crash_chance`".into());
+        }
+        if proggen.max_crashes != 0 &&
+                proggen.max_crashes < proggen.min_crashes {
+            return Err("`proggen.max_crashes` is below \
+                        `proggen.min_crashes`".into());
+        }
+        let min_blocks = if proggen.target_blocks != 0 {
+            proggen.target_blocks
+        } else {
+            proggen.min_blocks
+        };
+        if proggen.max_blocks != 0 && proggen.max_blocks < min_blocks {
+            return Err("`proggen.max_blocks` is below `proggen.min_blocks`, \
+                        or `proggen.target_blocks` if set".into());
+        }
+        if !(proggen.target_tolerance >= 0. &&
+                proggen.target_tolerance.is_finite()) {
+            return Err("`proggen.target_tolerance` must be a non-negative \
+                        number".into());
+        }
+        if !proggen.crash_depths.is_empty() &&
+                proggen.crash_depths.iter().all(|&x| x == 0) {
+            return Err("`proggen.crash_depths` needs a non-zero weight"
+                .into());
+        }
+        if !(0. ..=1.).contains(&proggen.non_coverage_crash_fraction) {
+            return Err("`proggen.non_coverage_crash_fraction` must be \
+                        between 0 and 1".into());
+        }
+        if !proggen.non_coverage_crash_depths.is_empty() &&
+                proggen.non_coverage_crash_depths.iter().all(|&x| x == 0) {
+            return Err("`proggen.non_coverage_crash_depths` needs a non-zero \
+                        weight".into());
+        }
+        let classes = proggen::CrashClass::ALL.len();
+        if !proggen.crash_classes.is_empty() &&
+                (proggen.crash_classes.len() > classes ||
+                 proggen.crash_classes.iter().all(|&x| x == 0)) {
+            return Err(format!("`proggen.crash_classes` needs a non-zero \
+                                weight and at most {} weights", classes));
+        }
+        if !proggen.condition_bits.is_empty() &&
+                (proggen.condition_bits.len() > 8 ||
+                 proggen.condition_bits.iter().all(|&x| x == 0)) {
+            return Err("`proggen.condition_bits` needs a non-zero weight \
+                        and at most 8 weights".into());
+        }
+        if !(0. ..1.).contains(&proggen.dead_fraction) {
+            return Err("`proggen.dead_fraction` must be at least 0 and \
+                        below 1".into());
+        }
+        if proggen.dead_fraction != 0. && proggen.integrity_chance != 0 {
+            return Err("`proggen.dead_fraction` can't be combined with \
+                        `proggen.integrity_chance`, whose checksums read \
+                        every byte of the input".into());
+        }
+        if proggen.config_bytes != 0 && (proggen.record_tags != 0 ||
+                proggen.config_bytes >= proggen.max_input_size_bits / 8) {
+            return Err("`proggen.config_bytes` must leave at least a byte \
+                        of the input to the data buffer, and can't be \
+                        combined with `proggen.record_tags`, whose records \
+                        span the whole input".into());
+        }
+        if proggen.length_field_chance != 0 && !proggen.variable_length {
+            return Err("`proggen.length_field_chance` requires \
+                        `proggen.variable_length`".into());
+        }
+        if proggen.record_tags != 0 && (!proggen.variable_length ||
+                proggen.stages > 1 || proggen.record_tags > 256 ||
+                proggen.max_input_size_bits < 24) {
+            return Err("`proggen.record_tags` requires \
+                        `proggen.variable_length` and at least 24 input \
+                        bits, can't be combined with `proggen.stages`, and \
+                        is at most 256".into());
+        }
+        if proggen.integrity_chance != 0 && (proggen.record_tags != 0 ||
+                proggen.max_input_size_bits < 16) {
+            return Err("`proggen.integrity_chance` needs at least 16 input \
+                        bits, and can't be combined with \
+                        `proggen.record_tags`, whose records have no fixed \
+                        last byte".into());
+        }
+        if proggen.plateau_bits != 0 && (proggen.stages < 2 ||
+                proggen.plateau_bits > 64 ||
+                (proggen.plateau_bits > 8 &&
+                 !proggen.plateau_bits.is_multiple_of(8))) {
+            return Err("`proggen.plateau_bits` requires `proggen.stages`, \
+                        and is at most 8 bits or whole bytes up to 64 bits"
+                .into());
+        }
+        if !(proggen.rarity_exponent >= 0. &&
+                proggen.rarity_exponent.is_finite()) {
+            return Err("`proggen.rarity_exponent` must be a non-negative \
+                        number".into());
+        }
+        if !(proggen.target_depth >= 0. && proggen.target_depth.is_finite()) {
+            return Err("`proggen.target_depth` must be a non-negative \
+                        number".into());
+        }
+        if proggen.max_depth != 0 && (proggen.max_depth < proggen.min_depth ||
+                (proggen.max_depth as f64) < proggen.target_depth) {
+            return Err("`proggen.max_depth` is below `proggen.min_depth` or \
+                        `proggen.target_depth`".into());
+        }
+        if proggen.flaky_hit_chance == 0 {
+            return Err("`proggen.flaky_hit_chance` must be non-zero".into());
+        }
+        if proggen.conjunction_size < 2 {
+            return Err("`proggen.conjunction_size` must be at least 2"
+                .into());
+        }
+        if proggen.modules == 0 {
+            return Err("`proggen.modules` must be at least 1".into());
+        }
+        if proggen.modules > 1 && proggen.function_chance == 0 &&
+                proggen.record_tags == 0 {
+            return Err("`proggen.modules` requires functions to put in \
+                        them, from `proggen.function_chance` or \
+                        `proggen.record_tags`".into());
+        }
+        if proggen.entry_points == 0 || proggen.entry_points > 63 {
+            return Err("`proggen.entry_points` must be between 1 and 63"
+                .into());
+        }
+        if proggen.entry_points > 1 && proggen.function_chance == 0 {
+            return Err("`proggen.entry_points` requires functions to use as \
+                        entry points, from `proggen.function_chance`".into());
+        }
+        if proggen.entry_points > 1 && proggen.variable_length {
+            // Functions rely on their callers to check the input length
+            return Err("`proggen.entry_points` can't be combined with \
+                        `proggen.variable_length`".into());
+        }
+        if proggen.max_input_size_bits < 8 {
+            return Err("`proggen.max_input_size_bits` must be at least 8"
+                .into());
+        }
+        if !proggen.cfg.is_empty() {
+            // Shaped programs are a single tree of fixed length conditions
+            if proggen.variable_length || proggen.record_tags != 0 ||
+                    proggen.modules > 1 || proggen.entry_points > 1 {
+                return Err("`proggen.cfg` can't be combined with \
+                            `proggen.variable_length`, \
+                            `proggen.record_tags`, `proggen.modules`, or \
+                            `proggen.entry_points`".into());
+            }
+            proggen::cfg::Cfg::load(std::path::Path::new(&proggen.cfg))?;
+        }
+
+        let sweep = &self.sweep;
+        if sweep.max_cores == 0 || sweep.max_x_resolution == 0 ||
+                sweep.averages == 0 ||
+                sweep.max_y_resolution == 0 || sweep.threads == 0 {
+            return Err("Sweep resolutions, averages, and threads must be \
+                        non-zero".into());
+        }
+        if sweep.guided.is_empty() || sweep.shared_inputs.is_empty() ||
+                sweep.shared_results.is_empty() ||
+                sweep.deterministic.is_empty() ||
+                sweep.mutation_counts.is_empty() {
+            return Err("Sweep axes must have at least one value".into());
+        }
+        if sweep.mutators.is_empty() {
+            return Err("`sweep.mutators` must name at least one mutator"
+                .into());
+        }
+        if sweep.metrics.is_empty() {
+            return Err("`sweep.metrics` must name at least one metric"
+                .into());
+        }
+        if let Err(err) = sweep.havoc() {
+            return Err(format!("Invalid `sweep.havoc_stack` or \
+                                `sweep.havoc_ops`: {}", err));
+        }
+        if let Err(err) = sweep.mutation_counts() {
+            return Err(format!("Invalid `sweep.mutation_counts`: {}", err));
+        }
+        if sweep.mopt_period == 0 {
+            return Err("`sweep.mopt_period` must be non-zero".into());
+        }
+        if !(0.0..=1.0).contains(&sweep.dictionary_completeness) {
+            return Err("`sweep.dictionary_completeness` must be between 0 \
+                        and 1".into());
+        }
+        if !(0.0..=1.0).contains(&sweep.taint_chance) {
+            return Err("`sweep.taint_chance` must be between 0 and 1".into());
+        }
+        if !["exact", "bucket", "root-cause"].contains(
+                &sweep.dedup.as_str()) {
+            return Err(format!("Unknown `sweep.dedup` policy `{}`, expected \
+                                `exact`, `bucket`, or `root-cause`",
+                               sweep.dedup));
+        }
+        if sweep.dedup_bucket_size == 0 {
+            return Err("`sweep.dedup_bucket_size` must be non-zero".into());
+        }
+        if sweep.entry_points.is_empty() {
+            return Err("`sweep.entry_points` must name at least one entry \
+                        point".into());
+        }
+
+        if self.campaign.programs == 0 {
+            return Err("`campaign.programs` must be non-zero".into());
+        }
+
+        if self.build.profile.is_empty() {
+            return Err("`build.profile` must name a cargo profile".into());
+        }
+        if self.build.binary.as_os_str().is_empty() {
+            return Err("`build.binary` must be a path".into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_toml_subset() {
+        let document = parse(r#"
+            top = 1
+            [proggen]
+            if_chance = 0x10   # sixteen
+            tag = "a # b\"c"
+            [ sweep ]
+            averages = 1_000
+            time_constraint = 0.5
+            flags = [true, false, [2, "x"]]
+        "#).unwrap();
+
+        let get = |table: &str, key: &str| {
+            document[&(table.to_string(), key.to_string())].clone()
+        };
+        assert_eq!(document.len(), 6);
+        assert_eq!(get("", "top"), Value::Integer(1));
+        assert_eq!(get("proggen", "if_chance"), Value::Integer(16));
+        assert_eq!(get("proggen", "tag"), Value::String("a # b\"c".into()));
+        assert_eq!(get("sweep", "averages"), Value::Integer(1000));
+        assert_eq!(get("sweep", "time_constraint"), Value::Float(0.5));
+        assert_eq!(get("sweep", "flags"), Value::Array(vec![
+            Value::Bool(true), Value::Bool(false),
+            Value::Array(vec![Value::Integer(2), Value::String("x".into())]),
+        ]));
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for contents in ["[proggen", "key", "= 1", "a = \"x", "a = [1 2]",
+                         "a = 1 2", "a = nope", "a = 1\na = 2"] {
+            assert!(parse(contents).is_err(), "{:?}", contents);
+        }
+    }
+
+    #[test]
+    fn config_overrides_defaults() {
+        let config = parse("[proggen]\nmin_blocks = 7\n\
+                            [sweep]\ntime_constraint = 2")
+            .and_then(Config::from_document).unwrap();
+        assert_eq!(config.proggen.min_blocks, 7);
+        assert_eq!(config.proggen.if_chance,
+                   Config::default().proggen.if_chance);
+        assert_eq!(config.sweep.time_constraint, Some(2.0));
+    }
+
+    #[test]
+    fn config_rejects_invalid_settings() {
+        for contents in [
+            "[proggen]\nmin_blocks = -1",
+            "[proggen]\nif_chance = \"4\"",
+            "[proggen]\nif_chance = 0",
+            "[proggen]\nmax_input_size_bits = 7",
+            "[sweep]\naverages = 0",
+            "[sweep]\ntypo = 1",
+            "[other]\naverages = 1",
+        ] {
+            let result = parse(contents).and_then(Config::from_document);
+            assert!(result.is_err(), "{:?}", contents);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Simulation of how fuzzers scale with the number of cores
+//!
+//! Synthetic programs with known coverage are generated by [`proggen`],
+//! fuzzed by the simulated cores of a [`sim::Fuzzer`], and swept over many
+//! fuzzer configurations by [`sweep`], with the results reduced by
+//! [`analysis`]. Each of these lives in its own crate in the workspace and
+//! is re-exported here along with the command line driver.
+
+pub use fuzztheory_analysis as analysis;
+pub use fuzztheory_proggen as proggen;
+pub use fuzztheory_proggen::{log, rng};
+pub use fuzztheory_sim as sim;
+pub use fuzztheory_sim::{harness, sweep};
+
+pub mod build;
+pub mod campaign;
+pub mod cli;
+pub mod config;